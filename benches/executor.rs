@@ -0,0 +1,81 @@
+//! Step loop throughput
+//!
+//! Each benchmark runs a machine that never halts for a fixed number of
+//! steps, so the time is spent in the step loop rather than in loading:
+//!
+//! - `bounce`: two cells visited in turn, the loop at its cheapest
+//! - `counter`: a binary counter, reading and writing across a growing
+//!   number
+//! - `left_walk`: the head moving left on every step, growing the tape at
+//!   its front
+//!
+//! Run with `cargo bench --bench executor`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use turing_machine::executor::{Executor, RunConfig};
+use turing_machine::{Direction, TuringMachine};
+
+fn bounce() -> TuringMachine {
+    TuringMachine::builder()
+        .initial("a")
+        .input_symbol('0')
+        .transition("a", '0', "b", '0', Direction::R)
+        .transition("b", '_', "a", '_', Direction::L)
+        .build()
+        .expect("valid machine")
+}
+
+fn counter() -> TuringMachine {
+    // `back` walks to the right end, `inc` adds one walking back left
+    TuringMachine::builder()
+        .initial("back")
+        .input_symbols(['0', '1'])
+        .transition("back", '0', "back", '0', Direction::R)
+        .transition("back", '1', "back", '1', Direction::R)
+        .transition("back", '_', "inc", '_', Direction::L)
+        .transition("inc", '1', "inc", '0', Direction::L)
+        .transition("inc", '0', "back", '1', Direction::R)
+        .transition("inc", '_', "back", '1', Direction::R)
+        .build()
+        .expect("valid machine")
+}
+
+fn left_walk() -> TuringMachine {
+    TuringMachine::builder()
+        .initial("walk")
+        .input_symbol('1')
+        .transition("walk", '_', "walk", '1', Direction::L)
+        .transition("walk", '1', "walk", '1', Direction::L)
+        .build()
+        .expect("valid machine")
+}
+
+fn steps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("steps");
+    group.sample_size(20);
+    let machines = [
+        ("bounce", bounce(), "0", 1_000_000),
+        ("counter", counter(), "0", 1_000_000),
+        ("left_walk", left_walk(), "1", 100_000),
+    ];
+    for (name, machine, input, max_steps) in &machines {
+        let config = RunConfig {
+            max_steps: *max_steps,
+            ..RunConfig::default()
+        };
+        group.throughput(Throughput::Elements(*max_steps as u64));
+        group.bench_with_input(BenchmarkId::new(*name, max_steps), &config, |b, config| {
+            b.iter(|| {
+                let result = Executor::new(machine)
+                    .run_with(black_box(input), config)
+                    .expect("runs");
+                black_box(result.steps)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, steps);
+criterion_main!(benches);
@@ -0,0 +1,21 @@
+//! Generates the gRPC service from `proto/` when the `grpc` feature is on
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+#[cfg(feature = "grpc")]
+fn grpc() {
+    let proto = "proto/turing_machine.proto";
+    println!("cargo:rerun-if-changed={}", proto);
+    // Use the vendored compiler unless PROTOC names another
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc");
+        std::env::set_var("PROTOC", protoc);
+    }
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_protos(&[proto], &["proto"])
+        .expect("cannot compile the gRPC service");
+}
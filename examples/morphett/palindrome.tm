@@ -0,0 +1,25 @@
+; Accepts binary palindromes, such as 0110 or 101.
+; Repeatedly erases the first symbol, then the last one if it matches.
+
+; State 0: erase the first symbol and remember it
+0 0 _ r carry0
+0 1 _ r carry1
+0 _ _ * halt-accept
+
+; carry0, carry1: move to the end of the input
+carry0 _ _ l check0
+carry0 * * r carry0
+carry1 _ _ l check1
+carry1 * * r carry1
+
+; check0, check1: the last symbol must match the first
+check0 0 _ l back
+check0 _ _ * halt-accept   ; a single symbol was left
+check0 * * * halt-reject
+check1 1 _ l back
+check1 _ _ * halt-accept
+check1 * * * halt-reject
+
+; back: return to the start of what is left
+back _ _ r 0 !
+back * * l back
@@ -0,0 +1,10 @@
+# The two-state busy beaver: started on an empty tape of zeros, it
+# writes four ones in six steps and halts.
+#! start A
+#! end H
+#! fill 0
+
+A 0 1 r B
+A 1 1 l B
+B 0 1 l A
+B 1 1 r H
@@ -0,0 +1,407 @@
+//! Machines with complex amplitudes on their transitions (experimental)
+//!
+//! A toy model of quantum Turing machines for teaching. The file format is
+//! that of non-deterministic machines, except that every target carries an
+//! amplitude, a real number or a `[re, im]` pair:
+//!
+//! ```json
+//! "transitions": {
+//!     "h,_": [["mix", "0", "S", 0.7071], ["mix", "1", "S", 0.7071]],
+//!     "mix,1": [["check", "0", "S", 0.7071], ["check", "1", "S", -0.7071]]
+//! }
+//! ```
+//!
+//! A [`Superposition`] holds every running configuration with its
+//! amplitude. A step follows all targets of all configurations at once,
+//! multiplying amplitudes along the way and adding up the amplitudes of
+//! branches that reach the same configuration, so branches can cancel out.
+//! Configurations in accept or reject states are then measured: their
+//! probability, the squared magnitude of their amplitude, is added to the
+//! acceptance or rejection probability and they stop running. A
+//! configuration without a transition is measured as halting by rejection
+//! (or acceptance, for machines that accept on halting).
+//!
+//! Nothing checks that the amplitudes make the machine unitary; if they do
+//! not, the probabilities are relative weights that need not add up to 1.
+//! The number of configurations is capped: beyond the cutoff, the least
+//! likely ones are dropped and their probability is reported as pruned.
+
+use crate::executor::RunDefaults;
+use crate::nondeterministic::{canonical, direction_at, read, wrap, write_and_move};
+use crate::{
+    formats, parse_machine_json, MachineJson, SymbolTable, TransitionKey, TransitionTarget,
+    TuringMachine,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::ops::{Add, Mul};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Configurations kept when no other cutoff is given
+pub const DEFAULT_MAX_CONFIGURATIONS: usize = 10_000;
+
+/// Amplitudes whose squared magnitude is below this are taken as cancelled
+const CANCELLED: f64 = 1e-12;
+
+/// A complex number
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// The squared magnitude, the probability of an amplitude
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.re, self.im) {
+            (re, im) if im.abs() < 5e-5 => write!(f, "{:.4}", re),
+            (re, im) if re.abs() < 5e-5 => write!(f, "{:.4}i", im),
+            (re, im) if im < 0.0 => write!(f, "{:.4}-{:.4}i", re, -im),
+            (re, im) => write!(f, "{:.4}+{:.4}i", re, im),
+        }
+    }
+}
+
+/// An amplitude as written in a file
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AmplitudeJson {
+    Real(f64),
+    Complex([f64; 2]),
+}
+
+/// A target with its amplitude: `[new_state, write_symbol, direction,
+/// amplitude]`
+#[derive(Debug, Deserialize)]
+struct TargetJson(String, String, String, AmplitudeJson);
+
+/// A transition value: a single target or a list of alternatives
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TargetsJson {
+    One(TargetJson),
+    Many(Vec<TargetJson>),
+}
+
+/// A Turing machine whose transitions lead to several targets at once,
+/// each with an amplitude
+#[derive(Debug)]
+pub struct AmplitudeMachine {
+    /// States, alphabets and halting states; its transitions are unused
+    base: TuringMachine,
+    transitions: HashMap<TransitionKey, Vec<(TransitionTarget, Complex)>>,
+}
+
+impl AmplitudeMachine {
+    /// Load an amplitude machine file (JSON, read as JSON5)
+    pub fn load(path: &Path) -> Result<AmplitudeMachine, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        AmplitudeMachine::from_json(&text)
+    }
+
+    /// Parse an amplitude machine definition
+    pub fn from_json(text: &str) -> Result<AmplitudeMachine, String> {
+        let invalid = |e: String| format!("Invalid machine file: {}", e);
+        let mut value: Value = json5::from_str(text).map_err(|e| invalid(e.to_string()))?;
+        let Some(object) = value.as_object_mut() else {
+            return Err(invalid("expected an object".to_string()));
+        };
+        let transitions = object
+            .insert("transitions".to_string(), Value::Object(Default::default()))
+            .ok_or_else(|| invalid("missing field `transitions`".to_string()))?;
+        let definition: MachineJson =
+            serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+        let mut base = parse_machine_json(&definition)?;
+        if base.read_only_input() {
+            return Err("read_only_input is not supported for amplitude machines".to_string());
+        }
+
+        let entries: BTreeMap<String, TargetsJson> =
+            serde_json::from_value(transitions).map_err(|e| invalid(e.to_string()))?;
+        let mut transitions: HashMap<TransitionKey, Vec<(TransitionTarget, Complex)>> =
+            HashMap::new();
+        for (key, targets) in entries {
+            let targets = match targets {
+                TargetsJson::One(target) => vec![target],
+                TargetsJson::Many(targets) => targets,
+            };
+            for TargetJson(state, symbol, direction, amplitude) in targets {
+                let (key, target) = formats::resolve_aliases(
+                    &definition.symbol_aliases,
+                    &key,
+                    &[state, symbol, direction],
+                );
+                let (from, to) =
+                    formats::parse_transition(&key, &target, Arc::make_mut(&mut base.symbols))?;
+                for state in [&from.0, &to.0] {
+                    if !base.states.contains(state) {
+                        return Err(format!("State {} not in states", state));
+                    }
+                }
+                for symbol in [from.1, to.1] {
+                    if !base.tape_alphabet.contains(&symbol) {
+                        return Err(format!(
+                            "Symbol {} not in tape alphabet",
+                            base.symbols.name(symbol)
+                        ));
+                    }
+                }
+                let amplitude = match amplitude {
+                    AmplitudeJson::Real(re) => Complex::new(re, 0.0),
+                    AmplitudeJson::Complex([re, im]) => Complex::new(re, im),
+                };
+                let targets = transitions.entry(from).or_default();
+                if targets.iter().any(|(target, _)| *target == to) {
+                    return Err(format!("Transition {} lists a target twice", key));
+                }
+                targets.push((to, amplitude));
+            }
+        }
+        Ok(AmplitudeMachine { base, transitions })
+    }
+
+    /// Transitions, each with every target it leads to and its amplitude
+    pub fn transitions(&self) -> &HashMap<TransitionKey, Vec<(TransitionTarget, Complex)>> {
+        &self.transitions
+    }
+
+    /// Symbol representing empty tape cells
+    pub fn blank_symbol(&self) -> char {
+        self.base.blank_symbol
+    }
+
+    /// Names of the machine's multi-character symbols
+    pub fn symbols(&self) -> &SymbolTable {
+        self.base.symbols()
+    }
+
+    /// Limits the machine recommends for its runs
+    pub fn run_defaults(&self) -> &RunDefaults {
+        self.base.run_defaults()
+    }
+}
+
+/// A running configuration: the state, the tape without the blank cells at
+/// its ends, and the head relative to that tape
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Configuration {
+    pub state: String,
+    pub tape: Vec<char>,
+    pub head: i32,
+}
+
+/// The running configurations of an amplitude machine, and the
+/// probabilities measured so far
+pub struct Superposition<'a> {
+    machine: &'a AmplitudeMachine,
+    configurations: HashMap<Configuration, Complex>,
+    max_configurations: usize,
+    steps: usize,
+    accepted: f64,
+    rejected: f64,
+    pruned: f64,
+    peak: usize,
+}
+
+impl<'a> Superposition<'a> {
+    /// The initial configuration on `input_string`, with amplitude 1
+    ///
+    /// At most `max_configurations` configurations are kept after each
+    /// step.
+    pub fn new(
+        machine: &'a AmplitudeMachine,
+        input_string: &str,
+        max_configurations: usize,
+    ) -> Result<Superposition<'a>, String> {
+        let base = &machine.base;
+        let input = base.map_input(input_string)?;
+        for symbol in &input {
+            if !base.alphabet.contains(symbol) {
+                return Err(format!(
+                    "Invalid input symbol: {}",
+                    base.symbols.name(*symbol)
+                ));
+            }
+        }
+        let tape = base.start_tape(&input)?;
+        let head = base.tape_setup.head() as i32;
+        let (state, tape, head) = canonical(base, &base.initial_state, &tape, head);
+        let mut superposition = Superposition {
+            machine,
+            configurations: HashMap::from([(Configuration { state, tape, head }, Complex::ONE)]),
+            max_configurations: max_configurations.max(1),
+            steps: 0,
+            accepted: 0.0,
+            rejected: 0.0,
+            pruned: 0.0,
+            peak: 1,
+        };
+        superposition.measure();
+        Ok(superposition)
+    }
+
+    /// Steps taken
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Probability of the runs that accepted
+    pub fn accept_probability(&self) -> f64 {
+        self.accepted
+    }
+
+    /// Probability of the runs that rejected
+    pub fn reject_probability(&self) -> f64 {
+        self.rejected
+    }
+
+    /// Probability of the configurations still running
+    pub fn running_probability(&self) -> f64 {
+        self.configurations.values().map(|a| a.norm_sqr()).sum()
+    }
+
+    /// Probability of the configurations dropped by the cutoff
+    pub fn pruned_probability(&self) -> f64 {
+        self.pruned
+    }
+
+    /// Most configurations running at once
+    pub fn peak_configurations(&self) -> usize {
+        self.peak
+    }
+
+    /// Whether no configuration is running
+    pub fn is_finished(&self) -> bool {
+        self.configurations.is_empty()
+    }
+
+    /// The running configurations with their amplitudes, most likely first
+    pub fn configurations(&self) -> Vec<(&Configuration, Complex)> {
+        let mut configurations: Vec<_> = self
+            .configurations
+            .iter()
+            .map(|(configuration, &amplitude)| (configuration, amplitude))
+            .collect();
+        configurations.sort_by(|a, b| {
+            b.1.norm_sqr()
+                .total_cmp(&a.1.norm_sqr())
+                .then_with(|| a.0.cmp(b.0))
+        });
+        configurations
+    }
+
+    /// Advance every running configuration by one step, then measure the
+    /// halted ones
+    pub fn step(&mut self) {
+        let base = &self.machine.base;
+        let mut next: HashMap<Configuration, Complex> = HashMap::new();
+        for (configuration, amplitude) in std::mem::take(&mut self.configurations) {
+            let Configuration {
+                state,
+                mut tape,
+                mut head,
+            } = configuration;
+            let symbol = read(&mut tape, &mut head, base.blank_symbol);
+            let Some(targets) = self.machine.transitions.get(&(state, symbol)) else {
+                if base.accept_on_halt {
+                    self.accepted += amplitude.norm_sqr();
+                } else {
+                    self.rejected += amplitude.norm_sqr();
+                }
+                continue;
+            };
+            for ((to_state, write_symbol, direction), weight) in targets {
+                let Some(direction) = direction_at(base, head, *direction) else {
+                    // Falling off a left-bounded tape
+                    self.rejected += (amplitude * *weight).norm_sqr();
+                    continue;
+                };
+                let mut tape = tape.clone();
+                let mut head = head;
+                write_and_move(&mut tape, &mut head, *write_symbol, direction);
+                wrap(base, &mut head);
+                let (state, tape, head) = canonical(base, to_state, &tape, head);
+                let sum = next.entry(Configuration { state, tape, head }).or_default();
+                *sum = *sum + amplitude * *weight;
+            }
+        }
+        next.retain(|_, amplitude| amplitude.norm_sqr() >= CANCELLED);
+        self.configurations = next;
+        self.steps += 1;
+        self.measure();
+        self.peak = self.peak.max(self.configurations.len());
+
+        if self.configurations.len() > self.max_configurations {
+            let dropped: Vec<Configuration> = self.configurations()[self.max_configurations..]
+                .iter()
+                .map(|(configuration, _)| (*configuration).clone())
+                .collect();
+            for configuration in dropped {
+                if let Some(amplitude) = self.configurations.remove(&configuration) {
+                    self.pruned += amplitude.norm_sqr();
+                }
+            }
+        }
+    }
+
+    /// Step until every configuration has halted or `max_steps` steps were
+    /// taken
+    pub fn run(&mut self, max_steps: usize) {
+        while !self.is_finished() && self.steps < max_steps {
+            self.step();
+        }
+    }
+
+    /// Move the configurations in accept or reject states out of the
+    /// superposition, adding up their probabilities
+    fn measure(&mut self) {
+        let base = &self.machine.base;
+        let (accepted, rejected) = (&mut self.accepted, &mut self.rejected);
+        self.configurations.retain(|configuration, amplitude| {
+            if base.accept_states.contains(&configuration.state) {
+                *accepted += amplitude.norm_sqr();
+                false
+            } else if base.reject_states.contains(&configuration.state) {
+                *rejected += amplitude.norm_sqr();
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
@@ -0,0 +1,561 @@
+//! Static analysis of machines, with a cache keyed by machine fingerprint
+//!
+//! [`analyze`] computes structural facts about a machine (reachability,
+//! implicit rejects, unused symbols) and [`lint`] turns them into warnings.
+//! [`minimize`] finds states that could be merged and [`metrics`] measures
+//! the shape of the transitions. Because machines used for grading are
+//! analyzed over and over, [`AnalysisCache`] stores all three on disk under
+//! the machine's [`fingerprint`], so repeated calls on an unchanged machine
+//! skip the work. The cache needs the `formats` feature.
+
+#[cfg(feature = "formats")]
+use crate::MachineJson;
+use crate::{Direction, SymbolTable, TransitionKey, TransitionTarget, TuringMachine};
+#[cfg(feature = "formats")]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(feature = "formats")]
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Structural facts about a machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Analysis {
+    pub state_count: usize,
+    pub transition_count: usize,
+    pub tape_symbol_count: usize,
+    /// States reachable from the initial state, sorted
+    pub reachable_states: Vec<String>,
+    /// States no run can ever enter, sorted
+    pub unreachable_states: Vec<String>,
+    /// Reachable states from which the machine can never halt, sorted
+    pub non_halting_states: Vec<String>,
+    /// `(state, symbol)` pairs of reachable working states without a
+    /// transition, which reject implicitly, or accept in a machine that
+    /// accepts by halting
+    pub implicit_rejects: Vec<(String, char)>,
+    /// Transitions out of accept or reject states, which are never taken
+    pub halting_state_transitions: Vec<(String, char)>,
+    /// Tape symbols other than the blank that no transition reads or writes
+    /// and that cannot appear in the input
+    pub unused_symbols: Vec<char>,
+}
+
+/// Analyze a machine
+pub fn analyze(machine: &TuringMachine) -> Analysis {
+    let is_halting = |state: &str| {
+        machine.accept_states.contains(state) || machine.reject_states.contains(state)
+    };
+
+    let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut predecessors: HashMap<&str, Vec<&str>> = HashMap::new();
+    for ((state, _), (new_state, _, _)) in &machine.transitions {
+        if is_halting(state) {
+            continue;
+        }
+        successors.entry(state).or_default().push(new_state);
+        predecessors.entry(new_state).or_default().push(state);
+    }
+
+    let reachable = search(&successors, [machine.initial_state.as_str()]);
+
+    let mut implicit_rejects = Vec::new();
+    for state in &reachable {
+        if is_halting(state) {
+            continue;
+        }
+        for symbol in &machine.tape_alphabet {
+            if !machine
+                .transitions
+                .contains_key(&(state.to_string(), *symbol))
+            {
+                implicit_rejects.push((state.to_string(), *symbol));
+            }
+        }
+    }
+    implicit_rejects.sort();
+
+    // A run can end in a halting state or on a missing transition
+    let halting_points = machine
+        .states
+        .iter()
+        .map(String::as_str)
+        .filter(|state| is_halting(state))
+        .chain(implicit_rejects.iter().map(|(state, _)| state.as_str()));
+    let can_halt = search(&predecessors, halting_points);
+
+    let mut halting_state_transitions: Vec<(String, char)> = machine
+        .transitions
+        .keys()
+        .filter(|(state, _)| is_halting(state))
+        .cloned()
+        .collect();
+    halting_state_transitions.sort();
+
+    let mut used: HashSet<char> = machine.alphabet.clone();
+    used.insert(machine.blank_symbol);
+    for ((_, read), (_, write, _)) in &machine.transitions {
+        used.insert(*read);
+        used.insert(*write);
+    }
+    let mut unused_symbols: Vec<char> = machine.tape_alphabet.difference(&used).copied().collect();
+    unused_symbols.sort();
+
+    let states_where = |keep: &dyn Fn(&str) -> bool| -> Vec<String> {
+        let mut states: Vec<String> = machine.states.iter().filter(|s| keep(s)).cloned().collect();
+        states.sort();
+        states
+    };
+    Analysis {
+        state_count: machine.states.len(),
+        transition_count: machine.transitions.len(),
+        tape_symbol_count: machine.tape_alphabet.len(),
+        reachable_states: states_where(&|s| reachable.contains(s)),
+        unreachable_states: states_where(&|s| !reachable.contains(s)),
+        non_halting_states: states_where(&|s| reachable.contains(s) && !can_halt.contains(s)),
+        implicit_rejects,
+        halting_state_transitions,
+        unused_symbols,
+    }
+}
+
+impl Analysis {
+    /// Bring the analysis up to date after the transition at `key` changed
+    ///
+    /// `previous` is the transition at `key` before the edit, as returned by
+    /// [`TuringMachine::set_transition`] or
+    /// [`TuringMachine::remove_transition`]. Edits that cannot change which
+    /// states are reachable or can halt (transitions out of unreachable or
+    /// halting states, or changes to the symbol written or the head move) are
+    /// applied locally; other edits fall back to [`analyze`].
+    pub fn update(
+        &mut self,
+        machine: &TuringMachine,
+        key: &TransitionKey,
+        previous: Option<&TransitionTarget>,
+    ) {
+        let (state, symbol) = key;
+        let current = machine.transitions.get(key);
+        let halting =
+            machine.accept_states.contains(state) || machine.reject_states.contains(state);
+        let reachable = self.reachable_states.binary_search(state).is_ok();
+        let same_successor = match (previous, current) {
+            (Some(before), Some(after)) => before.0 == after.0,
+            (None, None) => true,
+            _ => false,
+        };
+        if reachable && !halting && !same_successor {
+            *self = analyze(machine);
+            return;
+        }
+
+        self.transition_count = machine.transitions.len();
+        if halting {
+            let position = self.halting_state_transitions.binary_search(key);
+            match (current, position) {
+                (Some(_), Err(index)) => self.halting_state_transitions.insert(index, key.clone()),
+                (None, Ok(index)) => {
+                    self.halting_state_transitions.remove(index);
+                }
+                _ => {}
+            }
+        }
+
+        // Only the symbols of the edited transition can change their usage
+        let mut symbols = vec![*symbol];
+        symbols.extend(previous.map(|target| target.1));
+        symbols.extend(current.map(|target| target.1));
+        for symbol in symbols {
+            let used = symbol == machine.blank_symbol
+                || machine.alphabet.contains(&symbol)
+                || machine
+                    .transitions
+                    .iter()
+                    .any(|((_, read), (_, write, _))| *read == symbol || *write == symbol);
+            match (used, self.unused_symbols.binary_search(&symbol)) {
+                (true, Ok(index)) => {
+                    self.unused_symbols.remove(index);
+                }
+                (false, Err(index)) => self.unused_symbols.insert(index, symbol),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// States reachable from `start` along `edges`, including `start` itself
+fn search<'a>(
+    edges: &HashMap<&'a str, Vec<&'a str>>,
+    start: impl IntoIterator<Item = &'a str>,
+) -> HashSet<&'a str> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<&str> = VecDeque::new();
+    for state in start {
+        if seen.insert(state) {
+            queue.push_back(state);
+        }
+    }
+    while let Some(state) = queue.pop_front() {
+        for next in edges.get(state).into_iter().flatten() {
+            if seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+/// Warnings about likely mistakes, derived from an analysis
+///
+/// Symbols are written with their names from `symbols`.
+pub fn lint(analysis: &Analysis, symbols: &SymbolTable) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for state in &analysis.unreachable_states {
+        warnings.push(format!("state '{}' is unreachable", state));
+    }
+    for state in &analysis.non_halting_states {
+        warnings.push(format!("state '{}' can never halt", state));
+    }
+    for (state, symbol) in &analysis.halting_state_transitions {
+        warnings.push(format!(
+            "transition '{},{}' leaves a halting state and is never taken",
+            state,
+            symbols.name(*symbol)
+        ));
+    }
+    for symbol in &analysis.unused_symbols {
+        warnings.push(format!(
+            "tape symbol '{}' is never used",
+            symbols.name(*symbol)
+        ));
+    }
+    warnings
+}
+
+/// States of a machine that behave alike and could be merged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Minimization {
+    /// Groups of two or more equivalent states, each sorted, in order of
+    /// their first state
+    pub equivalent_states: Vec<Vec<String>>,
+    /// Reachable states left once equivalent states are merged
+    pub minimal_state_count: usize,
+}
+
+/// Find the states of a machine that could be merged
+///
+/// Accept states are alike, as are reject states, since their transitions
+/// are never taken. Working states are alike if on every tape symbol they
+/// write the same symbol, move the same way and go to alike states, or both
+/// have no transition. Classes of alike states are split until they no
+/// longer change.
+pub fn minimize(machine: &TuringMachine) -> Minimization {
+    let kind = |state: &str| {
+        if machine.accept_states.contains(state) {
+            0
+        } else if machine.reject_states.contains(state) {
+            1
+        } else {
+            2
+        }
+    };
+    let mut states: Vec<&str> = machine.states.iter().map(String::as_str).collect();
+    states.sort();
+    let mut symbols: Vec<char> = machine.tape_alphabet.iter().copied().collect();
+    symbols.sort();
+
+    let mut class: HashMap<&str, usize> =
+        states.iter().map(|state| (*state, kind(state))).collect();
+    let mut class_count = class.values().collect::<HashSet<_>>().len();
+    loop {
+        // A state's class and, in terms of classes, what it does on each symbol
+        let mut signatures = HashMap::new();
+        let mut refined = HashMap::new();
+        for state in &states {
+            let moves: Vec<Option<(Option<usize>, char, Direction)>> = if kind(state) == 2 {
+                symbols
+                    .iter()
+                    .map(|symbol| {
+                        let target = machine.transitions.get(&(state.to_string(), *symbol));
+                        target.map(|(next, write, direction)| {
+                            (class.get(next.as_str()).copied(), *write, *direction)
+                        })
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let next_class = signatures.len();
+            let id = *signatures
+                .entry((class[state], moves))
+                .or_insert(next_class);
+            refined.insert(*state, id);
+        }
+        let stable = signatures.len() == class_count;
+        class_count = signatures.len();
+        class = refined;
+        if stable {
+            break;
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+    for state in &states {
+        groups
+            .entry(class[state])
+            .or_default()
+            .push(state.to_string());
+    }
+    let mut equivalent_states: Vec<Vec<String>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    equivalent_states.sort();
+
+    let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+    for ((state, _), (new_state, _, _)) in &machine.transitions {
+        if kind(state) == 2 {
+            successors.entry(state).or_default().push(new_state);
+        }
+    }
+    let reachable = search(&successors, [machine.initial_state.as_str()]);
+    let minimal_state_count = reachable
+        .iter()
+        .filter_map(|state| class.get(state))
+        .collect::<HashSet<_>>()
+        .len();
+    Minimization {
+        equivalent_states,
+        minimal_state_count,
+    }
+}
+
+/// Measures of the shape of a machine's transitions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// Transitions moving the head left, right, or not at all
+    pub moves_left: usize,
+    pub moves_right: usize,
+    pub stays: usize,
+    /// Transitions writing a symbol other than the one they read
+    pub rewrites: usize,
+    /// Transitions staying in their state
+    pub self_loops: usize,
+    /// Most transitions out of a single state
+    pub max_out_degree: usize,
+    /// Distinct pairs of states joined by a transition
+    pub state_edges: usize,
+    /// McCabe complexity of the state graph: its edges minus its states,
+    /// plus two
+    pub cyclomatic_complexity: usize,
+}
+
+/// Measure a machine's transitions
+pub fn metrics(machine: &TuringMachine) -> Metrics {
+    let mut metrics = Metrics {
+        moves_left: 0,
+        moves_right: 0,
+        stays: 0,
+        rewrites: 0,
+        self_loops: 0,
+        max_out_degree: 0,
+        state_edges: 0,
+        cyclomatic_complexity: 0,
+    };
+    let mut out_degree: HashMap<&str, usize> = HashMap::new();
+    let mut edges: HashSet<(&str, &str)> = HashSet::new();
+    for ((state, read), (new_state, write, direction)) in &machine.transitions {
+        match direction {
+            Direction::L => metrics.moves_left += 1,
+            Direction::R => metrics.moves_right += 1,
+            Direction::S => metrics.stays += 1,
+        }
+        metrics.rewrites += usize::from(read != write);
+        metrics.self_loops += usize::from(state == new_state);
+        *out_degree.entry(state).or_default() += 1;
+        edges.insert((state, new_state));
+    }
+    metrics.max_out_degree = out_degree.into_values().max().unwrap_or(0);
+    metrics.state_edges = edges.len();
+    metrics.cyclomatic_complexity = (edges.len() + 2).saturating_sub(machine.states.len());
+    metrics
+}
+
+/// A stable identifier of a machine's definition
+///
+/// Machines that differ only in the order of their declarations have the
+/// same fingerprint. The crate version is included, so cached results are
+/// recomputed after an upgrade.
+#[cfg(feature = "formats")]
+pub fn fingerprint(machine: &TuringMachine) -> String {
+    // The definition is written with sorted sets and transitions
+    let canonical = serde_json::to_string(&MachineJson::from(machine)).unwrap_or_default();
+    // 64-bit FNV-1a, which unlike std's hasher is stable across builds
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in env!("CARGO_PKG_VERSION")
+        .bytes()
+        .chain([0])
+        .chain(canonical.bytes())
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// On-disk cache of analyses, minimizations and metrics, one JSON file per
+/// fingerprint and kind of result
+#[cfg(feature = "formats")]
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+#[cfg(feature = "formats")]
+impl AnalysisCache {
+    /// Use `dir` as the cache directory; it is created on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        AnalysisCache { dir: dir.into() }
+    }
+
+    /// `$XDG_CACHE_HOME/turing-machine/analysis`, or `~/.cache/...` if unset
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CACHE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => Path::new(&env::var_os("HOME")?).join(".cache"),
+        };
+        Some(base.join("turing-machine").join("analysis"))
+    }
+
+    /// The cached [`analyze`] result for a machine
+    pub fn analyze(&self, machine: &TuringMachine) -> Result<Analysis, String> {
+        self.get_or_compute(machine, "analysis", analyze)
+    }
+
+    /// The cached [`minimize`] result for a machine
+    pub fn minimize(&self, machine: &TuringMachine) -> Result<Minimization, String> {
+        self.get_or_compute(machine, "minimization", minimize)
+    }
+
+    /// The cached [`metrics`] of a machine
+    pub fn metrics(&self, machine: &TuringMachine) -> Result<Metrics, String> {
+        self.get_or_compute(machine, "metrics", metrics)
+    }
+
+    /// Return the cached `kind` of result for a machine, computing and
+    /// storing it if there is none
+    ///
+    /// Unreadable cache entries are recomputed; failing to store a result is
+    /// an error.
+    fn get_or_compute<T: Serialize + DeserializeOwned>(
+        &self,
+        machine: &TuringMachine,
+        kind: &str,
+        compute: fn(&TuringMachine) -> T,
+    ) -> Result<T, String> {
+        let path = self
+            .dir
+            .join(format!("{}.{}.json", fingerprint(machine), kind));
+        let cached = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok());
+        if let Some(result) = cached {
+            return Ok(result);
+        }
+
+        let result = compute(machine);
+        let text = serde_json::to_string(&result).map_err(|e| e.to_string())?;
+        fs::create_dir_all(&self.dir)
+            .and_then(|()| fs::write(&path, text))
+            .map_err(|e| format!("Cache error: {}: {}", path.display(), e))?;
+        Ok(result)
+    }
+
+    /// Remove all cached results, returning how many there were
+    pub fn clear(&self) -> Result<usize, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("Cache error: {}: {}", self.dir.display(), e)),
+        };
+        let mut removed = 0;
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path)
+                    .map_err(|e| format!("Cache error: {}: {}", path.display(), e))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{parse_machine_definition, parse_machine_json};
+
+    /// Accepts inputs of one symbol through two states that do the same
+    const TWINS: &str = r#"{
+        "states": ["q0", "q1", "q2", "accept", "reject"],
+        "alphabet": ["0", "1"],
+        "tape_alphabet": ["0", "1", "_"],
+        "initial_state": "q0",
+        "accept_states": ["accept"],
+        "reject_states": ["reject"],
+        "transitions": {
+            "q0,0": ["q1", "0", "R"],
+            "q0,1": ["q2", "1", "R"],
+            "q1,_": ["accept", "_", "S"],
+            "q2,_": ["accept", "_", "S"]
+        }
+    }"#;
+
+    fn load(text: &str) -> TuringMachine {
+        parse_machine_json(&parse_machine_definition(text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn merges_states_that_do_the_same() {
+        let minimization = minimize(&load(TWINS));
+        assert_eq!(minimization.equivalent_states, [["q1", "q2"]]);
+        // The reject state is never reached
+        assert_eq!(minimization.minimal_state_count, 3);
+
+        let palindrome = load(include_str!("../examples/palindrome.json"));
+        assert!(minimize(&palindrome).equivalent_states.is_empty());
+    }
+
+    #[test]
+    fn measures_transitions() {
+        let metrics = metrics(&load(TWINS));
+        assert_eq!((metrics.moves_right, metrics.stays), (2, 2));
+        assert_eq!((metrics.rewrites, metrics.self_loops), (0, 0));
+        assert_eq!((metrics.max_out_degree, metrics.state_edges), (2, 4));
+        assert_eq!(metrics.cyclomatic_complexity, 1);
+    }
+
+    #[test]
+    fn caches_every_kind_of_result() {
+        let dir = std::env::temp_dir().join(format!("turing-analysis-{}", std::process::id()));
+        let cache = AnalysisCache::new(&dir);
+        let machine = load(TWINS);
+        assert_eq!(cache.analyze(&machine).unwrap(), analyze(&machine));
+        assert_eq!(cache.minimize(&machine).unwrap(), minimize(&machine));
+        assert_eq!(cache.metrics(&machine).unwrap(), metrics(&machine));
+        // Cached results are read back rather than recomputed
+        let path = dir.join(format!("{}.minimization.json", fingerprint(&machine)));
+        fs::write(
+            &path,
+            r#"{"equivalent_states": [], "minimal_state_count": 7}"#,
+        )
+        .unwrap();
+        assert_eq!(cache.minimize(&machine).unwrap().minimal_state_count, 7);
+        assert_eq!(cache.clear().unwrap(), 3);
+        assert_eq!(cache.clear().unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
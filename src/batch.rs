@@ -0,0 +1,46 @@
+//! Running a machine on many inputs at once
+//!
+//! [`run`] runs a machine on every input of a batch, each run on its own,
+//! and returns the results in input order. With the `parallel` feature the
+//! runs are spread over all CPU cores (as many threads as
+//! `RAYON_NUM_THREADS` asks for, if set); without it they are taken one
+//! after the other. Either way a batch gives the same results in the same
+//! order, since no run depends on another.
+
+use crate::executor::{Executor, RunConfig};
+use crate::{ExecutionResult, TuringMachine};
+use std::sync::atomic::AtomicBool;
+
+/// Run `machine` on every input within the limits of `config`, returning
+/// the result of each in input order
+///
+/// Setting `interrupt` stops every run still going, as with
+/// [`Executor::interrupt_on`].
+pub fn run<S: AsRef<str> + Sync>(
+    machine: &TuringMachine,
+    inputs: &[S],
+    config: &RunConfig,
+    interrupt: Option<&AtomicBool>,
+) -> Vec<Result<ExecutionResult, String>> {
+    map(inputs, |input| {
+        let mut executor = Executor::new(machine);
+        if let Some(flag) = interrupt {
+            executor = executor.interrupt_on(flag);
+        }
+        executor.run_with(input.as_ref(), config)
+    })
+}
+
+/// Apply `f` to every item, on all CPU cores with the `parallel` feature,
+/// returning the results in item order
+pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
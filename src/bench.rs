@@ -0,0 +1,195 @@
+//! Benchmarks of machines and of the executor
+//!
+//! [`bench()`] runs a machine on an input over and over and measures every
+//! run: its wall time, from which the steps taken per second follow, how
+//! far the tape grew, and the heap allocations it made, which a program
+//! counts by installing a [`CountingAllocator`] as its global allocator:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+//! ```
+//!
+//! Runs take no observers, so they are as fast as with
+//! [`Executor::run_with`](crate::executor::Executor::run_with) and take the
+//! same steps. One run before the measured ones warms up caches and the
+//! allocator.
+
+use crate::executor::{self, RunConfig};
+use crate::{ExecutionResult, TuringMachine};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Measurements of the runs of a machine on one input
+#[derive(Debug)]
+pub struct Bench {
+    /// The last run, interrupted if Ctrl+C cut the benchmark short
+    pub result: ExecutionResult,
+    /// Wall time of every measured run, in the order they were taken
+    pub times: Vec<Duration>,
+    /// Tape cells at the start of a run, the input included
+    pub start_cells: usize,
+    /// Tape cells at the end of a run
+    pub end_cells: usize,
+    /// Heap allocations of the measured run that made fewest, if counted
+    pub allocations: Option<Allocations>,
+}
+
+impl Bench {
+    /// The spread of the wall times, `None` if no run was measured
+    pub fn wall_time(&self) -> Option<Distribution> {
+        Distribution::of(&self.times)
+    }
+
+    /// Steps taken per second by the run of median wall time
+    pub fn steps_per_second(&self) -> Option<f64> {
+        let median = self.wall_time()?.median.as_secs_f64();
+        (median > 0.0).then(|| self.result.steps as f64 / median)
+    }
+}
+
+/// The spread of a set of wall times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Distribution {
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    /// The time 90% of the runs took at most
+    pub p90: Duration,
+    pub max: Duration,
+}
+
+impl Distribution {
+    /// The spread of `times`, `None` if there are none
+    pub fn of(times: &[Duration]) -> Option<Self> {
+        if times.is_empty() {
+            return None;
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort();
+        let rank = |fraction: f64| sorted[((sorted.len() - 1) as f64 * fraction).round() as usize];
+        Some(Distribution {
+            min: sorted[0],
+            median: rank(0.5),
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p90: rank(0.9),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Heap allocations, and the bytes they asked for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Allocations {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Global allocator counting the allocations made through it, passing them
+/// on to the system allocator
+///
+/// Growing an allocation in place or by moving it counts as one more
+/// allocation of its new size.
+#[derive(Debug, Default)]
+pub struct CountingAllocator {
+    count: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+impl CountingAllocator {
+    pub const fn new() -> Self {
+        CountingAllocator {
+            count: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Allocations made since the program started
+    pub fn allocations(&self) -> Allocations {
+        Allocations {
+            count: self.count.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record(&self, size: usize) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: every call is passed on unchanged to the system allocator
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.record(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Run `machine` on `input_string` `iterations` times within the limits of
+/// `config`, after one run that is not measured, counting allocations
+/// with `allocator` if given
+///
+/// Setting `interrupt` stops the run under way, as with
+/// [`Executor::interrupt_on`](crate::executor::Executor::interrupt_on), and
+/// ends the benchmark with the runs measured so far.
+pub fn bench(
+    machine: &TuringMachine,
+    input_string: &str,
+    config: &RunConfig,
+    iterations: usize,
+    allocator: Option<&CountingAllocator>,
+    interrupt: Option<&AtomicBool>,
+) -> Result<Bench, String> {
+    let (result, start_cells, end_cells) =
+        executor::run_measuring_tape(machine, input_string, config, interrupt)?;
+    let mut bench = Bench {
+        result,
+        times: Vec::with_capacity(iterations),
+        start_cells,
+        end_cells,
+        allocations: None,
+    };
+    for _ in 0..iterations {
+        if bench.result.interrupted {
+            break;
+        }
+        let before = allocator.map(CountingAllocator::allocations);
+        let started = Instant::now();
+        let (result, _, _) =
+            executor::run_measuring_tape(machine, input_string, config, interrupt)?;
+        let elapsed = started.elapsed();
+        let after = allocator.map(CountingAllocator::allocations);
+        bench.result = result;
+        if bench.result.interrupted {
+            break;
+        }
+        bench.times.push(elapsed);
+        if let (Some(before), Some(after)) = (before, after) {
+            let made = Allocations {
+                count: after.count - before.count,
+                bytes: after.bytes - before.bytes,
+            };
+            bench.allocations = Some(match bench.allocations {
+                Some(fewest) if fewest.count <= made.count => fewest,
+                _ => made,
+            });
+        }
+    }
+    Ok(bench)
+}
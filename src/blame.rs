@@ -0,0 +1,107 @@
+//! Which step gave a tape cell its value
+//!
+//! [`Blame`] follows a run as a [`StepObserver`] and remembers every step
+//! that changed a cell. Asked about a cell afterwards, it returns the last
+//! of them up to a given step: the transition responsible for what the cell
+//! holds, and the step a debugger can jump to in order to watch it happen.
+//!
+//! Cells are numbered as in the tape at the step asked about. The tape grows
+//! at the front when the head moves past its left end, which shifts those
+//! numbers, so writes are kept under the cell's index in the initial tape
+//! and translated on lookup.
+
+use crate::executor::{StepEvent, StepObserver};
+use crate::{Direction, TuringMachine};
+use std::collections::HashMap;
+
+/// A step that changed a cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellWrite {
+    pub step: usize,
+    pub from_state: String,
+    /// The symbol the cell held before
+    pub read_symbol: char,
+    pub to_state: String,
+    pub write_symbol: char,
+    pub direction: Direction,
+}
+
+/// Observer recording which steps changed which cells
+///
+/// Transitions writing back the symbol they read are not recorded, since
+/// they leave the cell as it was.
+pub struct Blame {
+    /// Index of the head in the initial tape, negative left of it
+    head: i64,
+    tape_length: Option<usize>,
+    /// Cells added at the front of the tape, from the step given on
+    front: Vec<(usize, i64)>,
+    /// The index written in the current step, and whether it changed
+    pending: Option<(usize, bool)>,
+    /// Changes of every cell, by index in the initial tape, oldest first
+    writes: HashMap<i64, Vec<CellWrite>>,
+}
+
+impl Blame {
+    /// Follow a run of `machine`
+    pub fn new(machine: &TuringMachine) -> Self {
+        Blame {
+            head: machine.tape_setup().head() as i64,
+            tape_length: machine.tape_length(),
+            front: vec![(0, 0)],
+            pending: None,
+            writes: HashMap::new(),
+        }
+    }
+
+    /// The last change of the cell at `index`, numbered as in the tape after
+    /// `step`, made by that step or an earlier one
+    ///
+    /// `None` means the cell still held the symbol it started with.
+    pub fn last_write(&self, index: usize, step: usize) -> Option<&CellWrite> {
+        let front = self
+            .front
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= step)
+            .map_or(0, |&(_, front)| front);
+        self.writes
+            .get(&(index as i64 - front))?
+            .iter()
+            .rev()
+            .find(|write| write.step <= step)
+    }
+}
+
+impl StepObserver for Blame {
+    fn on_tape_write(&mut self, position: usize, old_symbol: char, new_symbol: char) {
+        self.pending = Some((position, old_symbol != new_symbol));
+    }
+
+    fn on_step(&mut self, event: &StepEvent) {
+        if let Some((position, changed)) = self.pending.take() {
+            let front = position as i64 - self.head;
+            if self.front.last().is_some_and(|&(_, last)| last != front) {
+                self.front.push((event.step, front));
+            }
+            if changed {
+                self.writes.entry(self.head).or_default().push(CellWrite {
+                    step: event.step,
+                    from_state: event.from_state.to_string(),
+                    read_symbol: event.read_symbol,
+                    to_state: event.to_state.to_string(),
+                    write_symbol: event.write_symbol,
+                    direction: event.direction,
+                });
+            }
+        }
+        match event.direction {
+            Direction::L => self.head -= 1,
+            Direction::R => self.head += 1,
+            Direction::S => {}
+        }
+        if let Some(length) = self.tape_length {
+            self.head = self.head.rem_euclid(length as i64);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Fluent construction of Turing machines in code
+
+use crate::executor::RunDefaults;
+use crate::info::Metadata;
+use crate::{
+    Direction, InputNormalization, LeftEdge, Output, SymbolTable, TapeSetup, TuringMachine,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Builder for [`TuringMachine`]
+///
+/// States and symbols used by transitions are registered automatically, so a
+/// machine can usually be described by its initial, accept and reject states,
+/// its input alphabet and its transitions:
+///
+/// ```
+/// use turing_machine::{Direction, TuringMachine};
+///
+/// // Accepts strings of 0s
+/// let machine = TuringMachine::builder()
+///     .initial("q0")
+///     .accept("qa")
+///     .input_symbols(['0', '1'])
+///     .transition("q0", '0', "q0", '0', Direction::R)
+///     .transition("q0", '_', "qa", '_', Direction::R)
+///     .build()?;
+///
+/// assert_eq!(machine.execute("000", 100)?.accepts, Some(true));
+/// assert_eq!(machine.execute("010", 100)?.accepts, Some(false));
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct TuringMachineBuilder {
+    states: HashSet<String>,
+    alphabet: HashSet<char>,
+    tape_alphabet: HashSet<char>,
+    transitions: HashMap<(String, char), (String, char, Direction)>,
+    initial_state: Option<String>,
+    accept_states: HashSet<String>,
+    reject_states: HashSet<String>,
+    blank_symbol: Option<char>,
+    read_only_input: bool,
+    accept_on_halt: bool,
+    input_normalization: InputNormalization,
+    left_edge: Option<LeftEdge>,
+    tape_length: Option<usize>,
+    tape_setup: TapeSetup,
+    output: Option<Output>,
+    run_defaults: RunDefaults,
+    metadata: Metadata,
+}
+
+impl TuringMachineBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a state
+    pub fn state(mut self, name: &str) -> Self {
+        self.states.insert(name.to_string());
+        self
+    }
+
+    /// Set the initial state, adding it to the states
+    pub fn initial(mut self, name: &str) -> Self {
+        self.initial_state = Some(name.to_string());
+        self.state(name)
+    }
+
+    /// Add an accepting state
+    pub fn accept(mut self, name: &str) -> Self {
+        self.accept_states.insert(name.to_string());
+        self.state(name)
+    }
+
+    /// Add a rejecting state
+    pub fn reject(mut self, name: &str) -> Self {
+        self.reject_states.insert(name.to_string());
+        self.state(name)
+    }
+
+    /// Add an input symbol, which is also a tape symbol
+    pub fn input_symbol(mut self, symbol: char) -> Self {
+        self.alphabet.insert(symbol);
+        self.tape_symbol(symbol)
+    }
+
+    /// Add several input symbols at once
+    pub fn input_symbols(self, symbols: impl IntoIterator<Item = char>) -> Self {
+        symbols
+            .into_iter()
+            .fold(self, |builder, symbol| builder.input_symbol(symbol))
+    }
+
+    /// Add a tape symbol that may not appear in the input
+    pub fn tape_symbol(mut self, symbol: char) -> Self {
+        self.tape_alphabet.insert(symbol);
+        self
+    }
+
+    /// Set the blank symbol (default: `_`)
+    pub fn blank(mut self, symbol: char) -> Self {
+        self.blank_symbol = Some(symbol);
+        self.tape_symbol(symbol)
+    }
+
+    /// Make the input cells read-only, see
+    /// [`TuringMachine::set_read_only_input`]
+    pub fn read_only_input(mut self) -> Self {
+        self.read_only_input = true;
+        self
+    }
+
+    /// Accept whenever the machine halts outside a reject state, see
+    /// [`TuringMachine::set_accept_on_halt`]
+    pub fn accept_on_halt(mut self) -> Self {
+        self.accept_on_halt = true;
+        self
+    }
+
+    /// Clean up inputs before they are run, see
+    /// [`TuringMachine::set_input_normalization`]
+    pub fn normalize_input(mut self, normalization: InputNormalization) -> Self {
+        self.input_normalization = normalization;
+        self
+    }
+
+    /// Bound the tape on the left, see [`TuringMachine::set_left_edge`]
+    pub fn left_edge(mut self, policy: LeftEdge) -> Self {
+        self.left_edge = Some(policy);
+        self
+    }
+
+    /// Make the tape a ring of `length` cells, see
+    /// [`TuringMachine::set_tape_length`]
+    pub fn tape_length(mut self, length: usize) -> Self {
+        self.tape_length = Some(length);
+        self
+    }
+
+    /// Prepare the tape and place the input and head, see
+    /// [`TuringMachine::set_tape_setup`]
+    ///
+    /// The tape symbols used are registered.
+    pub fn tape_setup(mut self, setup: TapeSetup) -> Self {
+        self.tape_alphabet.extend(&setup.contents);
+        self.tape_setup = setup;
+        self
+    }
+
+    /// Compute a function, see [`TuringMachine::set_output`]
+    ///
+    /// Output markers are registered as tape symbols.
+    pub fn output(mut self, output: Output) -> Self {
+        if let Output::Between(start, end) = output {
+            self.tape_alphabet.extend([start, end]);
+        }
+        self.output = Some(output);
+        self
+    }
+
+    /// Recommend limits for runs, see [`TuringMachine::set_run_defaults`]
+    pub fn run_defaults(mut self, defaults: RunDefaults) -> Self {
+        self.run_defaults = defaults;
+        self
+    }
+
+    /// Describe the machine, see [`TuringMachine::set_metadata`]
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Add a transition, registering its states and tape symbols
+    ///
+    /// A later transition for the same state and symbol replaces an earlier one.
+    pub fn transition(
+        self,
+        state: &str,
+        read: char,
+        new_state: &str,
+        write: char,
+        direction: Direction,
+    ) -> Self {
+        let mut builder = self
+            .state(state)
+            .state(new_state)
+            .tape_symbol(read)
+            .tape_symbol(write);
+        builder.transitions.insert(
+            (state.to_string(), read),
+            (new_state.to_string(), write, direction),
+        );
+        builder
+    }
+
+    /// Validate and build the machine
+    pub fn build(self) -> Result<TuringMachine, String> {
+        let initial_state = self
+            .initial_state
+            .ok_or_else(|| "No initial state set".to_string())?;
+        let blank_symbol = self.blank_symbol.unwrap_or('_');
+        let mut tape_alphabet = self.tape_alphabet;
+        tape_alphabet.insert(blank_symbol);
+        if let Some(symbol) = tape_alphabet.iter().find(|s| SymbolTable::is_reserved(**s)) {
+            return Err(format!(
+                "Symbol U+{:04X} is reserved for named symbols",
+                *symbol as u32
+            ));
+        }
+
+        let mut machine = TuringMachine::new(
+            self.states,
+            self.alphabet,
+            tape_alphabet,
+            self.transitions,
+            initial_state,
+            self.accept_states,
+            self.reject_states,
+            blank_symbol,
+        )?;
+        machine.set_read_only_input(self.read_only_input);
+        machine.set_accept_on_halt(self.accept_on_halt);
+        machine.set_input_normalization(self.input_normalization);
+        machine.set_left_edge(self.left_edge);
+        machine.set_tape_length(self.tape_length)?;
+        machine.set_tape_setup(self.tape_setup)?;
+        machine.set_output(self.output)?;
+        machine.set_run_defaults(self.run_defaults);
+        machine.set_metadata(self.metadata);
+        Ok(machine)
+    }
+}
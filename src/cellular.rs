@@ -0,0 +1,122 @@
+//! Elementary cellular automata
+//!
+//! An elementary cellular automaton is a row of cells, each 0 or 1, updated
+//! all at once: a cell's next value depends on itself and its two
+//! neighbours. The eight possible neighbourhoods, read as the binary numbers
+//! `111` down to `000`, pick the bits of the rule number from the highest
+//! down, so Rule 110 (`01101110`) turns `110` into 1 and `111` into 0.
+//! Rule 110 is Turing complete.
+//!
+//! The row has a fixed width and wraps around, so the leftmost and
+//! rightmost cells are neighbours. [`ascii_diagram`] and [`svg_diagram`]
+//! draw the generations one below the other, the usual space-time diagram.
+
+use std::fmt::Write;
+
+/// Cell size of an SVG diagram, in pixels
+const SVG_CELL: usize = 4;
+
+/// An elementary cellular automaton rule, numbered as by Wolfram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule(pub u8);
+
+impl Rule {
+    /// Parse a rule number from 0 to 255
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        text.trim()
+            .parse()
+            .map(Rule)
+            .map_err(|_| format!("Invalid rule: {} (expected a number from 0 to 255)", text))
+    }
+
+    /// The next value of a cell with the given neighbours
+    pub fn apply(self, left: bool, cell: bool, right: bool) -> bool {
+        let neighbourhood = (left as u8) << 2 | (cell as u8) << 1 | right as u8;
+        self.0 >> neighbourhood & 1 == 1
+    }
+
+    /// The next generation of `cells`, wrapping around at the ends
+    pub fn step(self, cells: &[bool]) -> Vec<bool> {
+        let width = cells.len();
+        (0..width)
+            .map(|i| {
+                let left = cells[(i + width - 1) % width];
+                let right = cells[(i + 1) % width];
+                self.apply(left, cells[i], right)
+            })
+            .collect()
+    }
+
+    /// `initial` followed by `generations` more generations
+    pub fn run(self, initial: Vec<bool>, generations: usize) -> Vec<Vec<bool>> {
+        let mut history = vec![initial];
+        for _ in 0..generations {
+            let next = self.step(&history[history.len() - 1]);
+            history.push(next);
+        }
+        history
+    }
+}
+
+/// A row of `width` cells holding `pattern` in the middle
+///
+/// The pattern is written with `1` or `#` for live cells and `0`, `.` or
+/// `_` for dead ones; an empty pattern is a single live cell. The row is
+/// widened to fit the pattern.
+pub fn initial_row(pattern: &str, width: usize) -> Result<Vec<bool>, String> {
+    let cells = if pattern.is_empty() {
+        vec![true]
+    } else {
+        pattern
+            .chars()
+            .map(|c| match c {
+                '1' | '#' => Ok(true),
+                '0' | '.' | '_' => Ok(false),
+                c => Err(format!("Invalid cell: {} (expected 0 or 1)", c)),
+            })
+            .collect::<Result<Vec<bool>, String>>()?
+    };
+    let width = width.max(cells.len());
+    let start = (width - cells.len()) / 2;
+    let mut row = vec![false; width];
+    row[start..start + cells.len()].copy_from_slice(&cells);
+    Ok(row)
+}
+
+/// Draw the generations as text, one line each, live cells as `█`
+pub fn ascii_diagram(history: &[Vec<bool>]) -> String {
+    let mut text = String::new();
+    for row in history {
+        let line: String = row.iter().map(|&c| if c { '█' } else { ' ' }).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// Draw the generations as an SVG image, one row of squares each
+pub fn svg_diagram(history: &[Vec<bool>]) -> String {
+    let width = history.first().map_or(0, Vec::len) * SVG_CELL;
+    let height = history.len() * SVG_CELL;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    for (y, row) in history.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, &c)| c) {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="black"/>"#,
+                x * SVG_CELL,
+                y * SVG_CELL,
+                s = SVG_CELL
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,337 @@
+//! User defaults for the command line program
+//!
+//! Defaults are read, in increasing order of precedence, from the config file
+//! `~/.config/turing-machine/config.toml` (or `$XDG_CONFIG_HOME/turing-machine`,
+//! or the file named by `TURING_MACHINE_CONFIG`), from `TURING_MACHINE_*`
+//! environment variables, from the `defaults` of the machine being run (see
+//! [`Config::with_machine_defaults`]), and from command line flags:
+//!
+//! ```toml
+//! max_steps = 50000           # or "unlimited"
+//! max_memory = "512M"
+//! max_cells = 1_000_000
+//! max_head_overshoot = 100
+//! min_head = 0
+//! timeout = "30s"
+//! examples_dir = "/home/me/machines"
+//! color = "never"
+//! tape_width = 30
+//! snapshot_every = 1000
+//! output_mode = "tape_trimmed"
+//! ```
+
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use turing_machine::executor::{
+    self, deserialize_size, parse_duration, parse_max_steps, parse_size, OutputMode, RunConfig,
+    RunDefaults, DEFAULT_MAX_STEPS,
+};
+
+/// Widest tape shown in visual mode, far beyond any terminal
+const MAX_TAPE_WIDTH: usize = 10_000;
+
+/// When to color terminal output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color unless `NO_COLOR` is set or output is not a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    fn parse(value: &str) -> Result<ColorMode, String> {
+        match value {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!(
+                "Invalid color setting '{}' (expected auto, always or never)",
+                value
+            )),
+        }
+    }
+}
+
+/// Program defaults, see the module documentation for where they come from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Step limit after which a run is reported as not halting, given as
+    /// `unlimited` for none
+    #[serde(deserialize_with = "deserialize_max_steps")]
+    pub max_steps: usize,
+    /// Memory a run may use for its tape and trace, in bytes; unlimited if
+    /// unset. Sizes may be given with a `K`, `M` or `G` suffix.
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max_memory: Option<usize>,
+    /// Tape cells a run may use before it stops without a verdict;
+    /// unlimited if unset
+    pub max_cells: Option<usize>,
+    /// Cells the head may move beyond the region that has held non-blank
+    /// symbols before the run fails; unchecked if unset
+    pub max_head_overshoot: Option<usize>,
+    /// Leftmost cell the head may visit, counted from the first input cell;
+    /// unchecked if unset
+    pub min_head: Option<i64>,
+    /// Time a run may take before it stops without a verdict, as a number
+    /// of seconds or text such as `"500ms"` or `"2m"`; unlimited if unset
+    #[serde(deserialize_with = "deserialize_timeout")]
+    pub timeout: Option<Duration>,
+    /// Directory scanned for example machines
+    pub examples_dir: PathBuf,
+    pub color: ColorMode,
+    /// Number of tape cells shown in visual mode
+    pub tape_width: usize,
+    /// Steps between the configurations visual mode keeps, those between
+    /// being taken again when shown; every configuration is kept if unset
+    pub snapshot_every: Option<usize>,
+    /// Whether the tape a run left is shown whole or without the blank
+    /// cells at either end
+    pub output_mode: OutputMode,
+    /// Settings given as command line flags, which machine defaults do not
+    /// replace
+    #[serde(skip)]
+    flags: Vec<&'static str>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_steps: DEFAULT_MAX_STEPS,
+            max_memory: None,
+            max_cells: None,
+            max_head_overshoot: None,
+            min_head: None,
+            timeout: None,
+            examples_dir: PathBuf::from("examples"),
+            color: ColorMode::Auto,
+            tape_width: 20,
+            snapshot_every: None,
+            output_mode: OutputMode::Tape,
+            flags: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file and apply environment overrides
+    ///
+    /// A missing config file is not an error; the built-in defaults are used.
+    pub fn load() -> Result<Config, String> {
+        let mut config = match config_path() {
+            Some(path) if path.exists() => {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("Config error: {}: {}", path.display(), e))?;
+                toml::from_str(&text)
+                    .map_err(|e| format!("Config error: {}: {}", path.display(), e))?
+            }
+            _ => Config::default(),
+        };
+
+        for (name, key) in [
+            ("TURING_MACHINE_MAX_STEPS", "max_steps"),
+            ("TURING_MACHINE_MAX_MEMORY", "max_memory"),
+            ("TURING_MACHINE_MAX_CELLS", "max_cells"),
+            ("TURING_MACHINE_MAX_HEAD_OVERSHOOT", "max_head_overshoot"),
+            ("TURING_MACHINE_MIN_HEAD", "min_head"),
+            ("TURING_MACHINE_TIMEOUT", "timeout"),
+            ("TURING_MACHINE_EXAMPLES_DIR", "examples_dir"),
+            ("TURING_MACHINE_COLOR", "color"),
+            ("TURING_MACHINE_TAPE_WIDTH", "tape_width"),
+            ("TURING_MACHINE_SNAPSHOT_EVERY", "snapshot_every"),
+            ("TURING_MACHINE_OUTPUT_MODE", "output_mode"),
+        ] {
+            if let Ok(value) = env::var(name) {
+                config
+                    .set(key, &value)
+                    .map_err(|e| format!("Config error: {}: {}", name, e))?;
+            }
+        }
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Remove the global flags (`--max-steps N`, `--max-memory SIZE`,
+    /// `--max-cells N`, `--max-head-overshoot N`, `--min-head N`,
+    /// `--timeout TIME`, `--examples-dir DIR`, `--color WHEN`,
+    /// `--tape-width N`, `--snapshot-every N`, `--output-mode MODE`) from
+    /// `args` and apply them
+    pub fn apply_flags(&mut self, args: &mut Vec<String>) -> Result<(), String> {
+        let mut i = 1;
+        while i < args.len() {
+            let key = match args[i].as_str() {
+                "--max-steps" => "max_steps",
+                "--max-memory" => "max_memory",
+                "--max-cells" => "max_cells",
+                "--max-head-overshoot" => "max_head_overshoot",
+                "--min-head" => "min_head",
+                "--timeout" => "timeout",
+                "--examples-dir" => "examples_dir",
+                "--color" => "color",
+                "--tape-width" => "tape_width",
+                "--snapshot-every" => "snapshot_every",
+                "--output-mode" => "output_mode",
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            if i + 1 >= args.len() {
+                return Err(format!("Missing value for {}", args[i]));
+            }
+            let value = args.remove(i + 1);
+            let flag = args.remove(i);
+            self.set(key, &value)
+                .map_err(|e| format!("{}: {}", flag, e))?;
+            self.flags.push(key);
+        }
+        self.check()
+    }
+
+    /// Apply the color setting to all colored output
+    pub fn apply_color(&self) {
+        match self.color {
+            ColorMode::Auto => {}
+            ColorMode::Always => colored::control::set_override(true),
+            ColorMode::Never => colored::control::set_override(false),
+        }
+    }
+
+    /// Limits for a single run
+    pub fn run_config(&self) -> RunConfig {
+        RunConfig {
+            max_steps: self.max_steps,
+            max_memory_bytes: self.max_memory,
+            max_cells: self.max_cells,
+            max_head_overshoot: self.max_head_overshoot,
+            min_head_position: self.min_head,
+            timeout: self.timeout,
+        }
+    }
+
+    /// The settings for running a machine with the recommended limits
+    /// `defaults`, which replace the configured ones except for those given
+    /// as flags
+    pub fn with_machine_defaults(&self, defaults: &RunDefaults) -> Config {
+        let unset = |key: &str| !self.flags.contains(&key);
+        let mut config = self.clone();
+        if let Some(max_steps) = defaults.max_steps.filter(|_| unset("max_steps")) {
+            config.max_steps = max_steps;
+        }
+        if let Some(max_memory) = defaults.max_memory.filter(|_| unset("max_memory")) {
+            config.max_memory = Some(max_memory);
+        }
+        if let Some(overshoot) = defaults
+            .max_head_overshoot
+            .filter(|_| unset("max_head_overshoot"))
+        {
+            config.max_head_overshoot = Some(overshoot);
+        }
+        if let Some(min_head) = defaults.min_head.filter(|_| unset("min_head")) {
+            config.min_head = Some(min_head);
+        }
+        if let Some(output_mode) = defaults.output_mode.filter(|_| unset("output_mode")) {
+            config.output_mode = output_mode;
+        }
+        config
+    }
+
+    /// Whether diagnostics written to stderr should be colored
+    pub fn color_stderr(&self) -> bool {
+        match self.color {
+            ColorMode::Auto => io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+
+    /// Set a single field from its textual value
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "max_steps" => self.max_steps = parse_max_steps(value)?,
+            "max_memory" => self.max_memory = Some(parse_size(value)?),
+            "max_cells" => self.max_cells = Some(parse_number(value)?),
+            "max_head_overshoot" => self.max_head_overshoot = Some(parse_number(value)?),
+            "min_head" => {
+                self.min_head = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("'{}' is not a whole number", value))?,
+                )
+            }
+            "timeout" => self.timeout = Some(parse_duration(value)?),
+            "examples_dir" => self.examples_dir = PathBuf::from(value),
+            "color" => self.color = ColorMode::parse(value)?,
+            "tape_width" => self.tape_width = parse_number(value)?,
+            "snapshot_every" => self.snapshot_every = Some(parse_number(value)?),
+            "output_mode" => self.output_mode = OutputMode::parse(value)?,
+            _ => return Err(format!("Unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.tape_width == 0 || self.tape_width > MAX_TAPE_WIDTH {
+            return Err(format!(
+                "Config error: tape_width must be between 1 and {}",
+                MAX_TAPE_WIDTH
+            ));
+        }
+        if self.snapshot_every == Some(0) {
+            return Err("Config error: snapshot_every must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn deserialize_max_steps<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    executor::deserialize_max_steps(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("max_steps needs a value"))
+}
+
+fn deserialize_timeout<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timeout {
+        Seconds(f64),
+        Text(String),
+    }
+    match Option::<Timeout>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Timeout::Seconds(seconds)) => Duration::try_from_secs_f64(seconds)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        Some(Timeout::Text(text)) => parse_duration(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parse a count, whose digits may be grouped with underscores as in
+/// `1_000_000`
+fn parse_number(value: &str) -> Result<usize, String> {
+    value
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("'{}' is not a non-negative number", value))
+}
+
+/// Location of the config file, if one can be determined
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("TURING_MACHINE_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => Path::new(&env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("turing-machine").join("config.toml"))
+}
@@ -0,0 +1,266 @@
+//! Batch execution of queued jobs from a spool directory
+//!
+//! Each job is a JSON file dropped into the spool directory:
+//!
+//! ```json
+//! { "machine": "machines/palindrome.json", "input": "0110", "trace": true }
+//! ```
+//!
+//! Machine paths are relative to the spool directory. `max_steps`, a number
+//! or `"unlimited"`, may be given to override the daemon's step limit for
+//! one job; otherwise the machine's recommended `max_steps` is used if it
+//! has one. A job is claimed by moving it into `running/`, executed by one
+//! of a fixed number of workers, and its report is written to
+//! `<output>/<job>.result.json`. Finished jobs are then moved to `done/`, or
+//! to `failed/` if they could not be run at all.
+//! Jobs left in `running/` by an interrupted daemon are queued again on start.
+
+use crate::executor::{self, Executor, DEFAULT_MAX_STEPS};
+use crate::pipeline::{TraceRecorder, TraceStep};
+use crate::{formats, ExecutionResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Job file layout
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobSpec {
+    machine: String,
+    input: String,
+    #[serde(default)]
+    trace: bool,
+    #[serde(default, deserialize_with = "executor::deserialize_max_steps")]
+    max_steps: Option<usize>,
+}
+
+/// Report written for every processed job
+#[derive(Debug, Serialize)]
+pub struct JobReport {
+    /// Job name, the job file name without its extension
+    pub job: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub machine: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<ExecutionResult>,
+    /// Every transition taken, recorded only when the job asks for a trace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<TraceStep>>,
+    /// Why the job could not be run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A spool directory watcher executing jobs with bounded concurrency
+#[derive(Debug, Clone)]
+pub struct Daemon {
+    spool: PathBuf,
+    output: PathBuf,
+    workers: usize,
+    max_steps: usize,
+    poll_interval: Duration,
+}
+
+impl Daemon {
+    /// Create a daemon reading jobs from `spool` and writing reports to `output`
+    pub fn new(spool: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        Daemon {
+            spool: spool.into(),
+            output: output.into(),
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            max_steps: DEFAULT_MAX_STEPS,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the number of jobs executed at the same time (at least one)
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Set the step limit for jobs that do not set their own
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Set how often the spool directory is scanned for new jobs
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Process jobs, calling `on_report` as each one finishes
+    ///
+    /// With `once`, only the jobs queued at the start are processed and the
+    /// call returns when they are done; otherwise the spool directory is
+    /// watched until the process is stopped.
+    pub fn run(&self, once: bool, on_report: &mut dyn FnMut(&JobReport)) -> Result<(), String> {
+        for dir in [
+            &self.output,
+            &self.spool.join("running"),
+            &self.spool.join("done"),
+            &self.spool.join("failed"),
+        ] {
+            fs::create_dir_all(dir).map_err(|e| format!("File error: {}: {}", dir.display(), e))?;
+        }
+        self.requeue_interrupted()?;
+
+        let (job_sender, job_receiver) = mpsc::channel::<PathBuf>();
+        let job_receiver = Mutex::new(job_receiver);
+        let (report_sender, report_receiver) = mpsc::channel::<JobReport>();
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers {
+                let job_receiver = &job_receiver;
+                let report_sender = report_sender.clone();
+                scope.spawn(move || loop {
+                    let next = job_receiver.lock().unwrap().recv();
+                    let Ok(job) = next else { break };
+                    if report_sender.send(self.process(&job)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(report_sender);
+
+            let mut job_sender = Some(job_sender);
+            loop {
+                if let Some(sender) = &job_sender {
+                    for job in self.claim_queued()? {
+                        // Workers only stop once the queue is closed
+                        let _ = sender.send(job);
+                    }
+                    if once {
+                        job_sender = None;
+                    }
+                }
+
+                let deadline = Instant::now() + self.poll_interval;
+                loop {
+                    let timeout = if job_sender.is_some() {
+                        deadline.saturating_duration_since(Instant::now())
+                    } else {
+                        Duration::MAX
+                    };
+                    match report_receiver.recv_timeout(timeout) {
+                        Ok(report) => on_report(&report),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                    }
+                }
+            }
+        })
+    }
+
+    /// Move jobs left in `running/` by an interrupted daemon back to the spool
+    fn requeue_interrupted(&self) -> Result<(), String> {
+        for job in job_files(&self.spool.join("running"))? {
+            let target = self.spool.join(job.file_name().unwrap_or_default());
+            fs::rename(&job, target).map_err(|e| format!("File error: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Claim all queued jobs by moving them into `running/`, oldest name first
+    fn claim_queued(&self) -> Result<Vec<PathBuf>, String> {
+        let mut claimed = Vec::new();
+        for job in job_files(&self.spool)? {
+            let target = self
+                .spool
+                .join("running")
+                .join(job.file_name().unwrap_or_default());
+            // Another daemon may have claimed the job in the meantime
+            if fs::rename(&job, &target).is_ok() {
+                claimed.push(target);
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Run a claimed job, write its report and retire the job file
+    fn process(&self, job: &Path) -> JobReport {
+        let name = job
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let mut report = JobReport {
+            job: name.clone(),
+            machine: None,
+            input: None,
+            result: None,
+            trace: None,
+            error: None,
+        };
+        if let Err(e) = self.execute(job, &mut report) {
+            report.error = Some(e);
+        }
+
+        let path = self.output.join(format!("{}.result.json", name));
+        let written = serde_json::to_string_pretty(&report)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(&path, text + "\n").map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            report.error = Some(format!("File error: {}: {}", path.display(), e));
+        }
+
+        let outcome = if report.error.is_some() {
+            "failed"
+        } else {
+            "done"
+        };
+        let target = self
+            .spool
+            .join(outcome)
+            .join(job.file_name().unwrap_or_default());
+        if let Err(e) = fs::rename(job, target) {
+            report.error = Some(format!("File error: {}", e));
+        }
+        report
+    }
+
+    /// Load and run the job's machine, filling in the report
+    fn execute(&self, job: &Path, report: &mut JobReport) -> Result<(), String> {
+        let text = fs::read_to_string(job).map_err(|e| format!("File error: {}", e))?;
+        let spec: JobSpec =
+            json5::from_str(&text).map_err(|e| format!("Invalid job file: {}", e))?;
+        let machine_path = self.spool.join(&spec.machine);
+        report.machine = Some(machine_path.clone());
+        report.input = Some(spec.input.clone());
+
+        let machine = formats::load_machine(&machine_path, &mut |_| {})?;
+        let mut recorder = TraceRecorder::default();
+        let mut executor = Executor::new(&machine);
+        if spec.trace {
+            executor = executor.observe(&mut recorder);
+        }
+        let max_steps = spec
+            .max_steps
+            .or(machine.run_defaults().max_steps)
+            .unwrap_or(self.max_steps);
+        let result = executor.run(&spec.input, max_steps)?;
+        report.result = Some(result);
+        report.trace = spec.trace.then_some(recorder.steps);
+        Ok(())
+    }
+}
+
+/// Job files (`*.json`) directly inside `dir`, sorted by name
+fn job_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("File error: {}: {}", dir.display(), e))?;
+    let mut jobs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    jobs.sort();
+    Ok(jobs)
+}
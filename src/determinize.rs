@@ -0,0 +1,338 @@
+//! Deterministic simulation of non-deterministic machines
+//!
+//! [`determinize`] builds the deterministic machine of the textbook proof
+//! that non-determinism adds no power to Turing machines: it keeps a queue
+//! of configurations of the non-deterministic machine on its tape and
+//! explores them breadth-first, accepting as soon as one of them accepts.
+//!
+//! Each configuration is the tape of the non-deterministic machine with the
+//! symbol standing for its state written in front of the cell under the
+//! head, and ends with `#`. Blank cells inside a configuration are written
+//! as `~`, so the queue is the only non-blank part of the tape:
+//!
+//! ```text
+//! 1 1 S 0 1 #  1 1 0 G 1 #
+//! ```
+//!
+//! The machine repeats one cycle on the first configuration of the queue.
+//! It accepts if the state accepts and drops the configuration if the state
+//! rejects. Otherwise it reads the state and the symbol under the head, and
+//! for each transition the machine can take appends a copy of the
+//! configuration to the queue, one cell at a time with the cell being
+//! copied marked `*`, and applies the transition to the copy. The original
+//! is then erased. The machine rejects once the queue is empty.
+//!
+//! `#`, `~`, `*` and the state symbols are replaced by other characters
+//! where the machine already uses them; the simulator's metadata lists the
+//! symbols chosen. Unlike [`NondeterministicMachine::explore`], the
+//! simulator does not skip configurations reached before, so where every
+//! branch ends in a loop it runs forever instead of rejecting.
+
+use crate::info::{MachineTest, Metadata};
+use crate::nondeterministic::NondeterministicMachine;
+use crate::{Direction, TuringMachine, TuringMachineBuilder};
+use std::collections::{HashMap, HashSet};
+
+/// Characters preferred for the end of a configuration, a blank cell inside
+/// one and the cell being copied
+const SEPARATORS: &str = "#|;";
+const BLANK_CELLS: &str = "~.";
+const MARKS: &str = "*+";
+/// Characters preferred for states, after the first letter of their name
+const STATE_SYMBOLS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Build a deterministic machine accepting the inputs `machine` accepts
+///
+/// Only machines on the default two-way infinite tape starting at the input
+/// can be converted. Input mapping and normalization are kept, and the
+/// tests of the machine's metadata that expect a verdict are carried over.
+pub fn determinize(machine: &NondeterministicMachine) -> Result<TuringMachine, String> {
+    let base = machine.base();
+    let unsupported = [
+        (base.left_edge.is_some(), "left_edge"),
+        (base.tape_length.is_some(), "tape_length"),
+        (!base.tape_setup.is_default(), "tape"),
+    ];
+    if let Some((_, field)) = unsupported.iter().find(|(set, _)| *set) {
+        return Err(format!(
+            "{} is not supported by the deterministic simulation",
+            field
+        ));
+    }
+
+    let blank = base.blank_symbol;
+    let mut used: HashSet<char> = base.tape_alphabet.clone();
+    let separator = fresh(&mut used, SEPARATORS.chars());
+    let blank_cell = fresh(&mut used, BLANK_CELLS.chars());
+    let mark = fresh(&mut used, MARKS.chars());
+    let mut states: Vec<&String> = base.states.iter().collect();
+    states.sort_by_key(|state| (*state != &base.initial_state, *state));
+    let mut state_symbols = HashMap::new();
+    for state in &states {
+        let initial = state.chars().next().map(|c| c.to_ascii_uppercase());
+        let symbol = fresh(&mut used, initial.into_iter().chain(STATE_SYMBOLS.chars()));
+        state_symbols.insert(*state, symbol);
+    }
+
+    let stored = |symbol: char| if symbol == blank { blank_cell } else { symbol };
+    let mut cells: Vec<char> = base.tape_alphabet.iter().map(|&s| stored(s)).collect();
+    cells.sort();
+    let copied: Vec<char> = cells
+        .iter()
+        .copied()
+        .chain(states.iter().map(|state| state_symbols[state]))
+        .collect();
+    let queue: Vec<char> = copied.iter().copied().chain([separator, mark]).collect();
+    let name = |symbol: char| base.symbols.name(symbol);
+
+    let mut docs = vec![format!(
+        "Configurations end with {}, {} is a blank cell and {} marks the cell being copied.",
+        separator, blank_cell, mark
+    )];
+    docs.push(format!(
+        "States: {}.",
+        states
+            .iter()
+            .map(|state| format!("{} = {}", state_symbols[state], state))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    let mut t = Table {
+        builder: TuringMachine::builder()
+            .initial("start")
+            .accept("accept")
+            .reject("reject")
+            .input_symbols(base.alphabet.iter().copied())
+            .blank(blank)
+            .metadata(Metadata {
+                name: Some(format!(
+                    "{} (deterministic)",
+                    base.metadata.name.as_deref().unwrap_or("Machine")
+                )),
+                description: Some(
+                    "Explores the configurations of a non-deterministic machine breadth-first"
+                        .to_string(),
+                ),
+                tests: base
+                    .metadata
+                    .tests
+                    .iter()
+                    .filter(|test| test.accepts.is_some())
+                    .map(|test| MachineTest {
+                        output: None,
+                        ..test.clone()
+                    })
+                    .collect(),
+                docs: Some(docs.join("\n")),
+                ..Metadata::default()
+            }),
+    };
+    let initial = state_symbols[&base.initial_state];
+    let rewind = |to: &str| format!("rewind[{}]", to);
+    let mut alphabet: Vec<char> = base.alphabet.iter().copied().collect();
+    alphabet.sort();
+
+    // Write the initial configuration: the initial state in front of the
+    // input, and the end of the configuration after it
+    for &symbol in alphabet.iter().chain([&blank]) {
+        t.add("start", symbol, "place_state", symbol, Direction::L);
+    }
+    t.add("place_state", blank, "end_input", initial, Direction::R);
+    t.skip("end_input", &alphabet, Direction::R);
+    t.add("end_input", blank, &rewind("find"), separator, Direction::L);
+
+    // Find the state of the first configuration
+    t.skip("find", &cells, Direction::R);
+    t.add("find", blank, "reject", blank, Direction::S);
+    for state in &states {
+        let symbol = state_symbols[state];
+        if base.accept_states.contains(*state) {
+            t.add("find", symbol, "accept", symbol, Direction::S);
+        } else if base.reject_states.contains(*state) {
+            t.add("find", symbol, &rewind("erase"), symbol, Direction::L);
+        } else {
+            t.add(
+                "find",
+                symbol,
+                &format!("read[{}]", state),
+                symbol,
+                Direction::R,
+            );
+        }
+    }
+
+    // Erase the first configuration and go on with the next one
+    t.add_many("erase", &copied, "erase", blank, Direction::R);
+    t.add("erase", separator, "find", blank, Direction::R);
+
+    let mut rewinds = vec!["find".to_string(), "erase".to_string()];
+    let mut transitions: Vec<_> = machine.transitions().iter().collect();
+    transitions.sort_by(|a, b| a.0.cmp(b.0));
+    let job = |state: &str, symbol: char, index: usize| {
+        format!("{}/{}/{}", state, name(symbol), index + 1)
+    };
+
+    // Dispatch on the symbol under the head, past the end of the
+    // configuration for a blank cell never visited
+    for state in &states {
+        if base.accept_states.contains(*state) || base.reject_states.contains(*state) {
+            continue;
+        }
+        let read = format!("read[{}]", state);
+        for &cell in cells.iter().chain([&separator]) {
+            let symbol = if cell == blank_cell || cell == separator {
+                blank
+            } else {
+                cell
+            };
+            let to = if machine
+                .transitions()
+                .contains_key(&((*state).clone(), symbol))
+            {
+                rewind(&format!("copy[{}]", job(state, symbol, 0)))
+            } else if base.accept_on_halt {
+                "accept".to_string()
+            } else {
+                rewind("erase")
+            };
+            t.add(&read, cell, &to, cell, Direction::L);
+        }
+    }
+
+    for ((state, symbol), targets) in transitions {
+        if base.accept_states.contains(state) || base.reject_states.contains(state) {
+            continue;
+        }
+        for (index, (new_state, write, direction)) in targets.iter().enumerate() {
+            let key = job(state, *symbol, index);
+            let at = |step: &str| format!("{}[{}]", step, key);
+            let done = match targets.get(index + 1) {
+                Some(_) => rewind(&format!("copy[{}]", job(state, *symbol, index + 1))),
+                None => rewind("erase"),
+            };
+            rewinds.push(at("copy"));
+
+            // Copy the first configuration to the end of the queue
+            for &cell in &copied {
+                let carry = format!("carry:{}[{}]", name(cell), key);
+                let back = format!("return:{}[{}]", name(cell), key);
+                t.add(&at("copy"), cell, &carry, mark, Direction::R);
+                t.skip(&carry, &queue, Direction::R);
+                t.add(&carry, blank, &back, cell, Direction::L);
+                let passed: Vec<char> = queue.iter().copied().filter(|&c| c != mark).collect();
+                t.skip(&back, &passed, Direction::L);
+                t.add(&back, mark, &at("copy"), cell, Direction::R);
+            }
+            t.add(
+                &at("copy"),
+                separator,
+                &at("close"),
+                separator,
+                Direction::R,
+            );
+            t.skip(&at("close"), &queue, Direction::R);
+            t.add(&at("close"), blank, &at("locate"), separator, Direction::L);
+
+            // Apply the transition to the copy
+            let from = state_symbols[state];
+            let to = state_symbols[new_state];
+            let write = stored(*write);
+            t.skip(&at("locate"), &cells, Direction::L);
+            match direction {
+                Direction::R | Direction::S => {
+                    let (first, second) = if *direction == Direction::R {
+                        (write, to)
+                    } else {
+                        (to, write)
+                    };
+                    t.add(&at("locate"), from, &at("put"), first, Direction::R);
+                    t.add(&at("put"), separator, &at("end"), second, Direction::R);
+                    t.add(&at("end"), blank, &done, separator, Direction::L);
+                    t.add_many(&at("put"), &cells, &done, second, Direction::L);
+                }
+                Direction::L => {
+                    t.add(&at("locate"), from, &at("put"), from, Direction::R);
+                    t.add(&at("put"), separator, &at("extend"), write, Direction::R);
+                    t.add(&at("extend"), blank, &at("back"), separator, Direction::L);
+                    t.add(&at("back"), write, &at("left"), write, Direction::L);
+                    t.add_many(&at("put"), &cells, &at("left"), write, Direction::L);
+                    t.add(&at("left"), from, &at("before"), from, Direction::L);
+                    for &cell in &cells {
+                        let drop = format!("drop:{}[{}]", name(cell), key);
+                        t.add(&at("before"), cell, &drop, to, Direction::R);
+                        t.add(&drop, from, &done, cell, Direction::L);
+                    }
+                    // The head moves off the left end of the configuration:
+                    // shift the rest right to make room for a blank cell
+                    for edge in [separator, blank] {
+                        t.add(&at("before"), edge, &at("insert"), edge, Direction::R);
+                    }
+                    let shift = |cell: char| format!("shift:{}[{}]", name(cell), key);
+                    t.add(&at("insert"), from, &shift(blank_cell), to, Direction::R);
+                    for &carried in cells.iter().chain([&separator]) {
+                        for &cell in cells.iter().chain([&separator]) {
+                            t.add(&shift(carried), cell, &shift(cell), carried, Direction::R);
+                        }
+                        t.add(&shift(carried), blank, &done, carried, Direction::L);
+                    }
+                }
+            }
+        }
+    }
+
+    // Go back to the front of the queue
+    for to in rewinds {
+        t.skip(&rewind(&to), &queue, Direction::L);
+        t.add(&rewind(&to), blank, &to, blank, Direction::R);
+    }
+
+    let mut simulator = t.builder.build()?;
+    simulator.symbols = base.symbols.clone();
+    simulator.input_map = base.input_map.clone();
+    simulator.input_normalization = base.input_normalization;
+    Ok(simulator)
+}
+
+/// Transitions of the simulator, added a symbol at a time
+struct Table {
+    builder: TuringMachineBuilder,
+}
+
+impl Table {
+    fn add(&mut self, state: &str, read: char, to: &str, write: char, direction: Direction) {
+        let builder = std::mem::take(&mut self.builder);
+        self.builder = builder.transition(state, read, to, write, direction);
+    }
+
+    /// On each symbol of `reads`, write `write`, move and continue in `to`
+    fn add_many(
+        &mut self,
+        state: &str,
+        reads: &[char],
+        to: &str,
+        write: char,
+        direction: Direction,
+    ) {
+        for &read in reads {
+            self.add(state, read, to, write, direction);
+        }
+    }
+
+    /// Move over the symbols of `reads`, staying in `state`
+    fn skip(&mut self, state: &str, reads: &[char], direction: Direction) {
+        for &read in reads {
+            self.add(state, read, state, read, direction);
+        }
+    }
+}
+
+/// The first of `preferred`, or else of the Latin letters with accents,
+/// that is not in `used`, which it is added to
+fn fresh(used: &mut HashSet<char>, preferred: impl Iterator<Item = char>) -> char {
+    let symbol = preferred
+        .chain('\u{c0}'..='\u{24f}')
+        .find(|c| !used.contains(c) && !c.is_whitespace() && *c != ',')
+        .expect("there are more characters than symbols and states");
+    used.insert(symbol);
+    symbol
+}
@@ -0,0 +1,285 @@
+//! State diagrams for documents
+//!
+//! [`mermaid`] writes a machine as a Mermaid `stateDiagram-v2`, which
+//! GitHub, GitLab and most wikis render inside a ```` ```mermaid ```` code
+//! block. Each transition is an arrow labelled `read → write, move`, and
+//! accept and reject states are shaded green and red.
+//!
+//! [`tikz`] writes it as a TikZ picture using the `automata` library, for
+//! LaTeX handouts and papers. States are placed in the [`layers`] of a
+//! breadth-first search from the initial state, from left to right.
+
+use crate::{Direction, TuringMachine};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+/// States in diagram order: the initial state first, then the others by name
+pub(crate) fn ordered_states(machine: &TuringMachine) -> Vec<&str> {
+    let mut states: Vec<&str> = machine
+        .states
+        .iter()
+        .map(String::as_str)
+        .filter(|state| *state != machine.initial_state)
+        .collect();
+    states.sort_unstable();
+    states.insert(0, &machine.initial_state);
+    states
+}
+
+/// Transitions sorted by state, in diagram order, and symbol read
+pub(crate) fn ordered_transitions<'a>(
+    machine: &'a TuringMachine,
+    ids: &HashMap<&str, usize>,
+) -> Vec<(&'a str, char, &'a str, char, Direction)> {
+    let mut transitions: Vec<_> = machine
+        .transitions
+        .iter()
+        .map(|((state, read), (next, write, direction))| {
+            (state.as_str(), *read, next.as_str(), *write, *direction)
+        })
+        .collect();
+    transitions.sort_by_key(|&(state, read, ..)| (ids[state], read));
+    transitions
+}
+
+/// States in layers for drawing from left to right
+///
+/// The initial state is alone in the first layer, and every other state is
+/// in the layer after the first state found with a transition into it, so
+/// most arrows point to the right. States no run can enter make up a last
+/// layer. Within a layer, states are in the order they were found.
+pub fn layers(machine: &TuringMachine) -> Vec<Vec<&str>> {
+    let states = ordered_states(machine);
+    let ids: HashMap<&str, usize> = states.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+    let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (state, _, next, ..) in ordered_transitions(machine, &ids) {
+        successors.entry(state).or_default().push(next);
+    }
+
+    let mut layer_of = HashMap::from([(states[0], 0)]);
+    let mut layers = vec![vec![states[0]]];
+    let mut queue = VecDeque::from([states[0]]);
+    while let Some(state) = queue.pop_front() {
+        let layer = layer_of[state] + 1;
+        for &next in successors.get(state).into_iter().flatten() {
+            if layer_of.contains_key(next) {
+                continue;
+            }
+            layer_of.insert(next, layer);
+            if layers.len() == layer {
+                layers.push(Vec::new());
+            }
+            layers[layer].push(next);
+            queue.push_back(next);
+        }
+    }
+    let unreached: Vec<&str> = states
+        .into_iter()
+        .filter(|state| !layer_of.contains_key(state))
+        .collect();
+    if !unreached.is_empty() {
+        layers.push(unreached);
+    }
+    layers
+}
+
+/// The label of a transition, `read → write, move`
+pub(crate) fn transition_label(
+    machine: &TuringMachine,
+    read: char,
+    write: char,
+    direction: Direction,
+) -> String {
+    format!(
+        "{} → {}, {:?}",
+        machine.symbols.name(read),
+        machine.symbols.name(write),
+        direction
+    )
+}
+
+/// The machine as a Mermaid state diagram
+///
+/// States are declared with generated ids and their names as descriptions,
+/// so names with spaces or punctuation are shown as they are.
+pub fn mermaid(machine: &TuringMachine) -> String {
+    let states = ordered_states(machine);
+    let ids: HashMap<&str, usize> = states.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+
+    let mut text = String::new();
+    if let Some(name) = &machine.metadata.name {
+        let title = name.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(text, "---\ntitle: \"{}\"\n---", title);
+    }
+    text.push_str("stateDiagram-v2\n");
+    for (i, state) in states.iter().enumerate() {
+        let _ = writeln!(text, "    state \"{}\" as s{}", mermaid_escape(state), i);
+    }
+    let _ = writeln!(text, "    [*] --> s0");
+    for (state, read, next, write, direction) in ordered_transitions(machine, &ids) {
+        let _ = writeln!(
+            text,
+            "    s{} --> s{} : {}",
+            ids[state],
+            ids[next],
+            mermaid_escape(&transition_label(machine, read, write, direction))
+        );
+    }
+
+    for (class, style, halting) in [
+        (
+            "accept",
+            "fill:#c8e6c9,stroke:#2e7d32",
+            &machine.accept_states,
+        ),
+        (
+            "reject",
+            "fill:#ffcdd2,stroke:#c62828",
+            &machine.reject_states,
+        ),
+    ] {
+        let members: Vec<String> = states
+            .iter()
+            .filter(|state| halting.contains(**state))
+            .map(|state| format!("s{}", ids[state]))
+            .collect();
+        if !members.is_empty() {
+            let _ = writeln!(text, "    classDef {} {},stroke-width:2px", class, style);
+            let _ = writeln!(text, "    class {} {}", members.join(","), class);
+        }
+    }
+    text
+}
+
+/// Replace the characters Mermaid treats as syntax by entity codes
+fn mermaid_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' | ';' | '"' | '<' | '>' | '{' | '}' | '%' | '\\' => {
+                let _ = write!(escaped, "#{};", c as u32);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Horizontal distance between layers in a TikZ picture, in centimetres
+const TIKZ_LAYER_SPACING: f64 = 3.5;
+
+/// Vertical distance between states of a layer in a TikZ picture
+const TIKZ_STATE_SPACING: f64 = 2.5;
+
+/// The machine as a TikZ picture
+///
+/// The picture needs `\usepackage{tikz}` and
+/// `\usetikzlibrary{automata, arrows.meta}`, which its first lines repeat
+/// as a comment. The initial state has the entry arrow, accept states are
+/// drawn with a double circle and shaded green, and reject states shaded
+/// red. Transitions between the same two states share one arrow, with a
+/// line of its label per transition, and the blank is written `\sqcup`.
+pub fn tikz(machine: &TuringMachine) -> String {
+    let mut text =
+        String::from("% \\usepackage{tikz}\n% \\usetikzlibrary{automata, arrows.meta}\n");
+    if let Some(name) = &machine.metadata.name {
+        let _ = writeln!(text, "% {}", name.replace('\n', " "));
+    }
+    text.push_str("\\begin{tikzpicture}[>={Stealth[round]}, shorten >=1pt, auto, semithick,\n");
+    text.push_str("    accept/.style={accepting, fill=green!15}, reject/.style={fill=red!15}]\n");
+
+    let mut ids = HashMap::new();
+    for (column, layer) in layers(machine).iter().enumerate() {
+        let middle = (layer.len() - 1) as f64 / 2.0;
+        for (row, state) in layer.iter().enumerate() {
+            let mut style = String::from("state");
+            if *state == machine.initial_state {
+                style.push_str(", initial");
+            }
+            if machine.accept_states.contains(*state) {
+                style.push_str(", accept");
+            } else if machine.reject_states.contains(*state) {
+                style.push_str(", reject");
+            }
+            let _ = writeln!(
+                text,
+                "  \\node[{}] (s{}) at ({}, {}) {{{}}};",
+                style,
+                ids.len(),
+                column as f64 * TIKZ_LAYER_SPACING,
+                (middle - row as f64) * TIKZ_STATE_SPACING,
+                latex_escape(state)
+            );
+            ids.insert(*state, ids.len());
+        }
+    }
+
+    // One arrow per pair of states, labelled with all their transitions
+    let mut arrows: Vec<((&str, &str), Vec<String>)> = Vec::new();
+    let mut arrow_of = HashMap::new();
+    for (state, read, next, write, direction) in ordered_transitions(machine, &ids) {
+        let label = format!(
+            "{} $\\to$ {}, {:?}",
+            tikz_symbol(machine, read),
+            tikz_symbol(machine, write),
+            direction
+        );
+        let arrow = *arrow_of.entry((state, next)).or_insert_with(|| {
+            arrows.push(((state, next), Vec::new()));
+            arrows.len() - 1
+        });
+        arrows[arrow].1.push(label);
+    }
+    if !arrows.is_empty() {
+        text.push_str("  \\path[->]\n");
+        for ((state, next), labels) in &arrows {
+            let edge = if state == next {
+                "edge[loop above]"
+            } else if arrow_of.contains_key(&(*next, *state)) {
+                "edge[bend left]"
+            } else {
+                "edge"
+            };
+            let _ = writeln!(
+                text,
+                "    (s{}) {} node[align=center] {{{}}} (s{})",
+                ids[state],
+                edge,
+                labels.join(" \\\\ "),
+                ids[next]
+            );
+        }
+        text.push_str("  ;\n");
+    }
+    text.push_str("\\end{tikzpicture}\n");
+    text
+}
+
+/// A symbol in a TikZ label: the blank as `\sqcup`, others as their names
+fn tikz_symbol(machine: &TuringMachine, symbol: char) -> String {
+    if symbol == machine.blank_symbol {
+        "$\\sqcup$".to_string()
+    } else {
+        latex_escape(&machine.symbols.name(symbol))
+    }
+}
+
+/// Escape the characters LaTeX treats as syntax
+fn latex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '<' => escaped.push_str("\\textless{}"),
+            '>' => escaped.push_str("\\textgreater{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
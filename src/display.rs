@@ -0,0 +1,452 @@
+//! Colored terminal rendering of machines and tapes
+
+use crate::grid::GridRun;
+use crate::pda::PdaRun;
+use crate::twostack::TwoStackRun;
+use crate::{Direction, ExecutionSnapshot, TuringMachine};
+use colored::Colorize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+impl TuringMachine {
+    /// Display the state diagram with transitions
+    pub fn display_state_diagram(
+        &self,
+        current_state: Option<&str>,
+        next_transition: Option<(char, &str, char, Direction)>,
+    ) {
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "STATE DIAGRAM".bold());
+        println!("{}", "=".repeat(60));
+
+        // Draw visual ASCII diagram
+        self.draw_state_diagram(current_state, next_transition);
+
+        // Display transitions grouped by state
+        println!("\n{}:", "Transitions".bold());
+        let mut transitions_by_state: HashMap<&String, Vec<(char, &String, char, Direction)>> =
+            HashMap::new();
+
+        for ((state, symbol), (new_state, write_symbol, direction)) in &self.transitions {
+            transitions_by_state.entry(state).or_default().push((
+                *symbol,
+                new_state,
+                *write_symbol,
+                *direction,
+            ));
+        }
+
+        let mut sorted_states: Vec<_> = transitions_by_state.keys().collect();
+        sorted_states.sort();
+
+        for state in sorted_states {
+            let mut state_header = format!("  {}:", state);
+            if let Some(current) = current_state {
+                if state.as_str() == current {
+                    state_header = state_header.bold().yellow().to_string();
+                }
+            }
+            println!("{}", state_header);
+
+            let mut transitions = transitions_by_state.get(state).unwrap().clone();
+            transitions.sort_by_key(|(s, _, _, _)| *s);
+
+            for (symbol, new_state, write_symbol, direction) in transitions {
+                let dir_str = match direction {
+                    Direction::L => "←",
+                    Direction::R => "→",
+                    Direction::S => "·",
+                };
+                let transition_str = format!(
+                    "    ({}) → write '{}', move {}, goto {}",
+                    self.symbols.name(symbol),
+                    self.symbols.name(write_symbol),
+                    dir_str,
+                    new_state
+                );
+
+                // Highlight the next transition to be executed
+                let is_next_transition =
+                    if let (Some(current), Some((next_sym, next_state, _, _))) =
+                        (current_state, next_transition)
+                    {
+                        state.as_str() == current
+                            && symbol == next_sym
+                            && new_state.as_str() == next_state
+                    } else {
+                        false
+                    };
+
+                if is_next_transition {
+                    println!("{}", format!("  ▶ {}", transition_str).bold().green());
+                } else if let Some(current) = current_state {
+                    if state.as_str() == current {
+                        println!("{}", transition_str.yellow());
+                    } else {
+                        println!("{}", transition_str);
+                    }
+                } else {
+                    println!("{}", transition_str);
+                }
+            }
+        }
+        println!();
+    }
+
+    /// Draw ASCII art diagram of state machine
+    fn draw_state_diagram(
+        &self,
+        current_state: Option<&str>,
+        next_transition: Option<(char, &str, char, Direction)>,
+    ) {
+        println!("\n{}:", "Visual Diagram".bold());
+
+        // Sort states for consistent display
+        let mut sorted_states: Vec<_> = self.states.iter().collect();
+        sorted_states.sort();
+
+        // Draw states with arrows connecting them
+        // Create a simple horizontal layout with arrows
+        for (i, state) in sorted_states.iter().enumerate() {
+            // Draw state box
+            let is_current = current_state.map(|c| c == state.as_str()).unwrap_or(false);
+            let is_accept = self.accept_states.contains(*state);
+            let is_reject = self.reject_states.contains(*state);
+
+            // State box components - dynamically sized based on state name (width = text width + 2)
+            let state_width = state.len();
+            // Ensure box is wide enough for accept/reject labels (8 chars: "✓ ACCEPT" or "✗ REJECT")
+            let content_width = if is_accept || is_reject {
+                state_width.max(8)
+            } else {
+                state_width
+            };
+            let horizontal_line = "─".repeat(content_width + 2);
+
+            let box_top = format!("┌{}┐", horizontal_line);
+            let state_line = format!("│ {:^width$} │", state.as_str(), width = content_width);
+            let type_line = if is_accept {
+                format!("│ {:^width$} │", "✓ ACCEPT", width = content_width)
+            } else if is_reject {
+                format!("│ {:^width$} │", "✗ REJECT", width = content_width)
+            } else {
+                format!("│ {} │", " ".repeat(content_width))
+            };
+            let box_bottom = format!("└{}┘", horizontal_line);
+
+            // Print state box
+            if is_current {
+                println!("  {}", box_top.bold().yellow());
+                println!("  {}", state_line.bold().yellow());
+                if is_accept {
+                    println!("  {}", type_line.green().bold().yellow());
+                } else if is_reject {
+                    println!("  {}", type_line.red().bold().yellow());
+                } else {
+                    println!("  {}", type_line.bold().yellow());
+                }
+                println!("  {}", box_bottom.bold().yellow());
+            } else {
+                println!("  {}", box_top);
+                println!("  {}", state_line);
+                if is_accept {
+                    println!("  {}", type_line.green());
+                } else if is_reject {
+                    println!("  {}", type_line.red());
+                } else {
+                    println!("  {}", type_line);
+                }
+                println!("  {}", box_bottom);
+            }
+
+            // Draw transitions from this state
+            let mut state_transitions = Vec::new();
+            for ((from_state, symbol), (to_state, write_symbol, direction)) in &self.transitions {
+                if from_state == *state {
+                    state_transitions.push((symbol, to_state.as_str(), write_symbol, direction));
+                }
+            }
+
+            if !state_transitions.is_empty() {
+                state_transitions.sort_by_key(|(s, _, _, _)| *s);
+
+                for (symbol, to_state, write_symbol, direction) in state_transitions {
+                    let dir_arrow = match direction {
+                        Direction::L => "←",
+                        Direction::R => "→",
+                        Direction::S => "·",
+                    };
+
+                    // Check if this is the next transition
+                    let is_next = if let (Some(current), Some((next_sym, next_state, _, _))) =
+                        (current_state, next_transition)
+                    {
+                        state.as_str() == current && *symbol == next_sym && to_state == next_state
+                    } else {
+                        false
+                    };
+
+                    // Arrow from current state (box above) to target state
+                    let arrow = format!(
+                        "      │ --[{}:{}{}]--> {}",
+                        self.symbols.name(*symbol),
+                        self.symbols.name(*write_symbol),
+                        dir_arrow,
+                        to_state
+                    );
+
+                    if is_next {
+                        println!("{}", arrow.bold().green());
+                    } else if is_current {
+                        println!("{}", arrow.yellow());
+                    } else {
+                        println!("{}", arrow);
+                    }
+
+                    // Add visual pointer to target state box
+                    let pointer = "      │              ↓";
+                    if is_next {
+                        println!("{}", pointer.bold().green());
+                    } else if is_current {
+                        println!("{}", pointer.yellow());
+                    } else {
+                        println!("{}", pointer);
+                    }
+                }
+            }
+
+            if i < sorted_states.len() - 1 {
+                println!();
+            }
+        }
+
+        // Show next transition if available
+        if let (Some(current), Some((symbol, next_state, write_symbol, direction))) =
+            (current_state, next_transition)
+        {
+            println!("\n{}:", "Next Transition".bold().green());
+            let dir_str = match direction {
+                Direction::L => "←",
+                Direction::R => "→",
+                Direction::S => "·",
+            };
+            println!(
+                "  {} --[read: '{}']-->",
+                current.bold().yellow(),
+                self.symbols.name(symbol).cyan()
+            );
+            println!("    • Write: '{}'", self.symbols.name(write_symbol).cyan());
+            println!("    • Move: {}", dir_str.cyan());
+            println!("    • Goto: {}", next_state.bold().yellow());
+        }
+
+        println!();
+    }
+
+    /// Display the tape with head position, showing at least `width` cells
+    pub fn display_tape(&self, snapshot: &ExecutionSnapshot, width: usize) {
+        println!("\n{}", "TAPE".bold());
+
+        // Determine visible range around head
+        let head_pos = snapshot.head_position;
+        let tape_len = i32::try_from(snapshot.tape.len()).unwrap_or(i32::MAX);
+
+        // Show at least `width` cells centered around head; the arithmetic
+        // saturates so empty tapes and huge widths cannot overflow
+        let width = i32::try_from(width).unwrap_or(i32::MAX).max(1);
+        let visible_start = head_pos.saturating_sub(width / 2).max(0);
+        let visible_end = head_pos
+            .saturating_add(width / 2)
+            .min(tape_len.saturating_sub(1))
+            .max(visible_start.saturating_add(width - 1));
+
+        // Cells holding named symbols are wider; the head indicator and
+        // position numbers below are padded to match
+        let cells: Vec<String> = (visible_start..=visible_end)
+            .map(|i| match snapshot.tape.get(i as usize) {
+                Some(&cell) if i >= 0 && cell != self.blank_symbol => {
+                    format!("[{}]", self.symbols.name(cell))
+                }
+                _ => "[_]".to_string(),
+            })
+            .collect();
+
+        // Print tape cells
+        print!("Tape:   ");
+        for (i, cell_str) in (visible_start..).zip(&cells) {
+            if i == head_pos {
+                print!("{}", cell_str.bold().green());
+            } else {
+                print!("{}", cell_str);
+            }
+        }
+        println!();
+
+        // Print head indicator
+        print!("Head:   ");
+        for (i, cell_str) in (visible_start..).zip(&cells) {
+            let cell_width = cell_str.chars().count();
+            if i == head_pos {
+                print!("{:^width$}", "^", width = cell_width);
+            } else {
+                print!("{}", " ".repeat(cell_width));
+            }
+        }
+        println!();
+
+        // Print position numbers
+        print!("Pos:    ");
+        for (i, cell_str) in (visible_start..).zip(&cells) {
+            print!("{:>width$}", i, width = cell_str.chars().count());
+        }
+        println!("\n");
+    }
+}
+
+impl GridRun<'_> {
+    /// Display the grid around the head, `width` cells across and `height`
+    /// cells down
+    pub fn display_grid(&self, width: usize, height: usize) {
+        println!("\n{}", "GRID".bold());
+
+        let (head_x, head_y) = self.head();
+        let width = i64::try_from(width).unwrap_or(i64::MAX).max(1);
+        let height = i64::try_from(height).unwrap_or(i64::MAX).max(1);
+        let left = head_x.saturating_sub(width / 2);
+        let top = head_y.saturating_sub(height / 2);
+        let blank = self.grid().blank_symbol();
+
+        // The head's cell is bracketed, so it shows without colors too
+        let separator = |x: i64, y: i64| {
+            if (x, y) == (head_x, head_y) {
+                '['
+            } else if (x - 1, y) == (head_x, head_y) {
+                ']'
+            } else {
+                ' '
+            }
+        };
+        for y in top..top.saturating_add(height) {
+            let mut line = String::from(" ");
+            let right = left.saturating_add(width);
+            for x in left..right {
+                line.push(separator(x, y));
+                let symbol = self.grid().get((x, y));
+                let cell = if symbol == blank {
+                    "·".dimmed().to_string()
+                } else {
+                    symbol.to_string()
+                };
+                if (x, y) == (head_x, head_y) {
+                    line.push_str(&cell.bold().green().to_string());
+                } else {
+                    line.push_str(&cell);
+                }
+            }
+            line.push(separator(right, y));
+            println!("{}", line.trim_end());
+        }
+        println!(
+            "Head:   ({}, {}){}",
+            head_x,
+            head_y,
+            self.heading()
+                .map(|heading| format!(" facing {}", heading.arrow()))
+                .unwrap_or_default()
+        );
+        println!(
+            "Window: x {}..{}, y {}..{}\n",
+            left,
+            left.saturating_add(width - 1),
+            top,
+            top.saturating_add(height - 1)
+        );
+    }
+}
+
+impl PdaRun<'_> {
+    /// Display the input with the next symbol to read, and the stack as a
+    /// column with its top row first, at most `height` symbols deep
+    pub fn display_stack(&self, height: usize) {
+        println!("\n{}", "INPUT".bold());
+        // The next symbol is bracketed, so it shows without colors too
+        let position = self.position();
+        let separator = |i: usize| {
+            if i == position {
+                '['
+            } else if i == position + 1 {
+                ']'
+            } else {
+                ' '
+            }
+        };
+        let mut line = String::from(" ");
+        for (i, symbol) in self.input().iter().enumerate() {
+            line.push(separator(i));
+            let cell = symbol.to_string();
+            match i.cmp(&position) {
+                Ordering::Less => line.push_str(&cell.dimmed().to_string()),
+                Ordering::Equal => line.push_str(&cell.bold().green().to_string()),
+                Ordering::Greater => line.push_str(&cell),
+            }
+        }
+        if position < self.input().len() {
+            line.push(separator(self.input().len()));
+        } else {
+            line.push_str(&" (all read)".dimmed().to_string());
+        }
+        println!("{}", line.trim_end());
+
+        println!("\n{} (depth {})", "STACK".bold(), self.stack().len());
+        let height = height.max(1);
+        for (i, symbol) in self.stack().iter().rev().take(height).enumerate() {
+            if i == 0 {
+                println!(" │ {} │ ← top", symbol.to_string().bold().green());
+            } else {
+                println!(" │ {} │", symbol);
+            }
+        }
+        if self.stack().len() > height {
+            println!(" │ ⋮ │ {} more", self.stack().len() - height);
+        }
+        println!(" └───┘\n");
+    }
+}
+
+impl TwoStackRun<'_> {
+    /// Display both stacks side by side as columns with their top rows
+    /// first, at most `height` symbols deep
+    pub fn display_stacks(&self, height: usize) {
+        let (left, right) = (self.left(), self.right());
+        let title = format!("LEFT (depth {})", left.len());
+        println!(
+            "\n{}{}{} (depth {})",
+            title.bold(),
+            " ".repeat(20usize.saturating_sub(title.chars().count())),
+            "RIGHT".bold(),
+            right.len()
+        );
+        let height = height.max(1);
+        // A column is seven characters wide, or blank below a short stack
+        let cell = |stack: &[char], row: usize| match stack.iter().rev().nth(row) {
+            Some(symbol) if row == 0 => format!(" │ {} │ ", symbol.to_string().bold().green()),
+            Some(symbol) => format!(" │ {} │ ", symbol),
+            None if row < stack.len().max(1) => " │   │ ".to_string(),
+            None => " ".repeat(7),
+        };
+        let more = |stack: &[char]| match stack.len().checked_sub(height) {
+            Some(more) if more > 0 => format!(" │ ⋮ │ {:<13}", format!("{} more", more)),
+            _ => " ".repeat(20),
+        };
+        for row in 0..height.min(left.len().max(right.len()).max(1)) {
+            let gap = " ".repeat(13);
+            let top = if row == 0 { "← top" } else { "" };
+            let line = format!("{}{}{}{}", cell(left, row), gap, cell(right, row), top);
+            println!("{}", line.trim_end());
+        }
+        if left.len() > height || right.len() > height {
+            println!("{}{}", more(left), more(right).trim_end());
+        }
+        println!(" └───┘{} └───┘\n", " ".repeat(14));
+    }
+}
@@ -0,0 +1,226 @@
+//! Dovetailed execution over a stream of inputs
+//!
+//! A recognizer may loop forever on inputs it does not accept, so running
+//! the inputs one after another can get stuck on the first such input.
+//! Dovetailing interleaves them instead: in every round a few more inputs
+//! are admitted, and every input still open is run again with a larger step
+//! budget. An input the machine accepts after `n` steps is found as soon as
+//! the budget reaches `n`, however many inputs loop before it, which is how
+//! the accepted inputs of a recognizable language can be listed in practice.
+//!
+//! Budgets start at one step and double every round up to the run's
+//! `max_steps`; an input still running at that budget is given up on. Runs
+//! restart from the initial configuration each round, which costs at most
+//! twice the steps of a single run at the final budget.
+//!
+//! Within a round, finished inputs are reported by their number of steps and
+//! then in input order, so inputs admitted together are reported in the
+//! order of their number of steps.
+
+use crate::executor::{Executor, RunConfig};
+use crate::{ExecutionResult, TuringMachine};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// An input the dovetailer is done with
+#[derive(Debug)]
+pub struct Finished {
+    /// Position of the input in the enumeration, from 0
+    pub index: usize,
+    pub input: String,
+    /// The final run; it has no verdict if the input was given up on after
+    /// `max_steps`
+    pub result: Result<ExecutionResult, String>,
+}
+
+/// An input still running, with its run at the last budget
+struct Open {
+    index: usize,
+    input: String,
+    result: Option<ExecutionResult>,
+}
+
+/// Runs a machine on a possibly infinite sequence of inputs, yielding each
+/// input as it finishes
+pub struct Dovetail<'a, I: Iterator<Item = String>> {
+    machine: &'a TuringMachine,
+    inputs: I,
+    config: RunConfig,
+    admit: usize,
+    interrupt: Option<&'a AtomicBool>,
+    admitted: usize,
+    budget: usize,
+    open: Vec<Open>,
+    finished: VecDeque<Finished>,
+}
+
+impl<'a, I: Iterator<Item = String>> Dovetail<'a, I> {
+    /// Dovetail `machine` over `inputs` within the limits of `config`
+    pub fn new(machine: &'a TuringMachine, inputs: I, config: &RunConfig) -> Self {
+        Dovetail {
+            machine,
+            inputs,
+            config: config.clone(),
+            admit: 1,
+            interrupt: None,
+            admitted: 0,
+            budget: 1,
+            open: Vec::new(),
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// Admit `count` new inputs per round (at least one, the default)
+    pub fn admit_per_round(mut self, count: usize) -> Self {
+        self.admit = count.max(1);
+        self
+    }
+
+    /// Stop when `flag` is set, as with [`Executor::interrupt_on`]
+    pub fn interrupt_on(mut self, flag: &'a AtomicBool) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    /// Inputs admitted so far
+    pub fn admitted(&self) -> usize {
+        self.admitted
+    }
+
+    /// Step budget of the next round
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Finished inputs waiting to be yielded without another round
+    pub fn pending(&self) -> usize {
+        self.finished.len()
+    }
+
+    /// Stop, returning the index, input and run at the last budget of every
+    /// input still running; the run is `None` if the input never had a round
+    pub fn into_open(self) -> impl Iterator<Item = (usize, String, Option<ExecutionResult>)> {
+        self.open
+            .into_iter()
+            .map(|open| (open.index, open.input, open.result))
+    }
+
+    fn interrupted(&self) -> bool {
+        self.interrupt
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Admit new inputs and run every open input with the current budget
+    ///
+    /// Returns false when there is nothing left to run.
+    fn round(&mut self) -> bool {
+        for input in self.inputs.by_ref().take(self.admit) {
+            self.open.push(Open {
+                index: self.admitted,
+                input,
+                result: None,
+            });
+            self.admitted += 1;
+        }
+        if self.open.is_empty() {
+            return false;
+        }
+
+        let config = RunConfig {
+            max_steps: self.budget.min(self.config.max_steps),
+            ..self.config.clone()
+        };
+        let last_round = config.max_steps == self.config.max_steps;
+        let mut finished = Vec::new();
+        let mut still_open = Vec::with_capacity(self.open.len());
+        for mut open in std::mem::take(&mut self.open) {
+            let mut executor = Executor::new(self.machine);
+            if let Some(flag) = self.interrupt {
+                executor = executor.interrupt_on(flag);
+            }
+            match executor.run_with(&open.input, &config) {
+                Ok(result) if result.interrupted => {
+                    open.result = Some(result);
+                    still_open.push(open);
+                }
+                Ok(result)
+                    if result.halted
+                        || last_round
+                        || result.resource_limit.is_some()
+                        || result.timed_out
+                        || result.cycle.is_some() =>
+                {
+                    finished.push(Finished {
+                        index: open.index,
+                        input: open.input,
+                        result: Ok(result),
+                    })
+                }
+                Ok(result) => {
+                    open.result = Some(result);
+                    still_open.push(open);
+                }
+                Err(e) => finished.push(Finished {
+                    index: open.index,
+                    input: open.input,
+                    result: Err(e),
+                }),
+            }
+        }
+        self.open = still_open;
+        self.budget = self.budget.saturating_mul(2);
+
+        finished.sort_by_key(|finished| {
+            let steps = finished.result.as_ref().map_or(0, |result| result.steps);
+            (steps, finished.index)
+        });
+        self.finished.extend(finished);
+        true
+    }
+}
+
+impl<I: Iterator<Item = String>> Iterator for Dovetail<'_, I> {
+    type Item = Finished;
+
+    fn next(&mut self) -> Option<Finished> {
+        loop {
+            if let Some(finished) = self.finished.pop_front() {
+                return Some(finished);
+            }
+            if self.interrupted() || !self.round() {
+                return None;
+            }
+        }
+    }
+}
+
+/// Every input over the machine's input alphabet, shortest first and in
+/// lexicographic order of the sorted alphabet within a length
+///
+/// The sequence is infinite unless the alphabet is empty, in which case the
+/// empty input is the only one.
+pub fn all_inputs(machine: &TuringMachine) -> impl Iterator<Item = String> + '_ {
+    let mut alphabet: Vec<char> = machine.alphabet().iter().copied().collect();
+    alphabet.sort_by_key(|symbol| machine.symbols().name(*symbol));
+    // Digits of the next input in base `alphabet.len()`
+    let mut next = Some(Vec::<usize>::new());
+    std::iter::from_fn(move || {
+        let digits = next.take()?;
+        let input: Vec<char> = digits.iter().map(|&digit| alphabet[digit]).collect();
+        if !alphabet.is_empty() {
+            let mut digits = digits;
+            match digits.iter().rposition(|&digit| digit + 1 < alphabet.len()) {
+                Some(position) => {
+                    digits[position] += 1;
+                    digits[position + 1..].fill(0);
+                }
+                None => {
+                    digits.fill(0);
+                    digits.push(0);
+                }
+            }
+            next = Some(digits);
+        }
+        Some(machine.symbols().render(&input))
+    })
+}
@@ -0,0 +1,1429 @@
+//! The step loop, with hooks for observing execution
+//!
+//! [`Executor`] runs a machine and reports every event to the registered
+//! [`StepObserver`]s. [`TuringMachine::execute`] and
+//! [`TuringMachine::execute_step_by_step`] are thin wrappers around it.
+//! [`Stepper`] takes the same steps one at a time, as its caller asks for
+//! them.
+//!
+//! Runs without observers take a transition that loops on its state across
+//! a run of the symbol it reads in one macro step, counted as the steps it
+//! stands for, as long as nothing else could happen in between: no head
+//! bounds are set, the tape is not circular and no read-only input is
+//! overwritten. Observed runs see every step.
+//!
+//! Every run watches for a configuration coming back, which proves that the
+//! machine loops forever. Rather than remembering every configuration, the
+//! run keeps one, replaced at steps 1, 2, 4, 8 and so on, and compares each
+//! later configuration with it (Brent's cycle detection): a loop is found
+//! within twice the steps it takes to enter and go round it once. The tape
+//! contents are compared by a hash kept up to date with every write, and
+//! only a matching hash is checked cell by cell. Machines repeating their
+//! steps further and further along the tape, translated cyclers, are caught
+//! by the same scheme comparing configurations at the edge of the tape,
+//! unless the tape is circular, the input read-only or the head bounded,
+//! which break the translation.
+
+use crate::program::{Program, StateId};
+use crate::recurrence::{Configuration, Recurrence};
+use crate::tape::Tape;
+use crate::{
+    Cycle, Direction, ExecutionResult, ExecutionSnapshot, LeftEdge, ResourceLimit, SymbolTable,
+    TuringMachine,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+/// A single transition, as reported to [`StepObserver::on_step`]
+#[derive(Debug)]
+pub struct StepEvent<'a> {
+    /// Number of transitions executed so far, including this one
+    pub step: usize,
+    pub from_state: &'a str,
+    pub read_symbol: char,
+    pub to_state: &'a str,
+    pub write_symbol: char,
+    /// The move made, which is [`Direction::S`] where the transition's left
+    /// move was blocked by [`LeftEdge::Stay`]
+    pub direction: Direction,
+    /// Head position after the move
+    pub head_position: i32,
+    /// Tape contents after the write
+    pub tape: &'a [char],
+    /// Approximate memory used by the tape and observers before this step
+    /// was reported
+    pub memory_bytes: usize,
+    /// The names of the machine's symbols, which the symbols above stand
+    /// for
+    pub symbols: &'a SymbolTable,
+}
+
+impl StepEvent<'_> {
+    /// Name of the symbol read
+    pub fn read_name(&self) -> String {
+        self.symbols.name(self.read_symbol)
+    }
+
+    /// Name of the symbol written
+    pub fn write_name(&self) -> String {
+        self.symbols.name(self.write_symbol)
+    }
+
+    /// Tape contents after the write, the name of the symbol in each cell
+    pub fn tape_names(&self) -> Vec<String> {
+        self.symbols.names(self.tape)
+    }
+}
+
+/// Callbacks for execution events
+///
+/// All methods have empty default implementations, so observers only need to
+/// implement the events they care about.
+pub trait StepObserver {
+    /// Called after every transition
+    fn on_step(&mut self, _event: &StepEvent) {}
+
+    /// Called whenever a transition writes to the tape, including writes that
+    /// leave the symbol unchanged
+    ///
+    /// `position` is an index into the tape as it is at that step; the tape
+    /// grows at the front when the head moves past its left end.
+    fn on_tape_write(&mut self, _position: usize, _old_symbol: char, _new_symbol: char) {}
+
+    /// Called once when the run ends; `result.halted` tells whether the
+    /// machine halted or ran out of steps
+    fn on_halt(&mut self, _result: &ExecutionResult) {}
+
+    /// Approximate heap memory held by the observer, such as a recorded
+    /// trace, counted towards [`RunConfig::max_memory_bytes`]
+    fn memory_bytes(&self) -> usize {
+        0
+    }
+}
+
+/// The unit type observes nothing, for [`run_async`] calls that need no
+/// observer
+impl StepObserver for () {}
+
+/// Steps between two looks at the clock in a run with a timeout
+const CLOCK_INTERVAL: usize = 1024;
+
+/// Step limit of runs that set none
+pub const DEFAULT_MAX_STEPS: usize = 10000;
+
+/// Step limit of runs that go on until the machine halts, provably loops or
+/// is stopped, written `unlimited`
+pub const UNLIMITED_STEPS: usize = usize::MAX;
+
+/// Limits and checks for a single run
+///
+/// Head positions are counted in cells from the first input cell, so cell
+/// `-1` is the blank just left of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunConfig {
+    /// Steps after which the run stops without a verdict
+    pub max_steps: usize,
+    /// Memory used by the tape and by observers (traces, snapshots) after
+    /// which the run stops without a verdict; unlimited if `None`
+    pub max_memory_bytes: Option<usize>,
+    /// Tape cells, the input included, beyond which the tape may not grow;
+    /// a run that needs more stops without a verdict. Unlimited if `None`.
+    ///
+    /// Unlike the memory limit, this bounds the tape alone, independently
+    /// of the observers of the run and of how cells are stored.
+    pub max_cells: Option<usize>,
+    /// Fail the run if the head moves more than this many cells beyond the
+    /// region that has held non-blank symbols (the input and every cell
+    /// written with a non-blank)
+    pub max_head_overshoot: Option<usize>,
+    /// Fail the run if the head moves left of this cell
+    pub min_head_position: Option<i64>,
+    /// Time after which the run stops without a verdict, however many steps
+    /// it took; unlimited if `None`
+    ///
+    /// Steps take very different times once sweeps are taken in one macro
+    /// step, so the time a run may take is better bounded directly. The
+    /// clock is read every few thousand steps.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            max_steps: DEFAULT_MAX_STEPS,
+            max_memory_bytes: None,
+            max_cells: None,
+            max_head_overshoot: None,
+            min_head_position: None,
+            timeout: None,
+        }
+    }
+}
+
+/// How the tape a run left is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Every cell the run used
+    #[default]
+    Tape,
+    /// The tape without the blank cells at either end
+    TapeTrimmed,
+}
+
+impl OutputMode {
+    /// Parse a mode by its name in machine files
+    pub fn parse(value: &str) -> Result<OutputMode, String> {
+        match value {
+            "tape" => Ok(OutputMode::Tape),
+            "tape_trimmed" => Ok(OutputMode::TapeTrimmed),
+            _ => Err(format!(
+                "Invalid output mode '{}' (expected tape or tape_trimmed)",
+                value
+            )),
+        }
+    }
+
+    /// The cells of [`ExecutionResult::tape_cells`] to show, as named
+    /// symbols written one after the other
+    pub fn render(self, result: &ExecutionResult, blank: &str) -> String {
+        let cells = result.tape_cells.as_slice();
+        let cells = match self {
+            OutputMode::Tape => cells,
+            OutputMode::TapeTrimmed => {
+                let start = cells.iter().position(|c| c != blank).unwrap_or(0);
+                let end = cells.iter().rposition(|c| c != blank).map_or(0, |i| i + 1);
+                &cells[start.min(end)..end]
+            }
+        };
+        cells.concat()
+    }
+}
+
+/// Limits a machine recommends for its runs, each replacing the general
+/// default when set, and how their results are shown
+///
+/// A machine known to need many steps can raise `max_steps` here instead of
+/// every user raising it for every run. The names match the settings of the
+/// command line program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunDefaults {
+    /// Read from `"unlimited"` too, see [`UNLIMITED_STEPS`]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_max_steps",
+        serialize_with = "serialize_max_steps"
+    )]
+    pub max_steps: Option<usize>,
+    /// In bytes; read from text like `"512M"` too
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_size"
+    )]
+    pub max_memory: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_head_overshoot: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_head: Option<i64>,
+    /// Not a limit, so [`RunDefaults::apply`] leaves it to the caller
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_mode: Option<OutputMode>,
+}
+
+impl RunDefaults {
+    /// Whether no default is set
+    pub fn is_empty(&self) -> bool {
+        *self == RunDefaults::default()
+    }
+
+    /// `config` with every limit set here replaced
+    pub fn apply(&self, config: &RunConfig) -> RunConfig {
+        RunConfig {
+            max_steps: self.max_steps.unwrap_or(config.max_steps),
+            max_memory_bytes: self.max_memory.or(config.max_memory_bytes),
+            max_cells: config.max_cells,
+            max_head_overshoot: self.max_head_overshoot.or(config.max_head_overshoot),
+            min_head_position: self.min_head.or(config.min_head_position),
+            timeout: config.timeout,
+        }
+    }
+}
+
+/// Parse a size in bytes, optionally with a `K`, `M` or `G` suffix (powers
+/// of 1024)
+pub fn parse_size(value: &str) -> Result<usize, String> {
+    let text = value.trim();
+    let (digits, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let multiplier: usize = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "'{}' is not a size such as 4096, 64K or 512M",
+                value
+            ))
+        }
+    };
+    digits
+        .parse::<usize>()
+        .map_err(|_| format!("'{}' is not a non-negative number", value))?
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Size '{}' is too large", value))
+}
+
+/// Read a size given either as a number of bytes or as text like `"512M"`
+pub fn deserialize_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(usize),
+        Text(String),
+    }
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parse a duration: a number with an `ms`, `s`, `m` or `h` suffix, or a
+/// number of seconds without one
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let text = value.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let seconds_per_unit = match unit.trim() {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" | "min" => 60.0,
+        "h" => 3600.0,
+        _ => {
+            return Err(format!(
+                "'{}' is not a duration such as 500ms, 5s or 2m",
+                value
+            ))
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a non-negative duration", value))?;
+    Duration::try_from_secs_f64(number * seconds_per_unit)
+        .map_err(|_| format!("Duration '{}' is too long", value))
+}
+
+/// Parse a step limit: a number of steps or `unlimited`
+pub fn parse_max_steps(value: &str) -> Result<usize, String> {
+    let text = value.trim();
+    if text.eq_ignore_ascii_case("unlimited") {
+        return Ok(UNLIMITED_STEPS);
+    }
+    text.parse()
+        .map_err(|_| format!("'{}' is not a step limit such as 50000 or unlimited", value))
+}
+
+/// Read a step limit given either as a number or as `"unlimited"`
+pub fn deserialize_max_steps<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Limit {
+        Steps(usize),
+        Text(String),
+    }
+    match Option::<Limit>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Limit::Steps(steps)) => Ok(Some(steps)),
+        Some(Limit::Text(text)) => parse_max_steps(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Write a step limit as a number, or as `"unlimited"`, which formats
+/// without 64-bit integers such as TOML can hold too
+pub fn serialize_max_steps<S: Serializer>(
+    max_steps: &Option<usize>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match max_steps {
+        Some(UNLIMITED_STEPS) => serializer.serialize_str("unlimited"),
+        Some(steps) => serializer.serialize_some(steps),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Runs a machine, notifying observers along the way
+pub struct Executor<'a> {
+    machine: &'a TuringMachine,
+    observers: Vec<&'a mut dyn StepObserver>,
+    interrupt: Option<&'a AtomicBool>,
+}
+
+impl<'a> Executor<'a> {
+    /// Create an executor for a machine
+    pub fn new(machine: &'a TuringMachine) -> Self {
+        Executor {
+            machine,
+            observers: Vec::new(),
+            interrupt: None,
+        }
+    }
+
+    /// Register an observer
+    pub fn observe(mut self, observer: &'a mut dyn StepObserver) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Stop the run as soon as `flag` is set, for example from a Ctrl+C
+    /// handler
+    ///
+    /// The flag is checked before every step. An interrupted run returns the
+    /// configuration reached so far with `interrupted` set; the flag is left
+    /// set for the caller to reset.
+    pub fn interrupt_on(mut self, flag: &'a AtomicBool) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    /// Execute the machine on the given input with a step limit
+    pub fn run(self, input_string: &str, max_steps: usize) -> Result<ExecutionResult, String> {
+        self.run_with(
+            input_string,
+            &RunConfig {
+                max_steps,
+                ..RunConfig::default()
+            },
+        )
+    }
+
+    /// Execute the machine on the given input within the limits of `config`
+    ///
+    /// Runs inside an `execute` tracing span; every transition is logged at
+    /// trace level.
+    #[tracing::instrument(
+        name = "execute",
+        skip_all,
+        fields(input = input_string, max_steps = config.max_steps)
+    )]
+    pub fn run_with(
+        mut self,
+        input_string: &str,
+        config: &RunConfig,
+    ) -> Result<ExecutionResult, String> {
+        let observers = &mut self.observers;
+        let mut run = Run::start(self.machine, input_string, config, observers)?;
+        let verdict = loop {
+            if let Some(verdict) = run.step(config, self.interrupt, observers)? {
+                break verdict;
+            }
+        };
+        Ok(run.finish(verdict, observers))
+    }
+}
+
+/// A run advanced one step at a time by its caller
+///
+/// Where [`Executor::run_with`] runs a machine to the end at once, a
+/// stepper takes a step whenever [`Stepper::step`] is called, so a caller
+/// such as an interactive debugger computes only the steps it shows, and
+/// shows the first one without waiting for the rest. Steps are taken singly,
+/// as in observed runs, and reported to `observer` as with
+/// [`Executor::observe`]; the limits of the run are checked before every
+/// step, as by [`Executor::run_with`].
+pub struct Stepper<'a, O: StepObserver> {
+    run: Run<'a>,
+    config: RunConfig,
+    observer: O,
+    /// How the run ended, once it has
+    result: Option<ExecutionResult>,
+    /// The error that ended the run, if one did
+    error: Option<String>,
+}
+
+impl<'a, O: StepObserver> Stepper<'a, O> {
+    /// Start a run of `machine` on `input_string` within the limits of
+    /// `config`
+    pub fn start(
+        machine: &'a TuringMachine,
+        input_string: &str,
+        config: &RunConfig,
+        mut observer: O,
+    ) -> Result<Self, String> {
+        let run = Run::start(machine, input_string, config, &[&mut observer])?;
+        Ok(Stepper {
+            run,
+            config: config.clone(),
+            observer,
+            result: None,
+            error: None,
+        })
+    }
+
+    /// Take the next step; returns `false` without changing anything once
+    /// the run is over, when [`Stepper::result`] tells how it ended
+    ///
+    /// An error, such as the head moving out of its bounds, also ends the
+    /// run, and is returned again by every later call.
+    pub fn step(&mut self) -> Result<bool, String> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        if self.result.is_some() {
+            return Ok(false);
+        }
+        let observers: &mut [&mut dyn StepObserver] = &mut [&mut self.observer];
+        match self.run.step(&self.config, None, observers) {
+            Ok(None) => Ok(true),
+            Ok(Some(verdict)) => {
+                self.result = Some(self.run.finish(verdict, observers));
+                Ok(false)
+            }
+            Err(error) => {
+                self.error = Some(error.clone());
+                Err(error)
+            }
+        }
+    }
+
+    /// Number of transitions executed so far
+    pub fn steps(&self) -> usize {
+        self.run.steps
+    }
+
+    /// The current configuration
+    pub fn snapshot(&self) -> ExecutionSnapshot {
+        ExecutionSnapshot {
+            tape: self.run.tape.cells().to_vec(),
+            head_position: self.run.head_position,
+            current_state: self.run.program.name(self.run.current_state).to_string(),
+            step: self.run.steps,
+            symbols: self.run.machine.symbols.clone(),
+        }
+    }
+
+    /// How the run ended, or `None` while it goes on or if it failed
+    pub fn result(&self) -> Option<&ExecutionResult> {
+        self.result.as_ref()
+    }
+
+    /// The observer of the run
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+}
+
+/// Take `steps` steps of `machine` again from `snapshot`, a configuration
+/// an earlier run reached, reporting them to `observer`
+///
+/// `input_start` is the first input cell of the tape of `snapshot`, and
+/// `input_len` the length of the input. The steps were checked by the
+/// earlier run, so no limits apply but the step count.
+pub(crate) fn replay(
+    machine: &TuringMachine,
+    snapshot: &ExecutionSnapshot,
+    input_start: usize,
+    input_len: usize,
+    steps: usize,
+    observer: &mut dyn StepObserver,
+) -> Result<(), String> {
+    let config = RunConfig {
+        max_steps: snapshot.step + steps,
+        ..RunConfig::default()
+    };
+    let mut run = Run::resume(machine, snapshot, input_start, input_len)?;
+    let observers: &mut [&mut dyn StepObserver] = &mut [observer];
+    while run.step(&config, None, observers)?.is_none() {}
+    Ok(())
+}
+
+/// Run `machine` as [`Executor::run_with`] does without observers, also
+/// returning how many cells the tape had at the start of the run and at
+/// its end
+pub(crate) fn run_measuring_tape(
+    machine: &TuringMachine,
+    input_string: &str,
+    config: &RunConfig,
+    interrupt: Option<&AtomicBool>,
+) -> Result<(ExecutionResult, usize, usize), String> {
+    let mut run = Run::start(machine, input_string, config, &[])?;
+    let start_cells = run.tape.len();
+    let verdict = loop {
+        if let Some(verdict) = run.step(config, interrupt, &mut [])? {
+            break verdict;
+        }
+    };
+    Ok((run.finish(verdict, &mut []), start_cells, run.tape.len()))
+}
+
+/// Execute `machine` on the given input within the limits of `config`,
+/// yielding to the async runtime every `options.yield_every` steps
+///
+/// The asynchronous counterpart of [`Executor::run_with`], for servers that
+/// run machines on the threads of an async runtime: a long run no longer
+/// holds up the other tasks of its thread. A single observer is notified
+/// as with [`Executor::observe`]; the future can be sent between threads
+/// if the observer can. Works with any runtime.
+#[tracing::instrument(
+    name = "execute",
+    skip_all,
+    fields(input = input_string, max_steps = config.max_steps)
+)]
+pub async fn run_async(
+    machine: &TuringMachine,
+    input_string: &str,
+    config: &RunConfig,
+    options: &AsyncOptions,
+    observer: &mut impl StepObserver,
+) -> Result<ExecutionResult, String> {
+    let interrupt = options.cancel.as_ref().map(|token| token.0.as_ref());
+    let yield_every = options.yield_every.max(1);
+    let mut run = Run::start(machine, input_string, config, &[&mut *observer])?;
+    let verdict = loop {
+        if let Some(verdict) = run.step(config, interrupt, &mut [&mut *observer])? {
+            break verdict;
+        }
+        if run.steps % yield_every == 0 {
+            YieldNow(false).await;
+        }
+    };
+    Ok(run.finish(verdict, &mut [observer]))
+}
+
+/// How an asynchronous run shares its thread
+#[derive(Debug, Clone)]
+pub struct AsyncOptions {
+    /// Steps taken between yields to the runtime
+    pub yield_every: usize,
+    /// Token stopping the run, which then returns the configuration reached
+    /// with `interrupted` set
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for AsyncOptions {
+    fn default() -> Self {
+        AsyncOptions {
+            yield_every: 1000,
+            cancel: None,
+        }
+    }
+}
+
+/// Handle for stopping asynchronous runs from elsewhere, such as another
+/// task; clones stop the same runs
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the runs using this token before their next step
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Future that is pending once, so the runtime can run other tasks
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Whether a run that ended accepts and whether it halted, as in
+/// [`ExecutionResult`]
+type Verdict = (Option<bool>, bool);
+
+/// A run between two steps
+struct Run<'a> {
+    machine: &'a TuringMachine,
+    program: &'a Program,
+    tape: Tape,
+    head_position: i32,
+    input_len: usize,
+    /// First cell of the input, which shifts right as the tape grows to the
+    /// left
+    input_start: usize,
+    /// Cells that have held a non-blank symbol, relative to the input
+    used: Option<(i64, i64)>,
+    current_state: StateId,
+    steps: usize,
+    interrupted: bool,
+    resource_limit: Option<ResourceLimit>,
+    peak_memory_bytes: usize,
+    /// When the run times out, if it has a timeout
+    deadline: Option<Instant>,
+    /// Steps left before the next look at the clock
+    until_clock: usize,
+    timed_out: bool,
+    /// Sum of [`cell_hash`] over the non-blank cells
+    tape_hash: u64,
+    /// Configuration later ones are compared with
+    saved: Saved,
+    /// Step at which the next configuration is saved
+    next_save: usize,
+    /// Watch for translated cycles, where they can be told apart
+    recurrence: Option<Recurrence>,
+    cycle: Option<Cycle>,
+}
+
+/// A configuration kept to notice the run coming back to it
+struct Saved {
+    step: usize,
+    state: StateId,
+    /// Head cell, relative to the input
+    head: i64,
+    tape_hash: u64,
+    /// The non-blank part of the tape and its first cell, relative to the
+    /// input
+    cells: Vec<char>,
+    first: i64,
+}
+
+impl<'a> Run<'a> {
+    /// The initial configuration of `machine` on `input_string`
+    fn start(
+        machine: &'a TuringMachine,
+        input_string: &str,
+        config: &RunConfig,
+        observers: &[&mut dyn StepObserver],
+    ) -> Result<Self, String> {
+        let input = machine.map_input(input_string)?;
+
+        // Validate input symbols
+        for symbol in &input {
+            if !machine.alphabet.contains(symbol) {
+                return Err(format!(
+                    "Invalid input symbol: {}",
+                    machine.symbols.name(*symbol)
+                ));
+            }
+        }
+
+        // Initialize tape with input
+        let tape = Tape::new(machine.start_tape(&input)?);
+        let input_len = input.len();
+        let input_start = machine.tape_setup.input_start;
+        let mut used = (input_len > 0).then(|| (0, input_len as i64 - 1));
+        for (position, symbol) in tape.cells().iter().enumerate() {
+            if *symbol != machine.blank_symbol {
+                let cell = position as i64 - input_start as i64;
+                used = Some(used.map_or((cell, cell), |(lo, hi)| (lo.min(cell), hi.max(cell))));
+            }
+        }
+        let program = machine.program();
+        let tape_hash = tape
+            .cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| **symbol != machine.blank_symbol)
+            .map(|(position, symbol)| cell_hash(position as i64 - input_start as i64, *symbol))
+            .fold(0, u64::wrapping_add);
+        let mut run = Run {
+            machine,
+            program,
+            peak_memory_bytes: memory_bytes(&tape, observers),
+            tape,
+            head_position: machine.tape_setup.head() as i32,
+            input_len,
+            input_start,
+            used,
+            current_state: program.initial(),
+            steps: 0,
+            interrupted: false,
+            resource_limit: None,
+            // Runs without a timeout never read the clock, which some
+            // targets such as WebAssembly in browsers do not have
+            deadline: config
+                .timeout
+                .and_then(|timeout| Instant::now().checked_add(timeout)),
+            until_clock: CLOCK_INTERVAL,
+            timed_out: false,
+            tape_hash,
+            saved: Saved {
+                step: 0,
+                state: program.initial(),
+                head: 0,
+                tape_hash: 0,
+                cells: Vec::new(),
+                first: 0,
+            },
+            next_save: 0,
+            recurrence: (machine.tape_length.is_none() && !machine.read_only_input)
+                .then(|| Recurrence::new(machine.blank_symbol)),
+            cycle: None,
+        };
+        run.save();
+        Ok(run)
+    }
+
+    /// The configuration `snapshot` of `machine`, reached by an earlier run
+    /// after the tape grew left until its input started at `input_start`,
+    /// to take the same steps again
+    ///
+    /// Cycles are not looked for again: the run goes on to its step limit.
+    fn resume(
+        machine: &'a TuringMachine,
+        snapshot: &ExecutionSnapshot,
+        input_start: usize,
+        input_len: usize,
+    ) -> Result<Self, String> {
+        let program = machine.program();
+        let state = program
+            .id(&snapshot.current_state)
+            .ok_or_else(|| format!("Unknown state: {}", snapshot.current_state))?;
+        Ok(Run {
+            machine,
+            program,
+            tape: Tape::new(snapshot.tape.clone()),
+            head_position: snapshot.head_position,
+            input_len,
+            input_start,
+            used: None,
+            current_state: state,
+            steps: snapshot.step,
+            interrupted: false,
+            resource_limit: None,
+            peak_memory_bytes: 0,
+            deadline: None,
+            until_clock: CLOCK_INTERVAL,
+            timed_out: false,
+            tape_hash: 0,
+            saved: Saved {
+                step: usize::MAX,
+                state,
+                head: 0,
+                tape_hash: 0,
+                cells: Vec::new(),
+                first: 0,
+            },
+            next_save: usize::MAX,
+            recurrence: None,
+            cycle: None,
+        })
+    }
+
+    /// Head cell, relative to the input
+    fn head_cell(&self) -> i64 {
+        self.head_position as i64 - self.input_start as i64
+    }
+
+    /// The non-blank part of the tape and its first cell, relative to the
+    /// input
+    fn written(&self) -> (&[char], i64) {
+        let blank = self.machine.blank_symbol;
+        let cells = self.tape.cells();
+        let Some(first) = cells.iter().position(|&symbol| symbol != blank) else {
+            return (&[], 0);
+        };
+        let last = cells
+            .iter()
+            .rposition(|&symbol| symbol != blank)
+            .unwrap_or(first);
+        (&cells[first..=last], first as i64 - self.input_start as i64)
+    }
+
+    /// Keep the current configuration for later ones to be compared with,
+    /// and double the steps until the next one is kept
+    fn save(&mut self) {
+        let (cells, first) = self.written();
+        let (cells, first) = (cells.to_vec(), first);
+        self.saved = Saved {
+            step: self.steps,
+            state: self.current_state,
+            head: self.head_cell(),
+            tape_hash: self.tape_hash,
+            cells,
+            first,
+        };
+        self.next_save = (self.steps * 2).max(1);
+    }
+
+    /// Whether the current configuration is the saved one, come back
+    fn repeats_saved(&self) -> bool {
+        let saved = &self.saved;
+        self.steps > saved.step
+            && self.current_state == saved.state
+            && self.tape_hash == saved.tape_hash
+            && self.head_cell() == saved.head
+            && self.written() == (saved.cells.as_slice(), saved.first)
+    }
+
+    /// The translated cycle the current configuration completes, if any
+    fn translated_cycle(&mut self, config: &RunConfig) -> Option<Cycle> {
+        if config.min_head_position.is_some() || config.max_head_overshoot.is_some() {
+            return None;
+        }
+        let head = self.head_cell();
+        let first = -(self.input_start as i64);
+        let recurrence = self.recurrence.as_mut()?;
+        recurrence.watch(&Configuration {
+            step: self.steps,
+            state: self.current_state,
+            head,
+            used: self.used,
+            cells: self.tape.cells(),
+            first,
+            left_bound: self.machine.left_edge.map(|_| first),
+        })
+    }
+
+    /// Account for `position` changing from `old` to `new` in the tape hash
+    fn rehash(&mut self, position: usize, old: char, new: char) {
+        let blank = self.machine.blank_symbol;
+        let cell = position as i64 - self.input_start as i64;
+        if old != blank {
+            self.tape_hash = self.tape_hash.wrapping_sub(cell_hash(cell, old));
+        }
+        if new != blank {
+            self.tape_hash = self.tape_hash.wrapping_add(cell_hash(cell, new));
+        }
+    }
+
+    /// Take the next step, or return the verdict if the run is over
+    fn step(
+        &mut self,
+        config: &RunConfig,
+        interrupt: Option<&AtomicBool>,
+        observers: &mut [&mut dyn StepObserver],
+    ) -> Result<Option<Verdict>, String> {
+        let (machine, program) = (self.machine, self.program);
+        if interrupt.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            self.interrupted = true;
+            return Ok(Some((None, false)));
+        }
+
+        if let Some(deadline) = self.deadline {
+            self.until_clock -= 1;
+            if self.until_clock == 0 {
+                self.until_clock = CLOCK_INTERVAL;
+                if Instant::now() >= deadline {
+                    self.timed_out = true;
+                    return Ok(Some((None, false)));
+                }
+            }
+        }
+
+        if config
+            .max_memory_bytes
+            .is_some_and(|limit| self.peak_memory_bytes > limit)
+        {
+            self.resource_limit = Some(ResourceLimit::Memory);
+            return Ok(Some((None, false)));
+        }
+
+        let cycle = if self.repeats_saved() {
+            Some(Cycle {
+                length: self.steps - self.saved.step,
+                step: self.steps,
+                shift: 0,
+            })
+        } else {
+            self.translated_cycle(config)
+        };
+        if let Some(cycle) = cycle {
+            debug!(
+                length = cycle.length,
+                step = cycle.step,
+                shift = cycle.shift,
+                "cycle"
+            );
+            self.cycle = Some(cycle);
+            return Ok(Some((None, false)));
+        }
+        if self.steps >= self.next_save {
+            self.save();
+        }
+
+        if self.steps >= config.max_steps {
+            // Max steps reached - likely infinite loop
+            return Ok(Some((None, false)));
+        }
+
+        // Check if in halting state
+        if let Some(accepts) = program.halts(self.current_state) {
+            return Ok(Some((Some(accepts), true)));
+        }
+
+        // Extend tape if needed, unless it already has all the cells it may
+        let grows = self.head_position < 0 || self.head_position >= self.tape.len() as i32;
+        if grows
+            && config
+                .max_cells
+                .is_some_and(|limit| self.tape.len() >= limit)
+        {
+            self.resource_limit = Some(ResourceLimit::Cells);
+            return Ok(Some((None, false)));
+        }
+        if self.head_position < 0 {
+            self.tape.push_front(machine.blank_symbol);
+            self.head_position = 0;
+            self.input_start += 1;
+        }
+        if self.head_position >= self.tape.len() as i32 {
+            self.tape.push_back(machine.blank_symbol);
+        }
+
+        // Read current symbol
+        let position = self.head_position as usize;
+        let current_symbol = self.tape.cells()[position];
+
+        // Look up transition
+        let Some(target) = program.target(self.current_state, current_symbol) else {
+            // No transition defined - implicit reject, or accept when
+            // the machine accepts by halting
+            return Ok(Some((Some(machine.accept_on_halt), true)));
+        };
+
+        // A left-bounded tape never grows left, so cell 0 is its first cell
+        let (write_symbol, mut direction) = (target.write, target.direction);
+        if direction == Direction::L && self.head_position == 0 && machine.tape_length.is_none() {
+            match machine.left_edge {
+                Some(LeftEdge::Stay) => direction = Direction::S,
+                Some(LeftEdge::Crash) => {
+                    return Err(format!(
+                        "Step {}: state {} moved the head off the left end of the tape",
+                        self.steps + 1,
+                        program.name(self.current_state)
+                    ))
+                }
+                Some(LeftEdge::Reject) => return Ok(Some((Some(false), true))),
+                None => {}
+            }
+        }
+
+        let input_cells = self.input_start..self.input_start + self.input_len;
+        if machine.read_only_input
+            && write_symbol != current_symbol
+            && input_cells.contains(&position)
+        {
+            return Err(format!(
+                "Step {}: state {} overwrote read-only input cell {} ('{}' with '{}')",
+                self.steps + 1,
+                program.name(self.current_state),
+                position - self.input_start,
+                machine.symbols.name(current_symbol),
+                machine.symbols.name(write_symbol)
+            ));
+        }
+
+        // Sweep across a run of the symbol just read in one macro step
+        let length = if observers.is_empty() && target.state == self.current_state {
+            self.sweep_length(config, current_symbol, write_symbol, direction)
+        } else {
+            1
+        };
+        if length > 1 {
+            let cells = match direction {
+                Direction::R => position..position + length,
+                _ => position + 1 - length..position + 1,
+            };
+            if write_symbol != machine.blank_symbol {
+                let (first, last) = (cells.start as i64, cells.end as i64 - 1);
+                let (first, last) = (
+                    first - self.input_start as i64,
+                    last - self.input_start as i64,
+                );
+                self.used = Some(
+                    self.used
+                        .map_or((first, last), |(lo, hi)| (lo.min(first), hi.max(last))),
+                );
+            }
+            if write_symbol != current_symbol {
+                for position in cells.clone() {
+                    self.rehash(position, current_symbol, write_symbol);
+                }
+                self.tape.cells_mut()[cells].fill(write_symbol);
+            }
+            match direction {
+                Direction::R => self.head_position += length as i32,
+                _ => self.head_position -= length as i32,
+            }
+            self.steps += length;
+            trace!(
+                step = self.steps,
+                state = %program.name(self.current_state),
+                read = %current_symbol,
+                write = %write_symbol,
+                direction = ?direction,
+                steps = length,
+                head = self.head_position,
+                "sweep"
+            );
+            return Ok(None);
+        }
+
+        if write_symbol != machine.blank_symbol {
+            let cell = position as i64 - self.input_start as i64;
+            self.used = Some(
+                self.used
+                    .map_or((cell, cell), |(lo, hi)| (lo.min(cell), hi.max(cell))),
+            );
+        }
+
+        // Write symbol
+        if write_symbol != current_symbol {
+            self.rehash(position, current_symbol, write_symbol);
+        }
+        self.tape.cells_mut()[position] = write_symbol;
+        for observer in observers.iter_mut() {
+            observer.on_tape_write(position, current_symbol, write_symbol);
+        }
+
+        // Move head, wrapping around a circular tape
+        match direction {
+            Direction::L => self.head_position -= 1,
+            Direction::R => self.head_position += 1,
+            Direction::S => {}
+        }
+        if let Some(length) = machine.tape_length {
+            self.head_position = self.head_position.rem_euclid(length as i32);
+        }
+
+        // Update state
+        let previous_state = program.name(self.current_state);
+        self.current_state = target.state;
+        self.steps += 1;
+        trace!(
+            step = self.steps,
+            state = %previous_state,
+            read = %current_symbol,
+            write = %write_symbol,
+            direction = ?direction,
+            head = self.head_position,
+            "transition"
+        );
+        check_head(
+            config,
+            self.head_position as i64 - self.input_start as i64,
+            self.used,
+            self.steps,
+            previous_state,
+        )?;
+
+        let memory_bytes = memory_bytes(&self.tape, observers);
+        self.peak_memory_bytes = self.peak_memory_bytes.max(memory_bytes);
+        if !observers.is_empty() {
+            let event = StepEvent {
+                step: self.steps,
+                from_state: previous_state,
+                read_symbol: current_symbol,
+                to_state: program.name(self.current_state),
+                write_symbol,
+                direction,
+                head_position: self.head_position,
+                tape: self.tape.cells(),
+                memory_bytes,
+                symbols: &self.machine.symbols,
+            };
+            for observer in observers.iter_mut() {
+                observer.on_step(&event);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Steps a transition looping on its state takes in a row, sweeping
+    /// across the cells holding `read` from the head on, or 1 if its steps
+    /// must be taken one at a time
+    ///
+    /// Only unobserved runs sweep, within the tape as it is, without head
+    /// bounds to check, a circular tape or a read-only input to protect.
+    /// A sweep stops short of the left end of a left-bounded tape, where
+    /// the edge policy applies, and of the step limit.
+    fn sweep_length(
+        &self,
+        config: &RunConfig,
+        read: char,
+        write: char,
+        direction: Direction,
+    ) -> usize {
+        let machine = self.machine;
+        if direction == Direction::S
+            || config.min_head_position.is_some()
+            || config.max_head_overshoot.is_some()
+            || machine.tape_length.is_some()
+            || (machine.read_only_input && write != read)
+        {
+            return 1;
+        }
+        let limit = config.max_steps - self.steps;
+        let cells = self.tape.cells();
+        let position = self.head_position as usize;
+        let length = match direction {
+            Direction::R => cells[position..]
+                .iter()
+                .take(limit)
+                .take_while(|&&symbol| symbol == read)
+                .count(),
+            _ => {
+                // Cell 0 is left to a single step when the edge has a policy
+                let first = usize::from(machine.left_edge.is_some());
+                cells[first.min(position)..=position]
+                    .iter()
+                    .rev()
+                    .take(limit)
+                    .take_while(|&&symbol| symbol == read)
+                    .count()
+            }
+        };
+        length.max(1)
+    }
+
+    /// The result of the run, which ended with `verdict`
+    fn finish(
+        &self,
+        (accepts, halted): Verdict,
+        observers: &mut [&mut dyn StepObserver],
+    ) -> ExecutionResult {
+        let machine = self.machine;
+        let result = ExecutionResult {
+            accepts,
+            steps: self.steps,
+            halted,
+            tape: machine.symbols.render(self.tape.cells()),
+            tape_cells: machine.symbols.names(self.tape.cells()),
+            head_position: self.head_position,
+            interrupted: self.interrupted,
+            memory_limit_exceeded: self.resource_limit == Some(ResourceLimit::Memory),
+            resource_limit: self.resource_limit,
+            timed_out: self.timed_out,
+            peak_memory_bytes: self
+                .peak_memory_bytes
+                .max(memory_bytes(&self.tape, observers)),
+            output: halted
+                .then(|| machine.read_output(self.tape.cells()))
+                .flatten(),
+            final_state: self.program.name(self.current_state).to_string(),
+            cycle: self.cycle,
+        };
+        debug!(
+            accepts = ?result.accepts,
+            final_state = %result.final_state,
+            steps = result.steps,
+            halted = result.halted,
+            interrupted = result.interrupted,
+            resource_limit = ?result.resource_limit,
+            timed_out = result.timed_out,
+            peak_memory_bytes = result.peak_memory_bytes,
+            "finished"
+        );
+        for observer in observers.iter_mut() {
+            observer.on_halt(&result);
+        }
+        result
+    }
+}
+
+/// Hash of a cell holding `symbol`, summed over the cells of a tape
+fn cell_hash(cell: i64, symbol: char) -> u64 {
+    // The finalizer of SplitMix64, which spreads small differences in the
+    // cell or symbol over every bit
+    let mut hash = (cell as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ symbol as u64;
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Approximate memory used by the tape and the observers
+fn memory_bytes(tape: &Tape, observers: &[&mut dyn StepObserver]) -> usize {
+    tape.capacity() * mem::size_of::<char>()
+        + observers
+            .iter()
+            .map(|observer| observer.memory_bytes())
+            .sum::<usize>()
+}
+
+/// Check the head bounds of `config` after a step that left `state`
+///
+/// `cell` is the head position and `used` the range of cells that have held
+/// non-blank symbols, both relative to the first input cell.
+fn check_head(
+    config: &RunConfig,
+    cell: i64,
+    used: Option<(i64, i64)>,
+    step: usize,
+    state: &str,
+) -> Result<(), String> {
+    if let Some(bound) = config.min_head_position {
+        if cell < bound {
+            return Err(format!(
+                "Step {}: state {} moved the head to cell {}, left of the bound {}",
+                step, state, cell, bound
+            ));
+        }
+    }
+    if let Some(limit) = config.max_head_overshoot {
+        let (lo, hi) = used.unwrap_or((0, 0));
+        let overshoot = (lo - cell).max(cell - hi).max(0);
+        if overshoot > limit as i64 {
+            return Err(format!(
+                "Step {}: state {} moved the head to cell {}, {} cells beyond the used tape (limit {})",
+                step, state, cell, overshoot, limit
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Observer that records a snapshot of the configuration at every step
+pub struct SnapshotRecorder {
+    pub snapshots: Vec<ExecutionSnapshot>,
+    bytes: usize,
+    symbols: Arc<SymbolTable>,
+}
+
+impl SnapshotRecorder {
+    /// Create a recorder holding the initial configuration for `input_string`
+    pub fn new(machine: &TuringMachine, input_string: &str) -> Self {
+        // An input that does not fit fails the run itself
+        let input = machine.map_input(input_string).unwrap_or_default();
+        let snapshot = ExecutionSnapshot {
+            tape: machine.start_tape(&input).unwrap_or(input),
+            head_position: machine.tape_setup.head() as i32,
+            current_state: machine.initial_state.clone(),
+            step: 0,
+            symbols: machine.symbols.clone(),
+        };
+        SnapshotRecorder {
+            bytes: snapshot_bytes(&snapshot),
+            snapshots: vec![snapshot],
+            symbols: machine.symbols.clone(),
+        }
+    }
+}
+
+impl StepObserver for SnapshotRecorder {
+    fn on_step(&mut self, event: &StepEvent) {
+        let snapshot = ExecutionSnapshot {
+            tape: event.tape.to_vec(),
+            head_position: event.head_position,
+            current_state: event.to_state.to_string(),
+            step: event.step,
+            symbols: self.symbols.clone(),
+        };
+        self.bytes += snapshot_bytes(&snapshot);
+        self.snapshots.push(snapshot);
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Approximate memory held by one snapshot
+fn snapshot_bytes(snapshot: &ExecutionSnapshot) -> usize {
+    mem::size_of::<ExecutionSnapshot>()
+        + mem::size_of_val(snapshot.tape.as_slice())
+        + snapshot.current_state.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{parse_machine_definition, parse_machine_json};
+
+    const MACHINE: &str = r#"{
+        "states": ["q0", "accept"],
+        "alphabet": ["1"],
+        "tape_alphabet": ["1", "_"],
+        "initial_state": "q0",
+        "accept_states": ["accept"],
+        "reject_states": [],
+        "blank_symbol": "_",
+        "defaults": {"max_steps": 100000, "output_mode": "tape_trimmed"},
+        "transitions": {
+            "q0,1": ["q0", "1", "R"],
+            "q0,_": ["accept", "_", "R"]
+        }
+    }"#;
+
+    #[test]
+    fn loads_output_mode_from_machine_defaults() {
+        let definition = parse_machine_definition(MACHINE).unwrap();
+        let machine = parse_machine_json(&definition).unwrap();
+        let defaults = machine.run_defaults();
+        assert_eq!(defaults.max_steps, Some(100000));
+        assert_eq!(defaults.output_mode, Some(OutputMode::TapeTrimmed));
+    }
+
+    #[test]
+    fn trims_blanks_only_in_trimmed_mode() {
+        let definition = parse_machine_definition(MACHINE).unwrap();
+        let machine = parse_machine_json(&definition).unwrap();
+        let result = machine.execute("11", 1000).unwrap();
+        assert_eq!(
+            OutputMode::Tape.render(&result, "_"),
+            result.tape_cells.concat()
+        );
+        assert!(OutputMode::Tape.render(&result, "_").ends_with('_'));
+        assert_eq!(OutputMode::TapeTrimmed.render(&result, "_"), "11");
+    }
+
+    #[test]
+    fn names_symbols_overwriting_read_only_input() {
+        let definition = parse_machine_definition(
+            r#"{
+                "states": ["q0", "acc"],
+                "alphabet": ["a"],
+                "tape_alphabet": ["a", "X1", "_"],
+                "initial_state": "q0",
+                "accept_states": ["acc"],
+                "reject_states": [],
+                "read_only_input": true,
+                "transitions": {"q0,a": ["acc", "X1", "R"]}
+            }"#,
+        )
+        .unwrap();
+        let machine = parse_machine_json(&definition).unwrap();
+        let error = machine.execute("a", 100).unwrap_err();
+        assert!(error.ends_with("('a' with 'X1')"), "{}", error);
+    }
+
+    #[test]
+    fn parses_output_modes_by_name() {
+        assert_eq!(OutputMode::parse("tape"), Ok(OutputMode::Tape));
+        assert_eq!(
+            OutputMode::parse("tape_trimmed"),
+            Ok(OutputMode::TapeTrimmed)
+        );
+        assert!(OutputMode::parse("trimmed").is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Textbook notation for machines and their configurations
+//!
+//! A configuration is written `u q v`: the machine is in state `q`, the tape
+//! holds `uv`, and the head is on the first symbol of `v`. Blanks beyond the
+//! visited tape are left out, except the one under the head. A machine is
+//! written as its formal tuple (Q, Σ, Γ, δ, q₀, F, R), with sets of accept and
+//! reject states.
+//!
+//! [`ConfigurationTrace`] writes every configuration of a run this way, one
+//! per line, the sequence used in proofs:
+//!
+//! ```text
+//! q0 0110
+//! 0 q0 110
+//! 01 q1 10
+//! ```
+
+use crate::executor::{StepEvent, StepObserver};
+use crate::{ExecutionResult, LeftEdge, Output, ResourceLimit, SymbolTable, TuringMachine};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io;
+
+/// A configuration in `u q v` notation, with symbols written by name
+pub fn configuration(
+    tape: &[char],
+    head_position: i32,
+    state: &str,
+    blank_symbol: char,
+    symbols: &SymbolTable,
+) -> String {
+    let head = head_position.clamp(0, tape.len() as i32) as usize;
+    let mut u = &tape[..head];
+    let mut v = tape[head..].to_vec();
+    if head_position < 0 {
+        v.insert(0, blank_symbol);
+    }
+    while let [blank, rest @ ..] = u {
+        if *blank != blank_symbol {
+            break;
+        }
+        u = rest;
+    }
+    while v.last() == Some(&blank_symbol) {
+        v.pop();
+    }
+    if v.is_empty() {
+        v.push(blank_symbol);
+    }
+    let u = symbols.render(u);
+    let v = symbols.render(&v);
+
+    if u.is_empty() {
+        format!("{} {}", state, v)
+    } else {
+        format!("{} {} {}", u, state, v)
+    }
+}
+
+/// The machine as its formal tuple, with δ listed entry by entry
+pub fn formal_definition(machine: &TuringMachine) -> String {
+    fn set<T: ToString>(items: impl IntoIterator<Item = T>) -> String {
+        let mut items: Vec<String> = items.into_iter().map(|i| i.to_string()).collect();
+        items.sort();
+        format!("{{{}}}", items.join(", "))
+    }
+    let symbols = machine.symbols();
+    let names = |set: &HashSet<char>| {
+        set.iter()
+            .map(|symbol| symbols.name(*symbol))
+            .collect::<Vec<_>>()
+    };
+
+    let mut text = String::from("M = (Q, Σ, Γ, δ, q₀, F, R) where\n");
+    let _ = writeln!(text, "  Q  = {}", set(&machine.states));
+    let _ = writeln!(text, "  Σ  = {}", set(names(&machine.alphabet)));
+    let _ = writeln!(
+        text,
+        "  Γ  = {}  ({} is the blank)",
+        set(names(&machine.tape_alphabet)),
+        symbols.name(machine.blank_symbol)
+    );
+    let _ = writeln!(text, "  q₀ = {}", machine.initial_state);
+    let _ = writeln!(
+        text,
+        "  F  = {}  (accept states)",
+        set(&machine.accept_states)
+    );
+    let _ = writeln!(
+        text,
+        "  R  = {}  (reject states)",
+        set(&machine.reject_states)
+    );
+    if machine.accept_on_halt {
+        let _ = writeln!(
+            text,
+            "  M accepts by halting: a configuration without a transition accepts unless its state is in R"
+        );
+    }
+    let _ = writeln!(text, "  δ: Q × Γ → Q × Γ × {{L, R, S}} is given by");
+
+    let mut transitions: Vec<_> = machine.transitions.iter().collect();
+    transitions.sort_by(|a, b| a.0.cmp(b.0));
+    for ((state, symbol), (new_state, write_symbol, direction)) in transitions {
+        let _ = writeln!(
+            text,
+            "    δ({}, {}) = ({}, {}, {:?})",
+            state,
+            symbols.name(*symbol),
+            new_state,
+            symbols.name(*write_symbol),
+            direction
+        );
+    }
+    text.push_str("  and is undefined elsewhere; an undefined move rejects");
+    if let Some(length) = machine.tape_length {
+        let _ = write!(
+            text,
+            "\nThe tape is circular with {} cells; the head wraps around at either end",
+            length
+        );
+    }
+    let edge = match machine.left_edge.filter(|_| machine.tape_length.is_none()) {
+        None => None,
+        Some(LeftEdge::Stay) => Some("a left move there stays put"),
+        Some(LeftEdge::Crash) => Some("a left move there is an error"),
+        Some(LeftEdge::Reject) => Some("a left move there rejects"),
+    };
+    if let Some(edge) = edge {
+        let _ = write!(
+            text,
+            "\nThe tape is bounded on the left at its first cell; {}",
+            edge
+        );
+    }
+    match machine.output {
+        None => {}
+        Some(Output::Tape) => text.push_str(
+            "\nThe machine computes a function; its output is the tape once it halts, without leading and trailing blanks",
+        ),
+        Some(Output::Between(start, end)) => {
+            let _ = write!(
+                text,
+                "\nThe machine computes a function; its output is what lies between the first {} and the next {} once it halts",
+                symbols.name(start),
+                symbols.name(end)
+            );
+        }
+    }
+    text
+}
+
+/// The final configuration of a run and the halting condition it meets
+pub fn explain(machine: &TuringMachine, result: &ExecutionResult) -> String {
+    let symbols = machine.symbols();
+    let tape: Vec<char> = result
+        .tape_cells
+        .iter()
+        .map(|name| symbols.get(name).unwrap_or(machine.blank_symbol))
+        .collect();
+    let state = &result.final_state;
+    let mut text = format!(
+        "Final configuration: ⟨{}⟩\n",
+        configuration(
+            &tape,
+            result.head_position,
+            state,
+            machine.blank_symbol,
+            symbols
+        )
+    );
+
+    let read = usize::try_from(result.head_position)
+        .ok()
+        .and_then(|head| tape.get(head).copied())
+        .unwrap_or(machine.blank_symbol);
+    let reason = match result.accepts {
+        Some(true) if machine.accept_states.contains(state) => format!(
+            "{} ∈ F, so this is an accepting configuration and M accepts the input.",
+            state
+        ),
+        Some(true) => format!(
+            "δ({}, {}) is undefined, so M halts, and since M accepts by halting outside R, it accepts the input.",
+            state,
+            symbols.name(read)
+        ),
+        Some(false) if machine.reject_states.contains(state) => format!(
+            "{} ∈ R, so this is a rejecting configuration and M rejects the input.",
+            state
+        ),
+        Some(false) => {
+            if machine.transitions.contains_key(&(state.clone(), read)) {
+                format!(
+                    "δ({}, {}) moves the head left from the first cell of the left-bounded tape, so M rejects the input.",
+                    state,
+                    symbols.name(read)
+                )
+            } else {
+                format!(
+                    "δ({}, {}) is undefined, so M halts in a state outside F and rejects the input.",
+                    state,
+                    symbols.name(read)
+                )
+            }
+        }
+        None if result.interrupted => format!(
+            "The run was interrupted after {} steps, before reaching a halting configuration.",
+            result.steps
+        ),
+        None if result.timed_out => format!(
+            "The time limit was reached after {} steps, before reaching a halting configuration.",
+            result.steps
+        ),
+        None if result.memory_limit_exceeded => format!(
+            "The memory limit was reached after {} steps, before reaching a halting configuration.",
+            result.steps
+        ),
+        None if result.resource_limit == Some(ResourceLimit::Cells) => format!(
+            "The tape reached its cell limit after {} steps, before reaching a halting configuration.",
+            result.steps
+        ),
+        None => match result.cycle {
+            Some(cycle) if cycle.shift == 0 => format!(
+                "M was back in the configuration of step {} after {} more steps, so it repeats them forever and never halts on this input.",
+                cycle.step - cycle.length,
+                cycle.length
+            ),
+            Some(cycle) => format!(
+                "After {} more steps, M was in the configuration of step {} moved {} cells {}, with everything it read in between moved along, so it repeats those steps further and further along the tape and never halts on this input.",
+                cycle.length,
+                cycle.step - cycle.length,
+                cycle.shift.unsigned_abs(),
+                if cycle.shift > 0 { "right" } else { "left" }
+            ),
+            None => format!(
+                "No halting configuration was reached within {} steps, so there is no verdict; M may loop on this input.",
+                result.steps
+            ),
+        },
+    };
+    text.push_str(&reason);
+    match (&result.output, machine.output) {
+        (Some(output), _) => {
+            let _ = write!(text, "\nM computes the output \"{}\".", output);
+        }
+        (None, Some(Output::Between(start, end))) if result.halted => {
+            let _ = write!(
+                text,
+                "\nThe tape has no {} followed by a {}, so M has no output.",
+                symbols.name(start),
+                symbols.name(end)
+            );
+        }
+        (None, _) => {}
+    }
+    text
+}
+
+/// Observer writing the configuration after every step as a line of text,
+/// starting with the initial configuration
+pub struct ConfigurationTrace<W: io::Write> {
+    out: W,
+    blank_symbol: char,
+    symbols: SymbolTable,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> ConfigurationTrace<W> {
+    /// Write the initial configuration of `machine` on `input_string` to `out`
+    pub fn new(machine: &TuringMachine, input_string: &str, mut out: W) -> Self {
+        let input = machine.map_input(input_string).unwrap_or_default();
+        let tape = machine.start_tape(&input).unwrap_or(input);
+        let line = configuration(
+            &tape,
+            machine.tape_setup.head() as i32,
+            &machine.initial_state,
+            machine.blank_symbol,
+            machine.symbols(),
+        );
+        let error = writeln!(out, "{}", line).err();
+        ConfigurationTrace {
+            out,
+            blank_symbol: machine.blank_symbol,
+            symbols: machine.symbols().clone(),
+            error,
+        }
+    }
+
+    /// Flush the output, reporting the first write error
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: io::Write> StepObserver for ConfigurationTrace<W> {
+    fn on_step(&mut self, event: &StepEvent) {
+        if self.error.is_some() {
+            return;
+        }
+        let line = configuration(
+            event.tape,
+            event.head_position,
+            event.to_state,
+            self.blank_symbol,
+            &self.symbols,
+        );
+        self.error = writeln!(self.out, "{}", line).err();
+    }
+}
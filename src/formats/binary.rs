@@ -0,0 +1,563 @@
+//! Compact binary machine format
+//!
+//! Intended for very large generated machines, where parsing JSON text
+//! dominates load time. The file starts with a magic header followed by a
+//! postcard-encoded [`MachineBinary`]; state and symbol names are stored
+//! once and referenced by index everywhere else. The symbols are stored in
+//! the order loading the machine interns them, so a machine read by
+//! [`load_binary`] gets the same characters for its named symbols as the
+//! same machine read from JSON, without parsing a single transition key.
+//! Files written before symbols were indexed (`TMB1`) are still read.
+//!
+//! Fields added later follow as optional trailing records:
+//! [`BinaryOptions`], the input map, the input normalization, the left edge
+//! policy, the circular tape length, [`BinaryTape`], the output of a machine
+//! computing a function, [`BinaryDefaults`], [`BinaryMetadata`], whether
+//! the machine accepts by halting, the output mode it recommends and the
+//! symbol aliases. A record is written only if it or a later one is not at
+//! its default, so older files still decode. Transitions keep the alias
+//! names they were written with, which [`load_binary`] resolves.
+
+use super::json::{ANY_SYMBOL, SAME_SYMBOL};
+use crate::executor::{OutputMode, RunDefaults};
+use crate::formats::{build_machine, OutputJson};
+use crate::info::{MachineTest, Metadata};
+use crate::{Direction, InputNormalization, LeftEdge, MachineJson, SymbolTable, TuringMachine};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Magic bytes identifying the format and its version
+const MAGIC: &[u8; 4] = b"TMB2";
+
+/// Magic bytes of the first version, with symbols stored as strings in
+/// every transition
+const MAGIC_V1: &[u8; 4] = b"TMB1";
+
+/// Binary layout of a machine definition
+#[derive(Debug, Serialize, Deserialize)]
+struct MachineBinary {
+    states: Vec<String>,
+    alphabet: Vec<String>,
+    tape_alphabet: Vec<String>,
+    initial_state: u32,
+    accept_states: Vec<u32>,
+    reject_states: Vec<u32>,
+    blank_symbol: Option<String>,
+    /// Every symbol the transitions read or write, in the order loading the
+    /// machine interns them
+    symbols: Vec<String>,
+    transitions: Vec<BinaryTransition>,
+}
+
+/// Binary layout of a machine definition in the first version
+#[derive(Debug, Serialize, Deserialize)]
+struct MachineBinaryV1 {
+    states: Vec<String>,
+    alphabet: Vec<String>,
+    tape_alphabet: Vec<String>,
+    initial_state: u32,
+    accept_states: Vec<u32>,
+    reject_states: Vec<u32>,
+    blank_symbol: Option<String>,
+    transitions: Vec<BinaryTransitionV1>,
+}
+
+/// Optional settings stored after the machine
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct BinaryOptions {
+    read_only_input: bool,
+}
+
+/// The initial tape and where the input and head start
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct BinaryTape {
+    initial_tape: Option<String>,
+    input_start: usize,
+    head_start: Option<usize>,
+}
+
+/// The recommended run limits, stored as plain numbers
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct BinaryDefaults {
+    max_steps: Option<usize>,
+    max_memory: Option<usize>,
+    max_head_overshoot: Option<usize>,
+    min_head: Option<i64>,
+}
+
+/// The descriptive fields, stored without omitting unset ones
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct BinaryMetadata {
+    name: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    tests: Vec<BinaryTest>,
+    docs: Option<String>,
+}
+
+/// A sample input and its expected result
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BinaryTest {
+    input: String,
+    accepts: Option<bool>,
+    output: Option<String>,
+}
+
+/// A single transition with states and symbols referenced by index
+#[derive(Debug, Serialize, Deserialize)]
+struct BinaryTransition {
+    state: u32,
+    symbol: u32,
+    new_state: u32,
+    write_symbol: u32,
+    direction: Direction,
+}
+
+/// A single transition of the first version, with states referenced by
+/// index
+#[derive(Debug, Serialize, Deserialize)]
+struct BinaryTransitionV1 {
+    state: u32,
+    symbol: String,
+    new_state: u32,
+    write_symbol: String,
+    direction: String,
+}
+
+impl MachineBinaryV1 {
+    /// The same machine with its symbols indexed
+    fn upgrade(self) -> Result<MachineBinary, String> {
+        let mut symbols = Vec::new();
+        let mut indices = HashMap::new();
+        let mut index = |name: String| {
+            *indices.entry(name).or_insert_with_key(|name| {
+                symbols.push(name.clone());
+                symbols.len() as u32 - 1
+            })
+        };
+        let transitions = self
+            .transitions
+            .into_iter()
+            .map(|t| {
+                Ok(BinaryTransition {
+                    state: t.state,
+                    symbol: index(t.symbol),
+                    new_state: t.new_state,
+                    write_symbol: index(t.write_symbol),
+                    direction: parse_direction(&t.direction)?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(MachineBinary {
+            states: self.states,
+            alphabet: self.alphabet,
+            tape_alphabet: self.tape_alphabet,
+            initial_state: self.initial_state,
+            accept_states: self.accept_states,
+            reject_states: self.reject_states,
+            blank_symbol: self.blank_symbol,
+            symbols,
+            transitions,
+        })
+    }
+}
+
+/// A direction as written in the JSON schema
+fn parse_direction(direction: &str) -> Result<Direction, String> {
+    match direction {
+        "L" => Ok(Direction::L),
+        "R" => Ok(Direction::R),
+        "S" | "N" => Ok(Direction::S),
+        _ => Err(format!("Invalid direction: {}", direction)),
+    }
+}
+
+/// Decode a binary machine definition into the JSON schema
+pub fn from_binary(bytes: &[u8]) -> Result<MachineJson, String> {
+    let (machine, mut definition) = decode(bytes)?;
+    let symbol = |index: u32| -> Result<String, String> {
+        machine
+            .symbols
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| format!("Symbol index {} out of range", index))
+    };
+    for t in &machine.transitions {
+        definition.transitions.insert(
+            format!("{},{}", state(&machine, t.state)?, symbol(t.symbol)?),
+            vec![
+                state(&machine, t.new_state)?,
+                symbol(t.write_symbol)?,
+                format!("{:?}", t.direction),
+            ],
+        );
+    }
+    Ok(definition)
+}
+
+/// Load a binary machine file straight into a machine
+///
+/// The transitions are built from their indices, without going through
+/// the string keys of the JSON schema. Only the checks a file written by
+/// [`to_binary`] could fail are made; if one fails, reading the file with
+/// [`from_binary`] and validating it reports the problem in detail.
+pub fn load_binary(bytes: &[u8]) -> Result<TuringMachine, String> {
+    let (machine, definition) = decode(bytes)?;
+    let aliases = &definition.symbol_aliases;
+    let names: Vec<&str> = machine
+        .symbols
+        .iter()
+        .map(|name| aliases.get(name).unwrap_or(name).as_str())
+        .collect();
+    let mut symbols = SymbolTable::new();
+    let chars = names
+        .iter()
+        .map(|name| symbols.intern(name))
+        .collect::<Result<Vec<char>, String>>()?;
+    let tape: HashSet<&str> = definition
+        .tape_alphabet
+        .iter()
+        .map(String::as_str)
+        .collect();
+    if let Some(symbol) = definition
+        .alphabet
+        .iter()
+        .find(|s| !tape.contains(s.as_str()))
+    {
+        return Err(format!("Input symbol {} not in tape alphabet", symbol));
+    }
+    // Which indexed symbols may be read and written
+    let readable: Vec<bool> = names
+        .iter()
+        .map(|name| tape.contains(name) || *name == ANY_SYMBOL.to_string())
+        .collect();
+    let writable: Vec<bool> = names
+        .iter()
+        .map(|name| tape.contains(name) || *name == SAME_SYMBOL.to_string())
+        .collect();
+
+    let mut transitions = HashMap::with_capacity(machine.transitions.len());
+    for t in &machine.transitions {
+        let (read, write) = (t.symbol as usize, t.write_symbol as usize);
+        if !readable.get(read).copied().unwrap_or(false)
+            || !writable.get(write).copied().unwrap_or(false)
+        {
+            return Err(format!(
+                "Transition of state index {} uses an unknown symbol",
+                t.state
+            ));
+        }
+        let key = (state(&machine, t.state)?, chars[read]);
+        let target = (state(&machine, t.new_state)?, chars[write], t.direction);
+        if transitions.insert(key, target).is_some() {
+            return Err(format!("Duplicate transition of state index {}", t.state));
+        }
+    }
+    build_machine(&definition, transitions, symbols)
+}
+
+/// The name of the state at `index`
+fn state(machine: &MachineBinary, index: u32) -> Result<String, String> {
+    machine
+        .states
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| format!("State index {} out of range", index))
+}
+
+/// Decode a binary machine definition, returning the machine's indexed
+/// transitions and its other fields as a definition without transitions
+fn decode(bytes: &[u8]) -> Result<(MachineBinary, MachineJson), String> {
+    let (machine, rest) = if let Some(payload) = bytes.strip_prefix(MAGIC.as_slice()) {
+        postcard::take_from_bytes(payload).map_err(|e| e.to_string())?
+    } else if let Some(payload) = bytes.strip_prefix(MAGIC_V1.as_slice()) {
+        let (machine, rest): (MachineBinaryV1, _) =
+            postcard::take_from_bytes(payload).map_err(|e| e.to_string())?;
+        (machine.upgrade()?, rest)
+    } else {
+        return Err("Not a binary machine file (bad header)".to_string());
+    };
+    let (options, rest): (BinaryOptions, _) = take_record(rest)?;
+    let (input_map, rest): (BTreeMap<String, String>, _) = take_record(rest)?;
+    let (normalize_input, rest): (InputNormalization, _) = take_record(rest)?;
+    let (left_edge, rest): (Option<LeftEdge>, _) = take_record(rest)?;
+    let (tape_length, rest): (Option<usize>, _) = take_record(rest)?;
+    let (tape, rest): (BinaryTape, _) = take_record(rest)?;
+    let (output, rest): (Option<OutputJson>, _) = take_record(rest)?;
+    let (defaults, rest): (BinaryDefaults, _) = take_record(rest)?;
+    let (metadata, rest): (BinaryMetadata, _) = take_record(rest)?;
+    let (accept_on_halt, rest): (bool, _) = take_record(rest)?;
+    let (output_mode, rest): (Option<OutputMode>, _) = take_record(rest)?;
+    let (symbol_aliases, rest): (BTreeMap<String, String>, _) = take_record(rest)?;
+    if !rest.is_empty() {
+        return Err(format!("{} unexpected bytes after the machine", rest.len()));
+    }
+
+    let definition = MachineJson {
+        initial_state: state(&machine, machine.initial_state)?,
+        accept_states: machine
+            .accept_states
+            .iter()
+            .map(|&i| state(&machine, i))
+            .collect::<Result<_, _>>()?,
+        reject_states: machine
+            .reject_states
+            .iter()
+            .map(|&i| state(&machine, i))
+            .collect::<Result<_, _>>()?,
+        transitions: BTreeMap::new(),
+        states: machine.states.clone(),
+        alphabet: machine.alphabet.clone(),
+        tape_alphabet: machine.tape_alphabet.clone(),
+        blank_symbol: machine.blank_symbol.clone(),
+        read_only_input: options.read_only_input,
+        accept_on_halt,
+        symbol_aliases,
+        input_map,
+        normalize_input,
+        left_edge,
+        tape_length,
+        initial_tape: tape.initial_tape,
+        input_start: tape.input_start,
+        head_start: tape.head_start,
+        output,
+        default_max_steps: None,
+        defaults: RunDefaults {
+            max_steps: defaults.max_steps,
+            max_memory: defaults.max_memory,
+            max_head_overshoot: defaults.max_head_overshoot,
+            min_head: defaults.min_head,
+            output_mode,
+        },
+        metadata: Metadata {
+            name: metadata.name,
+            description: metadata.description,
+            tags: metadata.tags,
+            tests: metadata
+                .tests
+                .into_iter()
+                .map(|test| MachineTest {
+                    input: test.input,
+                    accepts: test.accepts,
+                    output: test.output,
+                })
+                .collect(),
+            docs: metadata.docs,
+        },
+    };
+    Ok((machine, definition))
+}
+
+/// Decode the next trailing record, or its default if the file ends first
+fn take_record<T: Default + DeserializeOwned>(bytes: &[u8]) -> Result<(T, &[u8]), String> {
+    if bytes.is_empty() {
+        Ok((T::default(), bytes))
+    } else {
+        postcard::take_from_bytes(bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// Encode a JSON-schema machine definition in the binary format
+pub fn to_binary<'a>(definition: &'a MachineJson) -> Result<Vec<u8>, String> {
+    let indices: HashMap<&str, u32> = definition
+        .states
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.as_str(), i as u32))
+        .collect();
+    let index = |name: &str| -> Result<u32, String> {
+        indices
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("State {} not in states", name))
+    };
+
+    // Symbols are indexed in the order parsing the transitions interns them
+    let mut symbols = Vec::new();
+    let mut symbol_indices: HashMap<&str, u32> = HashMap::new();
+    let mut symbol_index = |name: &'a str| {
+        *symbol_indices.entry(name).or_insert_with(|| {
+            symbols.push(name.to_string());
+            symbols.len() as u32 - 1
+        })
+    };
+
+    let mut transitions = Vec::with_capacity(definition.transitions.len());
+    for (key, value) in &definition.transitions {
+        let Some((state, symbol)) = key.split_once(',') else {
+            return Err(format!("Invalid transition key: {}", key));
+        };
+        let [new_state, write_symbol, direction] = value.as_slice() else {
+            return Err(format!("Invalid transition value for key: {}", key));
+        };
+        transitions.push(BinaryTransition {
+            state: index(state)?,
+            symbol: symbol_index(symbol),
+            new_state: index(new_state)?,
+            write_symbol: symbol_index(write_symbol),
+            direction: parse_direction(direction)?,
+        });
+    }
+
+    let machine = MachineBinary {
+        states: definition.states.clone(),
+        alphabet: definition.alphabet.clone(),
+        tape_alphabet: definition.tape_alphabet.clone(),
+        initial_state: index(&definition.initial_state)?,
+        accept_states: definition
+            .accept_states
+            .iter()
+            .map(|s| index(s))
+            .collect::<Result<_, _>>()?,
+        reject_states: definition
+            .reject_states
+            .iter()
+            .map(|s| index(s))
+            .collect::<Result<_, _>>()?,
+        blank_symbol: definition.blank_symbol.clone(),
+        symbols,
+        transitions,
+    };
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(postcard::to_stdvec(&machine).map_err(|e| e.to_string())?);
+    let options = BinaryOptions {
+        read_only_input: definition.read_only_input,
+    };
+    let tape = BinaryTape {
+        initial_tape: definition.initial_tape.clone(),
+        input_start: definition.input_start,
+        head_start: definition.head_start,
+    };
+    let run_defaults = definition.run_defaults()?;
+    let defaults = BinaryDefaults {
+        max_steps: run_defaults.max_steps,
+        max_memory: run_defaults.max_memory,
+        max_head_overshoot: run_defaults.max_head_overshoot,
+        min_head: run_defaults.min_head,
+    };
+    let metadata = &definition.metadata;
+    let metadata = BinaryMetadata {
+        name: metadata.name.clone(),
+        description: metadata.description.clone(),
+        tags: metadata.tags.clone(),
+        tests: metadata
+            .tests
+            .iter()
+            .map(|test| BinaryTest {
+                input: test.input.clone(),
+                accepts: test.accepts,
+                output: test.output.clone(),
+            })
+            .collect(),
+        docs: metadata.docs.clone(),
+    };
+    // Each record paired with whether it is at its default
+    let records = [
+        (
+            postcard::to_stdvec(&options),
+            options == BinaryOptions::default(),
+        ),
+        (
+            postcard::to_stdvec(&definition.input_map),
+            definition.input_map.is_empty(),
+        ),
+        (
+            postcard::to_stdvec(&definition.normalize_input),
+            definition.normalize_input.is_none(),
+        ),
+        (
+            postcard::to_stdvec(&definition.left_edge),
+            definition.left_edge.is_none(),
+        ),
+        (
+            postcard::to_stdvec(&definition.tape_length),
+            definition.tape_length.is_none(),
+        ),
+        (postcard::to_stdvec(&tape), tape == BinaryTape::default()),
+        (
+            postcard::to_stdvec(&definition.output),
+            definition.output.is_none(),
+        ),
+        (
+            postcard::to_stdvec(&defaults),
+            defaults == BinaryDefaults::default(),
+        ),
+        (
+            postcard::to_stdvec(&metadata),
+            metadata == BinaryMetadata::default(),
+        ),
+        (
+            postcard::to_stdvec(&definition.accept_on_halt),
+            !definition.accept_on_halt,
+        ),
+        (
+            postcard::to_stdvec(&definition.defaults.output_mode),
+            definition.defaults.output_mode.is_none(),
+        ),
+        (
+            postcard::to_stdvec(&definition.symbol_aliases),
+            definition.symbol_aliases.is_empty(),
+        ),
+    ];
+    let needed = records
+        .iter()
+        .rposition(|(_, default)| !default)
+        .map_or(0, |last| last + 1);
+    for (record, _) in records.into_iter().take(needed) {
+        bytes.extend(record.map_err(|e| e.to_string())?);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{fixtures, parse_machine_json};
+
+    #[test]
+    fn round_trips_every_field() {
+        for definition in fixtures::definitions() {
+            let bytes = to_binary(&definition).unwrap();
+            assert_eq!(&bytes[..4], MAGIC);
+            // The step limit shorthand is stored as `defaults.max_steps`
+            let expected = MachineJson {
+                default_max_steps: None,
+                defaults: definition.run_defaults().unwrap(),
+                ..definition
+            };
+            assert_eq!(from_binary(&bytes).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn keeps_the_default_max_steps_shorthand() {
+        let definition = fixtures::definitions().pop().unwrap();
+        assert_eq!(definition.default_max_steps, Some(500));
+        let bytes = to_binary(&definition).unwrap();
+        assert_eq!(from_binary(&bytes).unwrap().defaults.max_steps, Some(500));
+        assert_eq!(
+            load_binary(&bytes).unwrap().run_defaults().max_steps,
+            Some(500)
+        );
+    }
+
+    #[test]
+    fn loads_the_machine_read_from_json() {
+        for definition in fixtures::definitions() {
+            let loaded = load_binary(&to_binary(&definition).unwrap()).unwrap();
+            let parsed = parse_machine_json(&definition).unwrap();
+            fixtures::assert_same_runs(&loaded, &parsed);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_files() {
+        assert!(from_binary(b"TMB9").is_err());
+        assert!(from_binary(b"").is_err());
+        let bytes = to_binary(&fixtures::definitions()[0]).unwrap();
+        assert!(from_binary(&bytes[..bytes.len() / 2]).is_err());
+        assert!(load_binary(&bytes[..bytes.len() / 2]).is_err());
+    }
+}
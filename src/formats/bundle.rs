@@ -0,0 +1,103 @@
+//! Machine bundles
+//!
+//! A `.tmpkg` bundle is a zip archive that keeps a machine together with
+//! everything describing it, split into files that are easy to read and
+//! edit on their own:
+//!
+//! - `machine.json`, the definition without its metadata
+//! - `metadata.json`, the name, description and tags
+//! - `tests.json`, the sample inputs with their expected results
+//! - `README.md`, the docs
+//!
+//! Only `machine.json` is required. When a bundle is read the other files
+//! are merged back into the metadata, replacing what `machine.json` may
+//! hold itself.
+
+use super::parse_machine_definition;
+use crate::info::{MachineTest, Metadata};
+use crate::MachineJson;
+use std::io::{Cursor, Read, Write};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Entry holding the definition
+const MACHINE_FILE: &str = "machine.json";
+/// Entry holding the name, description and tags
+const METADATA_FILE: &str = "metadata.json";
+/// Entry holding the tests
+const TESTS_FILE: &str = "tests.json";
+/// Entry holding the docs
+const DOCS_FILE: &str = "README.md";
+
+/// Decode a bundle into the JSON schema
+pub fn from_bundle(bytes: &[u8]) -> Result<MachineJson, String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut entry = |name: &str| -> Result<Option<String>, String> {
+        let mut file = match archive.by_name(name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(format!("{}: {}", name, e)),
+        };
+        let mut text = String::new();
+        file.read_to_string(&mut text)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        Ok(Some(text))
+    };
+
+    let machine =
+        entry(MACHINE_FILE)?.ok_or_else(|| format!("no {} in the bundle", MACHINE_FILE))?;
+    let mut definition =
+        parse_machine_definition(&machine).map_err(|e| format!("{}: {}", MACHINE_FILE, e))?;
+    if let Some(text) = entry(METADATA_FILE)? {
+        let metadata: Metadata =
+            serde_json::from_str(&text).map_err(|e| format!("{}: {}", METADATA_FILE, e))?;
+        definition.metadata.name = metadata.name;
+        definition.metadata.description = metadata.description;
+        definition.metadata.tags = metadata.tags;
+    }
+    if let Some(text) = entry(TESTS_FILE)? {
+        definition.metadata.tests = serde_json::from_str::<Vec<MachineTest>>(&text)
+            .map_err(|e| format!("{}: {}", TESTS_FILE, e))?;
+    }
+    if let Some(text) = entry(DOCS_FILE)? {
+        definition.metadata.docs = Some(text.trim_end().to_string());
+    }
+    Ok(definition)
+}
+
+/// Encode a definition as a bundle, leaving out the files it has nothing
+/// for
+pub fn to_bundle(definition: &MachineJson) -> Result<Vec<u8>, String> {
+    let mut machine = definition.clone();
+    let metadata = std::mem::take(&mut machine.metadata);
+    let mut files = vec![(
+        MACHINE_FILE,
+        serde_json::to_string_pretty(&machine).map_err(|e| e.to_string())? + "\n",
+    )];
+    let described = Metadata {
+        name: metadata.name,
+        description: metadata.description,
+        tags: metadata.tags,
+        ..Metadata::default()
+    };
+    if !described.is_empty() {
+        let text = serde_json::to_string_pretty(&described).map_err(|e| e.to_string())?;
+        files.push((METADATA_FILE, text + "\n"));
+    }
+    if !metadata.tests.is_empty() {
+        let text = serde_json::to_string_pretty(&metadata.tests).map_err(|e| e.to_string())?;
+        files.push((TESTS_FILE, text + "\n"));
+    }
+    if let Some(docs) = metadata.docs {
+        files.push((DOCS_FILE, docs + "\n"));
+    }
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, text) in files {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    Ok(zip.finish().map_err(|e| e.to_string())?.into_inner())
+}
@@ -0,0 +1,894 @@
+//! The machine DSL
+//!
+//! A compact text format for writing machines by hand, one transition per
+//! line:
+//!
+//! ```text
+//! # Accepts binary strings with an even number of 1s
+//! start even
+//! accept accept
+//!
+//! even 0 -> even 0 R
+//! even 1 -> odd 1 R
+//! even _ -> accept _ S
+//! odd  0 -> odd 0 R
+//! odd  1 -> even 1 R
+//!
+//! test 0110 accept
+//! test 1 reject
+//! ```
+//!
+//! A transition is `state symbol -> new_state new_symbol direction`, with
+//! `L`, `R` or `S` as the direction. The other lines are directives, a
+//! keyword followed by its values:
+//!
+//! - `start`, `accept`, `reject`, `blank`, `input` (the input alphabet),
+//!   `symbols` (the tape alphabet) and `states` take words
+//! - `name`, `description` and `docs` take the rest of the line as it is;
+//!   repeated `docs` lines are joined into several lines
+//! - `tags` takes words, and `test input accept|reject [output tape]` adds a
+//!   test to the metadata
+//! - `option field value` sets any other field of the JSON schema, such as
+//!   `option accept_on_halt true`, to a JSON value
+//!
+//! Any word can be quoted with `'` or `"`, so `q0 '1' -> q1 '0' R` is also a
+//! transition, and `#` starts a comment outside quotes, except on lines
+//! taking text.
+//!
+//! States and symbols need not be declared: `states` and `symbols` are
+//! completed with those the transitions use, in order of appearance, except
+//! for the names given with `option symbol_aliases`, and
+//! the input alphabet defaults to every tape symbol but the blank, which is
+//! `_` unless `blank` says otherwise. `*` and `=` are the wildcards of the
+//! JSON schema.
+
+use super::validate::{self, PathSegment};
+use crate::MachineJson;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Directives taking words
+const WORDS: [&str; 9] = [
+    "tags", "start", "accept", "reject", "blank", "input", "symbols", "states", "test",
+];
+/// Directives taking the rest of the line
+const TEXT: [&str; 3] = ["name", "description", "docs"];
+/// Directive setting other fields
+const OPTION: &str = "option";
+
+/// Fields set by directives rather than `option`
+const CORE_FIELDS: [(&str, &str); 8] = [
+    ("initial_state", "start"),
+    ("accept_states", "accept"),
+    ("reject_states", "reject"),
+    ("blank_symbol", "blank"),
+    ("alphabet", "input"),
+    ("tape_alphabet", "symbols"),
+    ("states", "states"),
+    ("transitions", "a transition line"),
+];
+/// Metadata fields, all of which have a directive
+const METADATA_FIELDS: [&str; 5] = ["name", "description", "tags", "tests", "docs"];
+
+/// Wildcards that are not symbols unless declared with `symbols`
+const ANY_SYMBOL: &str = "*";
+const SAME_SYMBOL: &str = "=";
+
+/// Parse a machine written in the DSL
+///
+/// Problems found once the machine is put together, such as a state that
+/// both accepts and rejects, are reported at the line that caused them.
+pub fn from_dsl(text: &str) -> Result<MachineJson, String> {
+    let parsed = Parser::default().parse(text)?;
+    let mut diagnostics = validate::check_schema(&parsed.value);
+    let mut definition = None;
+    if diagnostics.is_empty() {
+        let machine: MachineJson =
+            serde_json::from_value(parsed.value.clone()).map_err(|e| e.to_string())?;
+        diagnostics = validate::check_definition(&machine);
+        definition = Some(machine);
+    }
+    match definition {
+        Some(definition) if diagnostics.is_empty() => return Ok(definition),
+        _ => {}
+    }
+    for diagnostic in &mut diagnostics {
+        diagnostic.location = match diagnostic.path.as_slice() {
+            [PathSegment::Key(field), PathSegment::Key(key), ..] if field == "transitions" => {
+                parsed.transitions.get(key).copied()
+            }
+            [PathSegment::Key(field), ..] => parsed.fields.get(field.as_str()).copied(),
+            _ => None,
+        };
+    }
+    Err(validate::report(&diagnostics))
+}
+
+/// Write a machine in the DSL
+pub fn to_dsl(definition: &MachineJson) -> Result<String, String> {
+    let mut out = String::new();
+    let line = |out: &mut String, keyword: &str, words: &[String]| {
+        if !words.is_empty() {
+            out.push_str(keyword);
+            for word in words {
+                out.push(' ');
+                out.push_str(&quote(word));
+            }
+            out.push('\n');
+        }
+    };
+    let text = |out: &mut String, keyword: &str, text: &str| {
+        out.push_str(keyword);
+        if !text.is_empty() {
+            out.push(' ');
+            out.push_str(text);
+        }
+        out.push('\n');
+    };
+
+    let metadata = &definition.metadata;
+    for (keyword, value) in [
+        ("name", &metadata.name),
+        ("description", &metadata.description),
+    ] {
+        match value {
+            Some(value) if value.contains('\n') || value.trim() != value || value.is_empty() => {
+                return Err(format!(
+                    "the DSL cannot express a {} spanning lines or with spaces around it",
+                    keyword
+                ))
+            }
+            Some(value) => text(&mut out, keyword, value),
+            None => {}
+        }
+    }
+    line(&mut out, "tags", &metadata.tags);
+    line(
+        &mut out,
+        "start",
+        std::slice::from_ref(&definition.initial_state),
+    );
+    line(&mut out, "accept", &definition.accept_states);
+    line(&mut out, "reject", &definition.reject_states);
+    line(&mut out, "blank", definition.blank_symbol.as_slice());
+    line(&mut out, "input", &definition.alphabet);
+    line(&mut out, "symbols", &definition.tape_alphabet);
+    line(&mut out, "states", &definition.states);
+
+    // The remaining fields, as JSON
+    let value = serde_json::to_value(definition).map_err(|e| e.to_string())?;
+    let fields = value.as_object().ok_or("a machine must be an object")?;
+    for (field, value) in fields {
+        let core = CORE_FIELDS.iter().any(|(name, _)| name == field);
+        if !core && field != "metadata" {
+            let value = serde_json::to_string(value).map_err(|e| e.to_string())?;
+            out.push_str(&format!("{} {} {}\n", OPTION, field, value));
+        }
+    }
+
+    // Transitions grouped by state, in the order of `states`, aligned
+    let mut groups: BTreeMap<usize, Vec<[String; 5]>> = BTreeMap::new();
+    for (key, value) in &definition.transitions {
+        let (state, symbol) = key
+            .split_once(',')
+            .ok_or_else(|| format!("invalid transition key '{}'", key))?;
+        let [new_state, write, direction] = value.as_slice() else {
+            return Err(format!("invalid transition '{}'", key));
+        };
+        let order = definition
+            .states
+            .iter()
+            .position(|s| s == state)
+            .unwrap_or(usize::MAX);
+        groups.entry(order).or_default().push([
+            quote(state),
+            quote(symbol),
+            quote(new_state),
+            quote(write),
+            direction.clone(),
+        ]);
+    }
+    let width = |column: usize| {
+        groups
+            .values()
+            .flatten()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let (state_width, symbol_width, target_width) = (width(0), width(1), width(2));
+    for rows in groups.values() {
+        out.push('\n');
+        for [state, symbol, new_state, write, direction] in rows {
+            out.push_str(&format!(
+                "{:sw$} {:yw$} -> {:tw$} {} {}\n",
+                state,
+                symbol,
+                new_state,
+                write,
+                direction,
+                sw = state_width,
+                yw = symbol_width,
+                tw = target_width,
+            ));
+        }
+    }
+
+    if !metadata.tests.is_empty() {
+        out.push('\n');
+    }
+    for test in &metadata.tests {
+        out.push_str("test ");
+        out.push_str(&quote(&test.input));
+        match test.accepts {
+            Some(true) => out.push_str(" accept"),
+            Some(false) => out.push_str(" reject"),
+            None => {}
+        }
+        if let Some(output) = &test.output {
+            out.push_str(" output ");
+            out.push_str(&quote(output));
+        }
+        out.push('\n');
+    }
+    if let Some(docs) = &metadata.docs {
+        out.push('\n');
+        for line in docs.split('\n') {
+            text(&mut out, "docs", line);
+        }
+    }
+    Ok(out)
+}
+
+/// A word as written in the DSL, quoted where it would not read back as
+/// the same word
+fn quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && !TEXT.contains(&word)
+        && !word.contains("->")
+        && !word.contains("=>")
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '#' | '→'));
+    if plain {
+        word.to_string()
+    } else if word.contains('\'') {
+        format!("\"{}\"", word)
+    } else {
+        format!("'{}'", word)
+    }
+}
+
+/// A word of a line and the column it starts at
+#[derive(Debug)]
+enum Token {
+    Word(String, usize),
+    Arrow(usize),
+}
+
+impl Token {
+    fn column(&self) -> usize {
+        match self {
+            Token::Word(_, column) | Token::Arrow(column) => *column,
+        }
+    }
+}
+
+/// A parsed program, with where each part of it was written
+struct Parsed {
+    value: Value,
+    /// Line and column of each transition, by key
+    transitions: HashMap<String, (usize, usize)>,
+    /// Line and column where each field was first set, by field
+    fields: HashMap<String, (usize, usize)>,
+}
+
+#[derive(Default)]
+struct Parser {
+    words: HashMap<&'static str, Vec<String>>,
+    text: HashMap<&'static str, Vec<String>>,
+    options: Map<String, Value>,
+    tests: Vec<Value>,
+    /// Where each field was first set
+    fields: HashMap<String, (usize, usize)>,
+    /// States and symbols in order of appearance in transitions
+    states: Vec<String>,
+    symbols: Vec<String>,
+    transitions: BTreeMap<String, [String; 3]>,
+    locations: HashMap<String, (usize, usize)>,
+}
+
+impl Parser {
+    fn parse(mut self, text: &str) -> Result<Parsed, String> {
+        for (row, line) in text.lines().enumerate() {
+            // Text is taken as it is, so it is not split into words
+            let content = line.trim_start();
+            let keyword = content.split_whitespace().next().unwrap_or("");
+            if let Some(&keyword) = TEXT.iter().find(|k| **k == keyword) {
+                let column = line.chars().count() - content.chars().count() + 1;
+                let rest = &content[keyword.len()..];
+                self.text_directive(keyword, rest, row + 1, column)?;
+                continue;
+            }
+
+            let (tokens, end) = tokenize(line, row + 1)?;
+            let Some(first) = tokens.first() else {
+                continue;
+            };
+            let has_arrow = tokens.iter().any(|t| matches!(t, Token::Arrow(_)));
+            let keyword = match first {
+                Token::Word(word, column) if !has_arrow => Some((word.as_str(), *column)),
+                _ => None,
+            };
+            match keyword {
+                Some((OPTION, column)) => {
+                    let value_column = tokens.get(2).map_or(end + 1, Token::column);
+                    let value: String = line.chars().take(end).skip(value_column - 1).collect();
+                    self.option(&tokens[1..], &value, row + 1, column)?;
+                }
+                Some((keyword, column)) => {
+                    let Some(keyword) = WORDS.iter().find(|k| **k == keyword) else {
+                        return Err(at(
+                            row + 1,
+                            column,
+                            &format!(
+                                "'{}' is neither a directive nor a transition `state symbol -> new_state new_symbol direction`; the directives are {}, {} and {}",
+                                keyword,
+                                TEXT.join(", "),
+                                WORDS.join(", "),
+                                OPTION
+                            ),
+                        ));
+                    };
+                    self.word_directive(keyword, &tokens[1..], row + 1, column)?;
+                }
+                None => self.transition(&tokens, row + 1)?,
+            }
+        }
+        self.finish()
+    }
+
+    /// Record where a field was first set, failing if it can only be set
+    /// once and already was
+    fn set(&mut self, field: &str, once: bool, line: usize, column: usize) -> Result<(), String> {
+        match self.fields.get(field) {
+            Some(&(first, _)) if once => {
+                let directive = match CORE_FIELDS.iter().find(|(name, _)| *name == field) {
+                    Some((_, directive)) => directive.to_string(),
+                    None if TEXT.contains(&field) => field.to_string(),
+                    None => format!("{} {}", OPTION, field),
+                };
+                Err(at(
+                    line,
+                    column,
+                    &format!("`{}` is already given on line {}", directive, first),
+                ))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.fields.insert(field.to_string(), (line, column));
+                Ok(())
+            }
+        }
+    }
+
+    fn text_directive(
+        &mut self,
+        keyword: &'static str,
+        text: &str,
+        line: usize,
+        column: usize,
+    ) -> Result<(), String> {
+        // Docs keep their indentation, after the space following `docs`
+        let text = if keyword == "docs" {
+            self.set(keyword, false, line, column)?;
+            text.strip_prefix([' ', '\t'])
+                .unwrap_or(text)
+                .trim_end_matches('\r')
+        } else {
+            let text = text.trim();
+            if text.is_empty() {
+                return Err(at(line, column, &format!("`{}` needs a value", keyword)));
+            }
+            self.set(keyword, true, line, column)?;
+            text
+        };
+        self.text.entry(keyword).or_default().push(text.to_string());
+        Ok(())
+    }
+
+    fn word_directive(
+        &mut self,
+        keyword: &'static str,
+        tokens: &[Token],
+        line: usize,
+        column: usize,
+    ) -> Result<(), String> {
+        let words: Vec<String> = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(word, _) => word.clone(),
+                Token::Arrow(_) => unreachable!("directives have no arrow"),
+            })
+            .collect();
+        if words.is_empty() {
+            return Err(at(line, column, &format!("`{}` needs a value", keyword)));
+        }
+        if keyword == "test" {
+            return self.test(&words, line, column);
+        }
+        if let Some(token) = tokens
+            .iter()
+            .find(|token| matches!(token, Token::Word(word, _) if word.is_empty()))
+        {
+            return Err(at(
+                line,
+                token.column(),
+                "empty quotes; a word cannot be empty",
+            ));
+        }
+        let field = field(keyword);
+        let single = matches!(keyword, "start" | "blank");
+        if single && words.len() > 1 {
+            return Err(at(
+                line,
+                column,
+                &format!("`{}` takes one value, not {}", keyword, words.len()),
+            ));
+        }
+        self.set(field, single, line, column)?;
+        self.words.entry(keyword).or_default().extend(words);
+        Ok(())
+    }
+
+    fn test(&mut self, words: &[String], line: usize, column: usize) -> Result<(), String> {
+        let mut test = json!({ "input": words[0] });
+        let rest: Vec<&str> = words[1..].iter().map(String::as_str).collect();
+        let output = match rest.as_slice() {
+            ["accept", rest @ ..] => {
+                test["accepts"] = json!(true);
+                rest
+            }
+            ["reject", rest @ ..] => {
+                test["accepts"] = json!(false);
+                rest
+            }
+            rest => rest,
+        };
+        match output {
+            [] if test.get("accepts").is_some() => {}
+            ["output", tape] => test["output"] = json!(tape),
+            _ => {
+                return Err(at(
+                    line,
+                    column,
+                    "expected `test input accept`, `test input reject` or `test input output tape`",
+                ))
+            }
+        }
+        self.set("metadata", false, line, column)?;
+        self.tests.push(test);
+        Ok(())
+    }
+
+    fn option(
+        &mut self,
+        tokens: &[Token],
+        value: &str,
+        line: usize,
+        column: usize,
+    ) -> Result<(), String> {
+        let Some(Token::Word(field, _)) = tokens.first() else {
+            return Err(at(line, column, "expected `option field value`"));
+        };
+        if let Some((_, directive)) = CORE_FIELDS.iter().find(|(name, _)| name == field) {
+            return Err(at(
+                line,
+                column,
+                &format!(
+                    "{} is set with {}, not `option`",
+                    field,
+                    quote_directive(directive)
+                ),
+            ));
+        }
+        if field == "metadata" || METADATA_FIELDS.contains(&field.as_str()) {
+            return Err(at(
+                line,
+                column,
+                "the metadata is set with `name`, `description`, `tags`, `test` and `docs`",
+            ));
+        }
+        if value.trim().is_empty() {
+            return Err(at(
+                line,
+                column,
+                &format!("option {} needs a JSON value", field),
+            ));
+        }
+        let value_column = tokens.get(1).map_or(column, Token::column);
+        let parsed: Value = json5::from_str(value.trim()).map_err(|e| {
+            at(
+                line,
+                value_column,
+                &format!("invalid JSON for {}: {}", field, e),
+            )
+        })?;
+        self.set(field, true, line, column)?;
+        self.options.insert(field.clone(), parsed);
+        Ok(())
+    }
+
+    fn transition(&mut self, tokens: &[Token], line: usize) -> Result<(), String> {
+        let arrow = tokens
+            .iter()
+            .position(|t| matches!(t, Token::Arrow(_)))
+            .expect("transitions have an arrow");
+        let words = |tokens: &[Token]| -> Result<Vec<String>, String> {
+            tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Word(word, column) if word.is_empty() => Err(at(
+                        line,
+                        *column,
+                        "empty quotes; a state or symbol cannot be empty",
+                    )),
+                    Token::Word(word, _) => Ok(word.clone()),
+                    Token::Arrow(column) => Err(at(line, *column, "a transition has one `->`")),
+                })
+                .collect()
+        };
+        let (read, target) = (words(&tokens[..arrow])?, words(&tokens[arrow + 1..])?);
+        let arrow_column = tokens[arrow].column();
+        let [state, symbol] = read.as_slice() else {
+            return Err(at(
+                line,
+                tokens[0].column(),
+                &format!(
+                    "expected `state symbol` before `->`, found {}",
+                    count(read.len(), "word")
+                ),
+            ));
+        };
+        let end = tokens.last().map_or(arrow_column, Token::column);
+        let (new_state, write, direction) = match target.as_slice() {
+            [new_state, write, direction] => (new_state, write, direction),
+            [_, _] => {
+                return Err(at(
+                    line,
+                    end,
+                    "missing the direction (L, R or S) after the symbol to write",
+                ))
+            }
+            _ => {
+                return Err(at(
+                    line,
+                    arrow_column,
+                    &format!(
+                        "expected `new_state new_symbol direction` after `->`, found {}",
+                        count(target.len(), "word")
+                    ),
+                ))
+            }
+        };
+        let direction = match direction.as_str() {
+            "L" | "l" => "L",
+            "R" | "r" => "R",
+            "S" | "s" | "N" => "S",
+            other => {
+                return Err(at(
+                    line,
+                    end,
+                    &format!("unknown direction '{}'; expected L, R or S", other),
+                ))
+            }
+        };
+
+        let key = format!("{},{}", state, symbol);
+        if let Some(&(first, _)) = self.locations.get(&key) {
+            return Err(at(
+                line,
+                tokens[0].column(),
+                &duplicate(state, symbol, first),
+            ));
+        }
+        for name in [state, new_state] {
+            if !self.states.contains(name) {
+                self.states.push(name.clone());
+            }
+        }
+        for (name, wildcard) in [(symbol, ANY_SYMBOL), (write, SAME_SYMBOL)] {
+            if name != wildcard && !self.symbols.contains(name) {
+                self.symbols.push(name.clone());
+            }
+        }
+        self.locations
+            .insert(key.clone(), (line, tokens[0].column()));
+        self.transitions.insert(
+            key,
+            [new_state.clone(), write.clone(), direction.to_string()],
+        );
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Parsed, String> {
+        let mut take = |keyword: &str| self.words.remove(keyword).unwrap_or_default();
+        let (tags, accept, reject) = (take("tags"), take("accept"), take("reject"));
+        let (input, symbols, states) = (take("input"), take("symbols"), take("states"));
+        let declared_blank = take("blank").pop();
+        let blank = declared_blank.clone().unwrap_or_else(|| "_".to_string());
+        let Some(start) = take("start").pop() else {
+            return Err("no start state; add a line `start <state>`".to_string());
+        };
+
+        let mut all_states = states;
+        for state in std::iter::once(&start)
+            .chain(&self.states)
+            .chain(&accept)
+            .chain(&reject)
+        {
+            if !all_states.contains(state) {
+                all_states.push(state.clone());
+            }
+        }
+        // Alias names stand for tape symbols rather than being ones
+        let aliases = self
+            .options
+            .get("symbol_aliases")
+            .and_then(Value::as_object);
+        let is_alias =
+            |symbol: &String| aliases.is_some_and(|aliases| aliases.contains_key(symbol));
+        let mut tape_alphabet = symbols;
+        for symbol in input.iter().chain(&self.symbols).chain([&blank]) {
+            if !tape_alphabet.contains(symbol) && !is_alias(symbol) {
+                tape_alphabet.push(symbol.clone());
+            }
+        }
+        let alphabet = if input.is_empty() {
+            tape_alphabet
+                .iter()
+                .filter(|symbol| **symbol != blank)
+                .cloned()
+                .collect()
+        } else {
+            input
+        };
+
+        let mut value = json!({
+            "states": all_states,
+            "alphabet": alphabet,
+            "tape_alphabet": tape_alphabet,
+            "initial_state": start,
+            "accept_states": accept,
+            "reject_states": reject,
+            "transitions": self.transitions,
+        });
+        let object = value.as_object_mut().expect("built as an object");
+        if let Some(blank) = declared_blank {
+            object.insert("blank_symbol".to_string(), json!(blank));
+        }
+        object.extend(self.options);
+
+        let mut metadata = Map::new();
+        for field in ["name", "description"] {
+            if let Some(text) = self.text.remove(field) {
+                metadata.insert(field.to_string(), json!(text.join("\n")));
+            }
+        }
+        if !tags.is_empty() {
+            metadata.insert("tags".to_string(), json!(tags));
+        }
+        if !self.tests.is_empty() {
+            metadata.insert("tests".to_string(), Value::Array(self.tests));
+        }
+        if let Some(docs) = self.text.remove("docs") {
+            metadata.insert("docs".to_string(), json!(docs.join("\n")));
+        }
+        if !metadata.is_empty() {
+            // Metadata problems are reported where it was first set
+            let first = ["name", "description", "tags", "docs"]
+                .iter()
+                .filter_map(|field| self.fields.get(*field))
+                .chain(self.fields.get("metadata"))
+                .min()
+                .copied();
+            if let Some(first) = first {
+                self.fields.insert("metadata".to_string(), first);
+            }
+            object.insert("metadata".to_string(), Value::Object(metadata));
+        }
+
+        Ok(Parsed {
+            value,
+            transitions: self.locations,
+            fields: self.fields,
+        })
+    }
+}
+
+/// Where states are written in a program, for editors
+///
+/// Unlike [`from_dsl`] this reads past mistakes, skipping the lines it
+/// cannot make sense of, so a program being edited still has an outline.
+#[cfg(feature = "lsp")]
+#[derive(Debug, Default)]
+pub(crate) struct Outline {
+    /// Every state written, in order
+    pub(crate) states: Vec<Mention>,
+    /// Every transition, in order, duplicates included
+    pub(crate) transitions: Vec<Transition>,
+}
+
+/// A state written somewhere in a program
+#[cfg(feature = "lsp")]
+#[derive(Debug)]
+pub(crate) struct Mention {
+    pub(crate) name: String,
+    /// One-based line and column, in characters
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    /// Length in characters, quotes included
+    pub(crate) width: usize,
+    pub(crate) role: Role,
+}
+
+/// What a state is written as
+#[cfg(feature = "lsp")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// The state a transition reads in
+    Source,
+    /// The state a transition moves to
+    Target,
+    /// A word of a `start`, `accept`, `reject` or `states` directive
+    Directive(&'static str),
+}
+
+/// A transition line
+#[cfg(feature = "lsp")]
+#[derive(Debug)]
+pub(crate) struct Transition {
+    pub(crate) state: String,
+    pub(crate) symbol: String,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+/// Outline a program
+#[cfg(feature = "lsp")]
+pub(crate) fn outline(text: &str) -> Outline {
+    let mut outline = Outline::default();
+    for (row, line) in text.lines().enumerate() {
+        let keyword = line.split_whitespace().next().unwrap_or("");
+        if TEXT.contains(&keyword) {
+            continue;
+        }
+        let Ok((tokens, _)) = tokenize(line, row + 1) else {
+            continue;
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let mut mention = |token: &Token, role: Role| {
+            if let Token::Word(name, column) = token {
+                let quoted = matches!(chars.get(column - 1), Some('\'' | '"'));
+                outline.states.push(Mention {
+                    name: name.clone(),
+                    line: row + 1,
+                    column: *column,
+                    width: name.chars().count() + if quoted { 2 } else { 0 },
+                    role,
+                });
+            }
+        };
+        match tokens.iter().position(|t| matches!(t, Token::Arrow(_))) {
+            Some(arrow) => {
+                let (Some(state), Some(target)) = (tokens.first(), tokens.get(arrow + 1)) else {
+                    continue;
+                };
+                mention(state, Role::Source);
+                mention(target, Role::Target);
+                if let (Token::Word(state, column), Some(Token::Word(symbol, _))) =
+                    (state, tokens.get(1))
+                {
+                    outline.transitions.push(Transition {
+                        state: state.clone(),
+                        symbol: symbol.clone(),
+                        line: row + 1,
+                        column: *column,
+                    });
+                }
+            }
+            None => {
+                let Some(&directive) = ["start", "accept", "reject", "states"]
+                    .iter()
+                    .find(|d| **d == keyword)
+                else {
+                    continue;
+                };
+                for token in &tokens[1..] {
+                    mention(token, Role::Directive(directive));
+                }
+            }
+        }
+    }
+    outline
+}
+
+/// The error for a second transition reading the same symbol in a state
+pub(crate) fn duplicate(state: &str, symbol: &str, first: usize) -> String {
+    format!(
+        "state {} already has a transition reading '{}', on line {}",
+        state, symbol, first
+    )
+}
+
+/// The field of the JSON schema a directive sets
+fn field(keyword: &'static str) -> &'static str {
+    CORE_FIELDS
+        .iter()
+        .find(|(_, directive)| *directive == keyword)
+        .map_or(keyword, |(field, _)| field)
+}
+
+fn quote_directive(directive: &str) -> String {
+    if directive.contains(' ') {
+        directive.to_string()
+    } else {
+        format!("`{}`", directive)
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+fn at(line: usize, column: usize, message: &str) -> String {
+    format!("{}:{}: {}", line, column, message)
+}
+
+/// Split a line into words and arrows, dropping its comment
+///
+/// Also returns the length in characters of the line without its comment.
+fn tokenize(line: &str, row: usize) -> Result<(Vec<Token>, usize), String> {
+    let chars: Vec<char> = line.chars().collect();
+    let arrow_at = |i: usize| chars[i] == '-' && chars.get(i + 1) == Some(&'>');
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            return Ok((tokens, i));
+        } else if c == '→' || arrow_at(i) {
+            tokens.push(Token::Arrow(column));
+            i += if c == '→' { 1 } else { 2 };
+        } else if c == '\'' || c == '"' {
+            let Some(length) = chars[i + 1..].iter().position(|&q| q == c) else {
+                return Err(at(row, column, "unclosed quote"));
+            };
+            tokens.push(Token::Word(
+                chars[i + 1..i + 1 + length].iter().collect(),
+                column,
+            ));
+            i += length + 2;
+        } else if c == '=' && chars.get(i + 1) == Some(&'>') {
+            return Err(at(
+                row,
+                column,
+                "use `->` between what a transition reads and what it does",
+            ));
+        } else {
+            let start = i;
+            while i < chars.len()
+                && !chars[i].is_whitespace()
+                && !matches!(chars[i], '#' | '\'' | '"' | '→')
+                && !arrow_at(i)
+            {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect(), column));
+        }
+    }
+    Ok((tokens, chars.len()))
+}
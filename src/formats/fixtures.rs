@@ -0,0 +1,86 @@
+//! Machine definitions shared by the format tests
+
+use super::{parse_machine_definition, MachineJson};
+use crate::TuringMachine;
+
+/// A definition setting most optional fields, with a multi-character
+/// symbol and an alias
+const FEATURES: &str = r##"{
+    "states": ["start", "scan", "accept", "reject"],
+    "alphabet": ["a", "b"],
+    "tape_alphabet": ["a", "b", "_", "#", "mark"],
+    "initial_state": "start",
+    "accept_states": ["accept"],
+    "reject_states": ["reject"],
+    "blank_symbol": "_",
+    "symbol_aliases": {"hash": "#"},
+    "read_only_input": true,
+    "input_map": {"A": "a", "B": "b"},
+    "normalize_input": {"strip_whitespace": true, "fold_case": false},
+    "left_edge": "stay",
+    "initial_tape": "#",
+    "input_start": 1,
+    "head_start": 0,
+    "defaults": {
+        "max_steps": 500,
+        "max_memory": 1048576,
+        "max_head_overshoot": 10,
+        "output_mode": "tape_trimmed"
+    },
+    "metadata": {
+        "name": "Features",
+        "description": "Scans its input right of a marker.",
+        "tags": ["test"],
+        "tests": [{"input": "ab", "accepts": true}],
+        "docs": "Every optional field."
+    },
+    "transitions": {
+        "start,hash": ["scan", "hash", "R"],
+        "scan,a": ["scan", "a", "R"],
+        "scan,b": ["scan", "b", "R"],
+        "scan,_": ["accept", "mark", "S"]
+    }
+}"##;
+
+/// A definition whose step limit is given by the `default_max_steps`
+/// shorthand
+const STEP_LIMIT: &str = r##"{
+    "states": ["q0", "accept"],
+    "alphabet": ["1"],
+    "tape_alphabet": ["1", "_"],
+    "initial_state": "q0",
+    "accept_states": ["accept"],
+    "reject_states": [],
+    "default_max_steps": 500,
+    "transitions": {
+        "q0,1": ["q0", "1", "R"],
+        "q0,_": ["accept", "_", "S"]
+    }
+}"##;
+
+/// The example machines, [`FEATURES`] and [`STEP_LIMIT`], as parsed from
+/// JSON
+pub(crate) fn definitions() -> Vec<MachineJson> {
+    [
+        include_str!("../../examples/even_ones.json"),
+        include_str!("../../examples/binary_increment.json"),
+        include_str!("../../examples/palindrome.json"),
+        FEATURES,
+        STEP_LIMIT,
+    ]
+    .into_iter()
+    .map(|text| parse_machine_definition(text).expect("fixture parses"))
+    .collect()
+}
+
+/// Assert that two machines give the same results on a few inputs
+pub(crate) fn assert_same_runs(left: &TuringMachine, right: &TuringMachine) {
+    for input in ["", "0", "1", "0110", "111", "ab", "ba", "aab"] {
+        let run = |machine: &TuringMachine| {
+            machine
+                .execute(input, 1000)
+                .map(|result| (result.accepts, result.steps, result.tape))
+        };
+        assert_eq!(run(left), run(right), "input {:?}", input);
+    }
+}
@@ -0,0 +1,240 @@
+//! JFLAP machine files
+//!
+//! JFLAP saves Turing machines as XML `.jff` files: `<state>` elements with
+//! an `id`, a `name` and `<initial/>` or `<final/>` markers, and
+//! `<transition>` elements with `<from>`, `<to>`, `<read>`, `<write>` and
+//! `<move>`. An empty `<read/>` or `<write/>` is the blank.
+//!
+//! JFLAP halts and accepts as soon as a final state is reached, so final
+//! states become accept states and transitions leaving them are dropped.
+//! Reading `~` matches any symbol and writing `~` writes back the symbol
+//! read; reading `!a` matches any symbol but `a`. Transitions reading a
+//! symbol explicitly take precedence over these.
+//!
+//! Multi-tape machines, building blocks and non-deterministic machines
+//! cannot be imported. Files are read only; machines cannot be saved as
+//! `.jff`.
+
+use crate::MachineJson;
+use roxmltree::{Document, Node};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Symbols JFLAP reads as "any symbol" and "any symbol but the next one"
+const ANY: &str = "~";
+const NOT: char = '!';
+
+/// Decode a JFLAP file into the JSON schema
+pub fn from_jflap(text: &str) -> Result<MachineJson, String> {
+    let document = Document::parse(text).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+    if !root.has_tag_name("structure") {
+        return Err("expected a <structure> element".to_string());
+    }
+    let kind = child_text(root, "type");
+    if kind != "turing" {
+        return Err(format!("not a Turing machine (its type is \"{}\")", kind));
+    }
+    let tapes = child_text(root, "tapes");
+    if !tapes.is_empty() && tapes != "1" {
+        return Err(format!("machines with {} tapes are not supported", tapes));
+    }
+    let automaton = child(root, "automaton").unwrap_or(root);
+    if child(automaton, "block").is_some() {
+        return Err("building blocks are not supported".to_string());
+    }
+
+    // States, named after their JFLAP names where those are usable
+    let mut names: HashMap<&str, String> = HashMap::new();
+    let mut states = Vec::new();
+    let mut initial = Vec::new();
+    let mut finals = BTreeSet::new();
+    for state in children(automaton, "state") {
+        let id = state
+            .attribute("id")
+            .ok_or("a <state> has no id attribute")?;
+        let mut name: String = state
+            .attribute("name")
+            .unwrap_or("")
+            .chars()
+            .map(|c| {
+                if c.is_whitespace() || c == ',' {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        if name.is_empty() || states.contains(&name) {
+            name = format!("q{}", id);
+        }
+        if names.insert(id, name.clone()).is_some() {
+            return Err(format!("state id {} is used twice", id));
+        }
+        if child(state, "initial").is_some() {
+            initial.push(name.clone());
+        }
+        if child(state, "final").is_some() {
+            finals.insert(name.clone());
+        }
+        states.push(name);
+    }
+    let initial_state = match initial.as_slice() {
+        [state] => state.clone(),
+        [] => return Err("no initial state".to_string()),
+        _ => {
+            return Err(format!(
+                "more than one initial state: {}",
+                initial.join(", ")
+            ))
+        }
+    };
+
+    let mut rules = Vec::new();
+    for transition in children(automaton, "transition") {
+        let state = |field: &str| -> Result<String, String> {
+            let id = child_text(transition, field);
+            names
+                .get(id)
+                .cloned()
+                .ok_or_else(|| format!("a transition's <{}> is unknown state id '{}'", field, id))
+        };
+        let from = state("from")?;
+        let to = state("to")?;
+        let direction = match child_text(transition, "move") {
+            "R" => "R",
+            "L" => "L",
+            "S" => "S",
+            other => return Err(format!("unknown move '{}' from state {}", other, from)),
+        };
+        rules.push(Rule {
+            from,
+            read: child_text(transition, "read").to_string(),
+            to,
+            write: child_text(transition, "write").to_string(),
+            direction,
+        });
+    }
+
+    // The blank is "_" unless the machine uses it as a symbol
+    let mut symbols = BTreeSet::new();
+    for rule in &rules {
+        let read = rule.read.strip_prefix(NOT).unwrap_or(&rule.read);
+        for symbol in [read, rule.write.as_str()] {
+            if !symbol.is_empty() && symbol != ANY {
+                symbols.insert(symbol.to_string());
+            }
+        }
+    }
+    let blank = if symbols.contains("_") { "□" } else { "_" };
+    let mut tape_alphabet = symbols.clone();
+    tape_alphabet.insert(blank.to_string());
+
+    // Explicit reads first, so that wildcards only fill the gaps
+    let mut transitions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut explicit = BTreeSet::new();
+    let (exact, wild): (Vec<&Rule>, Vec<&Rule>) = rules
+        .iter()
+        .filter(|rule| !finals.contains(&rule.from))
+        .partition(|rule| rule.read != ANY && !rule.read.starts_with(NOT));
+    for rule in exact {
+        let read = if rule.read.is_empty() {
+            blank
+        } else {
+            &rule.read
+        };
+        add(&mut transitions, rule, read, blank)?;
+        explicit.insert((rule.from.as_str(), read.to_string()));
+    }
+    for rule in wild {
+        let excluded = rule.read.strip_prefix(NOT);
+        let excluded = excluded.map(|symbol| if symbol.is_empty() { blank } else { symbol });
+        for read in &tape_alphabet {
+            if Some(read.as_str()) != excluded
+                && !explicit.contains(&(rule.from.as_str(), read.clone()))
+            {
+                add(&mut transitions, rule, read, blank)?;
+            }
+        }
+    }
+
+    let notes: Vec<&str> = children(automaton, "note")
+        .map(|note| child_text(note, "text"))
+        .filter(|text| !text.is_empty())
+        .collect();
+    let mut definition = json!({
+        "states": states,
+        "alphabet": symbols,
+        "tape_alphabet": tape_alphabet,
+        "initial_state": initial_state,
+        "accept_states": finals,
+        "reject_states": [],
+        "blank_symbol": blank,
+        "transitions": transitions,
+    });
+    if !notes.is_empty() {
+        definition["metadata"] = json!({ "docs": notes.join("\n\n") });
+    }
+    serde_json::from_value(definition).map_err(|e| e.to_string())
+}
+
+/// A JFLAP transition, with state names resolved
+struct Rule {
+    from: String,
+    read: String,
+    to: String,
+    write: String,
+    direction: &'static str,
+}
+
+/// Add the transition `rule` takes on reading `read`
+///
+/// A second transition for the same state and symbol going elsewhere makes
+/// the machine non-deterministic, which is an error.
+fn add(
+    transitions: &mut BTreeMap<String, Vec<String>>,
+    rule: &Rule,
+    read: &str,
+    blank: &str,
+) -> Result<(), String> {
+    let write = match rule.write.as_str() {
+        "" => blank,
+        ANY => read,
+        symbol => symbol,
+    };
+    let target = vec![
+        rule.to.clone(),
+        write.to_string(),
+        rule.direction.to_string(),
+    ];
+    let key = format!("{},{}", rule.from, read);
+    match transitions.get(&key) {
+        Some(existing) if *existing != target => Err(format!(
+            "state {} has more than one transition reading '{}'; non-deterministic machines are not supported",
+            rule.from, read
+        )),
+        _ => {
+            transitions.insert(key, target);
+            Ok(())
+        }
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+/// The trimmed text of the first `name` child, empty if there is none
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map_or("", str::trim)
+}
@@ -0,0 +1,522 @@
+//! The JSON machine schema, the common representation of all formats
+
+use super::validate;
+use crate::executor::{self, RunDefaults};
+use crate::info::Metadata;
+use crate::{
+    Direction, InputNormalization, LeftEdge, Output, SymbolTable, TapeSetup, TransitionKey,
+    TransitionTarget, TuringMachine,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Helper struct for JSON (de)serialization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineJson {
+    pub states: Vec<String>,
+    pub alphabet: Vec<String>,
+    pub tape_alphabet: Vec<String>,
+    pub initial_state: String,
+    pub accept_states: Vec<String>,
+    pub reject_states: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blank_symbol: Option<String>,
+    /// Readable names for tape symbols, usable in transitions in place of
+    /// the symbols, e.g. `{"blank": "_", "mark": "X"}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub symbol_aliases: BTreeMap<String, String>,
+    /// Whether the cells holding the input may only be overwritten with the
+    /// symbol already there
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only_input: bool,
+    /// Whether halting without a transition accepts, so that only reject
+    /// states reject
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub accept_on_halt: bool,
+    /// Symbols replaced in every input before it is checked and run, e.g.
+    /// `{"a": "0", "b": "1"}` to run a machine over {0,1} on inputs over {a,b}
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub input_map: BTreeMap<String, String>,
+    /// Whitespace stripping and case folding applied to every input before
+    /// the input map
+    #[serde(default, skip_serializing_if = "InputNormalization::is_none")]
+    pub normalize_input: InputNormalization,
+    /// Bounds the tape on the left, at cell 0, and says what a
+    /// left move from there does; the tape is unbounded when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_edge: Option<LeftEdge>,
+    /// Number of cells of a circular tape, on which the head wraps around at
+    /// either end; the tape is not circular when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tape_length: Option<usize>,
+    /// Symbols on the tape from cell 0 before the input is written, e.g.
+    /// `"#"` for a marker left of an input placed further right
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_tape: Option<String>,
+    /// Cell holding the first input symbol
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub input_start: usize,
+    /// Cell the head starts on, the first input cell when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_start: Option<usize>,
+    /// Where a machine computing a function leaves its output; the machine
+    /// only accepts or rejects when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputJson>,
+    /// Step limit recommended for runs of the machine, a shorthand for
+    /// `defaults.max_steps`; written there when the machine is saved
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "executor::deserialize_max_steps",
+        serialize_with = "executor::serialize_max_steps"
+    )]
+    pub default_max_steps: Option<usize>,
+    /// Limits recommended for runs of the machine, used unless the user
+    /// sets them explicitly
+    #[serde(default, skip_serializing_if = "RunDefaults::is_empty")]
+    pub defaults: RunDefaults,
+    /// Name, description, tags, sample inputs and documentation, which do
+    /// not affect runs
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    pub transitions: BTreeMap<String, Vec<String>>,
+}
+
+/// The `output` field of a definition: `"tape"`, or
+/// `{"between": [start, end]}` with the markers given by symbol name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputJson {
+    Tape,
+    Between(String, String),
+}
+
+/// Whether a count is zero, for fields left out at their default
+pub(super) fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// Parse a machine definition from JSON text
+///
+/// The text is read as JSON5, so plain JSON works unchanged while comments
+/// (`//`, `/* */`) and trailing commas are also accepted. The definition is
+/// validated against the machine schema; errors name the offending key and
+/// its line and column, one problem per line.
+pub fn parse_machine_definition(text: &str) -> Result<MachineJson, String> {
+    let value = json5::from_str::<serde_json::Value>(text).map_err(|e| e.to_string())?;
+    check_definition_value(text, value)
+}
+
+/// Validate a definition parsed from `text`, in which problems are located
+pub(super) fn check_definition_value(
+    text: &str,
+    value: serde_json::Value,
+) -> Result<MachineJson, String> {
+    let _span = tracing::debug_span!("validate").entered();
+    let mut diagnostics = validate::check_schema(&value);
+    if diagnostics.is_empty() {
+        let definition = serde_json::from_value::<MachineJson>(value).map_err(|e| e.to_string())?;
+        diagnostics = validate::check_definition(&definition);
+        if diagnostics.is_empty() {
+            return Ok(definition);
+        }
+    }
+
+    tracing::debug!(problems = diagnostics.len(), "validation failed");
+    validate::locate(text, &mut diagnostics);
+    validate::sort(&mut diagnostics);
+    Err(validate::report(&diagnostics))
+}
+
+impl MachineJson {
+    /// The run defaults with the `default_max_steps` shorthand merged into
+    /// `defaults.max_steps`
+    pub fn run_defaults(&self) -> Result<RunDefaults, String> {
+        let mut defaults = self.defaults;
+        if let Some(max_steps) = self.default_max_steps {
+            if defaults.max_steps.is_some_and(|set| set != max_steps) {
+                return Err(
+                    "default_max_steps and defaults.max_steps set different step limits"
+                        .to_string(),
+                );
+            }
+            defaults.max_steps = Some(max_steps);
+        }
+        Ok(defaults)
+    }
+}
+
+/// A transition entry with the symbol read and the symbol written replaced
+/// by the symbols they stand for if they are names in `aliases`
+pub fn resolve_aliases(
+    aliases: &BTreeMap<String, String>,
+    key: &str,
+    value: &[String],
+) -> (String, Vec<String>) {
+    let resolve = |name: &str| aliases.get(name).map_or(name, String::as_str).to_string();
+    let key = match key.split_once(',') {
+        Some((state, symbol)) => format!("{},{}", state, resolve(symbol)),
+        None => key.to_string(),
+    };
+    let mut value = value.to_vec();
+    if let Some(symbol) = value.get_mut(1) {
+        *symbol = resolve(symbol);
+    }
+    (key, value)
+}
+
+/// Parse a single `"state,symbol": [new_state, write_symbol, direction]` entry
+///
+/// Symbols longer than one character are interned in `symbols`.
+pub fn parse_transition(
+    key: &str,
+    value: &[String],
+    symbols: &mut SymbolTable,
+) -> Result<(TransitionKey, TransitionTarget), String> {
+    let parts: Vec<&str> = key.split(',').collect();
+    if parts.len() != 2 {
+        return Err(format!("Invalid transition key: {}", key));
+    }
+    let state = parts[0].to_string();
+    let symbol = symbols
+        .intern(parts[1])
+        .map_err(|e| format!("Invalid symbol in transition key {}: {}", key, e))?;
+
+    if value.len() != 3 {
+        return Err(format!("Invalid transition value for key: {}", key));
+    }
+    let new_state = value[0].clone();
+    let write_symbol = symbols
+        .intern(&value[1])
+        .map_err(|e| format!("Invalid write symbol in transition {}: {}", key, e))?;
+    let direction = match value[2].as_str() {
+        "L" => Direction::L,
+        "R" => Direction::R,
+        "S" | "N" => Direction::S,
+        _ => return Err(format!("Invalid direction: {}", value[2])),
+    };
+
+    Ok(((state, symbol), (new_state, write_symbol, direction)))
+}
+
+/// Read symbol of a transition that applies to every symbol without a
+/// transition of its own, unless `*` is a tape symbol
+pub const ANY_SYMBOL: char = '*';
+
+/// Written symbol meaning "write back the symbol read", unless `=` is a tape
+/// symbol
+pub const SAME_SYMBOL: char = '=';
+
+/// Replace `*` transitions by one transition per tape symbol and `=` by the
+/// symbol read
+///
+/// A `*` transition covers the symbols that have no transition of their own
+/// in the same state.
+pub fn expand_wildcards(
+    transitions: Vec<(TransitionKey, TransitionTarget)>,
+    tape_alphabet: &HashSet<char>,
+) -> Vec<(TransitionKey, TransitionTarget)> {
+    let any = !tape_alphabet.contains(&ANY_SYMBOL);
+    let same = !tape_alphabet.contains(&SAME_SYMBOL);
+    let write = |written: char, read: char| {
+        if same && written == SAME_SYMBOL {
+            read
+        } else {
+            written
+        }
+    };
+
+    let explicit: HashSet<TransitionKey> = transitions
+        .iter()
+        .filter(|((_, symbol), _)| !(any && *symbol == ANY_SYMBOL))
+        .map(|(key, _)| key.clone())
+        .collect();
+    let mut symbols: Vec<char> = tape_alphabet.iter().copied().collect();
+    symbols.sort();
+
+    let mut expanded = Vec::with_capacity(transitions.len());
+    for ((state, symbol), (new_state, written, direction)) in transitions {
+        if !(any && symbol == ANY_SYMBOL) {
+            let written = write(written, symbol);
+            expanded.push(((state, symbol), (new_state, written, direction)));
+            continue;
+        }
+        for &read in &symbols {
+            let key = (state.clone(), read);
+            if !explicit.contains(&key) {
+                expanded.push((key, (new_state.clone(), write(written, read), direction)));
+            }
+        }
+    }
+    expanded
+}
+
+/// Parse a Turing machine from JSON format
+pub fn parse_machine_json(json_data: &MachineJson) -> Result<TuringMachine, String> {
+    // Convert transitions from string keys to tuple keys
+    let mut symbols = SymbolTable::new();
+    let mut transitions = HashMap::new();
+    let aliases = &json_data.symbol_aliases;
+    for (key, value) in &json_data.transitions {
+        let (from, to) = if aliases.is_empty() {
+            parse_transition(key, value, &mut symbols)?
+        } else {
+            let (key, value) = resolve_aliases(aliases, key, value);
+            parse_transition(&key, &value, &mut symbols)?
+        };
+        transitions.insert(from, to);
+    }
+
+    build_machine(json_data, transitions, symbols)
+}
+
+/// Validate the non-transition fields of a definition and build the machine
+///
+/// `json_data.transitions` is ignored; the already parsed `transitions` are
+/// used instead, after [`expand_wildcards`]. `symbols` holds the named
+/// symbols interned while parsing them; the alphabets' names are added.
+pub fn build_machine(
+    json_data: &MachineJson,
+    transitions: HashMap<TransitionKey, TransitionTarget>,
+    mut symbols: SymbolTable,
+) -> Result<TuringMachine, String> {
+    let blank_symbol = match &json_data.blank_symbol {
+        Some(name) => symbols
+            .intern(name)
+            .map_err(|e| format!("Invalid blank symbol: {}", e))?,
+        None => '_',
+    };
+    let alphabet = json_data
+        .alphabet
+        .iter()
+        .map(|entry| symbols.intern(entry))
+        .collect::<Result<HashSet<char>, String>>()
+        .map_err(|e| format!("Invalid alphabet entry: {}", e))?;
+    let tape_alphabet = json_data
+        .tape_alphabet
+        .iter()
+        .map(|entry| symbols.intern(entry))
+        .collect::<Result<HashSet<char>, String>>()
+        .map_err(|e| format!("Invalid tape alphabet entry: {}", e))?;
+    let output = match &json_data.output {
+        None => None,
+        Some(OutputJson::Tape) => Some(Output::Tape),
+        Some(OutputJson::Between(start, end)) => {
+            let mut marker = |name: &str| {
+                symbols
+                    .intern(name)
+                    .map_err(|e| format!("Invalid output marker: {}", e))
+            };
+            Some(Output::Between(marker(start)?, marker(end)?))
+        }
+    };
+    // Most machines have no wildcards, and large ones are loaded faster
+    // without rebuilding their transitions
+    let any = !tape_alphabet.contains(&ANY_SYMBOL);
+    let same = !tape_alphabet.contains(&SAME_SYMBOL);
+    let has_wildcards = transitions.iter().any(|((_, read), (_, written, _))| {
+        (any && *read == ANY_SYMBOL) || (same && *written == SAME_SYMBOL)
+    });
+    let transitions = if has_wildcards {
+        expand_wildcards(transitions.into_iter().collect(), &tape_alphabet)
+            .into_iter()
+            .collect()
+    } else {
+        transitions
+    };
+
+    let mut machine = TuringMachine::new(
+        json_data.states.iter().cloned().collect(),
+        alphabet,
+        tape_alphabet,
+        transitions,
+        json_data.initial_state.clone(),
+        json_data.accept_states.iter().cloned().collect(),
+        json_data.reject_states.iter().cloned().collect(),
+        blank_symbol,
+    )?;
+    machine.set_read_only_input(json_data.read_only_input);
+    machine.set_accept_on_halt(json_data.accept_on_halt);
+    machine.set_input_map(parse_input_map(&json_data.input_map)?)?;
+    machine.set_input_normalization(json_data.normalize_input);
+    machine.set_left_edge(json_data.left_edge);
+    machine.set_tape_length(json_data.tape_length)?;
+    machine.set_symbols(symbols);
+    let contents = match &json_data.initial_tape {
+        Some(tape) => machine
+            .symbols()
+            .tokenize(tape)
+            .map_err(|e| format!("Invalid initial tape: {}", e))?,
+        None => Vec::new(),
+    };
+    machine.set_tape_setup(TapeSetup {
+        contents,
+        input_start: json_data.input_start,
+        head_start: json_data.head_start,
+    })?;
+    machine.set_output(output)?;
+    machine.set_run_defaults(json_data.run_defaults()?);
+    machine.set_metadata(json_data.metadata.clone());
+    machine.set_symbol_aliases(json_data.symbol_aliases.clone());
+    Ok(machine)
+}
+
+/// Convert the `input_map` entries of a definition to characters
+pub fn parse_input_map(entries: &BTreeMap<String, String>) -> Result<HashMap<char, char>, String> {
+    let single = |entry: &str| {
+        let mut chars = entry.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!(
+                "Input map entry '{}' must be a single character",
+                entry
+            )),
+        }
+    };
+    entries
+        .iter()
+        .map(|(from, to)| Ok((single(from)?, single(to)?)))
+        .collect()
+}
+
+impl From<&TuringMachine> for MachineJson {
+    /// Describe a machine in the definition schema
+    ///
+    /// Sets are written in sorted order so the output is deterministic.
+    fn from(machine: &TuringMachine) -> Self {
+        fn sorted<T: ToString>(items: impl IntoIterator<Item = T>) -> Vec<String> {
+            let mut items: Vec<String> = items.into_iter().map(|i| i.to_string()).collect();
+            items.sort();
+            items
+        }
+        let symbols = &machine.symbols;
+        let names = |set: &HashSet<char>| {
+            let mut names: Vec<String> = set.iter().map(|symbol| symbols.name(*symbol)).collect();
+            names.sort();
+            names
+        };
+
+        let transitions = machine
+            .transitions
+            .iter()
+            .map(|((state, symbol), (new_state, write_symbol, direction))| {
+                let direction = match direction {
+                    Direction::L => "L",
+                    Direction::R => "R",
+                    Direction::S => "S",
+                };
+                (
+                    format!("{},{}", state, symbols.name(*symbol)),
+                    vec![
+                        new_state.clone(),
+                        symbols.name(*write_symbol),
+                        direction.to_string(),
+                    ],
+                )
+            })
+            .collect();
+
+        MachineJson {
+            states: sorted(&machine.states),
+            alphabet: names(&machine.alphabet),
+            tape_alphabet: names(&machine.tape_alphabet),
+            initial_state: machine.initial_state.clone(),
+            accept_states: sorted(&machine.accept_states),
+            reject_states: sorted(&machine.reject_states),
+            blank_symbol: Some(symbols.name(machine.blank_symbol)),
+            symbol_aliases: machine.symbol_aliases.clone(),
+            read_only_input: machine.read_only_input,
+            accept_on_halt: machine.accept_on_halt,
+            input_map: machine
+                .input_map
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+            normalize_input: machine.input_normalization,
+            left_edge: machine.left_edge,
+            tape_length: machine.tape_length,
+            initial_tape: Some(symbols.render(&machine.tape_setup.contents))
+                .filter(|tape| !tape.is_empty()),
+            input_start: machine.tape_setup.input_start,
+            head_start: machine.tape_setup.head_start,
+            output: machine.output.map(|output| match output {
+                Output::Tape => OutputJson::Tape,
+                Output::Between(start, end) => {
+                    OutputJson::Between(symbols.name(start), symbols.name(end))
+                }
+            }),
+            default_max_steps: None,
+            defaults: machine.run_defaults,
+            metadata: machine.metadata.clone(),
+            transitions,
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Write the machine as pretty-printed JSON in the definition schema
+    ///
+    /// The output parses back with [`parse_machine_definition`] into an
+    /// equivalent machine.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&MachineJson::from(self)).map_err(|e| e.to_string())
+    }
+}
+
+impl Serialize for TuringMachine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MachineJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TuringMachine {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let definition = MachineJson::deserialize(deserializer)?;
+        super::checked(definition)
+            .and_then(|definition| parse_machine_json(&definition))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_like_parse_machine_definition() {
+        let text = r#"{
+            "states": ["q0", "acc"],
+            "alphabet": ["0"],
+            "tape_alphabet": ["0", "_"],
+            "initial_state": "q0",
+            "accept_states": ["acc"],
+            "reject_states": [],
+            "symbol_aliases": {"zero": "0"},
+            "transitions": {"q0,zero": ["acc", "zero", "R"]}
+        }"#;
+        let parsed = parse_machine_json(&parse_machine_definition(text).unwrap()).unwrap();
+        let deserialized = serde_json::from_str::<TuringMachine>(text).unwrap();
+        for machine in [&parsed, &deserialized] {
+            let result = machine.execute("0", 100).unwrap();
+            assert_eq!(result.accepts, Some(true));
+            assert_eq!(result.tape, "0");
+        }
+        assert_eq!(MachineJson::from(&parsed), MachineJson::from(&deserialized));
+    }
+
+    #[test]
+    fn deserializing_checks_the_definition() {
+        let text = r#"{
+            "states": ["q0"],
+            "alphabet": ["0"],
+            "tape_alphabet": ["0", "_"],
+            "initial_state": "q0",
+            "accept_states": ["acc"],
+            "reject_states": [],
+            "transitions": {}
+        }"#;
+        assert!(parse_machine_definition(text).is_err());
+        assert!(serde_json::from_str::<TuringMachine>(text).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! Machine definition file formats
+//!
+//! Every format converts to and from [`MachineJson`], the JSON schema
+//! described in the README, so a machine can be moved between formats
+//! without losing information.
+
+mod binary;
+#[cfg(feature = "packs")]
+mod bundle;
+pub(crate) mod dsl;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "jflap")]
+mod jflap;
+mod json;
+mod morphett;
+mod streaming;
+mod toml;
+mod tursi;
+pub mod validate;
+#[cfg(feature = "xlsx")]
+mod xlsx;
+mod yaml;
+
+pub use dsl::from_dsl;
+pub use validate::MACHINE_SCHEMA;
+pub use yaml::from_yaml;
+
+pub use json::{
+    build_machine, expand_wildcards, parse_input_map, parse_machine_definition, parse_machine_json,
+    parse_transition, resolve_aliases, MachineJson, OutputJson,
+};
+
+use crate::TuringMachine;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use streaming::StreamingError;
+
+/// Plain JSON files larger than this are loaded with the streaming parser
+const STREAMING_THRESHOLD: u64 = 1 << 20;
+
+/// Error for spreadsheets in a build without the `xlsx` feature
+#[cfg(not(feature = "xlsx"))]
+const XLSX_DISABLED: &str = "Spreadsheets are not supported; build with the `xlsx` feature";
+
+/// Error for machine bundles in a build without the `packs` feature
+#[cfg(not(feature = "packs"))]
+const BUNDLE_DISABLED: &str = "Machine bundles are not supported; build with the `packs` feature";
+
+/// Error for JFLAP files in a build without the `jflap` feature
+#[cfg(not(feature = "jflap"))]
+const JFLAP_DISABLED: &str = "JFLAP files are not supported; build with the `jflap` feature";
+
+/// Supported machine definition formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// JSON, read as JSON5 so comments and trailing commas are allowed
+    Json,
+    /// TOML with one table per state
+    Toml,
+    /// YAML with the same layout as JSON
+    Yaml,
+    /// The machine DSL, one transition per line
+    Dsl,
+    /// Compact binary encoding for large generated machines
+    Binary,
+    /// Excel spreadsheet with the transitions as a state × symbol grid
+    Xlsx,
+    /// JFLAP's XML format, which can only be read
+    Jflap,
+    /// Zip archive of the definition, metadata, tests and docs
+    Bundle,
+    /// Morphett's line-based format, or tursi's with its directives, which
+    /// can only be read
+    Morphett,
+}
+
+impl Format {
+    /// Pick a format from a file extension, if it is a recognized one
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("json" | "json5" | "jsonc") => Some(Format::Json),
+            Some("toml") => Some(Format::Toml),
+            Some("yaml" | "yml") => Some(Format::Yaml),
+            Some("tml") => Some(Format::Dsl),
+            Some("tmb") => Some(Format::Binary),
+            Some("xlsx") => Some(Format::Xlsx),
+            Some("jff") => Some(Format::Jflap),
+            Some("tmpkg") => Some(Format::Bundle),
+            Some("tm") => Some(Format::Morphett),
+            _ => None,
+        }
+    }
+}
+
+/// Read a machine definition, choosing the format from the file extension
+///
+/// Files with an unknown extension are read as JSON.
+#[tracing::instrument(name = "parse", skip_all, fields(path = %path.display()))]
+pub fn read_definition(path: &Path) -> Result<MachineJson, String> {
+    let bytes = fs::read(path).map_err(|e| format!("File error: {}", e))?;
+    match Format::from_path(path).unwrap_or(Format::Json) {
+        Format::Json => parse_machine_definition(as_text(&bytes)?)
+            .map_err(|e| format!("Invalid JSON in file: {}", e)),
+        Format::Toml => toml::from_toml(as_text(&bytes)?)
+            .and_then(checked)
+            .map_err(|e| format!("Invalid TOML in file: {}", e)),
+        Format::Yaml => {
+            yaml::from_yaml(as_text(&bytes)?).map_err(|e| format!("Invalid YAML in file: {}", e))
+        }
+        Format::Dsl => {
+            dsl::from_dsl(as_text(&bytes)?).map_err(|e| format!("Invalid machine file: {}", e))
+        }
+        Format::Binary => binary::from_binary(&bytes)
+            .and_then(checked)
+            .map_err(|e| format!("Invalid binary machine file: {}", e)),
+        #[cfg(feature = "xlsx")]
+        Format::Xlsx => xlsx::from_xlsx(&bytes)
+            .and_then(checked)
+            .map_err(|e| format!("Invalid spreadsheet: {}", e)),
+        #[cfg(not(feature = "xlsx"))]
+        Format::Xlsx => Err(XLSX_DISABLED.to_string()),
+        #[cfg(feature = "jflap")]
+        Format::Jflap => jflap::from_jflap(as_text(&bytes)?)
+            .and_then(checked)
+            .map_err(|e| format!("Invalid JFLAP file: {}", e)),
+        #[cfg(not(feature = "jflap"))]
+        Format::Jflap => Err(JFLAP_DISABLED.to_string()),
+        #[cfg(feature = "packs")]
+        Format::Bundle => {
+            bundle::from_bundle(&bytes).map_err(|e| format!("Invalid machine bundle: {}", e))
+        }
+        #[cfg(not(feature = "packs"))]
+        Format::Bundle => Err(BUNDLE_DISABLED.to_string()),
+        Format::Morphett => {
+            let text = as_text(&bytes)?;
+            if tursi::is_tursi(text) {
+                tursi::from_tursi(text)
+                    .and_then(checked)
+                    .map_err(|e| format!("Invalid tursi program: {}", e))
+            } else {
+                morphett::from_morphett(text)
+                    .and_then(checked)
+                    .map_err(|e| format!("Invalid Morphett program: {}", e))
+            }
+        }
+    }
+}
+
+/// Run the cross-reference checks on a definition read from a non-JSON
+/// format or deserialized directly
+fn checked(definition: MachineJson) -> Result<MachineJson, String> {
+    let diagnostics = validate::check_definition(&definition);
+    if diagnostics.is_empty() {
+        Ok(definition)
+    } else {
+        Err(validate::report(&diagnostics))
+    }
+}
+
+/// Load and validate a machine, choosing the format from the file extension
+///
+/// Large `.json` files are parsed in a streaming fashion with bounded memory,
+/// calling `progress` with the number of transitions read so far, unless
+/// they use something only the regular parser supports, such as JSON5
+/// comments. Binary files are built into a machine directly,
+/// and read as a definition only to report what is wrong with them.
+#[tracing::instrument(name = "load", skip_all, fields(path = %path.display()))]
+pub fn load_machine(path: &Path, progress: &mut dyn FnMut(usize)) -> Result<TuringMachine, String> {
+    let is_large_json = path.extension().and_then(|s| s.to_str()) == Some("json")
+        && fs::metadata(path).is_ok_and(|m| m.len() > STREAMING_THRESHOLD);
+    if is_large_json {
+        let file = File::open(path).map_err(|e| format!("File error: {}", e))?;
+        tracing::debug!("using the streaming parser");
+        match streaming::parse_json_streaming(BufReader::new(file), progress) {
+            Ok((definition, transitions, symbols)) => {
+                return build_machine(&definition, transitions, symbols)
+                    .map_err(|e| format!("Error creating machine: {}", e))
+            }
+            Err(StreamingError::Unsupported(e)) => {
+                tracing::debug!("not supported by the streaming parser: {}", e)
+            }
+            Err(StreamingError::Invalid(e)) => return Err(format!("Invalid JSON in file: {}", e)),
+            Err(StreamingError::Diagnostics(mut diagnostics)) => {
+                // Only read in full to locate the problems
+                let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+                validate::locate(&text, &mut diagnostics);
+                validate::sort(&mut diagnostics);
+                return Err(format!(
+                    "Invalid JSON in file: {}",
+                    validate::report(&diagnostics)
+                ));
+            }
+            Err(StreamingError::Transition(e)) => {
+                return Err(format!("Error creating machine: {}", e))
+            }
+        }
+    }
+
+    if Format::from_path(path) == Some(Format::Binary) {
+        let bytes = fs::read(path).map_err(|e| format!("File error: {}", e))?;
+        match binary::load_binary(&bytes) {
+            Ok(machine) => return Ok(machine),
+            Err(e) => tracing::info!("binary loader failed, retrying: {}", e),
+        }
+    }
+
+    let definition = read_definition(path)?;
+    let machine =
+        parse_machine_json(&definition).map_err(|e| format!("Error creating machine: {}", e))?;
+    tracing::debug!(
+        states = machine.states.len(),
+        transitions = machine.transitions.len(),
+        "loaded"
+    );
+    Ok(machine)
+}
+
+/// Write a machine definition, choosing the format from the file extension
+pub fn write_definition(path: &Path, definition: &MachineJson) -> Result<(), String> {
+    let format = Format::from_path(path)
+        .ok_or_else(|| format!("Unknown machine file extension: {}", path.display()))?;
+    let bytes = match format {
+        Format::Json => (serde_json::to_string_pretty(definition).map_err(|e| e.to_string())?
+            + "\n")
+            .into_bytes(),
+        Format::Toml => toml::to_toml(definition)?.into_bytes(),
+        Format::Yaml => yaml::to_yaml(definition)?.into_bytes(),
+        Format::Dsl => dsl::to_dsl(definition)?.into_bytes(),
+        Format::Binary => binary::to_binary(definition)?,
+        #[cfg(feature = "xlsx")]
+        Format::Xlsx => xlsx::to_xlsx(definition)?,
+        #[cfg(not(feature = "xlsx"))]
+        Format::Xlsx => return Err(XLSX_DISABLED.to_string()),
+        #[cfg(feature = "packs")]
+        Format::Bundle => bundle::to_bundle(definition)?,
+        #[cfg(not(feature = "packs"))]
+        Format::Bundle => return Err(BUNDLE_DISABLED.to_string()),
+        Format::Jflap => return Err("JFLAP files can only be read".to_string()),
+        Format::Morphett => return Err("Morphett and tursi programs can only be read".to_string()),
+    };
+    fs::write(path, bytes).map_err(|e| format!("File error: {}", e))
+}
+
+/// Interpret file contents as UTF-8 text
+fn as_text(bytes: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(bytes).map_err(|e| format!("File error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Extensions of the formats this build can write
+    fn written_extensions() -> Vec<&'static str> {
+        let mut extensions = vec!["json", "toml", "yaml", "tml", "tmb"];
+        if cfg!(feature = "xlsx") {
+            extensions.push("xlsx");
+        }
+        if cfg!(feature = "packs") {
+            extensions.push("tmpkg");
+        }
+        extensions
+    }
+
+    #[test]
+    fn converts_every_definition_without_loss() {
+        let dir = std::env::temp_dir().join(format!("turing-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (index, definition) in fixtures::definitions().into_iter().enumerate() {
+            for extension in written_extensions() {
+                let path = dir.join(format!("machine{}.{}", index, extension));
+                write_definition(&path, &definition).unwrap();
+                let mut expected = definition.clone();
+                if extension == "tmb" {
+                    // The step limit shorthand is stored as `defaults.max_steps`
+                    expected.defaults = definition.run_defaults().unwrap();
+                    expected.default_max_steps = None;
+                }
+                let read = read_definition(&path).unwrap();
+                assert_eq!(read, expected, "{}", path.display());
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_symbol_aliases_in_every_written_format() {
+        let definition = fixtures::definitions().remove(3);
+        assert_eq!(definition.symbol_aliases["hash"], "#");
+        let dir = std::env::temp_dir().join(format!("turing-aliases-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for extension in written_extensions() {
+            let path = dir.join(format!("features.{}", extension));
+            write_definition(&path, &definition).unwrap();
+            let read = read_definition(&path).unwrap();
+            assert_eq!(
+                read.symbol_aliases, definition.symbol_aliases,
+                "{}",
+                extension
+            );
+            assert_eq!(
+                read.transitions["start,hash"], definition.transitions["start,hash"],
+                "{}",
+                extension
+            );
+            fixtures::assert_same_runs(
+                &load_machine(&path, &mut |_| {}).unwrap(),
+                &parse_machine_json(&definition).unwrap(),
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_the_same_problems_with_either_json_loader() {
+        let transition = |values: [&str; 3]| values.map(String::from).to_vec();
+        let mut bad_direction = fixtures::definitions().remove(0);
+        bad_direction
+            .transitions
+            .insert("q1,1".to_string(), transition(["q0", "1", "X"]));
+        let mut undeclared = fixtures::definitions().remove(0);
+        undeclared
+            .transitions
+            .insert("q0,x".to_string(), transition(["nowhere", "0", "R"]));
+        let mut unparsable = fixtures::definitions().remove(0);
+        unparsable.tape_alphabet.push("a b".to_string());
+        unparsable
+            .transitions
+            .insert("q1,1".to_string(), transition(["q0", "a b", "R"]));
+
+        let dir = std::env::temp_dir().join(format!("turing-loaders-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for definition in [bad_direction, undeclared, unparsable] {
+            let text = serde_json::to_string_pretty(&definition).unwrap();
+            let small = dir.join("small.json");
+            fs::write(&small, &text).unwrap();
+            // Trailing whitespace takes the file to the streaming parser
+            let large = dir.join("large.json");
+            fs::write(&large, text + &" ".repeat(STREAMING_THRESHOLD as usize)).unwrap();
+            let mut streamed_progress = false;
+            let streamed = load_machine(&large, &mut |_| streamed_progress = true).unwrap_err();
+            assert!(streamed_progress);
+            assert_eq!(streamed, load_machine(&small, &mut |_| {}).unwrap_err());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_symbol_aliases_through_a_machine() {
+        let definition = fixtures::definitions().remove(3);
+        let machine = parse_machine_json(&definition).unwrap();
+        assert_eq!(
+            MachineJson::from(&machine).symbol_aliases,
+            definition.symbol_aliases
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Morphett's line-based machine format
+//!
+//! The format of Anthony Morphett's online simulator, which many published
+//! machines are written in. Each line is one rule:
+//!
+//! ```text
+//! ; Replaces every 0 by 1, then accepts
+//! 0 0 1 r 0
+//! 0 1 1 r 0
+//! 0 _ _ * halt-accept
+//! ```
+//!
+//! that is `state symbol new_symbol direction new_state`. `_` is the blank,
+//! `;` starts a comment and a trailing `!` (a breakpoint in the simulator)
+//! is ignored. Directions are `l`, `r` and `*` for staying. A `*` matches
+//! any state or symbol when read, and keeps the state or symbol when
+//! written; rules for a specific state and symbol take precedence over
+//! wildcards, and the first of several rules that match equally is used.
+//!
+//! Machines start in state `0`, or in the first state of the first rule if
+//! there is no state `0`. States whose names start with `halt` halt: those
+//! containing `reject` reject and the others accept. The comments at the
+//! top of the file become the machine's docs. Files are read only.
+
+use crate::MachineJson;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Symbol, state or direction matching anything, or keeping what was there
+pub(super) const WILDCARD: &str = "*";
+/// Blank symbol
+const BLANK: &str = "_";
+/// Prefix of the names of halting states
+const HALT: &str = "halt";
+
+/// Decode a Morphett program into the JSON schema
+pub fn from_morphett(text: &str) -> Result<MachineJson, String> {
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut states: Vec<String> = Vec::new();
+    let mut symbols = BTreeSet::from([BLANK.to_string()]);
+    let mut docs: Vec<&str> = Vec::new();
+    let mut in_header = true;
+
+    for (row, line) in text.lines().enumerate() {
+        let (code, comment) = line.split_once(';').unwrap_or((line, ""));
+        let code = code.trim();
+        if code.is_empty() {
+            if in_header && line.trim_start().starts_with(';') {
+                docs.push(comment.trim());
+            } else if in_header && !docs.is_empty() {
+                in_header = false;
+            }
+            continue;
+        }
+        in_header = false;
+
+        let mut fields: Vec<&str> = code.split_whitespace().collect();
+        if fields.last() == Some(&"!") {
+            fields.pop();
+        } else if let Some(last) = fields.last_mut() {
+            *last = last.strip_suffix('!').unwrap_or(last);
+        }
+        let [state, read, write, direction, next] = fields[..] else {
+            return Err(format!(
+                "line {}: expected `state symbol new_symbol direction new_state`",
+                row + 1
+            ));
+        };
+        for symbol in [read, write] {
+            if symbol.chars().count() != 1 || symbol == "," {
+                return Err(format!(
+                    "line {}: '{}' is not a symbol; symbols are single characters other than ','",
+                    row + 1,
+                    symbol
+                ));
+            }
+            if symbol != WILDCARD {
+                symbols.insert(symbol.to_string());
+            }
+        }
+        let direction = match direction {
+            "l" | "L" => "L",
+            "r" | "R" => "R",
+            "*" | "s" | "S" => "S",
+            other => {
+                return Err(format!(
+                    "line {}: unknown direction '{}'; use l, r or *",
+                    row + 1,
+                    other
+                ))
+            }
+        };
+        for name in [state, next] {
+            if name != WILDCARD && !states.iter().any(|s| s == name) {
+                if name.contains(',') {
+                    return Err(format!(
+                        "line {}: state '{}' contains a comma",
+                        row + 1,
+                        name
+                    ));
+                }
+                states.push(name.to_string());
+            }
+        }
+        rules.push(Rule {
+            state,
+            read,
+            write,
+            direction,
+            next,
+        });
+    }
+    if rules.is_empty() {
+        return Err("the program has no rules".to_string());
+    }
+
+    let halts = |state: &str| state.starts_with(HALT);
+    let initial_state = if states.iter().any(|s| s == "0") {
+        "0"
+    } else {
+        states
+            .first()
+            .map(String::as_str)
+            .ok_or("no state is named")?
+    };
+
+    let transitions = resolve(&rules, &states, &symbols, halts);
+    let (reject, accept): (Vec<&String>, Vec<&String>) = states
+        .iter()
+        .filter(|state| halts(state))
+        .partition(|state| state.contains("reject"));
+    let mut definition = json!({
+        "states": states,
+        "alphabet": symbols.iter().filter(|s| *s != BLANK).collect::<Vec<_>>(),
+        "tape_alphabet": symbols,
+        "initial_state": initial_state,
+        "accept_states": accept,
+        "reject_states": reject,
+        "blank_symbol": BLANK,
+        "transitions": transitions,
+    });
+    let docs = docs.join("\n");
+    if !docs.trim().is_empty() {
+        definition["metadata"] = json!({ "docs": docs.trim() });
+    }
+    serde_json::from_value(definition).map_err(|e| e.to_string())
+}
+
+/// One line of the program
+pub(super) struct Rule<'a> {
+    pub(super) state: &'a str,
+    pub(super) read: &'a str,
+    pub(super) write: &'a str,
+    pub(super) direction: &'static str,
+    pub(super) next: &'a str,
+}
+
+/// One transition for each state that does not halt and each symbol some
+/// rule matches, picking the rule the simulator would, keyed as in the
+/// JSON schema
+pub(super) fn resolve<'a>(
+    rules: &[Rule<'a>],
+    states: &'a [String],
+    symbols: &'a BTreeSet<String>,
+    halts: impl Fn(&str) -> bool,
+) -> BTreeMap<String, [&'a str; 3]> {
+    let mut transitions = BTreeMap::new();
+    for state in states.iter().filter(|state| !halts(state)) {
+        for symbol in symbols {
+            let rule = [
+                (state.as_str(), symbol.as_str()),
+                (state.as_str(), WILDCARD),
+                (WILDCARD, symbol.as_str()),
+                (WILDCARD, WILDCARD),
+            ]
+            .into_iter()
+            .find_map(|key| rules.iter().find(|rule| (rule.state, rule.read) == key));
+            if let Some(rule) = rule {
+                let write = if rule.write == WILDCARD {
+                    symbol
+                } else {
+                    rule.write
+                };
+                let next = if rule.next == WILDCARD {
+                    state
+                } else {
+                    rule.next
+                };
+                transitions.insert(
+                    format!("{},{}", state, symbol),
+                    [next, write, rule.direction],
+                );
+            }
+        }
+    }
+    transitions
+}
@@ -0,0 +1,380 @@
+//! Streaming loader for very large JSON machine files
+//!
+//! The regular loaders first materialize the whole definition, including a
+//! map of `"state,symbol"` strings, and only then convert it into a
+//! [`TuringMachine`]. For huge generated machines that doubles peak memory.
+//! This loader reads from a buffered reader and converts each transition as
+//! soon as it is parsed, so neither the file text nor the string-keyed map is
+//! ever held in memory.
+//!
+//! Only strict JSON without symbol aliases is supported here; other files
+//! need the full parser. The result is validated as the full parser
+//! validates it, transitions that cannot be parsed included, so both report
+//! the same problems.
+
+use super::validate::{self, Diagnostic};
+use super::{parse_transition, MachineJson};
+use crate::executor;
+use crate::{SymbolTable, TransitionKey, TransitionTarget};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+
+/// How many transitions to parse between progress reports
+const PROGRESS_INTERVAL: usize = 10_000;
+
+pub type Transitions = HashMap<TransitionKey, TransitionTarget>;
+
+/// Transitions that could not be parsed, as written, with the reason
+type Rejected = BTreeMap<String, (Vec<String>, String)>;
+
+/// Why the streaming parser did not load a machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamingError {
+    /// The file is not strict JSON, or uses symbol aliases; the full parser
+    /// may still read it, or will report what is wrong with it
+    Unsupported(String),
+    /// The file is a JSON object, but not a valid machine definition
+    Invalid(String),
+    /// The definition has these problems, not yet located in the file text
+    Diagnostics(Vec<Diagnostic>),
+    /// The definition is valid, but this transition cannot be parsed
+    Transition(String),
+}
+
+/// Parse a machine from JSON, reporting the number of transitions parsed so far
+///
+/// `progress` is called every few thousand transitions and once at the end.
+/// The returned definition has an empty `transitions` map; the parsed
+/// transitions are returned alongside it, with the named symbols they use.
+pub fn parse_json_streaming<R: Read>(
+    reader: R,
+    progress: &mut dyn FnMut(usize),
+) -> Result<(MachineJson, Transitions, SymbolTable), StreamingError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut aliased = false;
+    let parsed = MachineSeed {
+        progress,
+        aliased: &mut aliased,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|parsed| deserializer.end().map(|()| parsed));
+    match parsed {
+        Ok((definition, transitions, symbols, rejected)) => {
+            check(&definition, &transitions, &symbols, rejected)?;
+            Ok((definition, transitions, symbols))
+        }
+        Err(e) if aliased || e.is_syntax() || e.is_eof() => {
+            Err(StreamingError::Unsupported(e.to_string()))
+        }
+        Err(e) => Err(StreamingError::Invalid(e.to_string())),
+    }
+}
+
+/// Validate a streamed definition and its transitions against the schema
+/// and for undeclared states and symbols, as the full parser does
+///
+/// Only the `rejected` transitions are checked against the schema, since
+/// the parsed ones fit it.
+fn check(
+    definition: &MachineJson,
+    transitions: &Transitions,
+    symbols: &SymbolTable,
+    rejected: Rejected,
+) -> Result<(), StreamingError> {
+    let mut value =
+        serde_json::to_value(definition).map_err(|e| StreamingError::Invalid(e.to_string()))?;
+    value["transitions"] = rejected
+        .iter()
+        .map(|(key, (value, _))| (key.clone(), serde_json::Value::from(value.clone())))
+        .collect();
+    let mut diagnostics = validate::check_schema(&value);
+    if diagnostics.is_empty() {
+        let parsed = transitions.iter().map(|((state, read), target)| {
+            let (new_state, written, direction) = target;
+            (
+                format!("{},{}", state, symbols.name(*read)),
+                vec![
+                    new_state.clone(),
+                    symbols.name(*written),
+                    format!("{:?}", direction),
+                ],
+            )
+        });
+        let rejected = rejected
+            .iter()
+            .map(|(key, (value, _))| (key.clone(), value.clone()));
+        diagnostics = validate::check_definition_with(definition, parsed.chain(rejected));
+    }
+    if !diagnostics.is_empty() {
+        // Transitions come in no particular order
+        validate::sort(&mut diagnostics);
+        return Err(StreamingError::Diagnostics(diagnostics));
+    }
+    // The full parser stops at the first transition in key order
+    match rejected.into_values().next() {
+        Some((_, e)) => Err(StreamingError::Transition(e)),
+        None => Ok(()),
+    }
+}
+
+/// Deserializes the top-level object, streaming the `transitions` field
+struct MachineSeed<'a> {
+    progress: &'a mut dyn FnMut(usize),
+    /// Set when the definition has symbol aliases
+    aliased: &'a mut bool,
+}
+
+impl<'de> DeserializeSeed<'de> for MachineSeed<'_> {
+    type Value = (MachineJson, Transitions, SymbolTable, Rejected);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for MachineSeed<'_> {
+    type Value = (MachineJson, Transitions, SymbolTable, Rejected);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a machine definition object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut states = None;
+        let mut alphabet = None;
+        let mut tape_alphabet = None;
+        let mut initial_state = None;
+        let mut accept_states = None;
+        let mut reject_states = None;
+        let mut blank_symbol = None;
+        let mut read_only_input = false;
+        let mut accept_on_halt = false;
+        let mut input_map = BTreeMap::new();
+        let mut normalize_input = Default::default();
+        let mut left_edge = None;
+        let mut tape_length = None;
+        let mut initial_tape = None;
+        let mut input_start = 0;
+        let mut head_start = None;
+        let mut output = None;
+        let mut default_max_steps = None;
+        let mut defaults = Default::default();
+        let mut metadata = Default::default();
+        let mut transitions = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "states" => states = Some(map.next_value()?),
+                "alphabet" => alphabet = Some(map.next_value()?),
+                "tape_alphabet" => tape_alphabet = Some(map.next_value()?),
+                "initial_state" => initial_state = Some(map.next_value()?),
+                "accept_states" => accept_states = Some(map.next_value()?),
+                "reject_states" => reject_states = Some(map.next_value()?),
+                "blank_symbol" => blank_symbol = map.next_value()?,
+                "read_only_input" => read_only_input = map.next_value()?,
+                "accept_on_halt" => accept_on_halt = map.next_value()?,
+                "input_map" => input_map = map.next_value()?,
+                "symbol_aliases" => {
+                    // Transitions are parsed as they are read, possibly
+                    // before the aliases
+                    let aliases: BTreeMap<String, String> = map.next_value()?;
+                    if !aliases.is_empty() {
+                        *self.aliased = true;
+                        return Err(de::Error::custom(
+                            "symbol_aliases are resolved by the regular parser",
+                        ));
+                    }
+                }
+                "normalize_input" => normalize_input = map.next_value()?,
+                "left_edge" => left_edge = map.next_value()?,
+                "tape_length" => tape_length = map.next_value()?,
+                "initial_tape" => initial_tape = map.next_value()?,
+                "input_start" => input_start = map.next_value()?,
+                "head_start" => head_start = map.next_value()?,
+                "output" => output = map.next_value()?,
+                "default_max_steps" => {
+                    default_max_steps = map.next_value::<StepLimit>()?.0;
+                }
+                "defaults" => defaults = map.next_value()?,
+                "metadata" => metadata = map.next_value()?,
+                "transitions" => {
+                    transitions = Some(map.next_value_seed(TransitionsSeed {
+                        progress: &mut *self.progress,
+                    })?)
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let definition = MachineJson {
+            states: states.ok_or_else(|| de::Error::missing_field("states"))?,
+            alphabet: alphabet.ok_or_else(|| de::Error::missing_field("alphabet"))?,
+            tape_alphabet: tape_alphabet
+                .ok_or_else(|| de::Error::missing_field("tape_alphabet"))?,
+            initial_state: initial_state
+                .ok_or_else(|| de::Error::missing_field("initial_state"))?,
+            accept_states: accept_states
+                .ok_or_else(|| de::Error::missing_field("accept_states"))?,
+            reject_states: reject_states
+                .ok_or_else(|| de::Error::missing_field("reject_states"))?,
+            blank_symbol,
+            symbol_aliases: BTreeMap::new(),
+            read_only_input,
+            accept_on_halt,
+            input_map,
+            normalize_input,
+            left_edge,
+            tape_length,
+            initial_tape,
+            input_start,
+            head_start,
+            output,
+            default_max_steps,
+            defaults,
+            metadata,
+            transitions: BTreeMap::new(),
+        };
+        let (transitions, symbols, rejected) =
+            transitions.ok_or_else(|| de::Error::missing_field("transitions"))?;
+        Ok((definition, transitions, symbols, rejected))
+    }
+}
+
+/// A step limit, given as a number or as `"unlimited"`
+#[derive(Deserialize)]
+struct StepLimit(#[serde(deserialize_with = "executor::deserialize_max_steps")] Option<usize>);
+
+/// Deserializes the `transitions` object one entry at a time
+struct TransitionsSeed<'a> {
+    progress: &'a mut dyn FnMut(usize),
+}
+
+impl<'de> DeserializeSeed<'de> for TransitionsSeed<'_> {
+    type Value = (Transitions, SymbolTable, Rejected);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TransitionsSeed<'_> {
+    type Value = (Transitions, SymbolTable, Rejected);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of transitions")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut transitions = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+        let mut symbols = SymbolTable::new();
+        let mut rejected = Rejected::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let value: Vec<String> = map.next_value()?;
+            match parse_transition(&key, &value, &mut symbols) {
+                Ok((from, to)) => {
+                    transitions.insert(from, to);
+                }
+                // Reported with the rest of the definition's problems
+                Err(e) => {
+                    rejected.insert(key, (value, e));
+                }
+            }
+
+            if transitions.len() % PROGRESS_INTERVAL == 0 {
+                (self.progress)(transitions.len());
+            }
+        }
+        (self.progress)(transitions.len());
+        Ok((transitions, symbols, rejected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{build_machine, fixtures, parse_machine_definition, parse_machine_json};
+
+    fn stream(text: &str) -> Result<(MachineJson, Transitions, SymbolTable), StreamingError> {
+        parse_json_streaming(text.as_bytes(), &mut |_| {})
+    }
+
+    #[test]
+    fn agrees_with_the_regular_parser() {
+        for definition in fixtures::definitions() {
+            let text = serde_json::to_string(&definition).unwrap();
+            if !definition.symbol_aliases.is_empty() {
+                assert!(matches!(stream(&text), Err(StreamingError::Unsupported(_))));
+                continue;
+            }
+            let (streamed, transitions, symbols) = stream(&text).unwrap();
+            let streamed_machine = build_machine(&streamed, transitions, symbols).unwrap();
+            assert_eq!(
+                streamed,
+                MachineJson {
+                    transitions: BTreeMap::new(),
+                    ..definition.clone()
+                }
+            );
+            fixtures::assert_same_runs(
+                &streamed_machine,
+                &parse_machine_json(&definition).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn reports_progress_once_at_the_end() {
+        let text = serde_json::to_string(&fixtures::definitions()[1]).unwrap();
+        let mut reports = Vec::new();
+        parse_json_streaming(text.as_bytes(), &mut |count| reports.push(count)).unwrap();
+        assert_eq!(reports, [fixtures::definitions()[1].transitions.len()]);
+    }
+
+    #[test]
+    fn leaves_json5_and_aliases_to_the_regular_parser() {
+        let mut definition = fixtures::definitions()[0].clone();
+        let text = serde_json::to_string_pretty(&definition).unwrap();
+        let commented = format!("// A comment\n{}", text);
+        assert!(matches!(
+            stream(&commented),
+            Err(StreamingError::Unsupported(_))
+        ));
+
+        definition
+            .symbol_aliases
+            .insert("zero".to_string(), "0".to_string());
+        let text = serde_json::to_string(&definition).unwrap();
+        assert!(matches!(stream(&text), Err(StreamingError::Unsupported(_))));
+    }
+
+    #[test]
+    fn validates_as_the_regular_parser_does() {
+        let mut definition = fixtures::definitions()[0].clone();
+        definition.transitions.insert(
+            "q0,x".to_string(),
+            vec!["nowhere".to_string(), "0".to_string(), "R".to_string()],
+        );
+        let text = serde_json::to_string_pretty(&definition).unwrap();
+        let Err(StreamingError::Diagnostics(streamed)) = stream(&text) else {
+            panic!("undeclared state and symbol accepted");
+        };
+        let streamed = validate::report(&streamed);
+        let regular = parse_machine_definition(&text).unwrap_err();
+        for problem in [
+            "transitions.q0,x[0] references state 'nowhere' not in states",
+            "transitions.q0,x references symbol 'x' not in tape_alphabet",
+        ] {
+            assert!(streamed.contains(problem), "{}", streamed);
+            assert!(regular.contains(problem), "{}", regular);
+        }
+
+        definition.states.clear();
+        let text = serde_json::to_string(&definition).unwrap();
+        assert!(matches!(stream(&text), Err(StreamingError::Diagnostics(_))));
+    }
+}
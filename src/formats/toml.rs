@@ -0,0 +1,174 @@
+//! TOML machine format
+//!
+//! Transitions are grouped into one table per state, keyed by the symbol
+//! read:
+//!
+//! ```toml
+//! [transitions.q0]
+//! "0" = ["q0", "0", "R"]
+//! "_" = ["accept", "_", "R"]
+//! ```
+
+use crate::executor::{self, RunDefaults};
+use crate::formats::OutputJson;
+use crate::info::Metadata;
+use crate::{InputNormalization, LeftEdge, MachineJson};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// TOML layout of a machine definition
+#[derive(Debug, Serialize, Deserialize)]
+struct MachineToml {
+    states: Vec<String>,
+    alphabet: Vec<String>,
+    tape_alphabet: Vec<String>,
+    initial_state: String,
+    accept_states: Vec<String>,
+    reject_states: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blank_symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    read_only_input: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    accept_on_halt: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    left_edge: Option<LeftEdge>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tape_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    initial_tape: Option<String>,
+    #[serde(default, skip_serializing_if = "super::json::is_zero")]
+    input_start: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    head_start: Option<usize>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "executor::deserialize_max_steps",
+        serialize_with = "executor::serialize_max_steps"
+    )]
+    default_max_steps: Option<usize>,
+    // Tables must come after plain values in TOML; `output` is a table for
+    // markers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<OutputJson>,
+    #[serde(default, skip_serializing_if = "RunDefaults::is_empty")]
+    defaults: RunDefaults,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    metadata: Metadata,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    symbol_aliases: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    input_map: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "InputNormalization::is_none")]
+    normalize_input: InputNormalization,
+    #[serde(default)]
+    transitions: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+/// Parse a TOML machine definition into the JSON schema
+pub fn from_toml(text: &str) -> Result<MachineJson, String> {
+    let machine: MachineToml = ::toml::from_str(text).map_err(|e| e.to_string())?;
+
+    let mut transitions = BTreeMap::new();
+    for (state, by_symbol) in machine.transitions {
+        for (symbol, value) in by_symbol {
+            transitions.insert(format!("{},{}", state, symbol), value);
+        }
+    }
+
+    Ok(MachineJson {
+        states: machine.states,
+        alphabet: machine.alphabet,
+        tape_alphabet: machine.tape_alphabet,
+        initial_state: machine.initial_state,
+        accept_states: machine.accept_states,
+        reject_states: machine.reject_states,
+        blank_symbol: machine.blank_symbol,
+        read_only_input: machine.read_only_input,
+        accept_on_halt: machine.accept_on_halt,
+        symbol_aliases: machine.symbol_aliases,
+        input_map: machine.input_map,
+        normalize_input: machine.normalize_input,
+        left_edge: machine.left_edge,
+        tape_length: machine.tape_length,
+        initial_tape: machine.initial_tape,
+        input_start: machine.input_start,
+        head_start: machine.head_start,
+        output: machine.output,
+        default_max_steps: machine.default_max_steps,
+        defaults: machine.defaults,
+        metadata: machine.metadata,
+        transitions,
+    })
+}
+
+/// Render a JSON-schema machine definition as TOML
+pub fn to_toml(definition: &MachineJson) -> Result<String, String> {
+    let mut transitions: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for (key, value) in &definition.transitions {
+        let Some((state, symbol)) = key.split_once(',') else {
+            return Err(format!("Invalid transition key: {}", key));
+        };
+        transitions
+            .entry(state.to_string())
+            .or_default()
+            .insert(symbol.to_string(), value.clone());
+    }
+
+    let machine = MachineToml {
+        states: definition.states.clone(),
+        alphabet: definition.alphabet.clone(),
+        tape_alphabet: definition.tape_alphabet.clone(),
+        initial_state: definition.initial_state.clone(),
+        accept_states: definition.accept_states.clone(),
+        reject_states: definition.reject_states.clone(),
+        blank_symbol: definition.blank_symbol.clone(),
+        read_only_input: definition.read_only_input,
+        accept_on_halt: definition.accept_on_halt,
+        symbol_aliases: definition.symbol_aliases.clone(),
+        input_map: definition.input_map.clone(),
+        normalize_input: definition.normalize_input,
+        left_edge: definition.left_edge,
+        tape_length: definition.tape_length,
+        initial_tape: definition.initial_tape.clone(),
+        input_start: definition.input_start,
+        head_start: definition.head_start,
+        output: definition.output.clone(),
+        default_max_steps: definition.default_max_steps,
+        defaults: definition.defaults,
+        metadata: definition.metadata.clone(),
+        transitions,
+    };
+    ::toml::to_string(&machine).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixtures;
+
+    #[test]
+    fn round_trips_every_field() {
+        for definition in fixtures::definitions() {
+            let text = to_toml(&definition).unwrap();
+            assert_eq!(from_toml(&text).unwrap(), definition, "{}", text);
+        }
+    }
+
+    #[test]
+    fn groups_transitions_by_state() {
+        let text = to_toml(&fixtures::definitions()[0]).unwrap();
+        assert!(text.contains("[transitions.q0]"), "{}", text);
+        assert!(text.contains(r#"_ = ["accept", "_", "R"]"#), "{}", text);
+    }
+
+    #[test]
+    fn rejects_transition_keys_without_a_symbol() {
+        let mut definition = fixtures::definitions().remove(0);
+        definition
+            .transitions
+            .insert("q0".to_string(), vec!["q0".into(), "0".into(), "R".into()]);
+        assert!(to_toml(&definition).unwrap_err().contains("q0"));
+    }
+}
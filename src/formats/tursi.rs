@@ -0,0 +1,202 @@
+//! The tursi simulator's machine format
+//!
+//! Programs for the tursi simulator, found in older course material, are
+//! rules in the order of Morphett's format, headed by directives:
+//!
+//! ```text
+//! # Busy beaver with two states
+//! #! start A
+//! #! end H
+//! #! fill 0
+//! A 0 1 r B
+//! A 1 1 l B
+//! B 0 1 l A
+//! B 1 1 n H
+//! ```
+//!
+//! that is `state symbol new_symbol direction new_state`, with the
+//! directions `l`, `r` and `n` for no move. The directives are `start`
+//! for the initial state, `end` for the final states, which accept,
+//! `states` to declare states and `fill` for the blank, written `#!name`,
+//! `#! name` or `#name`; other lines starting with `#` are comments, and
+//! the comments at the top become the machine's docs. A `*` matches any
+//! symbol when read and keeps the symbol when written, as in Morphett's
+//! format.
+//!
+//! Without a `start` directive the machine starts in the first declared
+//! state, or in the first state of the first rule. The blank is `_`
+//! unless `fill` sets another. Files are read only.
+
+use super::morphett::{resolve, Rule, WILDCARD};
+use crate::MachineJson;
+use serde_json::json;
+use std::collections::BTreeSet;
+
+/// Names of the directives
+const DIRECTIVES: [&str; 4] = ["start", "end", "states", "fill"];
+
+/// Whether a program is in tursi's format rather than Morphett's, which
+/// has no directives
+pub fn is_tursi(text: &str) -> bool {
+    text.lines().any(|line| directive(line).is_some())
+}
+
+/// The name and arguments of a directive line
+fn directive(line: &str) -> Option<(&str, Vec<&str>)> {
+    let rest = line.trim().strip_prefix('#')?;
+    let rest = match rest.strip_prefix('!') {
+        Some(rest) => rest.trim_start(),
+        None => rest,
+    };
+    let mut words = rest.split_whitespace();
+    let name = words.next().filter(|name| DIRECTIVES.contains(name))?;
+    if !rest.starts_with(name) {
+        return None;
+    }
+    Some((name, words.collect()))
+}
+
+/// Decode a tursi program into the JSON schema
+pub fn from_tursi(text: &str) -> Result<MachineJson, String> {
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut states: Vec<String> = Vec::new();
+    let mut start: Option<&str> = None;
+    let mut end: Vec<&str> = Vec::new();
+    let mut blank = "_";
+    let mut read_symbols: Vec<&str> = Vec::new();
+    let mut docs: Vec<&str> = Vec::new();
+    let mut in_header = true;
+
+    for (row, line) in text.lines().enumerate() {
+        let code = line.trim();
+        if let Some((name, arguments)) = directive(line) {
+            match (name, &arguments[..]) {
+                ("start", [state]) => {
+                    start = Some(state);
+                    add_state(&mut states, row, state)?;
+                }
+                ("fill", [symbol]) if symbol.chars().count() == 1 && *symbol != "," => {
+                    blank = symbol;
+                }
+                ("end" | "states", names) if !names.is_empty() => {
+                    for state in names {
+                        add_state(&mut states, row, state)?;
+                    }
+                    if name == "end" {
+                        end.extend(names);
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "line {}: expected `#! start state`, `#! end state...`, `#! states state...` or `#! fill symbol`",
+                        row + 1
+                    ))
+                }
+            }
+            continue;
+        }
+        if let Some(comment) = code.strip_prefix('#') {
+            if in_header {
+                docs.push(comment.trim());
+            }
+            continue;
+        }
+        if code.is_empty() {
+            if in_header && !docs.is_empty() {
+                in_header = false;
+            }
+            continue;
+        }
+        in_header = false;
+
+        let [state, read, write, direction, next] = code.split_whitespace().collect::<Vec<_>>()[..]
+        else {
+            return Err(format!(
+                "line {}: expected `state symbol new_symbol direction new_state`",
+                row + 1
+            ));
+        };
+        for symbol in [read, write] {
+            if symbol.chars().count() != 1 || symbol == "," {
+                return Err(format!(
+                    "line {}: '{}' is not a symbol; symbols are single characters other than ','",
+                    row + 1,
+                    symbol
+                ));
+            }
+            if symbol != WILDCARD {
+                read_symbols.push(symbol);
+            }
+        }
+        let direction = match direction {
+            "l" | "L" => "L",
+            "r" | "R" => "R",
+            "n" | "N" => "S",
+            other => {
+                return Err(format!(
+                    "line {}: unknown direction '{}'; use l, r or n",
+                    row + 1,
+                    other
+                ))
+            }
+        };
+        if state == WILDCARD || next == WILDCARD {
+            return Err(format!(
+                "line {}: states cannot be '*'; only symbols match anything",
+                row + 1
+            ));
+        }
+        add_state(&mut states, row, state)?;
+        add_state(&mut states, row, next)?;
+        rules.push(Rule {
+            state,
+            read,
+            write,
+            direction,
+            next,
+        });
+    }
+    if rules.is_empty() {
+        return Err("the program has no rules".to_string());
+    }
+
+    let initial_state = start
+        .map(str::to_string)
+        .or_else(|| states.first().cloned())
+        .ok_or("no state is named")?;
+    let mut symbols: BTreeSet<String> = read_symbols.iter().map(|s| s.to_string()).collect();
+    symbols.insert(blank.to_string());
+    let transitions = resolve(&rules, &states, &symbols, |state| end.contains(&state));
+
+    let mut definition = json!({
+        "states": states,
+        "alphabet": symbols.iter().filter(|s| *s != blank).collect::<Vec<_>>(),
+        "tape_alphabet": symbols,
+        "initial_state": initial_state,
+        "accept_states": end,
+        "reject_states": [],
+        "blank_symbol": blank,
+        "transitions": transitions,
+    });
+    let docs = docs.join("\n");
+    if !docs.trim().is_empty() {
+        definition["metadata"] = json!({ "docs": docs.trim() });
+    }
+    serde_json::from_value(definition).map_err(|e| e.to_string())
+}
+
+/// Declare a state, if it is new
+fn add_state(states: &mut Vec<String>, row: usize, name: &str) -> Result<(), String> {
+    if states.iter().any(|s| s == name) {
+        return Ok(());
+    }
+    if name.contains(',') {
+        return Err(format!(
+            "line {}: state '{}' contains a comma",
+            row + 1,
+            name
+        ));
+    }
+    states.push(name.to_string());
+    Ok(())
+}
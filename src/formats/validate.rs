@@ -0,0 +1,310 @@
+//! Validation of machine definitions with precise error locations
+//!
+//! Definitions are checked in two passes: against the JSON Schema shipped in
+//! `schema/machine.schema.json`, and then for cross-references the schema
+//! cannot express, such as transitions using states or symbols that were
+//! never declared. Every problem is reported with the key path it concerns
+//! and, for JSON text, the line and column of that key.
+
+use super::json::{resolve_aliases, ANY_SYMBOL, SAME_SYMBOL};
+use super::MachineJson;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// JSON Schema for the machine definition format
+pub const MACHINE_SCHEMA: &str = include_str!("../../schema/machine.schema.json");
+
+/// A single problem found in a machine definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Key path of the offending value, e.g. `transitions.q0,x`
+    pub path: Vec<PathSegment>,
+    pub message: String,
+    /// One-based line and column, when the source text is known
+    pub location: Option<(usize, usize)>,
+}
+
+/// A step in a key path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl Diagnostic {
+    fn new(path: Vec<PathSegment>, message: String) -> Self {
+        Diagnostic {
+            path,
+            message,
+            location: None,
+        }
+    }
+
+    /// The key path in dotted notation
+    pub fn path_string(&self) -> String {
+        let mut text = String::new();
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) if text.is_empty() => text.push_str(key),
+                PathSegment::Key(key) => {
+                    text.push('.');
+                    text.push_str(key);
+                }
+                PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+            }
+        }
+        if text.is_empty() {
+            text.push_str("machine");
+        }
+        text
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((line, column)) = self.location {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        write!(f, "{} {}", self.path_string(), self.message)
+    }
+}
+
+/// Shorthand for building key paths
+fn key(name: &str) -> PathSegment {
+    PathSegment::Key(name.to_string())
+}
+
+/// Check a parsed JSON value against the machine schema
+pub fn check_schema(value: &Value) -> Vec<Diagnostic> {
+    static VALIDATOR: OnceLock<jsonschema::Validator> = OnceLock::new();
+    let validator = VALIDATOR.get_or_init(|| {
+        let schema: Value = serde_json::from_str(MACHINE_SCHEMA).expect("bundled schema is JSON");
+        jsonschema::validator_for(&schema).expect("bundled schema is valid")
+    });
+
+    validator
+        .iter_errors(value)
+        .map(|error| {
+            let path = error
+                .instance_path()
+                .as_str()
+                .split('/')
+                .skip(1)
+                .map(|segment| {
+                    let segment = segment.replace("~1", "/").replace("~0", "~");
+                    match segment.parse() {
+                        Ok(index) => PathSegment::Index(index),
+                        Err(_) => PathSegment::Key(segment),
+                    }
+                })
+                .collect();
+            Diagnostic::new(path, format!("is invalid: {}", error))
+        })
+        .collect()
+}
+
+/// Check that everything a definition refers to has been declared
+pub fn check_definition(definition: &MachineJson) -> Vec<Diagnostic> {
+    check_definition_with(
+        definition,
+        definition
+            .transitions
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    )
+}
+
+/// Check a definition as [`check_definition`] does, taking its transitions
+/// from `transitions` rather than from the definition
+pub fn check_definition_with(
+    definition: &MachineJson,
+    transitions: impl Iterator<Item = (String, Vec<String>)> + Clone,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let states: HashSet<&str> = definition.states.iter().map(String::as_str).collect();
+    let tape_alphabet: HashSet<&str> = definition
+        .tape_alphabet
+        .iter()
+        .map(String::as_str)
+        .collect();
+
+    let mut check_state = |path: Vec<PathSegment>, state: &str| {
+        if !states.contains(state) {
+            diagnostics.push(Diagnostic::new(
+                path,
+                format!("references state '{}' not in states", state),
+            ));
+        }
+    };
+    check_state(vec![key("initial_state")], &definition.initial_state);
+    for (i, state) in definition.accept_states.iter().enumerate() {
+        check_state(vec![key("accept_states"), PathSegment::Index(i)], state);
+    }
+    for (i, state) in definition.reject_states.iter().enumerate() {
+        check_state(vec![key("reject_states"), PathSegment::Index(i)], state);
+    }
+    for (transition, value) in transitions.clone() {
+        if let Some((state, _)) = transition.split_once(',') {
+            check_state(vec![key("transitions"), key(&transition)], state);
+        }
+        if let Some(new_state) = value.first() {
+            check_state(
+                vec![key("transitions"), key(&transition), PathSegment::Index(0)],
+                new_state,
+            );
+        }
+    }
+
+    let mut check_symbol = |path: Vec<PathSegment>, symbol: &str| {
+        if !tape_alphabet.contains(symbol) {
+            diagnostics.push(Diagnostic::new(
+                path,
+                format!("references symbol '{}' not in tape_alphabet", symbol),
+            ));
+        }
+    };
+    for (i, symbol) in definition.alphabet.iter().enumerate() {
+        check_symbol(vec![key("alphabet"), PathSegment::Index(i)], symbol);
+    }
+    check_symbol(
+        vec![key("blank_symbol")],
+        definition.blank_symbol.as_deref().unwrap_or("_"),
+    );
+    let aliases = &definition.symbol_aliases;
+    for (alias, symbol) in aliases {
+        check_symbol(vec![key("symbol_aliases"), key(alias)], symbol);
+    }
+    // `*` and `=` are wildcards unless they are tape symbols
+    let any = ANY_SYMBOL.to_string();
+    let same = SAME_SYMBOL.to_string();
+    let mut resolved_keys: HashMap<String, String> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (transition, value) in transitions {
+        let (resolved, value) = resolve_aliases(aliases, &transition, &value);
+        if let Some((_, symbol)) = resolved.split_once(',') {
+            if symbol != any {
+                check_symbol(vec![key("transitions"), key(&transition)], symbol);
+            }
+        }
+        if let Some(write_symbol) = value.get(1).filter(|symbol| **symbol != same) {
+            check_symbol(
+                vec![key("transitions"), key(&transition), PathSegment::Index(1)],
+                write_symbol,
+            );
+        }
+        if let Some(other) = resolved_keys.insert(resolved, transition.clone()) {
+            duplicates.push(Diagnostic::new(
+                vec![key("transitions"), key(&transition)],
+                format!(
+                    "is the same transition as '{}' once aliases are resolved",
+                    other
+                ),
+            ));
+        }
+    }
+    diagnostics.extend(duplicates);
+    for alias in aliases.keys() {
+        if tape_alphabet.contains(alias.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                vec![key("symbol_aliases"), key(alias)],
+                "is also a tape symbol".to_string(),
+            ));
+        }
+    }
+
+    for (from, to) in &definition.input_map {
+        if !definition.alphabet.contains(to) {
+            diagnostics.push(Diagnostic::new(
+                vec![key("input_map"), key(from)],
+                format!("maps to symbol '{}' not in alphabet", to),
+            ));
+        }
+    }
+
+    if definition.tape_length.is_some() && definition.left_edge.is_some() {
+        diagnostics.push(Diagnostic::new(
+            vec![key("left_edge")],
+            "cannot be combined with tape_length, since a circular tape has no edge".to_string(),
+        ));
+    }
+
+    for (i, state) in definition.reject_states.iter().enumerate() {
+        if definition.accept_states.contains(state) {
+            diagnostics.push(Diagnostic::new(
+                vec![key("reject_states"), PathSegment::Index(i)],
+                format!("state '{}' is also an accept state", state),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Fill in line and column of each diagnostic from the JSON source text
+pub fn locate(text: &str, diagnostics: &mut [Diagnostic]) {
+    for diagnostic in diagnostics {
+        let mut offset = 0;
+        let mut found = None;
+        for segment in &diagnostic.path {
+            let PathSegment::Key(name) = segment else {
+                break;
+            };
+            match find_key(text, offset, name) {
+                Some(position) => {
+                    offset = position;
+                    found = Some(position);
+                }
+                None => break,
+            }
+        }
+        diagnostic.location = found.map(|position| line_column(text, position));
+    }
+}
+
+/// Find an object key (quoted or bare) at or after `from`
+fn find_key(text: &str, from: usize, name: &str) -> Option<usize> {
+    let candidates = [
+        format!("\"{}\"", name),
+        format!("'{}'", name),
+        name.to_string(),
+    ];
+    candidates
+        .iter()
+        .filter_map(|needle| {
+            text[from..]
+                .match_indices(needle.as_str())
+                .find_map(|(i, _)| {
+                    let start = from + i;
+                    let rest = text[start + needle.len()..].trim_start();
+                    rest.starts_with(':').then_some(start)
+                })
+        })
+        .min()
+}
+
+/// Convert a byte offset into a one-based line and column
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before.chars().count(), |i| before[i + 1..].chars().count())
+        + 1;
+    (line, column)
+}
+
+/// Order diagnostics by location, then by key path
+pub fn sort(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by_cached_key(|diagnostic| (diagnostic.location, diagnostic.path_string()));
+}
+
+/// Render diagnostics one per line
+pub fn report(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,267 @@
+//! Excel spreadsheet machine format
+//!
+//! The `transitions` sheet is the transition table as a state × symbol
+//! grid: the header row lists the symbols read, the first column the
+//! states, and each cell holds `new_state, write_symbol, direction`. Empty
+//! cells are undefined transitions.
+//!
+//! The `machine` sheet lists the other fields of the definition as name and
+//! value rows. Lists such as `accept_states` are written comma-separated;
+//! values that are neither lists nor text, such as `normalize_input`, are
+//! written as JSON. When `states` or `tape_alphabet` is missing, the states
+//! of the grid's first column or the symbols of its header row are used.
+//!
+//! Written spreadsheets keep the conventions of hand-made tables: bold
+//! headers frozen in place, and the accept and reject states shaded green
+//! and red. Formatting is ignored when reading.
+
+use crate::MachineJson;
+use calamine::{open_workbook_from_rs, Data, Range, Reader, Xlsx, XlsxError};
+use rust_xlsxwriter::{Color, Format, Workbook, Worksheet};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+/// Name of the sheet holding the transition grid
+const TRANSITIONS_SHEET: &str = "transitions";
+/// Name of the sheet holding the other fields
+const MACHINE_SHEET: &str = "machine";
+
+/// Fields written as comma-separated lists
+const LIST_FIELDS: &[&str] = &[
+    "states",
+    "alphabet",
+    "tape_alphabet",
+    "accept_states",
+    "reject_states",
+];
+/// Fields always read as text, even if they look like JSON
+const TEXT_FIELDS: &[&str] = &["initial_state", "blank_symbol", "initial_tape", "left_edge"];
+
+/// Shading of accept and reject states
+const ACCEPT_COLOR: u32 = 0xC6EFCE;
+const REJECT_COLOR: u32 = 0xFFC7CE;
+
+/// Decode a spreadsheet into the JSON schema
+pub fn from_xlsx(bytes: &[u8]) -> Result<MachineJson, String> {
+    let mut workbook = open_workbook_from_rs::<Xlsx<_>, _>(Cursor::new(bytes))
+        .map_err(|e: XlsxError| e.to_string())?;
+    let sheet = |workbook: &mut Xlsx<_>, name: &str| -> Result<Option<Range<Data>>, String> {
+        if !workbook.sheet_names().iter().any(|sheet| sheet == name) {
+            return Ok(None);
+        }
+        workbook
+            .worksheet_range(name)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    };
+    let grid = sheet(&mut workbook, TRANSITIONS_SHEET)?
+        .ok_or_else(|| format!("no sheet named {}", TRANSITIONS_SHEET))?;
+    let fields = sheet(&mut workbook, MACHINE_SHEET)?
+        .ok_or_else(|| format!("no sheet named {}", MACHINE_SHEET))?;
+
+    let grid = read_grid(&grid)?;
+    let mut definition = Map::new();
+    for (i, row) in fields.rows().enumerate() {
+        let name = text(row.first());
+        let value = text(row.get(1));
+        if name.is_empty() || (i == 0 && name == "field") {
+            continue;
+        }
+        definition.insert(name.clone(), field_value(&name, &value));
+    }
+    let list = |items: Vec<String>| Value::from(items);
+    definition
+        .entry("states")
+        .or_insert_with(|| list(grid.states));
+    definition
+        .entry("tape_alphabet")
+        .or_insert_with(|| list(grid.symbols));
+    definition.insert(
+        "transitions".to_string(),
+        serde_json::to_value(grid.transitions).map_err(|e| e.to_string())?,
+    );
+    serde_json::from_value(Value::Object(definition))
+        .map_err(|e| format!("{} sheet: {}", MACHINE_SHEET, e))
+}
+
+/// The contents of the `transitions` sheet
+struct Grid {
+    /// States of the first column, in row order
+    states: Vec<String>,
+    /// Symbols of the header row, in column order
+    symbols: Vec<String>,
+    /// Transitions in definition form
+    transitions: BTreeMap<String, Vec<String>>,
+}
+
+/// Read the transition grid
+fn read_grid(grid: &Range<Data>) -> Result<Grid, String> {
+    let (top, left) = grid.start().unwrap_or_default();
+    let mut rows = grid.rows().enumerate();
+    let symbols: Vec<String> = match rows.next() {
+        Some((_, header)) => header.iter().skip(1).map(|cell| text(Some(cell))).collect(),
+        None => Vec::new(),
+    };
+
+    let mut states = Vec::new();
+    let mut transitions = BTreeMap::new();
+    for (i, row) in rows {
+        let state = text(row.first());
+        if state.is_empty() {
+            continue;
+        }
+        for (j, cell) in row.iter().enumerate().skip(1) {
+            let value = text(Some(cell));
+            if value.is_empty() {
+                continue;
+            }
+            let cell_name = cell_name(top + i as u32, left + j as u32);
+            let symbol = symbols
+                .get(j - 1)
+                .filter(|symbol| !symbol.is_empty())
+                .ok_or_else(|| format!("{}: no symbol in the header row", cell_name))?;
+            let target: Vec<String> = value
+                .split(',')
+                .map(|part| part.trim().to_string())
+                .collect();
+            if target.len() != 3 || target.iter().any(String::is_empty) {
+                return Err(format!(
+                    "{}: expected \"new_state, write_symbol, direction\", found \"{}\"",
+                    cell_name, value
+                ));
+            }
+            transitions.insert(format!("{},{}", state, symbol), target);
+        }
+        states.push(state);
+    }
+    Ok(Grid {
+        states,
+        symbols: symbols.into_iter().filter(|s| !s.is_empty()).collect(),
+        transitions,
+    })
+}
+
+/// The text of a cell, trimmed; numbers are written without a fraction when
+/// they have none
+fn text(cell: Option<&Data>) -> String {
+    cell.map(|cell| cell.to_string().trim().to_string())
+        .unwrap_or_default()
+}
+
+/// A field of the `machine` sheet as a JSON value
+fn field_value(name: &str, value: &str) -> Value {
+    if LIST_FIELDS.contains(&name) {
+        Value::from(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>(),
+        )
+    } else if TEXT_FIELDS.contains(&name) {
+        Value::from(value)
+    } else {
+        serde_json::from_str(value).unwrap_or_else(|_| Value::from(value))
+    }
+}
+
+/// Spreadsheet name of a cell, such as `transitions!B3`
+fn cell_name(row: u32, col: u32) -> String {
+    let mut letters = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        n -= 1;
+        letters.push(char::from(b'A' + (n % 26) as u8));
+        n /= 26;
+    }
+    let column: String = letters.iter().rev().collect();
+    format!("{}!{}{}", TRANSITIONS_SHEET, column, row + 1)
+}
+
+/// Encode a definition as a spreadsheet
+pub fn to_xlsx(definition: &MachineJson) -> Result<Vec<u8>, String> {
+    let mut workbook = Workbook::new();
+    write_grid(workbook.add_worksheet(), definition).map_err(|e| e.to_string())?;
+    write_fields(workbook.add_worksheet(), definition)?;
+    workbook.save_to_buffer().map_err(|e| e.to_string())
+}
+
+/// Write the transition grid, with a column for every tape symbol and a row
+/// for every state, even where no transition is defined
+fn write_grid(
+    sheet: &mut Worksheet,
+    definition: &MachineJson,
+) -> Result<(), rust_xlsxwriter::XlsxError> {
+    // Read symbols outside the tape alphabet, such as the `*` wildcard, get
+    // columns of their own after it
+    let mut symbols = definition.tape_alphabet.clone();
+    let mut states = definition.states.clone();
+    for key in definition.transitions.keys() {
+        if let Some((state, symbol)) = key.split_once(',') {
+            if !symbols.iter().any(|s| s == symbol) {
+                symbols.push(symbol.to_string());
+            }
+            if !states.iter().any(|s| s == state) {
+                states.push(state.to_string());
+            }
+        }
+    }
+
+    let header = Format::new().set_bold().set_background_color(Color::Gray);
+    let bold = Format::new().set_bold();
+    sheet.set_name(TRANSITIONS_SHEET)?;
+    sheet.write_string_with_format(0, 0, "state", &header)?;
+    for (col, symbol) in (1..).zip(&symbols) {
+        sheet.write_string_with_format(0, col, symbol, &header)?;
+    }
+    for (row, state) in (1..).zip(&states) {
+        let format = if definition.accept_states.contains(state) {
+            bold.clone().set_background_color(Color::RGB(ACCEPT_COLOR))
+        } else if definition.reject_states.contains(state) {
+            bold.clone().set_background_color(Color::RGB(REJECT_COLOR))
+        } else {
+            bold.clone()
+        };
+        sheet.write_string_with_format(row, 0, state, &format)?;
+        for (col, symbol) in (1..).zip(&symbols) {
+            if let Some(target) = definition.transitions.get(&format!("{},{}", state, symbol)) {
+                sheet.write_string(row, col, target.join(", "))?;
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 1)?;
+    sheet.autofit();
+    Ok(())
+}
+
+/// Write every field but the transitions as name and value rows
+fn write_fields(sheet: &mut Worksheet, definition: &MachineJson) -> Result<(), String> {
+    let Value::Object(fields) = serde_json::to_value(definition).map_err(|e| e.to_string())? else {
+        return Err("a definition is not a JSON object".to_string());
+    };
+    let header = Format::new().set_bold().set_background_color(Color::Gray);
+    let mut write = || -> Result<(), rust_xlsxwriter::XlsxError> {
+        sheet.set_name(MACHINE_SHEET)?;
+        sheet.write_string_with_format(0, 0, "field", &header)?;
+        sheet.write_string_with_format(0, 1, "value", &header)?;
+        let rows = fields.iter().filter(|(name, _)| *name != "transitions");
+        for (row, (name, value)) in (1..).zip(rows) {
+            let text = match value {
+                Value::String(text) => text.clone(),
+                Value::Array(items) if LIST_FIELDS.contains(&name.as_str()) => items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                value => value.to_string(),
+            };
+            sheet.write_string(row, 0, name)?;
+            sheet.write_string(row, 1, text)?;
+        }
+        sheet.set_freeze_panes(1, 0)?;
+        sheet.autofit();
+        Ok(())
+    };
+    write().map_err(|e| e.to_string())
+}
@@ -0,0 +1,79 @@
+//! YAML machine format
+//!
+//! The same schema as JSON, written as YAML:
+//!
+//! ```yaml
+//! # Accepts binary strings with an even number of 1s
+//! states: [even, odd, accept]
+//! alphabet: [0, 1]
+//! transitions:
+//!   even,0: [even, 0, R]
+//!   even,_: [accept, _, R]
+//! ```
+//!
+//! Files are read with `serde_yaml` straight into [`MachineJson`], so each
+//! scalar takes the type of the field it fills: `alphabet: [0, 1]` declares
+//! the symbols "0" and "1", while `tape_length: 64` is a number.
+
+use super::json::check_definition_value;
+use crate::MachineJson;
+
+/// Parse a YAML machine definition, validated like a JSON one
+pub fn from_yaml(text: &str) -> Result<MachineJson, String> {
+    let definition: MachineJson = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(definition).map_err(|e| e.to_string())?;
+    check_definition_value(text, value)
+}
+
+/// Render a machine definition as YAML
+pub fn to_yaml(definition: &MachineJson) -> Result<String, String> {
+    serde_yaml::to_string(definition).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{fixtures, parse_machine_json};
+
+    #[test]
+    fn round_trips_every_field() {
+        for definition in fixtures::definitions() {
+            let text = to_yaml(&definition).unwrap();
+            assert_eq!(from_yaml(&text).unwrap(), definition, "{}", text);
+        }
+    }
+
+    #[test]
+    fn loads_the_machine_read_from_json() {
+        for definition in fixtures::definitions() {
+            let loaded = from_yaml(&to_yaml(&definition).unwrap()).unwrap();
+            fixtures::assert_same_runs(
+                &parse_machine_json(&loaded).unwrap(),
+                &parse_machine_json(&definition).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn types_scalars_by_field() {
+        let text = include_str!("../../examples/divisible_by_three.yaml");
+        let definition = from_yaml(text).unwrap();
+        assert_eq!(definition.alphabet, ["0", "1"]);
+        assert_eq!(definition.transitions["r0,1"], ["r1", "1", "R"]);
+        assert_eq!(definition.metadata.tests[0].accepts, Some(true));
+
+        let sized = format!("{}tape_length: 64\naccept_on_halt: true\n", text);
+        let definition = from_yaml(&sized).unwrap();
+        assert_eq!(definition.tape_length, Some(64));
+        assert!(definition.accept_on_halt);
+    }
+
+    #[test]
+    fn reports_invalid_files() {
+        assert!(from_yaml("states: [a\n").is_err());
+        assert!(from_yaml("states: {a: b}\n").is_err());
+        let text = include_str!("../../examples/divisible_by_three.yaml");
+        let unknown = text.replace("initial_state: r0", "initial_state: r9");
+        assert!(from_yaml(&unknown).unwrap_err().contains("r9"));
+    }
+}
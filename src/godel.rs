@@ -0,0 +1,427 @@
+//! Gödel numbering of machines
+//!
+//! [`encode`] writes a machine as a bitstring in the style of the textbook
+//! encodings, from which [`decode`] rebuilds it. States and tape symbols
+//! are numbered from 1 in the order a breadth-first search from the
+//! initial state meets them, following each state's transitions in order
+//! of the symbol read: the initial state is 1, the blank is 1, the input
+//! symbols come next and then the other tape symbols. A number `k` is
+//! written as `k` zeros, and the directions `L`, `R` and `S` as 1, 2 and 3.
+//! The code is
+//!
+//! ```text
+//! 0^states 1 0^symbols 1 0^last 11 accept 11 reject 11 t 11 t ...
+//! ```
+//!
+//! where the input symbols are numbered 2 to `last`, `accept` and `reject`
+//! are the numbers of those states separated by single ones, and each
+//! transition `t` from state `q` reading `x` to state `p` writing `y` and
+//! moving `d` is `0^q 1 0^x 1 0^p 1 0^y 1 0^d`, in order of `q` and `x`.
+//!
+//! Names only break ties the search leaves open: between symbols a state
+//! reads that were not met before, and between states no run can enter,
+//! which are searched from after the others. Renaming the states of a
+//! machine therefore keeps its code as long as the states it cannot enter
+//! have no transitions, and so does renaming symbols that are never first
+//! met side by side. Decoding gives states `q0`, `q1`, ... and symbols
+//! `_`, `0`, `1`, ... for the input and `A`, `B`, ... for the rest, and
+//! encoding the decoded machine gives the code back.
+//!
+//! The code with a `1` in front is the binary numeral of the machine's
+//! Gödel number, which [`to_decimal`] and [`from_decimal`] convert.
+
+use crate::{Direction, SymbolTable, TuringMachine};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Names of the input symbols of a decoded machine, in order
+const INPUT_NAMES: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Names of the other tape symbols of a decoded machine, in order
+const TAPE_NAMES: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Decimal digits held by a limb of a decimal number
+const LIMB: u64 = 1_000_000_000;
+
+/// Bits moved at a time between binary and decimal numbers
+const CHUNK: u32 = 29;
+
+/// The machine's code, a string of `0` and `1`
+///
+/// Settings outside the transition table, such as a read-only input or an
+/// output, have no place in the code and are an error; run defaults and
+/// metadata are left out.
+pub fn encode(machine: &TuringMachine) -> Result<String, String> {
+    let unsupported = [
+        (machine.read_only_input, "read_only_input"),
+        (machine.accept_on_halt, "accept_on_halt"),
+        (!machine.input_map.is_empty(), "input_map"),
+        (!machine.input_normalization.is_none(), "normalize_input"),
+        (machine.left_edge.is_some(), "left_edge"),
+        (machine.tape_length.is_some(), "tape_length"),
+        (!machine.tape_setup.is_default(), "tape"),
+        (machine.output.is_some(), "output"),
+    ];
+    if let Some((_, field)) = unsupported.iter().find(|(set, _)| *set) {
+        return Err(format!("{} cannot be encoded", field));
+    }
+
+    let Numbering {
+        states,
+        symbols,
+        last_input,
+    } = Numbering::search(machine);
+
+    let numbers = |halting: &HashSet<String>| {
+        let mut numbers: Vec<usize> = halting.iter().map(|s| states[s.as_str()]).collect();
+        numbers.sort_unstable();
+        numbers
+            .iter()
+            .map(|&k| unary(k))
+            .collect::<Vec<_>>()
+            .join("1")
+    };
+    let mut parts = vec![
+        [states.len(), symbols.len(), last_input]
+            .map(unary)
+            .join("1"),
+        numbers(&machine.accept_states),
+        numbers(&machine.reject_states),
+    ];
+    let mut transitions: Vec<[usize; 5]> = machine
+        .transitions
+        .iter()
+        .map(|((state, read), (next, write, direction))| {
+            let direction = match direction {
+                Direction::L => 1,
+                Direction::R => 2,
+                Direction::S => 3,
+            };
+            [
+                states[state.as_str()],
+                symbols[read],
+                states[next.as_str()],
+                symbols[write],
+                direction,
+            ]
+        })
+        .collect();
+    transitions.sort_unstable();
+    parts.extend(
+        transitions
+            .iter()
+            .map(|transition| transition.map(unary).join("1")),
+    );
+    Ok(parts.join("11"))
+}
+
+/// Numbers of the states and tape symbols of a machine in its code
+struct Numbering<'a> {
+    states: HashMap<&'a str, usize>,
+    symbols: HashMap<char, usize>,
+    /// Number of the last input symbol
+    last_input: usize,
+}
+
+impl<'a> Numbering<'a> {
+    /// Number states and symbols in the order a breadth-first search from
+    /// the initial state meets them
+    fn search(machine: &'a TuringMachine) -> Self {
+        let blank = machine.blank_symbol;
+        let is_input = |symbol: char| symbol != blank && machine.alphabet.contains(&symbol);
+        let last_input = 1 + machine.alphabet.iter().filter(|&&s| s != blank).count();
+        let mut numbering = Numbering {
+            states: HashMap::new(),
+            symbols: HashMap::from([(blank, 1)]),
+            last_input,
+        };
+        let (mut next_input, mut next_other) = (2, last_input + 1);
+        let mut number_symbol = |symbols: &mut HashMap<char, usize>, symbol: char| {
+            symbols.entry(symbol).or_insert_with(|| {
+                let counter = if is_input(symbol) {
+                    &mut next_input
+                } else {
+                    &mut next_other
+                };
+                *counter += 1;
+                *counter - 1
+            });
+        };
+
+        let mut outgoing: HashMap<&str, Vec<(char, &str, char)>> = HashMap::new();
+        for ((state, read), (next, write, _)) in &machine.transitions {
+            outgoing
+                .entry(state.as_str())
+                .or_default()
+                .push((*read, next.as_str(), *write));
+        }
+        let mut unreached: Vec<&str> = machine.states.iter().map(String::as_str).collect();
+        unreached.sort_unstable();
+        let roots = std::iter::once(machine.initial_state.as_str()).chain(unreached);
+
+        let mut queue = VecDeque::new();
+        for root in roots {
+            if numbering.states.contains_key(root) {
+                continue;
+            }
+            numbering.states.insert(root, numbering.states.len() + 1);
+            queue.push_back(root);
+            while let Some(state) = queue.pop_front() {
+                let Some(transitions) = outgoing.get_mut(state) else {
+                    continue;
+                };
+                // Symbols already numbered first, then new ones by name
+                let symbols = &numbering.symbols;
+                transitions.sort_unstable_by_key(|(read, ..)| {
+                    (symbols.get(read).copied().unwrap_or(usize::MAX), *read)
+                });
+                for &(read, next, write) in transitions.iter() {
+                    number_symbol(&mut numbering.symbols, read);
+                    number_symbol(&mut numbering.symbols, write);
+                    if !numbering.states.contains_key(next) {
+                        numbering.states.insert(next, numbering.states.len() + 1);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        // Symbols no transition uses, which are interchangeable
+        let mut unused: Vec<char> = machine.tape_alphabet.iter().copied().collect();
+        unused.sort_unstable();
+        for symbol in unused {
+            number_symbol(&mut numbering.symbols, symbol);
+        }
+        numbering
+    }
+}
+
+/// Rebuild a machine from its code
+pub fn decode(code: &str) -> Result<TuringMachine, String> {
+    if let Some(c) = code.chars().find(|c| *c != '0' && *c != '1') {
+        return Err(format!("Invalid character in code: '{}'", c));
+    }
+    let mut parts = code.split("11");
+    let mut header = parts.next().unwrap_or_default().split('1');
+    let mut count = |what: &str| {
+        header
+            .next()
+            .map(str::len)
+            .filter(|count| *count > 0)
+            .ok_or_else(|| format!("Code has no number of {}", what))
+    };
+    let (state_count, symbol_count, last_input) =
+        (count("states")?, count("symbols")?, count("input symbols")?);
+    if header.next().is_some() {
+        return Err("Code has extra numbers before the accept states".to_string());
+    }
+    if last_input > symbol_count {
+        return Err("Code has more input symbols than tape symbols".to_string());
+    }
+    let input_count = last_input - 1;
+
+    let width = (state_count - 1).to_string().len();
+    let state_names: Vec<String> = (0..state_count)
+        .map(|k| format!("q{:0width$}", k, width = width))
+        .collect();
+    let mut table = SymbolTable::new();
+    let mut symbols = vec!['_'];
+    for k in 0..symbol_count - 1 {
+        let name = if k < input_count {
+            INPUT_NAMES
+                .chars()
+                .nth(k)
+                .map_or_else(|| format!("i{}", k), String::from)
+        } else {
+            TAPE_NAMES
+                .chars()
+                .nth(k - input_count)
+                .map_or_else(|| format!("t{}", k - input_count), String::from)
+        };
+        symbols.push(table.intern(&name)?);
+    }
+    let state = |k: usize| {
+        state_names
+            .get(k.wrapping_sub(1))
+            .cloned()
+            .ok_or_else(|| format!("Code has no state {}", k))
+    };
+    let symbol = |k: usize| {
+        symbols
+            .get(k.wrapping_sub(1))
+            .copied()
+            .ok_or_else(|| format!("Code has no symbol {}", k))
+    };
+
+    let mut halting = |what: &str| -> Result<HashSet<String>, String> {
+        let part = parts
+            .next()
+            .ok_or_else(|| format!("Code ends before the {} states", what))?;
+        if part.is_empty() {
+            return Ok(HashSet::new());
+        }
+        part.split('1').map(|zeros| state(zeros.len())).collect()
+    };
+    let accept_states = halting("accept")?;
+    let reject_states = halting("reject")?;
+
+    let mut transitions = HashMap::new();
+    for part in parts {
+        let numbers: Vec<usize> = part.split('1').map(str::len).collect();
+        let [q, x, p, y, d] = numbers[..] else {
+            return Err(format!("Transition {} does not have five numbers", part));
+        };
+        let direction = match d {
+            1 => Direction::L,
+            2 => Direction::R,
+            3 => Direction::S,
+            _ => return Err(format!("Transition {} has no direction {}", part, d)),
+        };
+        let key = (state(q)?, symbol(x)?);
+        if transitions.contains_key(&key) {
+            return Err(format!(
+                "Code has two transitions from state {} on symbol {}",
+                q, x
+            ));
+        }
+        transitions.insert(key, (state(p)?, symbol(y)?, direction));
+    }
+
+    let mut machine = TuringMachine::new(
+        state_names.iter().cloned().collect(),
+        symbols[1..=input_count].iter().copied().collect(),
+        symbols.iter().copied().collect(),
+        transitions,
+        state_names[0].clone(),
+        accept_states,
+        reject_states,
+        '_',
+    )?;
+    machine.set_symbols(table);
+    Ok(machine)
+}
+
+/// The Gödel number of a code, in decimal
+pub fn to_decimal(code: &str) -> String {
+    // Little-endian limbs of nine decimal digits, starting from the leading 1
+    let mut limbs: Vec<u64> = vec![1];
+    for chunk in code.as_bytes().chunks(CHUNK as usize) {
+        let mut carry = chunk
+            .iter()
+            .fold(0, |value, bit| value << 1 | u64::from(*bit == b'1'));
+        for limb in &mut limbs {
+            let value = (*limb << chunk.len()) + carry;
+            *limb = value % LIMB;
+            carry = value / LIMB;
+        }
+        while carry > 0 {
+            limbs.push(carry % LIMB);
+            carry /= LIMB;
+        }
+    }
+    let mut text = limbs.last().map_or_else(String::new, u64::to_string);
+    for limb in limbs.iter().rev().skip(1) {
+        text.push_str(&format!("{:09}", limb));
+    }
+    text
+}
+
+/// The code of a Gödel number given in decimal
+pub fn from_decimal(number: &str) -> Result<String, String> {
+    let number = number.trim();
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Not a decimal number: {}", number));
+    }
+    // Big-endian limbs of nine decimal digits
+    let digits = number.trim_start_matches('0');
+    let first = digits.len() % 9;
+    let mut limbs: Vec<u64> = Vec::new();
+    if first > 0 {
+        limbs.push(digits[..first].parse().unwrap_or(0));
+    }
+    for start in (first..digits.len()).step_by(9) {
+        limbs.push(digits[start..start + 9].parse().unwrap_or(0));
+    }
+
+    // Divide by 2^CHUNK until nothing is left, collecting the remainders
+    let mut chunks = Vec::new();
+    while limbs.iter().any(|limb| *limb > 0) {
+        let mut remainder = 0;
+        for limb in &mut limbs {
+            let value = remainder * LIMB + *limb;
+            *limb = value >> CHUNK;
+            remainder = value & ((1 << CHUNK) - 1);
+        }
+        chunks.push(remainder);
+        while limbs.first() == Some(&0) {
+            limbs.remove(0);
+        }
+    }
+    let mut bits = String::new();
+    for chunk in chunks.iter().rev() {
+        bits.push_str(&format!("{:0width$b}", chunk, width = CHUNK as usize));
+    }
+    match bits.find('1') {
+        Some(leading) => Ok(bits[leading + 1..].to_string()),
+        None => Err("0 is not the Gödel number of a machine".to_string()),
+    }
+}
+
+/// `count` zeros
+fn unary(count: usize) -> String {
+    "0".repeat(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{parse_machine_definition, parse_machine_json};
+
+    const EVEN_ONES: &str = include_str!("../examples/even_ones.json");
+
+    fn load(text: &str) -> TuringMachine {
+        parse_machine_json(&parse_machine_definition(text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn renaming_states_keeps_the_code() {
+        let renamed = EVEN_ONES
+            .replace("\"q1", "\"zz")
+            .replace("\"q0", "\"start")
+            .replace("\"accept\"", "\"yes\"");
+        assert_eq!(
+            encode(&load(&renamed)).unwrap(),
+            encode(&load(EVEN_ONES)).unwrap()
+        );
+    }
+
+    #[test]
+    fn decoding_rebuilds_the_machine() {
+        let machine = load(EVEN_ONES);
+        let code = encode(&machine).unwrap();
+        let decoded = decode(&code).unwrap();
+        assert_eq!(encode(&decoded).unwrap(), code);
+        for input in ["", "0", "1", "0110", "111", "1011"] {
+            let run = |machine: &TuringMachine| {
+                let result = machine.execute(input, 100).unwrap();
+                (result.accepts, result.steps, result.tape)
+            };
+            assert_eq!(run(&decoded), run(&machine), "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn converts_codes_to_decimal_and_back() {
+        let code = encode(&load(EVEN_ONES)).unwrap();
+        assert_eq!(from_decimal(&to_decimal(&code)).unwrap(), code);
+        assert_eq!(to_decimal("01"), "5");
+        assert_eq!(from_decimal("5").unwrap(), "01");
+        assert!(from_decimal("0").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        assert!(decode("0120").is_err());
+        assert!(decode("").is_err());
+        assert!(decode("0001").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Golden traces: recorded runs that later versions must reproduce exactly
+//!
+//! A golden trace is every configuration of a machine's runs on a fixed set
+//! of inputs, written in `u q v` notation as by
+//! [`ConfigurationTrace`](crate::formal::ConfigurationTrace), each run
+//! headed by its input and followed by its outcome:
+//!
+//! ```text
+//! == "01"
+//! q0 01
+//! 0 q0 1
+//! 01 q1 _
+//! 01_ reject _
+//! -> rejects (state: reject, steps: 3)
+//! ```
+//!
+//! Traces are recorded once and kept next to the machines. Comparing a
+//! fresh recording with the kept one byte for byte catches any change in
+//! how the executor steps, including ones no verdict reveals.
+
+use crate::executor::{Executor, RunConfig};
+use crate::formal::ConfigurationTrace;
+use crate::race::{count_inputs_of_length, inputs_of_length};
+use crate::TuringMachine;
+
+/// Length up to which every input is recorded, besides the machine's own
+/// tests
+pub const GOLDEN_INPUT_LENGTH: usize = 3;
+
+/// Most inputs of one length recorded; longer inputs over large alphabets
+/// are left out
+pub const GOLDEN_INPUTS_PER_LENGTH: usize = 32;
+
+/// Step limit of recorded runs, kept low so that looping machines give
+/// short traces
+pub const GOLDEN_MAX_STEPS: usize = 200;
+
+/// The inputs recorded for `machine`: the inputs of its metadata tests, then
+/// every input of up to [`GOLDEN_INPUT_LENGTH`] symbols not already listed,
+/// as long as a length has at most [`GOLDEN_INPUTS_PER_LENGTH`] of them
+pub fn golden_inputs(machine: &TuringMachine) -> Vec<String> {
+    let mut inputs: Vec<String> = Vec::new();
+    let tests = machine
+        .metadata()
+        .tests
+        .iter()
+        .map(|test| test.input.clone());
+    let generated = (0..=GOLDEN_INPUT_LENGTH)
+        .take_while(|&length| {
+            count_inputs_of_length(machine, length)
+                .is_some_and(|count| count <= GOLDEN_INPUTS_PER_LENGTH)
+        })
+        .flat_map(|length| inputs_of_length(machine, length));
+    for input in tests.chain(generated) {
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+    inputs
+}
+
+/// Record the golden trace of `machine` on `inputs`
+pub fn record(machine: &TuringMachine, inputs: &[String]) -> String {
+    let config = RunConfig {
+        max_steps: GOLDEN_MAX_STEPS,
+        ..RunConfig::default()
+    };
+    let mut text = Vec::new();
+    for input in inputs {
+        text.extend(format!("== {:?}\n", input).bytes());
+        let mut trace = ConfigurationTrace::new(machine, input, &mut text);
+        let result = Executor::new(machine)
+            .observe(&mut trace)
+            .run_with(input, &config);
+        // Writing to memory cannot fail
+        let _ = trace.finish();
+        let outcome = match result {
+            Ok(result) if !result.halted => match result.cycle {
+                Some(cycle) => format!(
+                    "loops (state: {}, steps: {}, cycle length: {}, shift: {})",
+                    result.final_state, result.steps, cycle.length, cycle.shift
+                ),
+                None => format!(
+                    "does not halt (state: {}, steps: {})",
+                    result.final_state, result.steps
+                ),
+            },
+            Ok(result) => {
+                let verdict = match (&result.output, result.accepts) {
+                    (Some(output), _) => format!("outputs {:?}", output),
+                    (None, _) if machine.output().is_some() => "has no output".to_string(),
+                    (None, Some(true)) => "accepts".to_string(),
+                    (None, _) => "rejects".to_string(),
+                };
+                format!(
+                    "{} (state: {}, steps: {})",
+                    verdict, result.final_state, result.steps
+                )
+            }
+            Err(e) => format!("error: {}", e),
+        };
+        text.extend(format!("-> {}\n", outcome).bytes());
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+/// The first difference between a kept trace and a fresh recording, or
+/// `None` if they are identical
+pub fn compare(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let mut input = None;
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        let (want, got) = (expected_lines.next(), actual_lines.next());
+        if want != got {
+            let run = input.map(|input| format!(" (run on {})", input));
+            let show = |line: Option<&str>| {
+                line.map_or("end of trace".to_string(), |l| format!("\"{}\"", l))
+            };
+            return Some(format!(
+                "line {}{}: expected {}, found {}",
+                line,
+                run.unwrap_or_default(),
+                show(want),
+                show(got)
+            ));
+        }
+        match want {
+            Some(line) => {
+                if let Some(header) = line.strip_prefix("== ") {
+                    input = Some(header);
+                }
+            }
+            None => break,
+        }
+    }
+    // Only line endings differ
+    Some("the traces differ in their line endings".to_string())
+}
@@ -0,0 +1,490 @@
+//! Machines on a two-dimensional tape, such as turmites and Langton's ant
+//!
+//! A grid machine reads and writes one cell of an unbounded grid and moves
+//! its head to one of the four neighbouring cells. Its file uses the fields
+//! of the machine schema, with the directions `U`, `D`, `L`, `R` and `S`
+//! (stay); `alphabet` may be left out if the machine takes no input:
+//!
+//! ```json
+//! {
+//!     "transitions": {
+//!         "q0,0": ["q0", "1", "D"]
+//!     }
+//! }
+//! ```
+//!
+//! With `"moves": "relative"` the machine is a turmite: its head faces a
+//! direction, north at the start, and every transition turns it (`L` left,
+//! `R` right, `U` around, `N` not at all) and then moves it one cell forward.
+//! Langton's ant is the turmite that turns right on a blank cell and left on
+//! a marked one, flipping the cell as it leaves:
+//!
+//! ```json
+//! {
+//!     "moves": "relative",
+//!     "transitions": {
+//!         "ant,_": ["ant", "#", "R"],
+//!         "ant,#": ["ant", "_", "L"]
+//!     }
+//! }
+//! ```
+//!
+//! Cells are `(x, y)` with `y` growing downwards. The input is written on row
+//! 0 from column 0, where the head starts.
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// How the directions of a grid machine's transitions are read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveMode {
+    /// Directions are compass moves: up, down, left, right or stay
+    #[default]
+    Absolute,
+    /// Directions are turns, each followed by a step forward
+    Relative,
+}
+
+/// Grid machine file layout
+#[derive(Debug, Deserialize)]
+struct GridJson {
+    states: Vec<String>,
+    #[serde(default)]
+    alphabet: Vec<String>,
+    tape_alphabet: Vec<String>,
+    initial_state: String,
+    accept_states: Vec<String>,
+    reject_states: Vec<String>,
+    blank_symbol: Option<String>,
+    #[serde(default)]
+    moves: MoveMode,
+    transitions: BTreeMap<String, Vec<String>>,
+}
+
+/// Where a grid transition takes the head
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridMove {
+    Up,
+    Down,
+    Left,
+    Right,
+    Stay,
+    /// Turn left, then step forward
+    TurnLeft,
+    /// Turn right, then step forward
+    TurnRight,
+    /// Turn around, then step forward
+    TurnAround,
+    /// Step forward without turning
+    Forward,
+}
+
+/// The direction a turmite's head faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heading {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    /// The heading after a quarter turn clockwise
+    fn right(self) -> Heading {
+        match self {
+            Heading::North => Heading::East,
+            Heading::East => Heading::South,
+            Heading::South => Heading::West,
+            Heading::West => Heading::North,
+        }
+    }
+
+    /// Change of `(x, y)` for a step forward
+    fn offset(self) -> (i64, i64) {
+        match self {
+            Heading::North => (0, -1),
+            Heading::East => (1, 0),
+            Heading::South => (0, 1),
+            Heading::West => (-1, 0),
+        }
+    }
+
+    /// Arrow pointing this way
+    pub fn arrow(self) -> char {
+        match self {
+            Heading::North => '↑',
+            Heading::East => '→',
+            Heading::South => '↓',
+            Heading::West => '←',
+        }
+    }
+}
+
+/// What a grid transition does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridTarget {
+    pub new_state: String,
+    pub write: char,
+    pub movement: GridMove,
+}
+
+/// An unbounded two-dimensional tape
+///
+/// Only cells holding something other than the blank are stored.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    cells: HashMap<(i64, i64), char>,
+    blank_symbol: char,
+}
+
+impl Grid {
+    /// An empty grid
+    pub fn new(blank_symbol: char) -> Grid {
+        Grid {
+            cells: HashMap::new(),
+            blank_symbol,
+        }
+    }
+
+    /// The symbol in a cell
+    pub fn get(&self, cell: (i64, i64)) -> char {
+        self.cells.get(&cell).copied().unwrap_or(self.blank_symbol)
+    }
+
+    /// Write a symbol to a cell
+    pub fn set(&mut self, cell: (i64, i64), symbol: char) {
+        if symbol == self.blank_symbol {
+            self.cells.remove(&cell);
+        } else {
+            self.cells.insert(cell, symbol);
+        }
+    }
+
+    /// Symbol of the cells never written
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    /// Number of cells holding something other than the blank
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Whether every cell is blank
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Smallest and largest `(x, y)` of the non-blank cells
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        let mut cells = self.cells.keys();
+        let first = *cells.next()?;
+        Some(cells.fold((first, first), |((x0, y0), (x1, y1)), &(x, y)| {
+            ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y)))
+        }))
+    }
+
+    /// The rows of the region from `from` to `to`, both included
+    pub fn rows(&self, from: (i64, i64), to: (i64, i64)) -> Vec<String> {
+        (from.1..=to.1)
+            .map(|y| (from.0..=to.0).map(|x| self.get((x, y))).collect())
+            .collect()
+    }
+}
+
+/// A Turing machine on a two-dimensional tape
+#[derive(Debug)]
+pub struct GridMachine {
+    states: HashSet<String>,
+    alphabet: HashSet<char>,
+    transitions: HashMap<(String, char), GridTarget>,
+    initial_state: String,
+    accept_states: HashSet<String>,
+    reject_states: HashSet<String>,
+    blank_symbol: char,
+    moves: MoveMode,
+}
+
+impl GridMachine {
+    /// Load a grid machine file (JSON, read as JSON5)
+    pub fn load(path: &Path) -> Result<GridMachine, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        GridMachine::from_json(&text)
+    }
+
+    /// Parse a grid machine definition
+    pub fn from_json(text: &str) -> Result<GridMachine, String> {
+        let json: GridJson =
+            json5::from_str(text).map_err(|e| format!("Invalid grid machine file: {}", e))?;
+
+        let single = |entry: &String, what: &str| -> Result<char, String> {
+            let mut chars = entry.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!(
+                    "{} entry '{}' must be a single character",
+                    what, entry
+                )),
+            }
+        };
+        let alphabet = json
+            .alphabet
+            .iter()
+            .map(|entry| single(entry, "Alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let tape_alphabet = json
+            .tape_alphabet
+            .iter()
+            .map(|entry| single(entry, "Tape alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let blank_symbol = match &json.blank_symbol {
+            Some(entry) => single(entry, "Blank symbol")?,
+            None => '_',
+        };
+        let states: HashSet<String> = json.states.iter().cloned().collect();
+        let accept_states: HashSet<String> = json.accept_states.iter().cloned().collect();
+        let reject_states: HashSet<String> = json.reject_states.iter().cloned().collect();
+
+        if !states.contains(&json.initial_state) {
+            return Err(format!(
+                "Initial state {} not in states",
+                json.initial_state
+            ));
+        }
+        if !accept_states.is_subset(&states) {
+            return Err("Accept states must be subset of states".to_string());
+        }
+        if !reject_states.is_subset(&states) {
+            return Err("Reject states must be subset of states".to_string());
+        }
+        if !accept_states.is_disjoint(&reject_states) {
+            return Err("Accept and reject states must be disjoint".to_string());
+        }
+        if !tape_alphabet.contains(&blank_symbol) {
+            return Err(format!(
+                "Blank symbol {} not in tape alphabet",
+                blank_symbol
+            ));
+        }
+        if let Some(symbol) = alphabet.iter().find(|s| !tape_alphabet.contains(s)) {
+            return Err(format!("Input symbol {} not in tape alphabet", symbol));
+        }
+
+        let mut transitions = HashMap::new();
+        for (key, value) in &json.transitions {
+            let (from, to) = parse_transition(key, value, json.moves)?;
+            for state in [&from.0, &to.new_state] {
+                if !states.contains(state) {
+                    return Err(format!("Transition {}: state {} not in states", key, state));
+                }
+            }
+            for symbol in [from.1, to.write] {
+                if !tape_alphabet.contains(&symbol) {
+                    return Err(format!(
+                        "Transition {}: symbol {} not in tape alphabet",
+                        key, symbol
+                    ));
+                }
+            }
+            transitions.insert(from, to);
+        }
+
+        Ok(GridMachine {
+            states,
+            alphabet,
+            transitions,
+            initial_state: json.initial_state,
+            accept_states,
+            reject_states,
+            blank_symbol,
+            moves: json.moves,
+        })
+    }
+
+    /// All state names
+    pub fn states(&self) -> &HashSet<String> {
+        &self.states
+    }
+
+    /// Transitions, keyed by state and the symbol under the head
+    pub fn transitions(&self) -> &HashMap<(String, char), GridTarget> {
+        &self.transitions
+    }
+
+    /// Symbol representing empty cells
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    /// Whether directions are compass moves or turns
+    pub fn moves(&self) -> MoveMode {
+        self.moves
+    }
+
+    /// Set up a run on the given input, before its first step
+    pub fn start(&self, input_string: &str) -> Result<GridRun<'_>, String> {
+        let mut grid = Grid::new(self.blank_symbol);
+        for (x, symbol) in input_string.chars().enumerate() {
+            if !self.alphabet.contains(&symbol) {
+                return Err(format!("Invalid input symbol: {}", symbol));
+            }
+            grid.set((x as i64, 0), symbol);
+        }
+        Ok(GridRun {
+            machine: self,
+            grid,
+            head: (0, 0),
+            heading: Heading::North,
+            state: self.initial_state.clone(),
+            steps: 0,
+        })
+    }
+}
+
+/// A run of a grid machine, advanced one step at a time
+#[derive(Debug, Clone)]
+pub struct GridRun<'a> {
+    machine: &'a GridMachine,
+    grid: Grid,
+    head: (i64, i64),
+    heading: Heading,
+    state: String,
+    steps: usize,
+}
+
+impl GridRun<'_> {
+    /// The verdict, once the machine has halted: accepting in an accept
+    /// state, rejecting in a reject state or without a transition
+    pub fn verdict(&self) -> Option<bool> {
+        let machine = self.machine;
+        if machine.accept_states.contains(&self.state) {
+            Some(true)
+        } else if machine.reject_states.contains(&self.state)
+            || !machine
+                .transitions
+                .contains_key(&(self.state.clone(), self.grid.get(self.head)))
+        {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Take one transition; returns `false` without changing anything if the
+    /// machine has halted
+    pub fn step(&mut self) -> bool {
+        if self.verdict().is_some() {
+            return false;
+        }
+        let key = (self.state.clone(), self.grid.get(self.head));
+        let target = &self.machine.transitions[&key];
+        self.grid.set(self.head, target.write);
+
+        let (dx, dy) = match target.movement {
+            GridMove::Up => (0, -1),
+            GridMove::Down => (0, 1),
+            GridMove::Left => (-1, 0),
+            GridMove::Right => (1, 0),
+            GridMove::Stay => (0, 0),
+            turn => {
+                self.heading = match turn {
+                    GridMove::TurnLeft => self.heading.right().right().right(),
+                    GridMove::TurnRight => self.heading.right(),
+                    GridMove::TurnAround => self.heading.right().right(),
+                    _ => self.heading,
+                };
+                self.heading.offset()
+            }
+        };
+        self.head = (self.head.0 + dx, self.head.1 + dy);
+        self.state = target.new_state.clone();
+        self.steps += 1;
+        true
+    }
+
+    /// Take transitions until the machine halts or has taken `max_steps`
+    pub fn run(&mut self, max_steps: usize) {
+        while self.steps < max_steps && self.step() {}
+    }
+
+    /// The tape
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// The cell under the head
+    pub fn head(&self) -> (i64, i64) {
+        self.head
+    }
+
+    /// The direction the head faces, for machines with relative moves
+    pub fn heading(&self) -> Option<Heading> {
+        (self.machine.moves == MoveMode::Relative).then_some(self.heading)
+    }
+
+    /// The current state
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// Number of transitions taken
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+/// Parse a `"state,symbol": [new_state, write_symbol, direction]` entry
+fn parse_transition(
+    key: &str,
+    value: &[String],
+    moves: MoveMode,
+) -> Result<((String, char), GridTarget), String> {
+    let Some((state, read)) = key.split_once(',') else {
+        return Err(format!("Invalid transition key: {}", key));
+    };
+    let single = |symbol: &str| {
+        let mut chars = symbol.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!(
+                "Transition {}: symbol '{}' must be a single character",
+                key, symbol
+            )),
+        }
+    };
+    let read = single(read)?;
+
+    let [new_state, write, direction] = value else {
+        return Err(format!("Invalid transition value for key: {}", key));
+    };
+    let write = single(write)?;
+    let movement = match (moves, direction.as_str()) {
+        (MoveMode::Absolute, "U") => GridMove::Up,
+        (MoveMode::Absolute, "D") => GridMove::Down,
+        (MoveMode::Absolute, "L") => GridMove::Left,
+        (MoveMode::Absolute, "R") => GridMove::Right,
+        (MoveMode::Absolute, "S" | "N") => GridMove::Stay,
+        (MoveMode::Relative, "L") => GridMove::TurnLeft,
+        (MoveMode::Relative, "R") => GridMove::TurnRight,
+        (MoveMode::Relative, "U") => GridMove::TurnAround,
+        (MoveMode::Relative, "N") => GridMove::Forward,
+        _ => {
+            return Err(format!(
+                "Transition {}: invalid direction: {}",
+                key, direction
+            ))
+        }
+    };
+
+    Ok((
+        (state.to_string(), read),
+        GridTarget {
+            new_state: new_state.clone(),
+            write,
+            movement,
+        },
+    ))
+}
@@ -0,0 +1,298 @@
+//! gRPC service mirroring the HTTP server
+//!
+//! For services where gRPC is the standard, such as grading services, the
+//! [`Server`]'s API is also offered as the `turing_machine.Machines`
+//! service of `proto/turing_machine.proto`, on the address given to
+//! [`Server::grpc_on`]. The service shares the server's machines and runs,
+//! so a machine uploaded over HTTP can be run over gRPC. `StreamRun`
+//! answers with every configuration as it is reached, as the server's
+//! WebSocket sessions do; the run waits for slow clients and stops when the
+//! client goes away.
+//!
+//! [`service`] gives the service for adding to a tonic server of one's own.
+
+use crate::executor::{Executor, StepEvent, StepObserver};
+use crate::server::{Refusal, RunReport, Server, StreamStep};
+use crate::{Direction, ExecutionResult, MachineJson, ResourceLimit, SymbolTable, TuringMachine};
+use proto::machines_server::{Machines, MachinesServer};
+use proto::stream_run_reply::Event;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+/// Messages and service generated from `proto/turing_machine.proto`
+pub mod proto {
+    tonic::include_proto!("turing_machine");
+}
+
+/// Replies of a `StreamRun` call waiting to be sent
+const STREAM_BUFFER: usize = 64;
+
+/// The service, answering with `server`'s machines and runs
+pub fn service(server: Server) -> MachinesServer<Service> {
+    MachinesServer::new(Service { server })
+}
+
+/// Answer gRPC calls on `listener` until the process is stopped
+pub(crate) fn serve(server: Server, listener: TcpListener) -> Result<(), String> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let listener = tokio::net::TcpListener::from_std(listener).map_err(|e| e.to_string())?;
+        tonic::transport::Server::builder()
+            .add_service(service(server))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Implementation of the `Machines` service
+pub struct Service {
+    server: Server,
+}
+
+impl Service {
+    /// Run `call` on the server on a thread that may block
+    async fn call<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&Server) -> Result<T, Refusal> + Send + 'static,
+    ) -> Result<T, Status> {
+        let server = self.server.clone();
+        tokio::task::spawn_blocking(move || call(&server))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(Status::from)
+    }
+}
+
+#[tonic::async_trait]
+impl Machines for Service {
+    async fn add_machine(
+        &self,
+        request: Request<proto::AddMachineRequest>,
+    ) -> Result<Response<proto::MachineSummary>, Status> {
+        let definition = request.into_inner().definition;
+        let summary = self
+            .call(move |server| server.add_machine(&definition))
+            .await?;
+        Ok(Response::new(proto::MachineSummary {
+            id: summary.id as u64,
+            name: summary.name,
+            states: summary.states as u64,
+            transitions: summary.transitions as u64,
+        }))
+    }
+
+    async fn list_machines(
+        &self,
+        _request: Request<proto::ListMachinesRequest>,
+    ) -> Result<Response<proto::ListMachinesReply>, Status> {
+        let machines = self
+            .server
+            .machine_summaries()
+            .into_iter()
+            .map(|summary| proto::MachineSummary {
+                id: summary.id as u64,
+                name: summary.name,
+                states: summary.states as u64,
+                transitions: summary.transitions as u64,
+            })
+            .collect();
+        Ok(Response::new(proto::ListMachinesReply { machines }))
+    }
+
+    async fn get_machine(
+        &self,
+        request: Request<proto::MachineId>,
+    ) -> Result<Response<proto::MachineDefinition>, Status> {
+        let machine = self.server.find_machine(id(request.into_inner().id))?;
+        let definition = serde_json::to_string_pretty(&MachineJson::from(machine.as_ref()))
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(proto::MachineDefinition { definition }))
+    }
+
+    async fn remove_machine(
+        &self,
+        request: Request<proto::MachineId>,
+    ) -> Result<Response<proto::RemoveMachineReply>, Status> {
+        self.server.remove_machine(id(request.into_inner().id))?;
+        Ok(Response::new(proto::RemoveMachineReply {}))
+    }
+
+    async fn run(
+        &self,
+        request: Request<proto::RunRequest>,
+    ) -> Result<Response<proto::RunReport>, Status> {
+        let request = request.into_inner();
+        let report = self
+            .call(move |server| {
+                let run = crate::server::RunRequest {
+                    input: request.input,
+                    trace: request.trace,
+                    max_steps: request.max_steps.map(id),
+                };
+                let report = server.run(id(request.machine), run)?;
+                let machine = server.find_machine(report.machine)?;
+                Ok(run_report(&machine.symbols, &report))
+            })
+            .await?;
+        Ok(Response::new(report))
+    }
+
+    async fn get_run(
+        &self,
+        request: Request<proto::RunId>,
+    ) -> Result<Response<proto::RunReport>, Status> {
+        let report = self.server.report(id(request.into_inner().id))?;
+        // Named symbols of a machine removed since show as stored
+        let symbols = match self.server.find_machine(report.machine) {
+            Ok(machine) => machine.symbols().clone(),
+            Err(_) => SymbolTable::new(),
+        };
+        Ok(Response::new(run_report(&symbols, &report)))
+    }
+
+    type StreamRunStream = ReceiverStream<Result<proto::StreamRunReply, Status>>;
+
+    async fn stream_run(
+        &self,
+        request: Request<proto::StreamRunRequest>,
+    ) -> Result<Response<Self::StreamRunStream>, Status> {
+        let request = request.into_inner();
+        let machine = self.server.find_machine(id(request.machine))?;
+        let config = self.server.run_config(&machine, request.max_steps.map(id));
+        let initial =
+            StreamStep::initial(&machine, &request.input).map_err(Status::invalid_argument)?;
+
+        let (replies, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let interrupt = AtomicBool::new(false);
+            let mut sender = Sender {
+                machine: &machine,
+                replies: &replies,
+                interrupt: &interrupt,
+            };
+            sender.send(Ok(Event::Step(snapshot(initial))));
+            let result = Executor::new(&machine)
+                .observe(&mut sender)
+                .interrupt_on(&interrupt)
+                .run_with(&request.input, &config);
+            let reply = match result {
+                Ok(result) => Ok(Event::Result(execution_result(&result))),
+                Err(e) => Err(Status::invalid_argument(e)),
+            };
+            if !interrupt.load(Ordering::Relaxed) {
+                sender.send(reply);
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+impl From<Refusal> for Status {
+    fn from(refusal: Refusal) -> Self {
+        match refusal {
+            Refusal::NotFound(message) => Status::not_found(message),
+            Refusal::Invalid(message) => Status::invalid_argument(message),
+        }
+    }
+}
+
+/// Observer sending the configurations of a run to a `StreamRun` client
+struct Sender<'a> {
+    machine: &'a TuringMachine,
+    replies: &'a mpsc::Sender<Result<proto::StreamRunReply, Status>>,
+    /// Set when the client is gone, to end the run
+    interrupt: &'a AtomicBool,
+}
+
+impl Sender<'_> {
+    /// Send a reply, waiting while the client is behind
+    fn send(&mut self, event: Result<Event, Status>) {
+        let reply = event.map(|event| proto::StreamRunReply { event: Some(event) });
+        if self.replies.blocking_send(reply).is_err() {
+            self.interrupt.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl StepObserver for Sender<'_> {
+    fn on_step(&mut self, event: &StepEvent) {
+        let step = StreamStep::after(self.machine, event);
+        self.send(Ok(Event::Step(snapshot(step))));
+    }
+}
+
+/// An id given over gRPC; ids too large to exist are kept unknown
+fn id(id: u64) -> usize {
+    usize::try_from(id).unwrap_or(usize::MAX)
+}
+
+fn snapshot(step: StreamStep) -> proto::Snapshot {
+    proto::Snapshot {
+        step: step.step as u64,
+        state: step.state,
+        tape: step.tape,
+        head_position: step.head_position,
+    }
+}
+
+fn execution_result(result: &ExecutionResult) -> proto::ExecutionResult {
+    proto::ExecutionResult {
+        accepts: result.accepts,
+        final_state: result.final_state.clone(),
+        steps: result.steps as u64,
+        halted: result.halted,
+        tape: result.tape.clone(),
+        head_position: result.head_position,
+        interrupted: result.interrupted,
+        memory_limit_exceeded: result.memory_limit_exceeded,
+        timed_out: result.timed_out,
+        resource_limit: match result.resource_limit {
+            Some(ResourceLimit::Memory) => "memory".to_string(),
+            Some(ResourceLimit::Cells) => "cells".to_string(),
+            None => String::new(),
+        },
+        peak_memory_bytes: result.peak_memory_bytes as u64,
+        output: result.output.clone(),
+        cycle: result.cycle.map(|cycle| proto::Cycle {
+            length: cycle.length as u64,
+            step: cycle.step as u64,
+            shift: cycle.shift,
+        }),
+    }
+}
+
+/// A run report, with the symbols of its trace written out by their names
+fn run_report(symbols: &SymbolTable, report: &RunReport) -> proto::RunReport {
+    let trace = report.trace.iter().flatten().map(|step| proto::TraceStep {
+        step: step.step as u64,
+        from_state: step.from_state.clone(),
+        read_symbol: symbols.name(step.read_symbol),
+        to_state: step.to_state.clone(),
+        write_symbol: symbols.name(step.write_symbol),
+        direction: match step.direction {
+            Direction::L => "L",
+            Direction::R => "R",
+            Direction::S => "S",
+        }
+        .to_string(),
+        head_position: step.head_position,
+        tape: symbols.render(&step.tape),
+    });
+    proto::RunReport {
+        id: report.id as u64,
+        machine: report.machine as u64,
+        input: report.input.clone(),
+        max_steps: report.max_steps as u64,
+        result: Some(execution_result(&report.result)),
+        trace: trace.collect(),
+    }
+}
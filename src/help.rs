@@ -0,0 +1,725 @@
+//! Help text for the command line program
+//!
+//! The commands, options and help topics are described once here, as data,
+//! and rendered both as terminal help (`help`, `help <topic>`, `--help`) and
+//! as a man page (`help --man`). Usage errors print the synopsis of their
+//! command from the same table, so the help cannot fall behind the parser.
+//! The `schema` topic is generated from the JSON Schema of the definition
+//! format itself.
+
+use serde_json::Value;
+use std::fmt::Write;
+use turing_machine::formats::MACHINE_SCHEMA;
+
+/// Name of the program in the man page
+const PROGRAM: &str = "turing_machine";
+
+/// A subcommand of the program
+pub struct Command {
+    pub name: &'static str,
+    /// Arguments after the command name, in synopsis notation
+    pub args: &'static str,
+    pub summary: &'static str,
+}
+
+/// An option accepted before or after any command
+pub struct Flag {
+    pub name: &'static str,
+    pub value: &'static str,
+    pub summary: &'static str,
+}
+
+/// A help page on one subject, see [`TOPICS`]
+pub struct Topic {
+    pub name: &'static str,
+    pub summary: &'static str,
+    /// Heading of the topic's section in the man page
+    section: &'static str,
+    blocks: fn() -> Vec<Block>,
+}
+
+/// A piece of a help page
+enum Block {
+    Paragraph(String),
+    /// Text shown verbatim, such as an example
+    Example(String),
+    /// A term and its description
+    Item(String, String),
+}
+
+/// All subcommands, in the order they are listed
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "run",
+        args:
+            "<file> <input | --stdin> [--map a=0,b=1] [--normalize] [--explain] [--ids <file | ->] [--no-warnings] [--format text|json|jsonl]",
+        summary: "Run a machine on one input, or on every line of standard input",
+    },
+    Command {
+        name: "race",
+        args: "<file> <input>... | <file> --length N",
+        summary: "Run a machine on several inputs at once until one is accepted",
+    },
+    Command {
+        name: "dovetail",
+        args: "<file> [--count N]",
+        summary: "List accepted inputs, shortest first, even if the machine loops on others",
+    },
+    Command {
+        name: "pipeline",
+        args: "<file> <input> [--trace]",
+        summary: "Run a pipeline of machines, each on the tape left by the previous one",
+    },
+    Command {
+        name: "multihead",
+        args: "<file> <input>",
+        summary: "Run a machine with several heads on one tape",
+    },
+    Command {
+        name: "nd",
+        args: "<file> <input> [--trace] [--record-choices <file>] [--replay-choices <file>]",
+        summary: "Explore a non-deterministic machine breadth-first, or follow one branch",
+    },
+    Command {
+        name: "determinize",
+        args: "<file> <output>",
+        summary: "Convert a non-deterministic machine into a deterministic machine simulating it",
+    },
+    Command {
+        name: "amplitude",
+        args: "<file> <input> [--max-configurations N] [--trace]",
+        summary: "Run a machine with complex amplitudes on its transitions and print the probability of acceptance (experimental)",
+    },
+    Command {
+        name: "grid",
+        args: "<file> [input] [--visual]",
+        summary: "Run a machine on a two-dimensional tape",
+    },
+    Command {
+        name: "pda",
+        args: "<file> [input] [--visual]",
+        summary: "Run a deterministic pushdown automaton",
+    },
+    Command {
+        name: "two-stack",
+        args: "<file> [input] [--visual]",
+        summary: "Run a two-stack machine",
+    },
+    Command {
+        name: "to-two-stack",
+        args: "<file> <output.json>",
+        summary: "Compile a machine into an equivalent two-stack machine",
+    },
+    Command {
+        name: "utm",
+        args: "<file> <input> [--encode] | --save <output>",
+        summary: "Run a machine through the universal Turing machine, print its encoding, or save the universal machine",
+    },
+    Command {
+        name: "encode",
+        args: "<file> [--decimal]",
+        summary: "Print a machine's Gödel code as a bitstring, or its Gödel number",
+    },
+    Command {
+        name: "decode",
+        args: "<code | -> <output> [--decimal]",
+        summary: "Rebuild the machine of a Gödel code or number",
+    },
+    Command {
+        name: "trajectory",
+        args: "<file> <input>... [--svg] [--map a=0,b=1] [--normalize]",
+        summary: "Chart the head positions of several runs",
+    },
+    Command {
+        name: "blame",
+        args: "<file> <input> <cell>",
+        summary: "Show which step last changed a cell of the final tape",
+    },
+    Command {
+        name: "ca",
+        args: "<rule> [cells] [--generations n] [--width n] [--svg]",
+        summary: "Draw the space-time diagram of an elementary cellular automaton",
+    },
+    Command {
+        name: "convert",
+        args: "<input> <output>",
+        summary: "Convert a machine between formats, chosen by file extension",
+    },
+    Command {
+        name: "complete",
+        args: "<file> -o <output>",
+        summary: "Add a transition to a reject state for every missing state and symbol",
+    },
+    Command {
+        name: "export",
+        args: "<file> mermaid|tikz|markdown|csv",
+        summary: "Print a machine as a Mermaid state diagram, a TikZ picture, or a Markdown or CSV transition table",
+    },
+    Command {
+        name: "render",
+        args: "<file> -o <output.svg|output.png>",
+        summary: "Draw a machine's state diagram as an SVG or PNG image",
+    },
+    Command {
+        name: "qr",
+        args: "<file> [--svg]",
+        summary: "Print a machine as a QR code and its text payload",
+    },
+    Command {
+        name: "load-qr",
+        args: "<payload | -> <output>",
+        summary: "Save the machine in a scanned QR payload",
+    },
+    Command {
+        name: "analyze",
+        args: "<file> [--no-cache]",
+        summary: "Report reachability, dead ends, mergeable states and metrics of a machine",
+    },
+    Command {
+        name: "lint",
+        args: "<file> [--no-cache]",
+        summary: "Warn about likely mistakes in a machine",
+    },
+    Command {
+        name: "bench",
+        args: "<file> [--input <input>]... [--iterations N] [--format text|json|jsonl]",
+        summary: "Time a machine's runs, with their speed, tape growth and allocations",
+    },
+    Command {
+        name: "golden",
+        args: "[--bless]",
+        summary: "Check the example machines against their recorded golden traces",
+    },
+    Command {
+        name: "info",
+        args: "<file> [--short]",
+        summary: "Print a machine's name, description, alphabets and size as JSON, or one summary line",
+    },
+    Command {
+        name: "cache",
+        args: "clear",
+        summary: "Remove the cached analyses, minimizations and metrics",
+    },
+    Command {
+        name: "pack",
+        args: "install <dir|archive.zip> | list | show <name> | remove <name> | <file> <output.tmpkg>",
+        summary: "Install and inspect content packs, or bundle a machine with its tests and docs",
+    },
+    Command {
+        name: "unpack",
+        args: "<bundle.tmpkg> <output>",
+        summary: "Write the machine of a bundle, with its tests and docs, to a machine file",
+    },
+    Command {
+        name: "exercise",
+        args: "list | show <pack/id> | check <pack/id> <file>",
+        summary: "List the exercises of the installed packs, or check a machine against one",
+    },
+    Command {
+        name: "tutorial",
+        args: "list | show <pack/id>",
+        summary: "List or print the tutorials of the installed packs",
+    },
+    Command {
+        name: "daemon",
+        args: "<spool-dir> <output-dir> [--jobs N] [--once]",
+        summary: "Run machines dropped into a spool directory",
+    },
+    Command {
+        name: "serve",
+        args: "[--port 8080] [--host 127.0.0.1] [--jobs N] [--stream-port 8081] [--grpc-port 50051]",
+        summary: "Run uploaded machines for web front-ends over an HTTP JSON API",
+    },
+    Command {
+        name: "lsp",
+        args: "",
+        summary: "Check DSL files in editors, as a language server on stdin and stdout",
+    },
+    Command {
+        name: "help",
+        args: "[topic | --man]",
+        summary: "Show help on a topic, or print the man page",
+    },
+    Command {
+        name: "--examples",
+        args: "",
+        summary: "Run the example machines on sample inputs",
+    },
+];
+
+/// Options accepted anywhere on the command line
+pub const FLAGS: &[Flag] = &[
+    Flag {
+        name: "--max-steps",
+        value: "N",
+        summary: "Step limit before a run counts as not halting, or unlimited (default: 10000)",
+    },
+    Flag {
+        name: "--max-memory",
+        value: "SIZE",
+        summary: "Memory a run may use for its tape and trace, e.g. 512M (default: unlimited)",
+    },
+    Flag {
+        name: "--max-cells",
+        value: "N",
+        summary: "Tape cells a run may use, e.g. 1_000_000 (default: unlimited)",
+    },
+    Flag {
+        name: "--timeout",
+        value: "TIME",
+        summary: "Time a run may take, e.g. 5s or 500ms (default: unlimited)",
+    },
+    Flag {
+        name: "--max-head-overshoot",
+        value: "N",
+        summary: "Cells the head may stray beyond the non-blank tape (default: unchecked)",
+    },
+    Flag {
+        name: "--min-head",
+        value: "N",
+        summary:
+            "Leftmost cell the head may visit, 0 being the first input cell (default: unchecked)",
+    },
+    Flag {
+        name: "--examples-dir",
+        value: "DIR",
+        summary: "Directory scanned for example machines (default: examples)",
+    },
+    Flag {
+        name: "--color",
+        value: "WHEN",
+        summary: "auto, always or never (default: auto)",
+    },
+    Flag {
+        name: "--tape-width",
+        value: "N",
+        summary: "Tape cells shown in visual mode (default: 20)",
+    },
+    Flag {
+        name: "--snapshot-every",
+        value: "N",
+        summary: "Keep every Nth configuration of a visual run and take the steps between them again when needed (default: every step)",
+    },
+    Flag {
+        name: "--map",
+        value: "a=0,b=1",
+        summary: "Replace symbols in every input before it is run",
+    },
+    Flag {
+        name: "--normalize",
+        value: "",
+        summary: "Strip whitespace from inputs and fold their case",
+    },
+    Flag {
+        name: "--tape",
+        value: "SYMBOLS",
+        summary: "Symbols on the tape from cell 0 before the input is written",
+    },
+    Flag {
+        name: "--input-start",
+        value: "N",
+        summary: "Cell holding the first input symbol (default: 0)",
+    },
+    Flag {
+        name: "--head-start",
+        value: "N",
+        summary: "Cell the head starts on (default: the first input cell)",
+    },
+    Flag {
+        name: "-v, -vv",
+        value: "",
+        summary: "Log execution details, or every transition, to stderr",
+    },
+    Flag {
+        name: "-h, --help",
+        value: "",
+        summary: "Show this overview",
+    },
+];
+
+/// All help topics, in the order they are listed
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "machines",
+        summary: "Writing a machine definition",
+        section: "MACHINE DEFINITIONS",
+        blocks: machines,
+    },
+    Topic {
+        name: "schema",
+        summary: "Every field of the definition format",
+        section: "DEFINITION FIELDS",
+        blocks: schema,
+    },
+    Topic {
+        name: "formats",
+        summary: "File formats, conversion and sharing",
+        section: "FILE FORMATS",
+        blocks: formats,
+    },
+    Topic {
+        name: "debugger",
+        summary: "Controls of the visual step-by-step mode",
+        section: "VISUAL MODE",
+        blocks: debugger,
+    },
+    Topic {
+        name: "config",
+        summary: "The config file and environment variables",
+        section: "CONFIGURATION",
+        blocks: config,
+    },
+    Topic {
+        name: "exit-codes",
+        summary: "Exit codes for scripts and graders",
+        section: "EXIT STATUS",
+        blocks: exit_codes,
+    },
+];
+
+fn paragraph(text: &str) -> Block {
+    Block::Paragraph(text.to_string())
+}
+
+fn example(text: &str) -> Block {
+    Block::Example(text.to_string())
+}
+
+fn item(term: &str, description: &str) -> Block {
+    Block::Item(term.to_string(), description.to_string())
+}
+
+fn machines() -> Vec<Block> {
+    vec![
+        paragraph("A Turing machine is defined using JSON with the following structure:"),
+        example(
+            r#"{
+    "states": ["q0", "q1", "accept", "reject"],
+    "alphabet": ["0", "1"],
+    "tape_alphabet": ["0", "1", "_"],
+    "initial_state": "q0",
+    "accept_states": ["accept"],
+    "reject_states": ["reject"],
+    "blank_symbol": "_",
+    "transitions": {
+        "q0,0": ["q0", "0", "R"],
+        "q0,1": ["q1", "1", "R"],
+        "q1,_": ["accept", "_", "R"]
+    }
+}"#,
+        ),
+        paragraph(
+            r#"Transition format: "state,symbol": [new_state, write_symbol, direction]. Direction: "L" (left), "R" (right), "S" or "N" (stay). A state and symbol without a transition rejects."#,
+        ),
+        paragraph(
+            "Comments (// and /* */) and trailing commas are allowed (JSON5). See the schema topic for all fields.",
+        ),
+        paragraph(
+            "A run reports whether the machine ACCEPTS or REJECTS the input, or DID NOT HALT within the step limit, and the final state reached. A run that comes back to an earlier configuration stops as PROVABLY NON-HALTING.",
+        ),
+    ]
+}
+
+/// The fields of the definition format, from the descriptions in the schema
+fn schema() -> Vec<Block> {
+    let schema: Value = serde_json::from_str(MACHINE_SCHEMA).unwrap_or_default();
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut fields: Vec<(&String, &Value)> = schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .collect();
+    // List the fields in the order they are written in the schema
+    fields.sort_by_key(|(name, _)| MACHINE_SCHEMA.find(&format!("\"{}\":", name)));
+
+    let mut blocks = vec![paragraph(
+        "A machine definition is an object with the following fields. The same fields are used in every file format.",
+    )];
+    for (name, field) in fields {
+        let description = field["description"].as_str().unwrap_or_default();
+        let term = if required.contains(&name.as_str()) {
+            name.to_string()
+        } else {
+            format!("{} (optional)", name)
+        };
+        blocks.push(Block::Item(term, description.to_string()));
+        // Settings grouped in an object, such as normalize_input
+        for (key, setting) in field["properties"].as_object().into_iter().flatten() {
+            let description = setting["description"].as_str().unwrap_or_default();
+            blocks.push(Block::Item(
+                format!("{}.{}", name, key),
+                description.to_string(),
+            ));
+        }
+    }
+    blocks.push(paragraph(
+        r#"Symbols are single characters or longer names without commas or whitespace, such as "blank" or "X1"."#,
+    ));
+    blocks
+}
+
+fn formats() -> Vec<Block> {
+    vec![
+        paragraph("The format of a machine file is chosen by its extension:"),
+        item(".json, .jsonc, .json5", "JSON, read as JSON5 so comments and trailing commas are allowed. Files with an unknown extension are read as JSON."),
+        item(".toml", "TOML, with one table of transitions per state, keyed by the symbol read."),
+        item(".tml", "The machine DSL: one transition per line, written state symbol -> new_state new_symbol direction, and directives such as start q0 and accept done."),
+        item(".yaml, .yml", "YAML, laid out like JSON. Values take the type of their field, so [0, 1] is two symbols."),
+        item(".jff", "JFLAP Turing machine, read only. Final states accept, an empty read or write is the blank, and ~ reads any symbol."),
+        item(".tm", "Morphett's simulator format, read only: one \"state symbol new_symbol direction new_state\" rule per line, with * wildcards and halt states named halt, halt-accept or halt-reject."),
+        item(".tm with directives", "tursi's simulator format, read only: the same rules with l, r and n for the directions, headed by #! start, #! end (the accepting final states), #! states and #! fill (the blank) directives."),
+        item(".tmb", "Compact binary format for very large generated machines."),
+        item(".tmpkg", "Machine bundle: a zip archive of machine.json, metadata.json, tests.json and README.md, so the tests and docs travel with the machine."),
+        item(".xlsx", "Excel spreadsheet: the transitions sheet is a grid of states by symbols read, with \"new_state, write_symbol, direction\" in each cell, and the machine sheet lists the other fields as name and value rows."),
+        paragraph("convert writes a machine in the format of the output file's extension:"),
+        example("turing_machine convert examples/even_ones.json even_ones.toml"),
+        paragraph(
+            "export prints a machine for documents. export <file> mermaid writes a Mermaid state diagram, with the accept and reject states shaded, to paste into a mermaid code block. export <file> tikz writes a TikZ picture for LaTeX, which needs the automata and arrows.meta libraries. export <file> markdown writes the transition table with a row per state and a column per tape symbol, and export <file> csv the same table as CSV. render <file> -o diagram.svg draws the state diagram as an image, SVG or PNG by the extension.",
+        ),
+        paragraph(
+            "qr prints a small machine as a QR code followed by its text payload, which starts with tm1:. load-qr turns a scanned or pasted payload back into a machine file; - reads it from standard input.",
+        ),
+    ]
+}
+
+fn debugger() -> Vec<Block> {
+    vec![
+        paragraph(
+            "The visual step-by-step mode shows the tape around the head, the current state and the transition about to be taken. It is offered by the interactive menu after an input is entered. Steps are computed as they are shown, so long runs start at once. Several inputs separated by ; can be switched between, each keeping its step.",
+        ),
+        item("n, Enter", "Next step"),
+        item("p", "Previous step"),
+        item("j", "Jump to a step by number; Ctrl+C stops a long jump"),
+        item("i", "Toggle the instantaneous descriptions of the last ten steps"),
+        item("<, >", "Previous and next input, if several were entered"),
+        item(
+            "b",
+            "Blame a cell: show the step that last changed it, and offer to jump there",
+        ),
+        item("q", "Quit the visual mode"),
+        paragraph(
+            "grid --visual steps through a grid machine with the same n, p, j and q controls. The number of tape cells shown is set by --tape-width.",
+        ),
+        paragraph(
+            "pda --visual does the same for a pushdown automaton, showing its input and its stack, top first.",
+        ),
+        paragraph(
+            "two-stack --visual shows the two stacks of a two-stack machine side by side, tops in the first row.",
+        ),
+    ]
+}
+
+fn config() -> Vec<Block> {
+    vec![
+        paragraph(
+            "Defaults are read from ~/.config/turing-machine/config.toml (or $XDG_CONFIG_HOME/turing-machine/config.toml, or the file named by TURING_MACHINE_CONFIG):",
+        ),
+        example(
+            r#"max_steps = 50000
+max_memory = "512M"
+max_cells = 1_000_000
+max_head_overshoot = 100
+min_head = 0
+examples_dir = "machines"
+color = "never"
+tape_width = 30
+snapshot_every = 1000
+output_mode = "tape_trimmed""#,
+        ),
+        paragraph(
+            "Each setting can be overridden by an environment variable named after it, such as TURING_MACHINE_MAX_STEPS, and for a single run by the command line option of the same name, such as --max-steps.",
+        ),
+        paragraph(
+            "A machine's \"defaults\" field overrides the config file and environment variables for the limits and output mode it sets, but not the command line options.",
+        ),
+    ]
+}
+
+fn exit_codes() -> Vec<Block> {
+    vec![
+        paragraph("run, race, multihead, nd, amplitude, grid, pda, two-stack, utm and pipeline exit with:"),
+        item("0", "Accepted"),
+        item("1", "Rejected"),
+        item("2", "Step or memory limit reached (did not halt)"),
+        item(
+            "3",
+            "The machine or another file could not be loaded, or is invalid",
+        ),
+        item("4", "Invalid command line arguments"),
+        item("130", "Interrupted with Ctrl+C"),
+        paragraph(
+            "run on a machine computing a function exits with 0 when it halts with an output and 1 when it halts without one.",
+        ),
+        paragraph(
+            "amplitude exits with 0 or 1 when the input is accepted or rejected with a probability over 1/2, and with 2 otherwise.",
+        ),
+        paragraph(
+            "Other commands exit with 0 on success, 3 on errors and 4 on invalid arguments; lint exits with 1 when it reports warnings, and exercise check when a test fails.",
+        ),
+    ]
+}
+
+/// The help topic called `name`
+pub fn topic(name: &str) -> Option<&'static Topic> {
+    TOPICS.iter().find(|topic| topic.name == name)
+}
+
+/// Synopsis of the command called `name`, for usage errors
+pub fn usage(program: &str, name: &str) -> String {
+    let args = COMMANDS
+        .iter()
+        .find(|command| command.name == name)
+        .map_or("", |command| command.args);
+    format!("Usage: {} {} {}", program, name, args)
+        .trim_end()
+        .to_string()
+}
+
+/// Wrap `text` to lines of at most `width` columns after `indent` spaces
+fn wrap(text: &str, indent: usize, width: usize) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && indent + line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    let padding = " ".repeat(indent);
+    lines
+        .iter()
+        .map(|line| format!("{}{}\n", padding, line))
+        .collect()
+}
+
+/// The overview shown by `help` and `--help`
+pub fn overview(program: &str) -> String {
+    let mut text = format!(
+        "Usage: {} [options] [command]\n\nRuns Turing machines and reports whether they accept or reject an input.\nWithout a command, the interactive menu starts.\n\nCommands:\n",
+        program
+    );
+    let width = COMMANDS.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for command in COMMANDS {
+        let _ = writeln!(text, "  {:width$}  {}", command.name, command.summary);
+    }
+    text.push_str("\nOptions:\n");
+    let flag = |flag: &Flag| {
+        format!("{} {}", flag.name, flag.value)
+            .trim_end()
+            .to_string()
+    };
+    let width = FLAGS.iter().map(|f| flag(f).len()).max().unwrap_or(0);
+    for f in FLAGS {
+        let _ = writeln!(text, "  {:width$}  {}", flag(f), f.summary);
+    }
+    let _ = writeln!(text, "\nHelp topics ({} help <topic>):", program);
+    let width = TOPICS.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for topic in TOPICS {
+        let _ = writeln!(text, "  {:width$}  {}", topic.name, topic.summary);
+    }
+    text
+}
+
+/// A help topic as text for the terminal
+pub fn render_topic(topic: &Topic) -> String {
+    const WIDTH: usize = 78;
+    let mut text = format!("{}\n\n", topic.summary);
+    for block in (topic.blocks)() {
+        match block {
+            Block::Paragraph(paragraph) => text.push_str(&wrap(&paragraph, 0, WIDTH)),
+            Block::Example(example) => {
+                for line in example.lines() {
+                    let _ = writeln!(text, "    {}", line);
+                }
+            }
+            Block::Item(term, description) => {
+                let _ = writeln!(text, "  {}", term);
+                text.push_str(&wrap(&description, 6, WIDTH));
+            }
+        }
+        text.push('\n');
+    }
+    text.trim_end().to_string() + "\n"
+}
+
+/// Escape text for roff, including a leading period or quote
+fn roff(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+/// The man page, in roff
+pub fn man_page() -> String {
+    let mut page = format!(
+        ".TH {} 1\n.SH NAME\n{} \\- run and analyze Turing machines\n",
+        PROGRAM.to_uppercase(),
+        PROGRAM
+    );
+    let _ = write!(
+        page,
+        ".SH SYNOPSIS\n.B {}\n[\\fIoptions\\fR] [\\fIcommand\\fR]\n",
+        PROGRAM
+    );
+    page.push_str(".SH DESCRIPTION\nRuns Turing machines and reports whether they accept or reject an input.\nWithout a command, the interactive menu starts.\n");
+
+    page.push_str(".SH COMMANDS\n");
+    for command in COMMANDS {
+        let _ = write!(
+            page,
+            ".TP\n\\fB{}\\fR {}\n{}\n",
+            roff(command.name),
+            roff(command.args),
+            roff(command.summary)
+        );
+    }
+    page.push_str(".SH OPTIONS\n");
+    for flag in FLAGS {
+        let _ = write!(
+            page,
+            ".TP\n\\fB{}\\fR {}\n{}\n",
+            roff(flag.name),
+            roff(flag.value),
+            roff(flag.summary)
+        );
+    }
+
+    for topic in TOPICS {
+        let _ = writeln!(page, ".SH {}", topic.section);
+        for block in (topic.blocks)() {
+            match block {
+                Block::Paragraph(paragraph) => {
+                    let _ = write!(page, ".PP\n{}\n", roff(&paragraph));
+                }
+                Block::Example(example) => {
+                    page.push_str(".PP\n.nf\n.RS 4\n");
+                    for line in example.lines() {
+                        let _ = writeln!(page, "{}", roff(line));
+                    }
+                    page.push_str(".RE\n.fi\n");
+                }
+                Block::Item(term, description) => {
+                    let _ = write!(
+                        page,
+                        ".TP\n\\fB{}\\fR\n{}\n",
+                        roff(&term),
+                        roff(&description)
+                    );
+                }
+            }
+        }
+    }
+    page
+}
@@ -0,0 +1,545 @@
+//! Runs recorded as the changes made by each step
+//!
+//! Keeping a snapshot of every configuration, as
+//! [`SnapshotRecorder`](crate::executor::SnapshotRecorder) does, copies the
+//! whole tape at every step: a million steps over a thousand cells take
+//! gigabytes. [`History`] keeps the initial configuration and, for every
+//! step, only what the step changed: the symbol it wrote over the one it
+//! read, the head position and the state it went to. Any configuration is
+//! rebuilt on demand by a [`Cursor`] replaying the steps up to it.
+//!
+//! The cell a step writes is the one under the head before the step, so it
+//! is not recorded. A head that has moved past either end of the tape finds
+//! a blank there, which the next step adds to the tape, as the executor
+//! does.
+//!
+//! Every delta also holds what undoes its step: the symbol overwritten, the
+//! head position and state before the step, and whether it added a cell to
+//! the tape. A cursor therefore steps back as cheaply as forward, without
+//! any configuration kept along the way.
+//!
+//! A history can also be sampled with [`History::sampled`], keeping the
+//! configuration every `n` steps, a keyframe, and the changes made since
+//! the last keyframe only. A cursor moving between two earlier keyframes
+//! takes the steps from the first of them again, so a run of any length
+//! takes memory for its keyframes alone, and no step is taken again more
+//! than `n` times for a move.
+
+use crate::executor::{self, StepEvent, StepObserver};
+use crate::tape::Tape;
+use crate::{ExecutionSnapshot, SymbolTable, TuringMachine};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+
+/// What one step changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delta {
+    /// Symbol the cell under the head held before the step
+    pub read_symbol: char,
+    /// Symbol the step wrote into that cell
+    pub write_symbol: char,
+    /// Head position after the move, in the tape as it is after the step
+    pub head_position: i32,
+    /// State after the step, as an index into [`History::states`]
+    pub state: u32,
+    /// Head position before the step, in the tape as it was before the step
+    pub from_head: i32,
+    /// State before the step, as an index into [`History::states`]
+    pub from_state: u32,
+    /// Whether the step first added the blank cell the head had moved onto,
+    /// at the front of the tape if `from_head` is negative and at the back
+    /// otherwise
+    pub grew: bool,
+}
+
+/// Where the run stands between two steps, to record what undoes the next
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    head_position: i32,
+    tape_len: usize,
+    state: u32,
+    /// Cells added at the front of the tape so far
+    front: usize,
+}
+
+impl Position {
+    /// Record the step of `event`, which went to `state`, and move past it
+    fn advance(&mut self, event: &StepEvent, state: u32) -> Delta {
+        let from_head = self.head_position;
+        let grew = from_head < 0 || from_head as usize >= self.tape_len;
+        let delta = Delta {
+            read_symbol: event.read_symbol,
+            write_symbol: event.write_symbol,
+            head_position: event.head_position,
+            state,
+            from_head,
+            from_state: self.state,
+            grew,
+        };
+        self.head_position = event.head_position;
+        self.tape_len += usize::from(grew);
+        self.front += usize::from(from_head < 0);
+        self.state = state;
+        delta
+    }
+}
+
+/// A configuration kept by a history
+#[derive(Debug, Clone)]
+struct Keyframe {
+    tape: Vec<char>,
+    head_position: i32,
+    state: u32,
+    /// Cells added at the front of the tape before it was reached
+    front: usize,
+}
+
+/// Observer recording a run as the initial configuration and the change
+/// made by every step
+pub struct History {
+    blank: char,
+    /// First cell of the input in the initial tape, and its length
+    input_start: usize,
+    input_len: usize,
+    /// The initial configuration, followed by one every `every` steps if
+    /// the history is sampled
+    keyframes: Vec<Keyframe>,
+    every: Option<usize>,
+    /// Where the last step recorded left the run
+    position: Position,
+    /// Names of the states reached, indexed by [`Delta::state`]; the initial
+    /// state comes first
+    states: Vec<String>,
+    ids: HashMap<String, u32>,
+    /// The change made by every step since the last keyframe
+    deltas: Vec<Delta>,
+    symbols: Arc<SymbolTable>,
+}
+
+impl History {
+    /// Create a history starting at the initial configuration for
+    /// `input_string`
+    pub fn new(machine: &TuringMachine, input_string: &str) -> Self {
+        // An input that does not fit fails the run itself
+        let input = machine.map_input(input_string).unwrap_or_default();
+        let input_len = input.len();
+        let tape = machine.start_tape(&input).unwrap_or(input);
+        let head_position = machine.tape_setup.head() as i32;
+        History {
+            blank: machine.blank_symbol,
+            input_start: machine.tape_setup.input_start,
+            input_len,
+            position: Position {
+                head_position,
+                tape_len: tape.len(),
+                state: 0,
+                front: 0,
+            },
+            keyframes: vec![Keyframe {
+                tape,
+                head_position,
+                state: 0,
+                front: 0,
+            }],
+            every: None,
+            states: vec![machine.initial_state.clone()],
+            ids: HashMap::from([(machine.initial_state.clone(), 0)]),
+            deltas: Vec::new(),
+            symbols: machine.symbols.clone(),
+        }
+    }
+
+    /// Create a history keeping the configuration every `every` steps, and
+    /// the changes made since the last of them
+    pub fn sampled(machine: &TuringMachine, input_string: &str, every: usize) -> Self {
+        History {
+            every: Some(every.max(1)),
+            ..History::new(machine, input_string)
+        }
+    }
+
+    /// Number of steps recorded
+    pub fn steps(&self) -> usize {
+        self.base() + self.deltas.len()
+    }
+
+    /// Step of the last keyframe, after which [`History::deltas`] starts
+    fn base(&self) -> usize {
+        self.every
+            .map_or(0, |every| (self.keyframes.len() - 1) * every)
+    }
+
+    /// The change made by every step since the last configuration kept: by
+    /// every step, unless the history is sampled
+    pub fn deltas(&self) -> &[Delta] {
+        &self.deltas
+    }
+
+    /// Names of the states, as indexed by [`Delta::state`]
+    pub fn states(&self) -> &[String] {
+        &self.states
+    }
+
+    /// The configuration of a run of `machine` after `step` steps, replayed
+    /// from the closest configuration kept before it; `None` past the last
+    /// step recorded
+    ///
+    /// A [`Cursor`] moving from step to step replays only the steps in
+    /// between.
+    pub fn snapshot(&self, machine: &TuringMachine, step: usize) -> Option<ExecutionSnapshot> {
+        let mut cursor = Cursor::new(self);
+        cursor
+            .seek(machine, self, step)
+            .then(|| cursor.snapshot(self))
+    }
+
+    /// The changes made by the `every` steps from the keyframe at step
+    /// `base`, taken again by `machine`
+    fn resimulate(&self, machine: &TuringMachine, base: usize, every: usize) -> Option<Vec<Delta>> {
+        let keyframe = &self.keyframes[base / every];
+        let start = ExecutionSnapshot {
+            tape: keyframe.tape.clone(),
+            head_position: keyframe.head_position,
+            current_state: self.states[keyframe.state as usize].clone(),
+            step: base,
+            symbols: self.symbols.clone(),
+        };
+        let mut segment = Segment {
+            ids: &self.ids,
+            position: Position {
+                head_position: keyframe.head_position,
+                tape_len: keyframe.tape.len(),
+                state: keyframe.state,
+                front: keyframe.front,
+            },
+            deltas: Vec::with_capacity(every),
+        };
+        let input_start = self.input_start + keyframe.front;
+        executor::replay(
+            machine,
+            &start,
+            input_start,
+            self.input_len,
+            every,
+            &mut segment,
+        )
+        .ok()?;
+        (segment.deltas.len() == every).then_some(segment.deltas)
+    }
+}
+
+impl StepObserver for History {
+    fn on_step(&mut self, event: &StepEvent) {
+        let next = self.ids.len() as u32;
+        let state = match self.ids.get(event.to_state) {
+            Some(&state) => state,
+            None => {
+                self.states.push(event.to_state.to_string());
+                self.ids.insert(event.to_state.to_string(), next);
+                next
+            }
+        };
+        self.deltas.push(self.position.advance(event, state));
+        if self
+            .every
+            .is_some_and(|every| event.step.is_multiple_of(every))
+        {
+            self.keyframes.push(Keyframe {
+                tape: event.tape.to_vec(),
+                head_position: event.head_position,
+                state,
+                front: self.position.front,
+            });
+            self.deltas.clear();
+        }
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.deltas.capacity() * mem::size_of::<Delta>()
+            + self
+                .keyframes
+                .iter()
+                .map(|keyframe| {
+                    mem::size_of::<Keyframe>() + mem::size_of_val(keyframe.tape.as_slice())
+                })
+                .sum::<usize>()
+            + self.states.iter().map(String::len).sum::<usize>()
+    }
+}
+
+/// Observer recording the steps taken again between two keyframes, with
+/// the states numbered as in the history
+struct Segment<'a> {
+    ids: &'a HashMap<String, u32>,
+    position: Position,
+    deltas: Vec<Delta>,
+}
+
+impl StepObserver for Segment<'_> {
+    fn on_step(&mut self, event: &StepEvent) {
+        // Every state reached again was reached by the run recorded
+        if let Some(&state) = self.ids.get(event.to_state) {
+            self.deltas.push(self.position.advance(event, state));
+        }
+    }
+}
+
+/// A configuration of a recorded run, rebuilt by replaying its steps
+///
+/// Moving forward replays the steps in between, and moving back undoes
+/// them. In a sampled history, moving to a step before the last keyframe
+/// starts from the keyframe before it, taking the steps up to the next
+/// keyframe again, and then moves as usual between the two.
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    tape: Tape,
+    head_position: i32,
+    state: u32,
+    step: usize,
+    /// Step the deltas the cursor moves through start at
+    base: usize,
+    /// The changes made by the steps taken again from the keyframe at
+    /// `base`, empty while the cursor moves through those of the history
+    segment: Vec<Delta>,
+}
+
+impl Cursor {
+    /// A cursor at the initial configuration of `history`
+    pub fn new(history: &History) -> Self {
+        let keyframe = &history.keyframes[0];
+        Cursor {
+            tape: Tape::new(keyframe.tape.clone()),
+            head_position: keyframe.head_position,
+            state: keyframe.state,
+            step: 0,
+            base: 0,
+            segment: Vec::new(),
+        }
+    }
+
+    /// Step the cursor is at
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Move to the configuration after `step` steps of `history`, a run of
+    /// `machine`; returns `false`, staying where it is, past the last step
+    /// recorded
+    pub fn seek(&mut self, machine: &TuringMachine, history: &History, step: usize) -> bool {
+        if step > history.steps() {
+            return false;
+        }
+        if let Some(every) = history.every {
+            let last = history.base();
+            let base = if step >= last {
+                last
+            } else {
+                step / every * every
+            };
+            // The history drops the deltas of a keyframe once the next one
+            // is taken, which the cursor may have been moving through
+            if base != self.base || (base < last && self.segment.is_empty()) {
+                let segment = if base < last {
+                    match history.resimulate(machine, base, every) {
+                        Some(segment) => segment,
+                        None => return false,
+                    }
+                } else {
+                    Vec::new()
+                };
+                let keyframe = &history.keyframes[base / every];
+                *self = Cursor {
+                    tape: Tape::new(keyframe.tape.clone()),
+                    head_position: keyframe.head_position,
+                    state: keyframe.state,
+                    step: base,
+                    base,
+                    segment,
+                };
+            }
+        }
+        let segment = mem::take(&mut self.segment);
+        let deltas = if segment.is_empty() {
+            &history.deltas
+        } else {
+            &segment
+        };
+        let (from, to) = (self.step - self.base, step - self.base);
+        if to < from {
+            for delta in deltas[to..from].iter().rev() {
+                self.undo(delta);
+            }
+        } else {
+            for delta in &deltas[from..to] {
+                self.apply(history.blank, delta);
+            }
+        }
+        self.segment = segment;
+        true
+    }
+
+    /// Take the step recorded in `delta`
+    fn apply(&mut self, blank: char, delta: &Delta) {
+        if self.head_position < 0 {
+            self.tape.push_front(blank);
+            self.head_position = 0;
+        }
+        if self.head_position as usize >= self.tape.len() {
+            self.tape.push_back(blank);
+        }
+        self.tape.cells_mut()[self.head_position as usize] = delta.write_symbol;
+        self.head_position = delta.head_position;
+        self.state = delta.state;
+        self.step += 1;
+    }
+
+    /// Take back the step recorded in `delta`, the last one taken
+    fn undo(&mut self, delta: &Delta) {
+        let position = delta.from_head.max(0) as usize;
+        self.tape.cells_mut()[position] = delta.read_symbol;
+        if delta.grew {
+            if delta.from_head < 0 {
+                self.tape.pop_front();
+            } else {
+                self.tape.pop_back();
+            }
+        }
+        self.head_position = delta.from_head;
+        self.state = delta.from_state;
+        self.step -= 1;
+    }
+
+    /// The configuration the cursor is at
+    pub fn snapshot(&self, history: &History) -> ExecutionSnapshot {
+        ExecutionSnapshot {
+            tape: self.tape.cells().to_vec(),
+            head_position: self.head_position,
+            current_state: history.states[self.state as usize].clone(),
+            step: self.step,
+            symbols: history.symbols.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::Executor;
+    use crate::formats::{parse_machine_definition, parse_machine_json};
+
+    /// Machines and inputs whose runs grow the tape at both ends and move
+    /// back and forth over it
+    const RUNS: [(&str, &str); 4] = [
+        (include_str!("../examples/binary_increment.json"), "111"),
+        (include_str!("../examples/binary_increment.json"), "1011"),
+        (include_str!("../examples/palindrome.json"), "0110110"),
+        (include_str!("../examples/palindrome.json"), "10"),
+    ];
+
+    fn load(text: &str) -> TuringMachine {
+        parse_machine_json(&parse_machine_definition(text).unwrap()).unwrap()
+    }
+
+    fn record(machine: &TuringMachine, input: &str, every: Option<usize>) -> History {
+        let mut history = match every {
+            Some(every) => History::sampled(machine, input, every),
+            None => History::new(machine, input),
+        };
+        Executor::new(machine)
+            .observe(&mut history)
+            .run(input, 1000)
+            .unwrap();
+        history
+    }
+
+    fn configuration(snapshot: &ExecutionSnapshot) -> (Vec<char>, i32, String, usize) {
+        (
+            snapshot.tape.clone(),
+            snapshot.head_position,
+            snapshot.current_state.clone(),
+            snapshot.step,
+        )
+    }
+
+    /// Every step forward, every step back, and jumps both ways
+    fn seeks(steps: usize) -> Vec<usize> {
+        let mut seeks: Vec<usize> = (0..=steps).chain((0..=steps).rev()).collect();
+        seeks.extend([steps, 0, steps / 2, 1, steps, steps.saturating_sub(1), 2]);
+        seeks.retain(|&step| step <= steps);
+        seeks
+    }
+
+    /// Check a cursor seeking through the history of each run against the
+    /// snapshots of a step by step run
+    fn check_seeks(every: Option<usize>) {
+        for (text, input) in RUNS {
+            let machine = load(text);
+            let expected = machine.execute_step_by_step(input, 1000).unwrap();
+            let history = record(&machine, input, every);
+            assert_eq!(history.steps() + 1, expected.len());
+
+            let mut cursor = Cursor::new(&history);
+            for step in seeks(history.steps()) {
+                assert!(cursor.seek(&machine, &history, step));
+                assert_eq!(
+                    configuration(&cursor.snapshot(&history)),
+                    configuration(&expected[step]),
+                    "{:?} on {:?} at step {}, keeping every {:?}",
+                    input,
+                    text,
+                    step,
+                    every
+                );
+            }
+            assert!(!cursor.seek(&machine, &history, history.steps() + 1));
+        }
+    }
+
+    #[test]
+    fn seeks_forward_and_back_like_a_step_by_step_run() {
+        check_seeks(None);
+    }
+
+    #[test]
+    fn seeks_through_sampled_histories() {
+        check_seeks(Some(1));
+        check_seeks(Some(3));
+    }
+
+    #[test]
+    fn undoes_growth_at_both_ends() {
+        let machine = load(include_str!("../examples/binary_increment.json"));
+        let history = record(&machine, "111", None);
+        let grew: Vec<i32> = history
+            .deltas()
+            .iter()
+            .filter(|delta| delta.grew)
+            .map(|delta| delta.from_head)
+            .collect();
+        assert_eq!(grew, [3, -1]);
+
+        let mut cursor = Cursor::new(&history);
+        cursor.seek(&machine, &history, history.steps());
+        assert_eq!(cursor.snapshot(&history).tape, ['1', '0', '0', '0', '_']);
+        cursor.seek(&machine, &history, 0);
+        assert_eq!(cursor.snapshot(&history).tape, ['1', '1', '1']);
+    }
+
+    #[test]
+    fn sampled_histories_keep_only_recent_deltas() {
+        let machine = load(include_str!("../examples/palindrome.json"));
+        let history = record(&machine, "0110110", Some(3));
+        assert!(history.steps() > 6);
+        assert_eq!(history.deltas().len(), history.steps() % 3);
+
+        // Before the last keyframe, the steps are taken again
+        let last = history.steps() / 3 * 3;
+        let expected = machine.execute_step_by_step("0110110", 1000).unwrap();
+        for step in [last - 1, last - 3, 1, last] {
+            let snapshot = history.snapshot(&machine, step).unwrap();
+            assert_eq!(configuration(&snapshot), configuration(&expected[step]));
+        }
+        assert!(history.snapshot(&machine, history.steps() + 1).is_none());
+    }
+}
@@ -0,0 +1,204 @@
+//! Descriptive metadata of machines, for front-ends
+//!
+//! A definition may carry a `metadata` object with a name, a description,
+//! tags, sample inputs with their expected results, and documentation:
+//!
+//! ```json
+//! "metadata": {
+//!     "name": "Even number of 1s",
+//!     "tags": ["regular", "parity"],
+//!     "tests": [{"input": "0110", "accepts": true}]
+//! }
+//! ```
+//!
+//! None of it affects runs. [`MachineInfo`] gathers the metadata with the
+//! alphabets and sizes of a loaded machine, as one serializable summary a
+//! GUI can show without parsing the definition itself. [`ShortInfo`] is the
+//! few numbers a script needs, on one line.
+
+#[cfg(feature = "formats")]
+use crate::nondeterministic::NondeterministicMachine;
+use crate::race::{count_inputs_of_length, inputs_of_length};
+use crate::TuringMachine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Descriptive fields of a definition
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// What the machine does, in a sentence or two
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Keywords for grouping and searching machines
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Sample inputs with their expected results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<MachineTest>,
+    /// Longer documentation, such as Markdown text or a link to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+}
+
+impl Metadata {
+    /// Whether no field is set
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+}
+
+/// A sample input and the result a run on it should have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MachineTest {
+    pub input: String,
+    /// Whether the machine accepts the input, if it decides a language
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepts: Option<bool>,
+    /// The output, if the machine computes a function
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+/// Summary of a machine for presentation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MachineInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// States, sorted
+    pub states: Vec<String>,
+    pub initial_state: String,
+    /// Accept states, sorted
+    pub accept_states: Vec<String>,
+    /// Reject states, sorted
+    pub reject_states: Vec<String>,
+    /// Input symbols by name, sorted
+    pub alphabet: Vec<String>,
+    /// Tape symbols by name, sorted
+    pub tape_alphabet: Vec<String>,
+    pub blank_symbol: String,
+    pub state_count: usize,
+    pub transition_count: usize,
+    /// Whether the machine computes a function rather than deciding a
+    /// language
+    pub computes_output: bool,
+    pub tags: Vec<String>,
+    pub tests: Vec<MachineTest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+}
+
+impl From<&TuringMachine> for MachineInfo {
+    fn from(machine: &TuringMachine) -> Self {
+        let symbols = machine.symbols();
+        let sorted = |names: &mut dyn Iterator<Item = String>| {
+            let mut names: Vec<String> = names.collect();
+            names.sort();
+            names
+        };
+        let metadata = machine.metadata().clone();
+        MachineInfo {
+            name: metadata.name,
+            description: metadata.description,
+            states: sorted(&mut machine.states.iter().cloned()),
+            initial_state: machine.initial_state.clone(),
+            accept_states: sorted(&mut machine.accept_states.iter().cloned()),
+            reject_states: sorted(&mut machine.reject_states.iter().cloned()),
+            alphabet: sorted(&mut machine.alphabet.iter().map(|&s| symbols.name(s))),
+            tape_alphabet: sorted(&mut machine.tape_alphabet.iter().map(|&s| symbols.name(s))),
+            blank_symbol: symbols.name(machine.blank_symbol),
+            state_count: machine.states.len(),
+            transition_count: machine.transitions.len(),
+            computes_output: machine.output().is_some(),
+            tags: metadata.tags,
+            tests: metadata.tests,
+            docs: metadata.docs,
+        }
+    }
+}
+
+/// Inputs up to this length are run to check that a machine is total
+pub const SHORT_TOTAL_LENGTH: usize = 8;
+
+/// Most inputs run for the totality check; larger input alphabets leave it
+/// open
+pub const SHORT_TOTAL_MAX_INPUTS: usize = 10_000;
+
+/// One-line summary of a machine for shell prompts, Makefiles and hooks
+///
+/// Displays as `states=12 trans=40 det=yes total≤8=yes`, where `total`
+/// tells whether every input of up to [`SHORT_TOTAL_LENGTH`] symbols halts
+/// within the step limit, and is `?` if that was not checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortInfo {
+    pub states: usize,
+    /// Transitions, counting every target of a non-deterministic one
+    pub transitions: usize,
+    pub deterministic: bool,
+    pub total: Option<bool>,
+}
+
+impl ShortInfo {
+    /// Summarize `machine`, running it on short inputs for at most
+    /// `max_steps` steps each
+    pub fn new(machine: &TuringMachine, max_steps: usize) -> Self {
+        ShortInfo {
+            states: machine.states.len(),
+            transitions: machine.transitions.len(),
+            deterministic: true,
+            total: halts_on_short_inputs(machine, max_steps),
+        }
+    }
+
+    /// Summarize a non-deterministic machine, without checking totality
+    #[cfg(feature = "formats")]
+    pub fn nondeterministic(machine: &NondeterministicMachine) -> Self {
+        let targets = machine.transitions().values();
+        ShortInfo {
+            states: machine.states().len(),
+            transitions: targets.clone().map(Vec::len).sum(),
+            deterministic: targets.into_iter().all(|targets| targets.len() == 1),
+            total: None,
+        }
+    }
+}
+
+impl fmt::Display for ShortInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        write!(
+            f,
+            "states={} trans={} det={} total≤{}={}",
+            self.states,
+            self.transitions,
+            yes_no(self.deterministic),
+            SHORT_TOTAL_LENGTH,
+            self.total.map_or("?", yes_no)
+        )
+    }
+}
+
+/// Whether every input of up to [`SHORT_TOTAL_LENGTH`] symbols halts within
+/// `max_steps`, or `None` if there are too many of them
+fn halts_on_short_inputs(machine: &TuringMachine, max_steps: usize) -> Option<bool> {
+    let mut count = 0usize;
+    for length in 0..=SHORT_TOTAL_LENGTH {
+        count = count.checked_add(count_inputs_of_length(machine, length)?)?;
+    }
+    if count > SHORT_TOTAL_MAX_INPUTS {
+        return None;
+    }
+    let halts = (0..=SHORT_TOTAL_LENGTH)
+        .flat_map(|length| inputs_of_length(machine, length))
+        .all(|input| {
+            machine
+                .execute(&input, max_steps)
+                .is_ok_and(|result| result.halted)
+        });
+    Some(halts)
+}
@@ -0,0 +1,82 @@
+//! Turing machine executor
+//!
+//! The core engine (machine model, builder, executor and analyses) depends
+//! only on `serde` and `tracing`. Machine files, and everything loaded from
+//! them, need the `formats` feature; spreadsheets, QR codes and content
+//! packs add `xlsx`, `qr` and `packs`, state diagrams rendered as images
+//! add `image`, the HTTP server adds `server` and its gRPC service `grpc`,
+//! the language server for the DSL `lsp`, and running batches of inputs
+//! on all CPU cores `parallel`.
+//! Colored rendering of state diagrams and tapes, used by the interactive
+//! program, is available with the `cli` feature, which enables all the
+//! others but `wasm`.
+
+#[cfg(feature = "formats")]
+pub mod amplitude;
+pub mod analysis;
+pub mod batch;
+pub mod bench;
+pub mod blame;
+pub mod builder;
+pub mod cellular;
+#[cfg(feature = "formats")]
+pub mod daemon;
+#[cfg(feature = "formats")]
+pub mod determinize;
+pub mod diagram;
+#[cfg(feature = "cli")]
+mod display;
+pub mod dovetail;
+pub mod executor;
+pub mod formal;
+#[cfg(feature = "formats")]
+pub mod formats;
+pub mod godel;
+pub mod golden;
+#[cfg(feature = "formats")]
+pub mod grid;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod history;
+pub mod info;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod machine;
+#[cfg(feature = "formats")]
+pub mod multihead;
+#[cfg(feature = "formats")]
+pub mod nondeterministic;
+#[cfg(feature = "packs")]
+pub mod pack;
+#[cfg(feature = "formats")]
+pub mod pda;
+#[cfg(feature = "formats")]
+pub mod pipeline;
+mod program;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod race;
+mod recurrence;
+#[cfg(feature = "image")]
+pub mod render;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod suspicious;
+pub mod symbols;
+pub mod table;
+mod tape;
+pub mod trajectory;
+#[cfg(feature = "formats")]
+pub mod twostack;
+pub mod universal;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use builder::TuringMachineBuilder;
+#[cfg(feature = "formats")]
+pub use formats::{parse_machine_definition, parse_machine_json, MachineJson};
+pub use machine::{
+    Cycle, Direction, ExecutionResult, ExecutionSnapshot, InputNormalization, LeftEdge, Output,
+    ResourceLimit, TapeSetup, TransitionKey, TransitionTarget, TuringMachine,
+};
+pub use symbols::SymbolTable;
@@ -0,0 +1,368 @@
+//! Language server for the machine DSL
+//!
+//! `turing_machine lsp` speaks the Language Server Protocol over stdin and
+//! stdout, so editors such as VS Code and Neovim check `.tml` files as
+//! they are written. It offers:
+//!
+//! - diagnostics: the errors [`from_dsl`] reports, every duplicate
+//!   transition, and warnings for states that have no transitions and are
+//!   not declared with `states`, `accept` or `reject`, which usually are
+//!   typos
+//! - go to definition: from any state to its first transition, or to where
+//!   it is declared if it has none
+//! - hover: what a state is, its transitions and how many lead to it
+//!
+//! Documents are synchronized in full on every change; programs are small.
+
+use crate::formats::dsl::{self, Mention, Outline, Role};
+use crate::formats::from_dsl;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+use std::collections::HashMap;
+
+/// Name the diagnostics are reported under
+const SOURCE: &str = "turing_machine";
+
+/// Error codes of the protocol
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Serve editors over stdin and stdout until they shut the server down
+pub fn run() -> Result<(), String> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(connection)?;
+    io_threads.join().map_err(|e| e.to_string())
+}
+
+/// Serve an editor over a connection until it shuts the server down
+pub fn serve(connection: Connection) -> Result<(), String> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    };
+    let capabilities = serde_json::to_value(capabilities).map_err(|e| e.to_string())?;
+    connection
+        .initialize(capabilities)
+        .map_err(|e| e.to_string())?;
+
+    // Texts by URI, as text since `Uri` has interior mutability
+    let mut documents: HashMap<String, String> = HashMap::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection
+                    .handle_shutdown(&request)
+                    .map_err(|e| e.to_string())?
+                {
+                    break;
+                }
+                let response = respond(&documents, request);
+                connection
+                    .sender
+                    .send(Message::Response(response))
+                    .map_err(|e| e.to_string())?;
+            }
+            Message::Notification(notification) => {
+                let Some((uri, text)) = update(&mut documents, notification) else {
+                    continue;
+                };
+                let params = PublishDiagnosticsParams::new(
+                    uri,
+                    text.as_deref().map_or_else(Vec::new, diagnostics),
+                    None,
+                );
+                connection
+                    .sender
+                    .send(Message::Notification(Notification::new(
+                        PublishDiagnostics::METHOD.to_string(),
+                        params,
+                    )))
+                    .map_err(|e| e.to_string())?;
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Apply a change to the open documents, returning the document changed
+/// and its text, or `None` as the text of a closed one
+fn update(
+    documents: &mut HashMap<String, String>,
+    notification: Notification,
+) -> Option<(Uri, Option<String>)> {
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params = notification
+                .extract::<DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+                .ok()?;
+            let document = params.text_document;
+            documents.insert(document.uri.to_string(), document.text.clone());
+            Some((document.uri, Some(document.text)))
+        }
+        DidChangeTextDocument::METHOD => {
+            let params = notification
+                .extract::<DidChangeTextDocumentParams>(DidChangeTextDocument::METHOD)
+                .ok()?;
+            // Changes are whole documents, so the last one is the text
+            let text = params.content_changes.into_iter().last()?.text;
+            let uri = params.text_document.uri;
+            documents.insert(uri.to_string(), text.clone());
+            Some((uri, Some(text)))
+        }
+        DidCloseTextDocument::METHOD => {
+            let params = notification
+                .extract::<DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+                .ok()?;
+            let uri = params.text_document.uri;
+            documents.remove(uri.as_str());
+            Some((uri, None))
+        }
+        _ => None,
+    }
+}
+
+/// Answer a request about an open document
+fn respond(documents: &HashMap<String, String>, request: Request) -> Response {
+    let id = request.id.clone();
+    match request.method.as_str() {
+        GotoDefinition::METHOD => {
+            match request.extract::<GotoDefinitionParams>(GotoDefinition::METHOD) {
+                Ok((id, params)) => {
+                    let at = params.text_document_position_params;
+                    let uri = at.text_document.uri;
+                    let location = documents
+                        .get(uri.as_str())
+                        .and_then(|text| definition(text, at.position))
+                        .map(|range| GotoDefinitionResponse::Scalar(Location::new(uri, range)));
+                    Response::new_ok(id, location)
+                }
+                Err(e) => Response::new_err(id, INVALID_PARAMS, format!("{:?}", e)),
+            }
+        }
+        HoverRequest::METHOD => match request.extract::<HoverParams>(HoverRequest::METHOD) {
+            Ok((id, params)) => {
+                let at = params.text_document_position_params;
+                let hover = documents
+                    .get(at.text_document.uri.as_str())
+                    .and_then(|text| hover(text, at.position));
+                Response::new_ok(id, hover)
+            }
+            Err(e) => Response::new_err(id, INVALID_PARAMS, format!("{:?}", e)),
+        },
+        method => Response::new_err(id, METHOD_NOT_FOUND, format!("unknown method {}", method)),
+    }
+}
+
+/// Problems with a program, errors first
+fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let diagnostic = |range, severity, message: String| Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some(SOURCE.to_string()),
+        message,
+        ..Diagnostic::default()
+    };
+    let mut found = Vec::new();
+    if let Err(errors) = from_dsl(text) {
+        for error in errors.lines() {
+            let (range, message) = match located(error) {
+                Some((line, column, message)) => (word_range(text, line, column), message),
+                None => (word_range(text, 1, 1), error),
+            };
+            found.push(diagnostic(
+                range,
+                DiagnosticSeverity::ERROR,
+                message.to_string(),
+            ));
+        }
+    }
+
+    // The parser stops at the first duplicate; report them all
+    let outline = dsl::outline(text);
+    let mut first: HashMap<(&str, &str), usize> = HashMap::new();
+    for transition in &outline.transitions {
+        let key = (transition.state.as_str(), transition.symbol.as_str());
+        let Some(&line) = first.get(&key) else {
+            first.insert(key, transition.line);
+            continue;
+        };
+        let range = word_range(text, transition.line, transition.column);
+        let message = dsl::duplicate(&transition.state, &transition.symbol, line);
+        if !found
+            .iter()
+            .any(|d| d.range == range && d.message == message)
+        {
+            found.push(diagnostic(range, DiagnosticSeverity::ERROR, message));
+        }
+    }
+
+    for mention in &outline.states {
+        if !defined(&outline, &mention.name) {
+            found.push(diagnostic(
+                mention_range(text, mention),
+                DiagnosticSeverity::WARNING,
+                format!(
+                    "state {} has no transitions and is not declared with `states`, `accept` or `reject`",
+                    mention.name
+                ),
+            ));
+        }
+    }
+    found
+}
+
+/// Whether a state has transitions or is declared as a state of its own
+fn defined(outline: &Outline, name: &str) -> bool {
+    outline.states.iter().any(|mention| {
+        mention.name == name
+            && matches!(
+                mention.role,
+                Role::Source | Role::Directive("states" | "accept" | "reject")
+            )
+    })
+}
+
+/// Where a state under the cursor is defined
+fn definition(text: &str, position: Position) -> Option<Range> {
+    let outline = dsl::outline(text);
+    let name = &mention_at(text, &outline, position)?.name;
+    let named = || outline.states.iter().filter(|m| m.name == *name);
+    let definition = named()
+        .find(|m| m.role == Role::Source)
+        .or_else(|| named().find(|m| m.role != Role::Target))?;
+    Some(mention_range(text, definition))
+}
+
+/// A description of the state under the cursor
+fn hover(text: &str, position: Position) -> Option<Hover> {
+    let outline = dsl::outline(text);
+    let mention = mention_at(text, &outline, position)?;
+    let name = &mention.name;
+    let declared = |directive: &'static str| {
+        outline
+            .states
+            .iter()
+            .any(|m| m.name == *name && m.role == Role::Directive(directive))
+    };
+    let kinds: Vec<&str> = [
+        ("start", "initial"),
+        ("accept", "accepting"),
+        ("reject", "rejecting"),
+    ]
+    .iter()
+    .filter(|(directive, _)| declared(directive))
+    .map(|(_, kind)| *kind)
+    .collect();
+
+    let mut value = format!("**state** `{}`", name);
+    if !kinds.is_empty() {
+        value.push_str(&format!(" ({})", kinds.join(", ")));
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    let transitions: Vec<&str> = outline
+        .transitions
+        .iter()
+        .filter(|t| t.state == *name)
+        .filter_map(|t| lines.get(t.line - 1))
+        .map(|line| line.trim())
+        .collect();
+    if transitions.is_empty() {
+        value.push_str("\n\nNo transitions");
+    } else {
+        value.push_str(&format!("\n\n```\n{}\n```", transitions.join("\n")));
+    }
+    let incoming = outline
+        .states
+        .iter()
+        .filter(|m| m.name == *name && m.role == Role::Target)
+        .count();
+    value.push_str(&format!(
+        "\n\n{} transition{} lead{} here",
+        incoming,
+        if incoming == 1 { "" } else { "s" },
+        if incoming == 1 { "s" } else { "" },
+    ));
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(mention_range(text, mention)),
+    })
+}
+
+/// The state written at a position
+fn mention_at<'a>(text: &str, outline: &'a Outline, position: Position) -> Option<&'a Mention> {
+    let line = position.line as usize + 1;
+    let row = text.lines().nth(line - 1)?;
+    let column = char_column(row, position.character);
+    outline
+        .states
+        .iter()
+        .find(|m| m.line == line && (m.column..m.column + m.width).contains(&column))
+}
+
+/// Split `line:column: message`
+fn located(error: &str) -> Option<(usize, usize, &str)> {
+    let (line, rest) = error.split_once(':')?;
+    let (column, message) = rest.split_once(": ")?;
+    Some((line.parse().ok()?, column.parse().ok()?, message))
+}
+
+fn mention_range(text: &str, mention: &Mention) -> Range {
+    range(text, mention.line, mention.column, mention.width)
+}
+
+/// The range of the word starting at a one-based line and column
+fn word_range(text: &str, line: usize, column: usize) -> Range {
+    let row = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let width = row
+        .chars()
+        .skip(column.saturating_sub(1))
+        .take_while(|c| !c.is_whitespace())
+        .count();
+    range(text, line, column, width)
+}
+
+/// A range of `width` characters from a one-based line and column
+///
+/// Positions in the protocol count UTF-16 code units, not characters.
+fn range(text: &str, line: usize, column: usize, width: usize) -> Range {
+    let row = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let units = |chars: usize| -> u32 {
+        row.chars().take(chars).map(char::len_utf16).sum::<usize>() as u32
+    };
+    let start = column.saturating_sub(1);
+    let line = line.saturating_sub(1) as u32;
+    Range::new(
+        Position::new(line, units(start)),
+        Position::new(line, units(start + width)),
+    )
+}
+
+/// The one-based column, in characters, of a position in UTF-16 code units
+fn char_column(row: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in row.chars().enumerate() {
+        units += c.len_utf16() as u32;
+        if units > character {
+            return i + 1;
+        }
+    }
+    row.chars().count() + 1
+}
@@ -0,0 +1,761 @@
+//! The Turing machine model and its execution results
+
+use crate::builder::TuringMachineBuilder;
+use crate::executor::{self, AsyncOptions, Executor, RunConfig, RunDefaults, SnapshotRecorder};
+use crate::info::Metadata;
+use crate::program::Program;
+use crate::symbols::SymbolTable;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
+/// Represents the direction the Turing machine head can move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    L, // Left
+    R, // Right
+    #[serde(alias = "N")]
+    S, // Stay
+}
+
+/// Result of executing a Turing machine
+#[derive(Debug, Serialize)]
+pub struct ExecutionResult {
+    pub accepts: Option<bool>, // True if accepts, False if rejects, None if didn't halt
+    pub final_state: String,
+    pub steps: usize,
+    pub halted: bool,
+    /// Tape contents, with named symbols written out by their names
+    pub tape: String,
+    /// Tape contents, the name of the symbol in each cell
+    pub tape_cells: Vec<String>,
+    /// Head position, in cells of `tape_cells`, when the run ended; it is
+    /// `-1` or the tape length if the head just moved onto a blank not yet
+    /// on the tape
+    pub head_position: i32,
+    /// True if the run was stopped early through [`Executor::interrupt_on`]
+    ///
+    /// [`Executor::interrupt_on`]: crate::executor::Executor::interrupt_on
+    pub interrupted: bool,
+    /// True if the run was stopped by [`RunConfig::max_memory_bytes`]
+    ///
+    /// [`RunConfig::max_memory_bytes`]: crate::executor::RunConfig::max_memory_bytes
+    pub memory_limit_exceeded: bool,
+    /// The limit on the resources of the run that stopped it, if one did;
+    /// [`ResourceLimit::Memory`] whenever `memory_limit_exceeded` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_limit: Option<ResourceLimit>,
+    /// True if the run was stopped by [`RunConfig::timeout`]
+    ///
+    /// [`RunConfig::timeout`]: crate::executor::RunConfig::timeout
+    pub timed_out: bool,
+    /// Largest approximate memory used by the tape and observers during the run
+    pub peak_memory_bytes: usize,
+    /// What a machine computing a function left on the tape, read as set by
+    /// [`TuringMachine::set_output`]; `None` if it has no output or did not
+    /// halt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// The configuration the run came back to, which proves the machine
+    /// never halts on this input; the run stops as soon as it is found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<Cycle>,
+}
+
+/// A configuration repeated during a run: same state, head cell and tape
+/// contents, or the same configuration moved along the tape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Cycle {
+    /// Steps between the two occurrences of the configuration
+    pub length: usize,
+    /// Step at which the configuration came back
+    pub step: usize,
+    /// Cells the configuration moved right, negative for left, in every
+    /// round of the cycle; 0 for a configuration repeated in place
+    pub shift: i64,
+}
+
+/// A limit on what a run may use, which stopped it without a verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceLimit {
+    /// The tape and observers used more than
+    /// [`RunConfig::max_memory_bytes`](crate::executor::RunConfig::max_memory_bytes)
+    Memory,
+    /// The tape needed more cells than
+    /// [`RunConfig::max_cells`](crate::executor::RunConfig::max_cells)
+    Cells,
+}
+
+/// State snapshot during step-by-step execution
+#[derive(Debug, Clone)]
+pub struct ExecutionSnapshot {
+    /// Tape contents, named symbols standing as the characters of `symbols`
+    pub tape: Vec<char>,
+    pub head_position: i32,
+    pub current_state: String,
+    pub step: usize,
+    /// The names of the machine's symbols
+    pub symbols: Arc<SymbolTable>,
+}
+
+impl ExecutionSnapshot {
+    /// Tape contents, the name of the symbol in each cell
+    pub fn tape_names(&self) -> Vec<String> {
+        self.symbols.names(&self.tape)
+    }
+}
+
+/// How inputs are cleaned up before they are checked and run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputNormalization {
+    /// Drop whitespace that is not an input symbol
+    pub strip_whitespace: bool,
+    /// Replace a symbol that is not an input symbol by its other case if
+    /// that one is
+    pub fold_case: bool,
+}
+
+impl InputNormalization {
+    /// Whether no normalization is applied
+    pub fn is_none(&self) -> bool {
+        *self == InputNormalization::default()
+    }
+}
+
+/// The tape before the input is placed, and where the input and head start
+///
+/// Cells are numbered from 0, the leftmost cell of the initial tape. By
+/// default the tape is blank, the input starts at cell 0 and so does the
+/// head.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TapeSetup {
+    /// Symbols written from cell 0 before the input; the input overwrites
+    /// the cells it covers
+    pub contents: Vec<char>,
+    /// Cell holding the first input symbol
+    pub input_start: usize,
+    /// Cell the head starts on, the first input cell if `None`
+    pub head_start: Option<usize>,
+}
+
+impl TapeSetup {
+    /// Whether this is the default blank tape with the input at cell 0
+    pub fn is_default(&self) -> bool {
+        *self == TapeSetup::default()
+    }
+
+    /// Cell the head starts on
+    pub fn head(&self) -> usize {
+        self.head_start.unwrap_or(self.input_start)
+    }
+}
+
+/// What happens when a machine with a left-bounded tape moves left from the
+/// first cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeftEdge {
+    /// The head stays on the first cell
+    Stay,
+    /// The run fails with an error naming the step
+    Crash,
+    /// The machine halts and rejects, as for an undefined transition
+    Reject,
+}
+
+/// Where a machine computing a function leaves its output on the tape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// The tape without its leading and trailing blanks
+    Tape,
+    /// The cells after the first `start` marker, up to the next `end` marker
+    Between(char, char),
+}
+
+/// Key of the transition function: (state, symbol read)
+pub type TransitionKey = (String, char);
+
+/// Value of the transition function: (new state, symbol written, head move)
+pub type TransitionTarget = (String, char, Direction);
+
+/// A Turing machine executor
+#[derive(Debug)]
+pub struct TuringMachine {
+    pub(crate) states: HashSet<String>,
+    pub(crate) alphabet: HashSet<char>,
+    pub(crate) tape_alphabet: HashSet<char>,
+    pub(crate) transitions: HashMap<(String, char), (String, char, Direction)>,
+    pub(crate) initial_state: String,
+    pub(crate) accept_states: HashSet<String>,
+    pub(crate) reject_states: HashSet<String>,
+    pub(crate) blank_symbol: char,
+    pub(crate) read_only_input: bool,
+    pub(crate) accept_on_halt: bool,
+    pub(crate) input_map: HashMap<char, char>,
+    pub(crate) input_normalization: InputNormalization,
+    pub(crate) symbols: Arc<SymbolTable>,
+    pub(crate) left_edge: Option<LeftEdge>,
+    pub(crate) tape_length: Option<usize>,
+    pub(crate) tape_setup: TapeSetup,
+    pub(crate) output: Option<Output>,
+    pub(crate) run_defaults: RunDefaults,
+    pub(crate) metadata: Metadata,
+    /// Readable names for tape symbols, kept to write the definition back;
+    /// the transitions already use the symbols they stand for
+    pub(crate) symbol_aliases: BTreeMap<String, String>,
+    /// The states and transitions numbered for the executor, compiled on
+    /// the first run and dropped when they change
+    program: OnceLock<Program>,
+}
+
+impl TuringMachine {
+    /// Create a new Turing machine
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        states: HashSet<String>,
+        alphabet: HashSet<char>,
+        tape_alphabet: HashSet<char>,
+        transitions: HashMap<(String, char), (String, char, Direction)>,
+        initial_state: String,
+        accept_states: HashSet<String>,
+        reject_states: HashSet<String>,
+        blank_symbol: char,
+    ) -> Result<Self, String> {
+        // Validate input
+        if !states.contains(&initial_state) {
+            return Err(format!("Initial state {} not in states", initial_state));
+        }
+        if !accept_states.is_subset(&states) {
+            return Err("Accept states must be subset of states".to_string());
+        }
+        if !reject_states.is_subset(&states) {
+            return Err("Reject states must be subset of states".to_string());
+        }
+        if !accept_states.is_disjoint(&reject_states) {
+            return Err("Accept and reject states must be disjoint".to_string());
+        }
+        if !tape_alphabet.contains(&blank_symbol) {
+            return Err(format!(
+                "Blank symbol {} not in tape alphabet",
+                blank_symbol
+            ));
+        }
+
+        Ok(TuringMachine {
+            states,
+            alphabet,
+            tape_alphabet,
+            transitions,
+            initial_state,
+            accept_states,
+            reject_states,
+            blank_symbol,
+            read_only_input: false,
+            accept_on_halt: false,
+            input_map: HashMap::new(),
+            input_normalization: InputNormalization::default(),
+            symbols: Arc::default(),
+            left_edge: None,
+            tape_length: None,
+            tape_setup: TapeSetup::default(),
+            output: None,
+            run_defaults: RunDefaults::default(),
+            metadata: Metadata::default(),
+            symbol_aliases: BTreeMap::new(),
+            program: OnceLock::new(),
+        })
+    }
+
+    /// Add or replace a single transition, returning the one it replaces
+    ///
+    /// Only the edited transition is validated, so editing a large machine
+    /// does not re-check the whole definition. Pair this with
+    /// [`Analysis::update`](crate::analysis::Analysis::update) to keep an
+    /// analysis current.
+    pub fn set_transition(
+        &mut self,
+        key: TransitionKey,
+        target: TransitionTarget,
+    ) -> Result<Option<TransitionTarget>, String> {
+        for state in [&key.0, &target.0] {
+            if !self.states.contains(state) {
+                return Err(format!("State {} not in states", state));
+            }
+        }
+        for symbol in [key.1, target.1] {
+            if !self.tape_alphabet.contains(&symbol) {
+                return Err(format!(
+                    "Symbol {} not in tape alphabet",
+                    self.symbols.name(symbol)
+                ));
+            }
+        }
+        self.program.take();
+        Ok(self.transitions.insert(key, target))
+    }
+
+    /// Remove a single transition, returning it if it existed
+    pub fn remove_transition(&mut self, key: &TransitionKey) -> Option<TransitionTarget> {
+        self.program.take();
+        self.transitions.remove(key)
+    }
+
+    /// Make the transition function total: give every state that does not
+    /// halt a transition on every tape symbol, returning the number added
+    ///
+    /// An added transition leaves the cell as it is, stays, and goes to a
+    /// reject state: the first reject state by name, or a new state named
+    /// `reject`. If the machine accepts by halting it goes to an accept state
+    /// instead, so the machine still accepts the same inputs. Either way a run
+    /// ending for want of a transition now takes one more step.
+    pub fn complete(&mut self) -> usize {
+        let (halting, name) = if self.accept_on_halt {
+            (&mut self.accept_states, "accept")
+        } else {
+            (&mut self.reject_states, "reject")
+        };
+        let target = match halting.iter().min() {
+            Some(state) => state.clone(),
+            None => {
+                let mut state = name.to_string();
+                let mut suffix = 1;
+                while self.states.contains(&state) {
+                    suffix += 1;
+                    state = format!("{}_{}", name, suffix);
+                }
+                halting.insert(state.clone());
+                self.states.insert(state.clone());
+                state
+            }
+        };
+
+        let mut added = 0;
+        for state in &self.states {
+            if self.accept_states.contains(state) || self.reject_states.contains(state) {
+                continue;
+            }
+            for &symbol in &self.tape_alphabet {
+                self.transitions
+                    .entry((state.clone(), symbol))
+                    .or_insert_with(|| {
+                        added += 1;
+                        (target.clone(), symbol, Direction::S)
+                    });
+            }
+        }
+        self.program.take();
+        added
+    }
+
+    /// The machine compiled for the executor
+    pub(crate) fn program(&self) -> &Program {
+        self.program.get_or_init(|| Program::new(self))
+    }
+
+    /// Start building a machine with [`TuringMachineBuilder`]
+    pub fn builder() -> TuringMachineBuilder {
+        TuringMachineBuilder::new()
+    }
+
+    /// All state names
+    pub fn states(&self) -> &HashSet<String> {
+        &self.states
+    }
+
+    /// Symbols allowed in the input string
+    pub fn alphabet(&self) -> &HashSet<char> {
+        &self.alphabet
+    }
+
+    /// Symbols allowed on the tape
+    pub fn tape_alphabet(&self) -> &HashSet<char> {
+        &self.tape_alphabet
+    }
+
+    /// The transition function
+    pub fn transitions(&self) -> &HashMap<TransitionKey, TransitionTarget> {
+        &self.transitions
+    }
+
+    /// Name of the starting state
+    pub fn initial_state(&self) -> &str {
+        &self.initial_state
+    }
+
+    /// Accepting state names
+    pub fn accept_states(&self) -> &HashSet<String> {
+        &self.accept_states
+    }
+
+    /// Rejecting state names
+    pub fn reject_states(&self) -> &HashSet<String> {
+        &self.reject_states
+    }
+
+    /// Symbol representing empty tape cells
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    /// Whether the input cells are read-only, see [`set_read_only_input`]
+    ///
+    /// [`set_read_only_input`]: TuringMachine::set_read_only_input
+    pub fn read_only_input(&self) -> bool {
+        self.read_only_input
+    }
+
+    /// Protect the cells holding the input from being overwritten
+    ///
+    /// A run that writes a different symbol into one of them fails with an
+    /// error naming the step and cell, which catches machines that destroy
+    /// their input before reading all of it. Writing back the symbol that was
+    /// read is allowed.
+    pub fn set_read_only_input(&mut self, read_only: bool) {
+        self.read_only_input = read_only;
+    }
+
+    /// Whether halting without a transition accepts, see
+    /// [`set_accept_on_halt`]
+    ///
+    /// [`set_accept_on_halt`]: TuringMachine::set_accept_on_halt
+    pub fn accept_on_halt(&self) -> bool {
+        self.accept_on_halt
+    }
+
+    /// Accept whenever the machine halts, unless it halts in a reject state
+    ///
+    /// Many textbooks and machine collections use this convention: a machine
+    /// accepts by running out of transitions, in any state, and only reject
+    /// states reject. Otherwise a missing transition rejects.
+    pub fn set_accept_on_halt(&mut self, accept: bool) {
+        self.accept_on_halt = accept;
+    }
+
+    /// Symbols replaced in every input before it is run
+    pub fn input_map(&self) -> &HashMap<char, char> {
+        &self.input_map
+    }
+
+    /// Replace symbols in every input before it is checked and run
+    ///
+    /// With `{'a': '0', 'b': '1'}`, a machine over {0,1} accepts the input
+    /// `abba` exactly when it accepts `0110`. Symbols without an entry are
+    /// kept. Every entry must map to an input symbol.
+    pub fn set_input_map(&mut self, input_map: HashMap<char, char>) -> Result<(), String> {
+        if let Some(to) = input_map.values().find(|to| !self.alphabet.contains(to)) {
+            return Err(format!("Input map target {} not in alphabet", to));
+        }
+        self.input_map = input_map;
+        Ok(())
+    }
+
+    /// Names of the symbols longer than one character
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Name symbols, so that inputs are read and tapes shown with the names
+    ///
+    /// The machine's symbols are the characters the table hands out.
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = Arc::new(symbols);
+    }
+
+    /// How inputs are cleaned up before they are run
+    pub fn input_normalization(&self) -> InputNormalization {
+        self.input_normalization
+    }
+
+    /// Clean up every input before the input map is applied
+    ///
+    /// Inputs pasted from elsewhere often carry stray spaces or the wrong
+    /// case. A symbol that is an input symbol or has an input map entry is
+    /// always kept as it is.
+    pub fn set_input_normalization(&mut self, normalization: InputNormalization) {
+        self.input_normalization = normalization;
+    }
+
+    /// The policy at the left end of the tape, or `None` if the tape is
+    /// unbounded on both sides
+    pub fn left_edge(&self) -> Option<LeftEdge> {
+        self.left_edge
+    }
+
+    /// Bound the tape on the left, at cell 0 of the initial tape
+    ///
+    /// The tape no longer grows to the left; a transition moving left from
+    /// the first cell is handled by `policy`. This models the textbook
+    /// one-way infinite tape. A circular tape has no edge, so the policy is
+    /// ignored once [`set_tape_length`] is set.
+    ///
+    /// [`set_tape_length`]: TuringMachine::set_tape_length
+    pub fn set_left_edge(&mut self, policy: Option<LeftEdge>) {
+        self.left_edge = policy;
+    }
+
+    /// Number of cells of the circular tape, or `None` if the tape is not
+    /// circular
+    pub fn tape_length(&self) -> Option<usize> {
+        self.tape_length
+    }
+
+    /// Make the tape a ring of `length` cells
+    ///
+    /// The input is written from cell 0 and the rest of the ring is blank.
+    /// Moving right from the last cell wraps around to cell 0 and moving left
+    /// from cell 0 wraps to the last cell. Inputs longer than the ring are
+    /// rejected with an error when run.
+    pub fn set_tape_length(&mut self, length: Option<usize>) -> Result<(), String> {
+        if length == Some(0) {
+            return Err("Tape length must be at least 1".to_string());
+        }
+        if length.is_some() && self.left_edge.is_some() {
+            return Err("A circular tape has no left edge; remove left_edge".to_string());
+        }
+        self.tape_length = length;
+        Ok(())
+    }
+
+    /// The tape before the input is placed, and where the input and head
+    /// start
+    pub fn tape_setup(&self) -> &TapeSetup {
+        &self.tape_setup
+    }
+
+    /// Write symbols on the tape before every run, or place the input or
+    /// the head elsewhere than cell 0
+    ///
+    /// A machine can then rely on markers around its input, e.g. a `#` at
+    /// cell 0 with the input from cell 5. Head positions reported by runs
+    /// are still cells of the tape; [`RunConfig`] bounds are still counted
+    /// from the first input cell.
+    ///
+    /// [`RunConfig`]: crate::executor::RunConfig
+    pub fn set_tape_setup(&mut self, setup: TapeSetup) -> Result<(), String> {
+        if let Some(symbol) = setup
+            .contents
+            .iter()
+            .find(|symbol| !self.tape_alphabet.contains(symbol))
+        {
+            return Err(format!(
+                "Initial tape symbol {} not in tape alphabet",
+                self.symbols.name(*symbol)
+            ));
+        }
+        if let Some(length) = self.tape_length {
+            let cells = setup
+                .contents
+                .len()
+                .max(setup.input_start)
+                .max(setup.head() + 1);
+            if cells > length {
+                return Err(format!(
+                    "The initial tape needs {} cells, more than the circular tape of {}",
+                    cells, length
+                ));
+            }
+        }
+        self.tape_setup = setup;
+        Ok(())
+    }
+
+    /// Where the machine leaves its output, or `None` if it decides a
+    /// language instead of computing a function
+    pub fn output(&self) -> Option<Output> {
+        self.output
+    }
+
+    /// Treat the machine as computing a function
+    ///
+    /// Once a run halts, in whatever state, the tape is read as set by
+    /// `output` and reported as [`ExecutionResult::output`]. With
+    /// [`Output::Between`], a run that halts without both markers on the
+    /// tape has no output.
+    pub fn set_output(&mut self, output: Option<Output>) -> Result<(), String> {
+        if let Some(Output::Between(start, end)) = output {
+            for marker in [start, end] {
+                if !self.tape_alphabet.contains(&marker) {
+                    return Err(format!(
+                        "Output marker {} not in tape alphabet",
+                        self.symbols.name(marker)
+                    ));
+                }
+            }
+        }
+        self.output = output;
+        Ok(())
+    }
+
+    /// Limits the machine recommends for its runs
+    pub fn run_defaults(&self) -> &RunDefaults {
+        &self.run_defaults
+    }
+
+    /// Recommend limits for runs of this machine, such as a higher
+    /// `max_steps` for a machine known to run long
+    pub fn set_run_defaults(&mut self, defaults: RunDefaults) {
+        self.run_defaults = defaults;
+    }
+
+    /// Name, description and other descriptive fields of the machine
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Describe the machine; the metadata does not affect runs
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    /// Readable names for tape symbols, by the name they stand for
+    pub fn symbol_aliases(&self) -> &BTreeMap<String, String> {
+        &self.symbol_aliases
+    }
+
+    /// Keep the symbol aliases of the definition; they do not affect runs
+    pub fn set_symbol_aliases(&mut self, aliases: BTreeMap<String, String>) {
+        self.symbol_aliases = aliases;
+    }
+
+    /// The default [`RunConfig`] with the machine's recommended limits
+    /// applied
+    pub fn run_config(&self) -> RunConfig {
+        self.run_defaults.apply(&RunConfig::default())
+    }
+
+    /// The output left on `tape` by a halted run
+    pub(crate) fn read_output(&self, tape: &[char]) -> Option<String> {
+        let cells = match self.output? {
+            Output::Tape => {
+                let blank = |symbol: &char| *symbol == self.blank_symbol;
+                let start = tape.iter().position(|s| !blank(s)).unwrap_or(tape.len());
+                let end = tape
+                    .iter()
+                    .rposition(|s| !blank(s))
+                    .map_or(start, |i| i + 1);
+                &tape[start..end]
+            }
+            Output::Between(start, end) => {
+                let from = tape.iter().position(|symbol| *symbol == start)? + 1;
+                let to = from + tape[from..].iter().position(|symbol| *symbol == end)?;
+                &tape[from..to]
+            }
+        };
+        Some(self.symbols.render(cells))
+    }
+
+    /// The tape at the start of a run on the mapped `input`: the tape
+    /// contents with the input written over them, filled up with blanks to
+    /// the head and, on a circular tape, to the full ring
+    pub(crate) fn start_tape(&self, input: &[char]) -> Result<Vec<char>, String> {
+        let setup = &self.tape_setup;
+        let input_end = setup.input_start + input.len();
+        let mut tape = setup.contents.clone();
+        let cells = tape.len().max(input_end).max(setup.head());
+        tape.resize(cells, self.blank_symbol);
+        tape[setup.input_start..input_end].copy_from_slice(input);
+        if let Some(length) = self.tape_length {
+            if tape.len() > length {
+                return Err(format!(
+                    "Input does not fit on the circular tape of {} cells ({} cells needed)",
+                    length,
+                    tape.len()
+                ));
+            }
+            tape.resize(length, self.blank_symbol);
+        }
+        Ok(tape)
+    }
+
+    /// The input's symbols after naming, normalization and the input map
+    pub(crate) fn map_input(&self, input_string: &str) -> Result<Vec<char>, String> {
+        let normalization = self.input_normalization;
+        let known =
+            |symbol: &char| self.alphabet.contains(symbol) || self.input_map.contains_key(symbol);
+        let symbols = self
+            .symbols
+            .tokenize(input_string)
+            .map_err(|e| format!("Invalid input symbol: {}", e))?;
+        Ok(symbols
+            .into_iter()
+            .filter(|symbol| {
+                !normalization.strip_whitespace || known(symbol) || !symbol.is_whitespace()
+            })
+            .map(|symbol| {
+                if !normalization.fold_case || known(&symbol) {
+                    return symbol;
+                }
+                // Only case changes that keep a single character count
+                [
+                    symbol.to_lowercase().to_string(),
+                    symbol.to_uppercase().to_string(),
+                ]
+                .iter()
+                .filter_map(|folded| {
+                    let mut chars = folded.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => None,
+                    }
+                })
+                .find(known)
+                .unwrap_or(symbol)
+            })
+            .map(|symbol| *self.input_map.get(&symbol).unwrap_or(&symbol))
+            .collect())
+    }
+
+    /// Execute the Turing machine on the given input
+    pub fn execute(&self, input_string: &str, max_steps: usize) -> Result<ExecutionResult, String> {
+        Executor::new(self).run(input_string, max_steps)
+    }
+
+    /// Execute the machine step-by-step, returning snapshots
+    pub fn execute_step_by_step(
+        &self,
+        input_string: &str,
+        max_steps: usize,
+    ) -> Result<Vec<ExecutionSnapshot>, String> {
+        let mut recorder = SnapshotRecorder::new(self, input_string);
+        Executor::new(self)
+            .observe(&mut recorder)
+            .run(input_string, max_steps)?;
+        Ok(recorder.snapshots)
+    }
+
+    /// Execute the Turing machine on the given input, yielding to the async
+    /// runtime every `options.yield_every` steps
+    ///
+    /// See [`run_async`](crate::executor::run_async).
+    pub async fn execute_async(
+        &self,
+        input_string: &str,
+        max_steps: usize,
+        options: &AsyncOptions,
+    ) -> Result<ExecutionResult, String> {
+        let config = RunConfig {
+            max_steps,
+            ..RunConfig::default()
+        };
+        executor::run_async(self, input_string, &config, options, &mut ()).await
+    }
+
+    /// Execute the machine step-by-step, returning snapshots, yielding to
+    /// the async runtime every `options.yield_every` steps
+    pub async fn execute_step_by_step_async(
+        &self,
+        input_string: &str,
+        max_steps: usize,
+        options: &AsyncOptions,
+    ) -> Result<Vec<ExecutionSnapshot>, String> {
+        let config = RunConfig {
+            max_steps,
+            ..RunConfig::default()
+        };
+        let mut recorder = SnapshotRecorder::new(self, input_string);
+        executor::run_async(self, input_string, &config, options, &mut recorder).await?;
+        Ok(recorder.snapshots)
+    }
+}
@@ -1,608 +1,78 @@
 use colored::Colorize;
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+mod config;
+mod help;
 
-/// Represents the direction the Turing machine head can move
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-enum Direction {
-    L, // Left
-    R, // Right
-}
-
-/// Result of executing a Turing machine
-#[derive(Debug)]
-struct ExecutionResult {
-    accepts: Option<bool>, // True if accepts, False if rejects, None if didn't halt
-    final_state: String,
-    steps: usize,
-    halted: bool,
-    tape: String,
-}
-
-/// State snapshot during step-by-step execution
-#[derive(Debug, Clone)]
-struct ExecutionSnapshot {
-    tape: Vec<char>,
-    head_position: i32,
-    current_state: String,
-    step: usize,
-}
-
-/// A Turing machine executor
-#[derive(Debug)]
-struct TuringMachine {
-    states: HashSet<String>,
-    alphabet: HashSet<char>,
-    tape_alphabet: HashSet<char>,
-    transitions: HashMap<(String, char), (String, char, Direction)>,
-    initial_state: String,
-    accept_states: HashSet<String>,
-    reject_states: HashSet<String>,
-    blank_symbol: char,
-}
-
-impl TuringMachine {
-    /// Create a new Turing machine
-    fn new(
-        states: HashSet<String>,
-        alphabet: HashSet<char>,
-        tape_alphabet: HashSet<char>,
-        transitions: HashMap<(String, char), (String, char, Direction)>,
-        initial_state: String,
-        accept_states: HashSet<String>,
-        reject_states: HashSet<String>,
-        blank_symbol: char,
-    ) -> Result<Self, String> {
-        // Validate input
-        if !states.contains(&initial_state) {
-            return Err(format!("Initial state {} not in states", initial_state));
-        }
-        if !accept_states.is_subset(&states) {
-            return Err("Accept states must be subset of states".to_string());
-        }
-        if !reject_states.is_subset(&states) {
-            return Err("Reject states must be subset of states".to_string());
-        }
-        if !accept_states.is_disjoint(&reject_states) {
-            return Err("Accept and reject states must be disjoint".to_string());
-        }
-        if !tape_alphabet.contains(&blank_symbol) {
-            return Err(format!("Blank symbol {} not in tape alphabet", blank_symbol));
-        }
-
-        Ok(TuringMachine {
-            states,
-            alphabet,
-            tape_alphabet,
-            transitions,
-            initial_state,
-            accept_states,
-            reject_states,
-            blank_symbol,
-        })
-    }
-
-    /// Execute the Turing machine on the given input
-    fn execute(&self, input_string: &str, max_steps: usize) -> Result<ExecutionResult, String> {
-        // Initialize tape with input
-        let mut tape: Vec<char> = if input_string.is_empty() {
-            vec![]
-        } else {
-            input_string.chars().collect()
-        };
-        let mut head_position: i32 = 0;
-        let mut current_state = self.initial_state.clone();
-        let mut steps = 0;
-
-        // Validate input symbols
-        for symbol in input_string.chars() {
-            if !self.alphabet.contains(&symbol) {
-                return Err(format!("Invalid input symbol: {}", symbol));
-            }
-        }
-
-        // Execute until halt or max steps
-        while steps < max_steps {
-            // Check if in halting state
-            if self.accept_states.contains(&current_state) {
-                return Ok(ExecutionResult {
-                    accepts: Some(true),
-                    final_state: current_state,
-                    steps,
-                    halted: true,
-                    tape: tape.iter().collect(),
-                });
-            }
-
-            if self.reject_states.contains(&current_state) {
-                return Ok(ExecutionResult {
-                    accepts: Some(false),
-                    final_state: current_state,
-                    steps,
-                    halted: true,
-                    tape: tape.iter().collect(),
-                });
-            }
-
-            // Extend tape if needed
-            if head_position < 0 {
-                tape.insert(0, self.blank_symbol);
-                head_position = 0;
-            }
-            if head_position >= tape.len() as i32 {
-                tape.push(self.blank_symbol);
-            }
-
-            // Read current symbol
-            let current_symbol = tape[head_position as usize];
-
-            // Look up transition
-            let transition_key = (current_state.clone(), current_symbol);
-            if let Some((new_state, write_symbol, direction)) = self.transitions.get(&transition_key)
-            {
-                // Write symbol
-                tape[head_position as usize] = *write_symbol;
-
-                // Move head
-                match direction {
-                    Direction::L => head_position -= 1,
-                    Direction::R => head_position += 1,
-                }
-
-                // Update state
-                current_state = new_state.clone();
-                steps += 1;
-            } else {
-                // No transition defined - implicit reject
-                return Ok(ExecutionResult {
-                    accepts: Some(false),
-                    final_state: current_state,
-                    steps,
-                    halted: true,
-                    tape: tape.iter().collect(),
-                });
-            }
-        }
-
-        // Max steps reached - likely infinite loop
-        Ok(ExecutionResult {
-            accepts: None,
-            final_state: current_state,
-            steps,
-            halted: false,
-            tape: tape.iter().collect(),
-        })
-    }
-
-    /// Execute the machine step-by-step, returning snapshots
-    fn execute_step_by_step(
-        &self,
-        input_string: &str,
-        max_steps: usize,
-    ) -> Result<Vec<ExecutionSnapshot>, String> {
-        let mut snapshots = Vec::new();
-
-        // Initialize tape with input
-        let mut tape: Vec<char> = if input_string.is_empty() {
-            vec![]
-        } else {
-            input_string.chars().collect()
-        };
-        let mut head_position: i32 = 0;
-        let mut current_state = self.initial_state.clone();
-        let mut step = 0;
-
-        // Validate input symbols
-        for symbol in input_string.chars() {
-            if !self.alphabet.contains(&symbol) {
-                return Err(format!("Invalid input symbol: {}", symbol));
-            }
-        }
-
-        // Save initial snapshot
-        snapshots.push(ExecutionSnapshot {
-            tape: tape.clone(),
-            head_position,
-            current_state: current_state.clone(),
-            step,
-        });
-
-        // Execute until halt or max steps
-        while step < max_steps {
-            // Check if in halting state
-            if self.accept_states.contains(&current_state)
-                || self.reject_states.contains(&current_state)
-            {
-                break;
-            }
-
-            // Extend tape if needed
-            if head_position < 0 {
-                tape.insert(0, self.blank_symbol);
-                head_position = 0;
-            }
-            if head_position >= tape.len() as i32 {
-                tape.push(self.blank_symbol);
-            }
-
-            // Read current symbol
-            let current_symbol = tape[head_position as usize];
-
-            // Look up transition
-            let transition_key = (current_state.clone(), current_symbol);
-            if let Some((new_state, write_symbol, direction)) = self.transitions.get(&transition_key)
-            {
-                // Write symbol
-                tape[head_position as usize] = *write_symbol;
-
-                // Move head
-                match direction {
-                    Direction::L => head_position -= 1,
-                    Direction::R => head_position += 1,
-                }
-
-                // Update state
-                current_state = new_state.clone();
-                step += 1;
-
-                // Save snapshot after transition
-                snapshots.push(ExecutionSnapshot {
-                    tape: tape.clone(),
-                    head_position,
-                    current_state: current_state.clone(),
-                    step,
-                });
-            } else {
-                // No transition defined - halt
-                break;
-            }
-        }
-
-        Ok(snapshots)
-    }
-
-    /// Display the state diagram with transitions
-    fn display_state_diagram(&self, current_state: Option<&str>, next_transition: Option<(char, &str, char, Direction)>) {
-        println!("\n{}", "=".repeat(60));
-        println!("{}", "STATE DIAGRAM".bold());
-        println!("{}", "=".repeat(60));
-
-        // Draw visual ASCII diagram
-        self.draw_state_diagram(current_state, next_transition);
-
-        // Display transitions grouped by state
-        println!("\n{}:", "Transitions".bold());
-        let mut transitions_by_state: HashMap<&String, Vec<(char, &String, char, Direction)>> =
-            HashMap::new();
-
-        for ((state, symbol), (new_state, write_symbol, direction)) in &self.transitions {
-            transitions_by_state
-                .entry(state)
-                .or_insert_with(Vec::new)
-                .push((*symbol, new_state, *write_symbol, *direction));
-        }
-
-        let mut sorted_states: Vec<_> = transitions_by_state.keys().collect();
-        sorted_states.sort();
-
-        for state in sorted_states {
-            let mut state_header = format!("  {}:", state);
-            if let Some(current) = current_state {
-                if state.as_str() == current {
-                    state_header = state_header.bold().yellow().to_string();
-                }
-            }
-            println!("{}", state_header);
-
-            let mut transitions = transitions_by_state.get(state).unwrap().clone();
-            transitions.sort_by_key(|(s, _, _, _)| *s);
-
-            for (symbol, new_state, write_symbol, direction) in transitions {
-                let dir_str = match direction {
-                    Direction::L => "←",
-                    Direction::R => "→",
-                };
-                let transition_str = format!(
-                    "    ({}) → write '{}', move {}, goto {}",
-                    symbol, write_symbol, dir_str, new_state
-                );
-
-                // Highlight the next transition to be executed
-                let is_next_transition = if let (Some(current), Some((next_sym, next_state, _, _))) = (current_state, next_transition) {
-                    state.as_str() == current && symbol == next_sym && new_state.as_str() == next_state
-                } else {
-                    false
-                };
-
-                if is_next_transition {
-                    println!("{}", format!("  ▶ {}", transition_str).bold().green());
-                } else if let Some(current) = current_state {
-                    if state.as_str() == current {
-                        println!("{}", transition_str.yellow());
-                    } else {
-                        println!("{}", transition_str);
-                    }
-                } else {
-                    println!("{}", transition_str);
-                }
-            }
-        }
-        println!();
-    }
-
-    /// Draw ASCII art diagram of state machine
-    fn draw_state_diagram(&self, current_state: Option<&str>, next_transition: Option<(char, &str, char, Direction)>) {
-        println!("\n{}:", "Visual Diagram".bold());
-        
-        // Sort states for consistent display
-        let mut sorted_states: Vec<_> = self.states.iter().collect();
-        sorted_states.sort();
-        
-        // Draw states with arrows connecting them
-        // Create a simple horizontal layout with arrows
-        for (i, state) in sorted_states.iter().enumerate() {
-            // Draw state box
-            let is_current = current_state.map(|c| c == state.as_str()).unwrap_or(false);
-            let is_accept = self.accept_states.contains(*state);
-            let is_reject = self.reject_states.contains(*state);
-            
-            // State box components - dynamically sized based on state name (width = text width + 2)
-            let state_width = state.len();
-            // Ensure box is wide enough for accept/reject labels (8 chars: "✓ ACCEPT" or "✗ REJECT")
-            let content_width = if is_accept || is_reject {
-                state_width.max(8)
-            } else {
-                state_width
-            };
-            let horizontal_line = "─".repeat(content_width + 2);
-            
-            let box_top = format!("┌{}┐", horizontal_line);
-            let state_line = format!("│ {:^width$} │", state.as_str(), width = content_width);
-            let type_line = if is_accept {
-                format!("│ {:^width$} │", "✓ ACCEPT", width = content_width)
-            } else if is_reject {
-                format!("│ {:^width$} │", "✗ REJECT", width = content_width)
-            } else {
-                format!("│ {} │", " ".repeat(content_width))
-            };
-            let box_bottom = format!("└{}┘", horizontal_line);
-            
-            // Print state box
-            if is_current {
-                println!("  {}", box_top.bold().yellow());
-                println!("  {}", state_line.bold().yellow());
-                if is_accept {
-                    println!("  {}", type_line.green().bold().yellow());
-                } else if is_reject {
-                    println!("  {}", type_line.red().bold().yellow());
-                } else {
-                    println!("  {}", type_line.bold().yellow());
-                }
-                println!("  {}", box_bottom.bold().yellow());
-            } else {
-                println!("  {}", box_top);
-                println!("  {}", state_line);
-                if is_accept {
-                    println!("  {}", type_line.green());
-                } else if is_reject {
-                    println!("  {}", type_line.red());
-                } else {
-                    println!("  {}", type_line);
-                }
-                println!("  {}", box_bottom);
-            }
-            
-            // Draw transitions from this state
-            let mut state_transitions = Vec::new();
-            for ((from_state, symbol), (to_state, write_symbol, direction)) in &self.transitions {
-                if from_state == *state {
-                    state_transitions.push((symbol, to_state.as_str(), write_symbol, direction));
-                }
-            }
-            
-            if !state_transitions.is_empty() {
-                state_transitions.sort_by_key(|(s, _, _, _)| *s);
-                
-                for (symbol, to_state, write_symbol, direction) in state_transitions {
-                    let dir_arrow = match direction {
-                        Direction::L => "←",
-                        Direction::R => "→",
-                    };
-                    
-                    // Check if this is the next transition
-                    let is_next = if let (Some(current), Some((next_sym, next_state, _, _))) = (current_state, next_transition) {
-                        state.as_str() == current && *symbol == next_sym && to_state == next_state
-                    } else {
-                        false
-                    };
-                    
-                    // Arrow from current state (box above) to target state
-                    let arrow = format!("      │ --[{}:{}{}]--> {}", 
-                        symbol, write_symbol, dir_arrow, to_state);
-                    
-                    if is_next {
-                        println!("{}", arrow.bold().green());
-                    } else if is_current {
-                        println!("{}", arrow.yellow());
-                    } else {
-                        println!("{}", arrow);
-                    }
-                    
-                    // Add visual pointer to target state box
-                    let pointer = format!("      │              ↓");
-                    if is_next {
-                        println!("{}", pointer.bold().green());
-                    } else if is_current {
-                        println!("{}", pointer.yellow());
-                    } else {
-                        println!("{}", pointer);
-                    }
-                }
-            }
-            
-            if i < sorted_states.len() - 1 {
-                println!();
-            }
-        }
-        
-        // Show next transition if available
-        if let (Some(current), Some((symbol, next_state, write_symbol, direction))) = (current_state, next_transition) {
-            println!("\n{}:", "Next Transition".bold().green());
-            let dir_str = match direction {
-                Direction::L => "←",
-                Direction::R => "→",
-            };
-            println!("  {} --[read: '{}']-->", current.bold().yellow(), symbol.to_string().cyan());
-            println!("    • Write: '{}'", write_symbol.to_string().cyan());
-            println!("    • Move: {}", dir_str.cyan());
-            println!("    • Goto: {}", next_state.bold().yellow());
-        }
-        
-        println!();
-    }
-
-    /// Display the tape with head position
-    fn display_tape(snapshot: &ExecutionSnapshot, blank_symbol: char) {
-        println!("\n{}", "TAPE".bold());
-        
-        // Determine visible range around head
-        let head_pos = snapshot.head_position;
-        let tape_len = snapshot.tape.len() as i32;
-        
-        // Show at least 20 cells centered around head
-        let visible_start = (head_pos - 10).max(0);
-        let visible_end = (head_pos + 10).min(tape_len - 1).max(visible_start + 19);
-        
-        // Print tape cells
-        print!("Tape:   ");
-        for i in visible_start..=visible_end {
-            if i >= 0 && i < tape_len {
-                let cell = snapshot.tape[i as usize];
-                let cell_str = if cell == blank_symbol {
-                    format!("[_]")
-                } else {
-                    format!("[{}]", cell)
-                };
-                
-                if i == head_pos {
-                    print!("{}", cell_str.bold().green());
-                } else {
-                    print!("{}", cell_str);
-                }
-            } else {
-                print!("[_]");
-            }
-        }
-        println!();
-        
-        // Print head indicator
-        print!("Head:   ");
-        for i in visible_start..=visible_end {
-            if i == head_pos {
-                print!(" ^ ");
-            } else {
-                print!("   ");
-            }
-        }
-        println!();
-        
-        // Print position numbers
-        print!("Pos:    ");
-        for i in visible_start..=visible_end {
-            print!("{:>3}", i);
-        }
-        println!("\n");
-    }
-}
-
-/// Helper struct for JSON deserialization
-#[derive(Debug, Deserialize)]
-struct MachineJson {
-    states: Vec<String>,
-    alphabet: Vec<String>,
-    tape_alphabet: Vec<String>,
-    initial_state: String,
-    accept_states: Vec<String>,
-    reject_states: Vec<String>,
-    blank_symbol: Option<String>,
-    transitions: HashMap<String, Vec<String>>,
-}
-
-/// Parse a Turing machine from JSON format
-fn parse_machine_json(json_data: &MachineJson) -> Result<TuringMachine, String> {
-    // Convert transitions from string keys to tuple keys
-    let mut transitions = HashMap::new();
-    for (key, value) in &json_data.transitions {
-        let parts: Vec<&str> = key.split(',').collect();
-        if parts.len() != 2 {
-            return Err(format!("Invalid transition key: {}", key));
-        }
-        let state = parts[0].to_string();
-        let symbol = parts[1]
-            .chars()
-            .next()
-            .ok_or_else(|| format!("Invalid symbol in transition key: {}", key))?;
-
-        if value.len() != 3 {
-            return Err(format!("Invalid transition value for key: {}", key));
-        }
-        let new_state = value[0].clone();
-        let write_symbol = value[1]
-            .chars()
-            .next()
-            .ok_or_else(|| format!("Invalid write symbol in transition: {}", key))?;
-        let direction = match value[2].as_str() {
-            "L" => Direction::L,
-            "R" => Direction::R,
-            _ => return Err(format!("Invalid direction: {}", value[2])),
-        };
+use config::Config;
+use turing_machine::amplitude::{self, AmplitudeMachine, Superposition};
+use turing_machine::analysis::{self, Analysis, AnalysisCache};
+use turing_machine::batch;
+use turing_machine::bench::{self, Bench, CountingAllocator};
+use turing_machine::blame::{Blame, CellWrite};
+use turing_machine::cellular;
+use turing_machine::daemon::Daemon;
+use turing_machine::determinize::determinize;
+use turing_machine::diagram;
+use turing_machine::dovetail::{self, Dovetail};
+use turing_machine::executor::{
+    Executor, RunConfig, StepEvent, StepObserver, Stepper, UNLIMITED_STEPS,
+};
+use turing_machine::godel;
+use turing_machine::golden;
+use turing_machine::grid::{GridMachine, GridRun};
+use turing_machine::history::{Cursor, History};
+use turing_machine::info::{MachineInfo, ShortInfo};
+use turing_machine::multihead::MultiHeadMachine;
+use turing_machine::nondeterministic::{self, NondeterministicMachine};
+use turing_machine::pack::{self, Pack, PackStore};
+use turing_machine::pda::{PdaMachine, PdaRun};
+use turing_machine::render;
+use turing_machine::server::Server;
+use turing_machine::suspicious::{RunWatcher, Warning};
+use turing_machine::table;
+use turing_machine::trajectory::{self, Trajectory};
+use turing_machine::twostack::{self, TwoStackMachine, TwoStackRun};
+use turing_machine::universal;
+use turing_machine::{
+    formal, formats, parse_machine_definition, parse_machine_json, pipeline, qr, race, Cycle,
+    Direction, ExecutionResult, ExecutionSnapshot, InputNormalization, MachineJson, ResourceLimit,
+    SymbolTable, TuringMachine,
+};
 
-        transitions.insert((state, symbol), (new_state, write_symbol, direction));
-    }
+/// Exit code of `run` and `pipeline` when the input is accepted
+const EXIT_ACCEPT: i32 = 0;
+/// Exit code of `run` and `pipeline` when the input is rejected
+const EXIT_REJECT: i32 = 1;
+/// Exit code of `run` and `pipeline` when the step or memory limit is reached
+const EXIT_STEP_LIMIT: i32 = 2;
+/// Exit code when a machine or other file cannot be loaded or is invalid
+const EXIT_ERROR: i32 = 3;
+/// Exit code for invalid command line arguments
+const EXIT_USAGE: i32 = 4;
+/// Exit code when a run is stopped with Ctrl+C
+const EXIT_INTERRUPTED: i32 = 130;
 
-    let blank_symbol = json_data
-        .blank_symbol
-        .as_ref()
-        .and_then(|s| s.chars().next())
-        .unwrap_or('_');
+/// Most inputs `race --length` generates
+const MAX_RACE_INPUTS: usize = 1 << 20;
+/// Accepted inputs `dovetail` lists without `--count`
+const DEFAULT_DOVETAIL_COUNT: usize = 10;
+/// Measured runs of every input `bench` takes without `--iterations`
+const DEFAULT_BENCH_ITERATIONS: usize = 10;
 
-    // Validate alphabet entries are single characters
-    for entry in &json_data.alphabet {
-        if entry.chars().count() != 1 {
-            return Err(format!(
-                "Alphabet entry '{}' must be a single character",
-                entry
-            ));
-        }
-    }
+/// Counts the allocations of `bench` runs
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new();
 
-    // Validate tape_alphabet entries are single characters
-    for entry in &json_data.tape_alphabet {
-        if entry.chars().count() != 1 {
-            return Err(format!(
-                "Tape alphabet entry '{}' must be a single character",
-                entry
-            ));
-        }
-    }
+/// Set by the Ctrl+C handler to stop the run in progress
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
-    TuringMachine::new(
-        json_data.states.iter().cloned().collect(),
-        json_data.alphabet.iter().flat_map(|s| s.chars()).collect(),
-        json_data
-            .tape_alphabet
-            .iter()
-            .flat_map(|s| s.chars())
-            .collect(),
-        transitions,
-        json_data.initial_state.clone(),
-        json_data.accept_states.iter().cloned().collect(),
-        json_data.reject_states.iter().cloned().collect(),
-        blank_symbol,
-    )
-}
+/// Whether a run is in progress; Ctrl+C outside of a run exits the program
+static RUNNING: AtomicBool = AtomicBool::new(false);
 
 /// Format a filename into a display name
 fn format_display_name(filename: &str) -> String {
@@ -621,14 +91,14 @@ fn format_display_name(filename: &str) -> String {
 }
 
 /// Load example Turing machines from the examples folder
-fn load_example_machines() -> HashMap<String, (TuringMachine, String)> {
+fn load_example_machines(examples_dir: &Path) -> HashMap<String, (TuringMachine, String)> {
     let mut examples = HashMap::new();
     
     // Try to load examples from the examples directory
-    if let Ok(entries) = fs::read_dir("examples") {
+    if let Ok(entries) = fs::read_dir(examples_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            if formats::Format::from_path(&path).is_none() {
                 continue;
             }
             
@@ -636,19 +106,19 @@ fn load_example_machines() -> HashMap<String, (TuringMachine, String)> {
                 continue;
             };
             
-            let Ok(json_str) = fs::read_to_string(&path) else {
-                continue;
-            };
-            
-            let Ok(json_data) = serde_json::from_str::<MachineJson>(&json_str) else {
-                continue;
-            };
-            
-            let Ok(machine) = parse_machine_json(&json_data) else {
-                continue;
+            let machine = match formats::load_machine(&path, &mut |_| {}) {
+                Ok(machine) => machine,
+                Err(e) => {
+                    tracing::warn!("skipping {}:\n{}", path.display(), e);
+                    continue;
+                }
             };
             
-            let display_name = format_display_name(filename);
+            let display_name = machine
+                .metadata()
+                .name
+                .clone()
+                .unwrap_or_else(|| format_display_name(filename));
             examples.insert(filename.to_string(), (machine, display_name));
         }
     }
@@ -656,87 +126,67 @@ fn load_example_machines() -> HashMap<String, (TuringMachine, String)> {
     examples
 }
 
+/// The installed content packs
+fn pack_store() -> Result<PackStore, String> {
+    PackStore::default_dir()
+        .map(PackStore::new)
+        .ok_or_else(|| "No pack directory (neither XDG_DATA_HOME nor HOME is set)".to_string())
+}
+
+/// Load the machines of the installed packs, keyed by `pack/file stem`
+fn load_pack_machines() -> HashMap<String, (TuringMachine, String)> {
+    let mut machines = HashMap::new();
+    let Ok(store) = pack_store() else {
+        return machines;
+    };
+    for pack in store.packs() {
+        let name = &pack.manifest.name;
+        for (key, (machine, display_name)) in
+            load_example_machines(&pack.dir.join(pack::MACHINES_DIR))
+        {
+            machines.insert(
+                format!("{}/{}", name, key),
+                (machine, format!("{} ({})", display_name, name)),
+            );
+        }
+    }
+    machines
+}
+
 /// Create example Turing machines for testing (fallback if no examples folder)
 fn create_example_machines() -> HashMap<String, TuringMachine> {
     let mut examples = HashMap::new();
 
     // Machine 1: Accepts strings with even number of 1s
-    let mut transitions = HashMap::new();
-    transitions.insert(
-        ("q0".to_string(), '0'),
-        ("q0".to_string(), '0', Direction::R),
-    );
-    transitions.insert(
-        ("q0".to_string(), '1'),
-        ("q1".to_string(), '1', Direction::R),
-    );
-    transitions.insert(
-        ("q0".to_string(), '_'),
-        ("accept".to_string(), '_', Direction::R),
-    );
-    transitions.insert(
-        ("q1".to_string(), '0'),
-        ("q1".to_string(), '0', Direction::R),
-    );
-    transitions.insert(
-        ("q1".to_string(), '1'),
-        ("q0".to_string(), '1', Direction::R),
-    );
-    transitions.insert(
-        ("q1".to_string(), '_'),
-        ("reject".to_string(), '_', Direction::R),
-    );
-
-    let even_ones = TuringMachine::new(
-        ["q0", "q1", "accept", "reject"]
-            .iter()
-            .map(|s| s.to_string())
-            .collect(),
-        ['0', '1'].iter().cloned().collect(),
-        ['0', '1', '_'].iter().cloned().collect(),
-        transitions,
-        "q0".to_string(),
-        ["accept"].iter().map(|s| s.to_string()).collect(),
-        ["reject"].iter().map(|s| s.to_string()).collect(),
-        '_',
-    )
-    .unwrap();
+    let even_ones = TuringMachine::builder()
+        .initial("q0")
+        .accept("accept")
+        .reject("reject")
+        .input_symbols(['0', '1'])
+        .blank('_')
+        .transition("q0", '0', "q0", '0', Direction::R)
+        .transition("q0", '1', "q1", '1', Direction::R)
+        .transition("q0", '_', "accept", '_', Direction::R)
+        .transition("q1", '0', "q1", '0', Direction::R)
+        .transition("q1", '1', "q0", '1', Direction::R)
+        .transition("q1", '_', "reject", '_', Direction::R)
+        .build()
+        .unwrap();
     examples.insert("even_ones".to_string(), even_ones);
 
     // Machine 2: Accept all strings
-    let mut transitions = HashMap::new();
-    transitions.insert(
-        ("q0".to_string(), '0'),
-        ("q0".to_string(), '0', Direction::R),
-    );
-    transitions.insert(
-        ("q0".to_string(), '1'),
-        ("q0".to_string(), '1', Direction::R),
-    );
-    transitions.insert(
-        ("q0".to_string(), 'a'),
-        ("q0".to_string(), 'a', Direction::R),
-    );
-    transitions.insert(
-        ("q0".to_string(), 'b'),
-        ("q0".to_string(), 'b', Direction::R),
-    );
-    transitions.insert(
-        ("q0".to_string(), '_'),
-        ("accept".to_string(), '_', Direction::R),
-    );
-
-    let accept_all = TuringMachine::new(
-        ["q0", "accept"].iter().map(|s| s.to_string()).collect(),
-        ['0', '1', 'a', 'b'].iter().cloned().collect(),
-        ['0', '1', 'a', 'b', '_'].iter().cloned().collect(),
-        transitions,
-        "q0".to_string(),
-        ["accept"].iter().map(|s| s.to_string()).collect(),
-        HashSet::new(),
-        '_',
-    )
-    .unwrap();
+    let accept_all = TuringMachine::builder()
+        .initial("q0")
+        .accept("accept")
+        .input_symbols(['0', '1', 'a', 'b'])
+        .blank('_')
+        .transition("q0", '0', "q0", '0', Direction::R)
+        .transition("q0", '1', "q0", '1', Direction::R)
+        .transition("q0", 'a', "q0", 'a', Direction::R)
+        .transition("q0", 'b', "q0", 'b', Direction::R)
+        .transition("q0", '_', "accept", '_', Direction::R)
+        .build()
+        .unwrap();
     examples.insert("accept_all".to_string(), accept_all);
 
     examples
@@ -748,7 +198,7 @@ fn print_menu() {
     println!("TURING MACHINE EXECUTOR");
     println!("{}", "=".repeat(60));
     println!("1. Run example machine");
-    println!("2. Define custom machine (JSON format)");
+    println!("2. Define custom machine (JSON, YAML or DSL format)");
     println!("3. Load machine from file");
     println!("4. Help");
     println!("5. Exit");
@@ -760,40 +210,17 @@ fn print_help() {
     println!("\n{}", "=".repeat(60));
     println!("HELP - Turing Machine Format");
     println!("{}", "=".repeat(60));
-    println!(
-        r#"
-A Turing machine is defined using JSON with the following structure:
-
-{{
-    "states": ["q0", "q1", "accept", "reject"],
-    "alphabet": ["0", "1"],
-    "tape_alphabet": ["0", "1", "_"],
-    "initial_state": "q0",
-    "accept_states": ["accept"],
-    "reject_states": ["reject"],
-    "blank_symbol": "_",
-    "transitions": {{
-        "q0,0": ["q0", "0", "R"],
-        "q0,1": ["q1", "1", "R"],
-        "q1,_": ["accept", "_", "R"]
-    }}
-}}
-
-Transition format: "state,symbol": [new_state, write_symbol, direction]
-Direction: "L" (left), "R" (right)
-
-The program will:
-1. Execute the machine on your input string
-2. Report if it ACCEPTS or REJECTS (halts)
-3. Show the final state reached
-"#
-    );
+    if let Some(topic) = help::topic("machines") {
+        println!("\n{}", help::render_topic(topic));
+    }
+    println!("More topics: turing_machine help");
 }
 
 /// Run one of the predefined example machines
-fn run_example_machine() {
-    // Try to load examples from the examples folder
-    let loaded_examples = load_example_machines();
+fn run_example_machine(config: &Config) {
+    // Try to load examples from the examples folder and installed packs
+    let mut loaded_examples = load_example_machines(&config.examples_dir);
+    loaded_examples.extend(load_pack_machines());
     
     // Prepare the examples list
     let examples_list: Vec<(String, String)> = if loaded_examples.is_empty() {
@@ -823,10 +250,9 @@ fn run_example_machine() {
     
     println!("{}", "=".repeat(60));
 
-    print!("\nSelect example (1-{}): ", examples_list.len());
-    io::stdout().flush().unwrap();
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
+    let Some(choice) = prompt(&format!("\nSelect example (1-{}): ", examples_list.len())) else {
+        return;
+    };
     
     let choice_num = match choice.trim().parse::<usize>() {
         Ok(num) if num > 0 && num <= examples_list.len() => num,
@@ -851,7 +277,7 @@ fn run_example_machine() {
         let fallback = create_example_machines();
         if fallback.contains_key(machine_key) {
             // Reload to avoid lifetime issues
-            return run_single_example(machine_key, machine_name);
+            return run_single_example(config, machine_key, machine_name);
         } else {
             println!("Machine '{}' not found!", machine_key);
             return;
@@ -861,531 +287,3893 @@ fn run_example_machine() {
     println!("\nSelected: {}", machine_name);
     println!("{}", "-".repeat(60));
 
-    loop {
-        print!("\nEnter input string (or 'back' to return): ");
-        io::stdout().flush().unwrap();
-        let mut input_str = String::new();
-        io::stdin().read_line(&mut input_str).unwrap();
-        let input_str = input_str.trim();
-
-        if input_str.eq_ignore_ascii_case("back") {
-            break;
-        }
-
-        // Ask if user wants visual mode
-        print!("Run in visual step-by-step mode? (y/n): ");
-        io::stdout().flush().unwrap();
-        let mut visual_mode = String::new();
-        io::stdin().read_line(&mut visual_mode).unwrap();
-        let visual_mode = visual_mode.trim().eq_ignore_ascii_case("y");
-
-        if visual_mode {
-            run_visual_mode(machine, input_str);
-        } else {
-            match machine.execute(input_str, 10000) {
-                Ok(result) => {
-                    println!("\n{}", "-".repeat(60));
-                    println!("EXECUTION RESULTS");
-                    println!("{}", "-".repeat(60));
-                    println!("Input string: '{}'", input_str);
-                    println!("Steps executed: {}", result.steps);
-                    println!("Final state: {}", result.final_state);
-                    println!("Machine halted: {}", result.halted);
-
-                    if let Some(true) = result.accepts {
-                        println!(
-                            "\n✓ RESULT: ACCEPTS (halts in state {})",
-                            result.final_state
-                        );
-                    } else if let Some(false) = result.accepts {
-                        println!("\n✗ RESULT: REJECTS (final state: {})", result.final_state);
-                    } else {
-                        println!("\n? RESULT: DID NOT HALT (possible infinite loop)");
-                    }
-                    println!("{}", "-".repeat(60));
-                }
-                Err(e) => println!("Error: {}", e),
-            }
-        }
-    }
+    run_inputs(config, machine);
 }
 
 /// Run a single example machine (helper for fallback case)
-fn run_single_example(machine_key: &str, machine_name: &str) {
+fn run_single_example(config: &Config, machine_key: &str, machine_name: &str) {
     let examples = create_example_machines();
     let machine = examples.get(machine_key).unwrap();
     
     println!("\nSelected: {}", machine_name);
     println!("{}", "-".repeat(60));
 
-    loop {
-        print!("\nEnter input string (or 'back' to return): ");
-        io::stdout().flush().unwrap();
-        let mut input_str = String::new();
-        io::stdin().read_line(&mut input_str).unwrap();
-        let input_str = input_str.trim();
-
-        if input_str.eq_ignore_ascii_case("back") {
-            break;
-        }
-
-        // Ask if user wants visual mode
-        print!("Run in visual step-by-step mode? (y/n): ");
-        io::stdout().flush().unwrap();
-        let mut visual_mode = String::new();
-        io::stdin().read_line(&mut visual_mode).unwrap();
-        let visual_mode = visual_mode.trim().eq_ignore_ascii_case("y");
-
-        if visual_mode {
-            run_visual_mode(machine, input_str);
-        } else {
-            match machine.execute(input_str, 10000) {
-                Ok(result) => {
-                    println!("\n{}", "-".repeat(60));
-                    println!("EXECUTION RESULTS");
-                    println!("{}", "-".repeat(60));
-                    println!("Input string: '{}'", input_str);
-                    println!("Steps executed: {}", result.steps);
-                    println!("Final state: {}", result.final_state);
-                    println!("Machine halted: {}", result.halted);
-
-                    if let Some(true) = result.accepts {
-                        println!(
-                            "\n✓ RESULT: ACCEPTS (halts in state {})",
-                            result.final_state
-                        );
-                    } else if let Some(false) = result.accepts {
-                        println!("\n✗ RESULT: REJECTS (final state: {})", result.final_state);
-                    } else {
-                        println!("\n? RESULT: DID NOT HALT (possible infinite loop)");
-                    }
-                    println!("{}", "-".repeat(60));
-                }
-                Err(e) => println!("Error: {}", e),
-            }
-        }
-    }
+    run_inputs(config, machine);
 }
 
-/// Allow user to define a custom Turing machine via JSON
-fn run_custom_machine() {
+/// Allow user to define a custom Turing machine via JSON, YAML or the DSL
+fn run_custom_machine(config: &Config) {
     println!("\n{}", "=".repeat(60));
-    println!("DEFINE CUSTOM MACHINE (JSON)");
+    println!("DEFINE CUSTOM MACHINE (JSON, YAML OR DSL)");
     println!("{}", "=".repeat(60));
-    println!("Enter JSON definition (type 'help' for format, 'cancel' to abort):");
+    println!("Enter JSON, YAML or DSL definition (type 'help' for format, 'cancel' to abort):");
     println!("You can enter it as a single line or multiple lines (end with empty line)");
     println!("{}", "-".repeat(60));
 
     let mut lines = Vec::new();
     loop {
-        let mut line = String::new();
-        io::stdin().read_line(&mut line).unwrap();
-        let line = line.trim();
+        let Some(line) = read_line() else {
+            return;
+        };
+        // Indentation is kept, since YAML depends on it
+        let line = line.trim_end();
+        let command = line.trim();
 
-        if line.eq_ignore_ascii_case("cancel") {
+        if command.eq_ignore_ascii_case("cancel") {
             return;
         }
-        if line.eq_ignore_ascii_case("help") {
+        if command.eq_ignore_ascii_case("help") {
             print_help();
-            println!("Continue entering JSON:");
+            println!("Continue entering the definition:");
             continue;
         }
-        if line.is_empty() && !lines.is_empty() {
+        if command.is_empty() && !lines.is_empty() {
             break;
         }
-        if !line.is_empty() {
+        if !command.is_empty() {
             lines.push(line.to_string());
         }
     }
 
-    let json_str = lines.join("\n");
+    let text = lines.join("\n");
+    // DSL transitions are the only lines with an arrow
+    let is_dsl = text.lines().any(|line| {
+        !line.trim_start().starts_with('#') && (line.contains("->") || line.contains('→'))
+    });
+    let definition = if text.trim_start().starts_with('{') {
+        parse_machine_definition(&text).map_err(|e| format!("Invalid JSON: {}", e))
+    } else if is_dsl {
+        formats::from_dsl(&text).map_err(|e| format!("Invalid machine: {}", e))
+    } else {
+        formats::from_yaml(&text).map_err(|e| format!("Invalid YAML: {}", e))
+    };
 
-    match serde_json::from_str::<MachineJson>(&json_str) {
+    match definition {
         Ok(json_data) => match parse_machine_json(&json_data) {
             Ok(machine) => {
                 println!("\n✓ Machine created successfully!");
-                println!("States: {}", machine.states.len());
-                println!("Transitions: {}", machine.transitions.len());
-
-                loop {
-                    print!("\nEnter input string (or 'back' to return): ");
-                    io::stdout().flush().unwrap();
-                    let mut input_str = String::new();
-                    io::stdin().read_line(&mut input_str).unwrap();
-                    let input_str = input_str.trim();
+                println!("States: {}", machine.states().len());
+                println!("Transitions: {}", machine.transitions().len());
 
-                    if input_str.eq_ignore_ascii_case("back") {
-                        break;
-                    }
-
-                    // Ask if user wants visual mode
-                    print!("Run in visual step-by-step mode? (y/n): ");
-                    io::stdout().flush().unwrap();
-                    let mut visual_mode = String::new();
-                    io::stdin().read_line(&mut visual_mode).unwrap();
-                    let visual_mode = visual_mode.trim().eq_ignore_ascii_case("y");
-
-                    if visual_mode {
-                        run_visual_mode(&machine, input_str);
-                    } else {
-                        match machine.execute(input_str, 10000) {
-                            Ok(result) => {
-                                println!("\n{}", "-".repeat(60));
-                                println!("EXECUTION RESULTS");
-                                println!("{}", "-".repeat(60));
-                                println!("Input string: '{}'", input_str);
-                                println!("Steps executed: {}", result.steps);
-                                println!("Final state: {}", result.final_state);
-                                println!("Machine halted: {}", result.halted);
-
-                                if let Some(true) = result.accepts {
-                                    println!(
-                                        "\n✓ RESULT: ACCEPTS (halts in state {})",
-                                        result.final_state
-                                    );
-                                } else if let Some(false) = result.accepts {
-                                    println!(
-                                        "\n✗ RESULT: REJECTS (final state: {})",
-                                        result.final_state
-                                    );
-                                } else {
-                                    println!("\n? RESULT: DID NOT HALT (possible infinite loop)");
-                                }
-                                println!("{}", "-".repeat(60));
-                            }
-                            Err(e) => println!("Error: {}", e),
-                        }
-                    }
-                }
+                run_inputs(config, &machine);
             }
             Err(e) => println!("Error creating machine: {}", e),
         },
-        Err(e) => println!("Invalid JSON: {}", e),
+        Err(e) => println!("{}", e),
     }
 }
 
-/// Load a Turing machine definition from a JSON file
-fn load_machine_from_file() {
+/// Load a Turing machine definition from a file in any supported format
+fn load_machine_from_file(config: &Config) {
     println!("\n{}", "=".repeat(60));
     println!("LOAD MACHINE FROM FILE");
     println!("{}", "=".repeat(60));
 
-    print!("Enter filename (or 'cancel' to abort): ");
-    io::stdout().flush().unwrap();
-    let mut filename = String::new();
-    io::stdin().read_line(&mut filename).unwrap();
+    let Some(filename) = prompt("Enter filename (or 'cancel' to abort): ") else {
+        return;
+    };
     let filename = filename.trim();
 
     if filename.eq_ignore_ascii_case("cancel") {
         return;
     }
 
-    match fs::read_to_string(filename) {
-        Ok(json_str) => match serde_json::from_str::<MachineJson>(&json_str) {
-            Ok(json_data) => match parse_machine_json(&json_data) {
-                Ok(machine) => {
-                    println!("\n✓ Machine loaded successfully!");
-                    println!("States: {}", machine.states.len());
-                    println!("Transitions: {}", machine.transitions.len());
+    let mut report_progress = |count: usize| {
+        eprint!("\rLoading transitions: {}", count);
+    };
+    let loaded = formats::load_machine(Path::new(filename), &mut report_progress);
+    match loaded {
+        Ok(machine) => {
+            println!("\n✓ Machine loaded successfully!");
+            println!("States: {}", machine.states().len());
+            println!("Transitions: {}", machine.transitions().len());
 
-                    loop {
-                        print!("\nEnter input string (or 'back' to return): ");
-                        io::stdout().flush().unwrap();
-                        let mut input_str = String::new();
-                        io::stdin().read_line(&mut input_str).unwrap();
-                        let input_str = input_str.trim();
+            run_inputs(config, &machine);
+        }
+        Err(e) => println!("{}", e),
+    }
+}
 
-                        if input_str.eq_ignore_ascii_case("back") {
-                            break;
-                        }
+/// Ask for inputs and run `machine` on them until the user goes back
+///
+/// Several inputs can be entered at once, separated by `;`. In visual mode
+/// their runs are all recorded first and can be switched between.
+fn run_inputs(config: &Config, machine: &TuringMachine) {
+    while let Some(line) =
+        prompt("\nEnter input string(s), separated by ';' (or 'back' to return): ")
+    {
+        let line = line.trim();
 
-                        // Ask if user wants visual mode
-                        print!("Run in visual step-by-step mode? (y/n): ");
-                        io::stdout().flush().unwrap();
-                        let mut visual_mode = String::new();
-                        io::stdin().read_line(&mut visual_mode).unwrap();
-                        let visual_mode = visual_mode.trim().eq_ignore_ascii_case("y");
+        if line.eq_ignore_ascii_case("back") {
+            break;
+        }
+        let inputs: Vec<String> = line
+            .split(';')
+            .map(|input| input.trim().to_string())
+            .collect();
 
-                        if visual_mode {
-                            run_visual_mode(&machine, input_str);
-                        } else {
-                            match machine.execute(input_str, 10000) {
-                                Ok(result) => {
-                                    println!("\n{}", "-".repeat(60));
-                                    println!("EXECUTION RESULTS");
-                                    println!("{}", "-".repeat(60));
-                                    println!("Input string: '{}'", input_str);
-                                    println!("Steps executed: {}", result.steps);
-                                    println!("Final state: {}", result.final_state);
-                                    println!("Machine halted: {}", result.halted);
-
-                                    if let Some(true) = result.accepts {
-                                        println!(
-                                            "\n✓ RESULT: ACCEPTS (halts in state {})",
-                                            result.final_state
-                                        );
-                                    } else if let Some(false) = result.accepts {
-                                        println!(
-                                            "\n✗ RESULT: REJECTS (final state: {})",
-                                            result.final_state
-                                        );
-                                    } else {
-                                        println!("\n? RESULT: DID NOT HALT (possible infinite loop)");
-                                    }
-                                    println!("{}", "-".repeat(60));
-                                }
-                                Err(e) => println!("Error: {}", e),
-                            }
-                        }
-                    }
-                }
-                Err(e) => println!("Error creating machine: {}", e),
-            },
-            Err(e) => println!("Invalid JSON in file: {}", e),
-        },
-        Err(e) => println!("File error: {}", e),
+        // Ask if user wants visual mode
+        let Some(visual_mode) = prompt("Run in visual step-by-step mode? (y/n): ") else {
+            break;
+        };
+        let visual_mode = visual_mode.trim().eq_ignore_ascii_case("y");
+
+        if visual_mode {
+            run_visual_mode(config, machine, &inputs);
+        } else {
+            for input_str in &inputs {
+                print_execution(config, machine, input_str);
+            }
+        }
     }
 }
 
-/// Run visual step-by-step execution mode
-fn run_visual_mode(machine: &TuringMachine, input_str: &str) {
-    println!("\n{}", "=".repeat(60));
-    println!("{}", "VISUAL STEP-BY-STEP MODE".bold().cyan());
-    println!("{}", "=".repeat(60));
-    println!("Input: '{}'", input_str);
-    
-    // Get all execution snapshots
-    match machine.execute_step_by_step(input_str, 10000) {
-        Ok(snapshots) => {
-            if snapshots.is_empty() {
-                println!("No snapshots generated.");
-                return;
+/// Run `machine` on one input and print the results
+fn print_execution(config: &Config, machine: &TuringMachine, input_str: &str) {
+    match execute(config, machine, input_str) {
+        Ok(result) => {
+            println!("\n{}", "-".repeat(60));
+            println!("EXECUTION RESULTS");
+            println!("{}", "-".repeat(60));
+            println!("Input string: '{}'", input_str);
+            println!("Steps executed: {}", result.steps);
+            println!("Final state: {}", result.final_state);
+            println!("Machine halted: {}", result.halted);
+            if let Some(output) = &result.output {
+                println!("Output: {}", output);
+            }
+            println!("Peak memory: {}", format_bytes(result.peak_memory_bytes));
+
+            if let Some(true) = result.accepts {
+                println!(
+                    "\n✓ RESULT: ACCEPTS (halts in state {})",
+                    result.final_state
+                );
+            } else if let Some(false) = result.accepts {
+                println!("\n✗ RESULT: REJECTS (final state: {})", result.final_state);
+            } else if result.memory_limit_exceeded {
+                println!(
+                    "\n! RESULT: MEMORY LIMIT EXCEEDED (state: {})",
+                    result.final_state
+                );
+            } else if result.resource_limit == Some(ResourceLimit::Cells) {
+                println!(
+                    "\n! RESULT: CELL LIMIT EXCEEDED (state: {}, head: {})",
+                    result.final_state, result.head_position
+                );
+            } else if result.timed_out {
+                println!(
+                    "\n! RESULT: TIMED OUT (state: {}, head: {})",
+                    result.final_state, result.head_position
+                );
+                println!("Tape: {}", shown_tape(config, machine, &result));
+            } else if result.interrupted {
+                println!(
+                    "\n! RESULT: INTERRUPTED (state: {}, head: {})",
+                    result.final_state, result.head_position
+                );
+                println!("Tape: {}", shown_tape(config, machine, &result));
+            } else if let Some(cycle) = result.cycle {
+                println!(
+                    "\n? RESULT: PROVABLY NON-HALTING ({})",
+                    describe_cycle(cycle)
+                );
+            } else {
+                println!("\n? RESULT: DID NOT HALT (possible infinite loop)");
+            }
+            println!("{}", "-".repeat(60));
+        }
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+/// Convert a machine definition between file formats
+///
+/// The machine is validated before it is written, so a successful conversion
+/// always produces a loadable file.
+fn convert_machine_file(input: &Path, output: &Path) -> Result<(), String> {
+    let definition = formats::read_definition(input)?;
+    parse_machine_json(&definition).map_err(|e| format!("Error creating machine: {}", e))?;
+    formats::write_definition(output, &definition)?;
+    println!("Converted {} -> {}", input.display(), output.display());
+    Ok(())
+}
+
+/// Bundle a machine file with its metadata, tests and docs into a
+/// `.tmpkg` archive
+fn pack_machine_file(input: &Path, output: &Path) -> Result<(), String> {
+    if formats::Format::from_path(output) != Some(formats::Format::Bundle) {
+        return Err(format!(
+            "Bundles are written to .tmpkg files, not {}",
+            output.display()
+        ));
+    }
+    let definition = formats::read_definition(input)?;
+    parse_machine_json(&definition).map_err(|e| format!("Error creating machine: {}", e))?;
+    formats::write_definition(output, &definition)?;
+    println!(
+        "Packed {} -> {} ({} tests{})",
+        input.display(),
+        output.display(),
+        definition.metadata.tests.len(),
+        if definition.metadata.docs.is_some() {
+            ", docs"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+/// Write the machine of a `.tmpkg` bundle, with its metadata, tests and
+/// docs, to a machine file
+fn unpack_machine_bundle(bundle: &Path, output: &Path) -> Result<(), String> {
+    if formats::Format::from_path(bundle) != Some(formats::Format::Bundle) {
+        return Err(format!("{} is not a .tmpkg bundle", bundle.display()));
+    }
+    let definition = formats::read_definition(bundle)?;
+    parse_machine_json(&definition).map_err(|e| format!("Error creating machine: {}", e))?;
+    formats::write_definition(output, &definition)?;
+    println!("Unpacked {} -> {}", bundle.display(), output.display());
+    Ok(())
+}
+
+/// Write a machine file with a transition for every state that does not
+/// halt and every tape symbol
+fn complete_machine_file(input: &Path, output: &Path) -> Result<(), String> {
+    let mut machine = formats::load_machine(input, &mut |_| {})?;
+    let added = machine.complete();
+    formats::write_definition(output, &MachineJson::from(&machine))?;
+    println!(
+        "Completed {} -> {} ({} transitions added)",
+        input.display(),
+        output.display(),
+        added
+    );
+    Ok(())
+}
+
+/// Print a machine file in a document format: `mermaid` for a Mermaid
+/// state diagram, `tikz` for a TikZ picture, or `markdown` or `csv` for
+/// its transition table
+fn export_machine_file(path: &Path, format: &str) -> Result<(), String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let text = match format {
+        "mermaid" => diagram::mermaid(&machine),
+        "tikz" => diagram::tikz(&machine),
+        "markdown" => table::markdown(&machine),
+        "csv" => table::csv(&machine),
+        _ => {
+            return Err(format!(
+                "Unknown export format '{}', expected mermaid, tikz, markdown or csv",
+                format
+            ))
+        }
+    };
+    print!("{}", text);
+    Ok(())
+}
+
+/// Draw a machine file's state diagram as an SVG or PNG image, chosen by
+/// the output file's extension
+fn render_machine_file(path: &Path, output: &Path) -> Result<(), String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let image = match output.extension().and_then(|s| s.to_str()) {
+        Some("svg") => render::svg(&machine).into_bytes(),
+        Some("png") => render::png(&machine)?,
+        _ => return Err(format!("Unknown image extension: {}", output.display())),
+    };
+    fs::write(output, image).map_err(|e| format!("File error: {}", e))?;
+    println!("Rendered {} -> {}", path.display(), output.display());
+    Ok(())
+}
+
+/// Print a machine file's Gödel code, or with `decimal` its Gödel number
+fn encode_machine_file(path: &Path, decimal: bool) -> Result<(), String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let code = godel::encode(&machine)?;
+    if decimal {
+        println!("{}", godel::to_decimal(&code));
+    } else {
+        println!("{}", code);
+    }
+    Ok(())
+}
+
+/// Rebuild the machine of a Gödel code, or one read from stdin for `-`,
+/// and write it to a definition file; with `decimal` the code is given as
+/// the Gödel number
+fn decode_machine_file(code: &str, output: &Path, decimal: bool) -> Result<(), String> {
+    let code = if code == "-" {
+        io::read_to_string(io::stdin()).map_err(|e| format!("Error reading stdin: {}", e))?
+    } else {
+        code.to_string()
+    };
+    let code = if decimal {
+        godel::from_decimal(&code)?
+    } else {
+        code.trim().to_string()
+    };
+    let machine = godel::decode(&code)?;
+    formats::write_definition(output, &MachineJson::from(&machine))?;
+    println!(
+        "Decoded machine with {} states and {} transitions -> {}",
+        machine.states().len(),
+        machine.transitions().len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Print a machine file as a QR code for the terminal, followed by its
+/// payload, or with `svg` as an SVG image
+fn print_machine_qr(path: &Path, svg: bool) -> Result<(), String> {
+    let definition = formats::read_definition(path)?;
+    parse_machine_json(&definition).map_err(|e| format!("Error creating machine: {}", e))?;
+    let payload = qr::to_payload(&definition)?;
+    if svg {
+        println!("{}", qr::to_svg(&payload)?);
+    } else {
+        println!("{}", qr::to_text(&payload)?);
+        println!("{}", payload);
+    }
+    Ok(())
+}
+
+/// Decode a QR payload, or one read from stdin for `-`, and write the
+/// machine to a definition file
+fn load_machine_qr(payload: &str, output: &Path) -> Result<(), String> {
+    let payload = if payload == "-" {
+        io::read_to_string(io::stdin()).map_err(|e| format!("Error reading stdin: {}", e))?
+    } else if Path::new(payload).is_file() {
+        return Err(
+            "Reading QR codes from images is not supported; scan the code and pass the text it holds"
+                .to_string(),
+        );
+    } else {
+        payload.to_string()
+    };
+    let definition = qr::from_payload(&payload)?;
+    parse_machine_json(&definition).map_err(|e| format!("Error creating machine: {}", e))?;
+    formats::write_definition(output, &definition)?;
+    println!("Loaded machine -> {}", output.display());
+    Ok(())
+}
+
+/// The tape `result` left, shown as `config` says for `machine` and cut
+/// short if long
+fn shown_tape(config: &Config, machine: &TuringMachine, result: &ExecutionResult) -> String {
+    let mode = config.with_machine_defaults(machine.run_defaults()).output_mode;
+    let blank = machine.symbols().name(machine.blank_symbol());
+    format_tape_preview(&mode.render(result, &blank))
+}
+
+/// Shorten a long tape for printing, keeping its beginning
+fn format_tape_preview(tape: &str) -> String {
+    const LIMIT: usize = 200;
+    let cells = tape.chars().count();
+    if cells <= LIMIT {
+        return tape.to_string();
+    }
+    let start: String = tape.chars().take(LIMIT).collect();
+    format!("{}... ({} cells)", start, cells)
+}
+
+/// Read a line from stdin, or `None` at the end of input or on a read error
+fn read_line() -> Option<String> {
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
+
+/// Print `message` and read the answer from stdin, see [`read_line`]
+fn prompt(message: &str) -> Option<String> {
+    print!("{}", message);
+    // A prompt that cannot be shown is not worth aborting for
+    let _ = io::stdout().flush();
+    read_line()
+}
+
+/// Call `f` with Ctrl+C interrupting its run instead of exiting the program
+fn interruptible<T>(f: impl FnOnce(&'static AtomicBool) -> T) -> T {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    RUNNING.store(true, Ordering::SeqCst);
+    let result = f(&INTERRUPTED);
+    RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Execute a machine; Ctrl+C stops the run and returns the partial result
+fn execute(
+    config: &Config,
+    machine: &TuringMachine,
+    input_str: &str,
+) -> Result<ExecutionResult, String> {
+    execute_observed(config, machine, input_str, Vec::new())
+}
+
+/// Execute a machine like [`execute`], reporting every step to `observers`
+fn execute_observed(
+    config: &Config,
+    machine: &TuringMachine,
+    input_str: &str,
+    observers: Vec<&mut dyn StepObserver>,
+) -> Result<ExecutionResult, String> {
+    let run_config = config
+        .with_machine_defaults(machine.run_defaults())
+        .run_config();
+    let mut progress = ProgressDisplay::default();
+    let result = interruptible(|flag| {
+        let mut executor = Executor::new(machine).interrupt_on(flag);
+        if io::stderr().is_terminal() {
+            executor = executor.observe(&mut progress);
+        }
+        for observer in observers {
+            executor = executor.observe(observer);
+        }
+        executor.run_with(input_str, &run_config)
+    });
+    progress.finish();
+    result
+}
+
+/// Steps between updates of the progress display
+const PROGRESS_INTERVAL: usize = 1 << 20;
+
+/// Observer showing the step count and memory use of long runs on stderr
+#[derive(Default)]
+struct ProgressDisplay {
+    shown: bool,
+}
+
+impl ProgressDisplay {
+    /// End the progress line, if one was shown
+    fn finish(&self) {
+        if self.shown {
+            eprintln!();
+        }
+    }
+}
+
+impl StepObserver for ProgressDisplay {
+    fn on_step(&mut self, event: &StepEvent) {
+        if event.step.is_multiple_of(PROGRESS_INTERVAL) {
+            eprint!(
+                "\rStep {}, memory {}   ",
+                event.step,
+                format_bytes(event.memory_bytes)
+            );
+            self.shown = true;
+        }
+    }
+}
+
+/// Format a step limit, `unlimited` for [`UNLIMITED_STEPS`]
+fn format_step_limit(max_steps: usize) -> String {
+    match max_steps {
+        UNLIMITED_STEPS => "unlimited".to_string(),
+        steps => steps.to_string(),
+    }
+}
+
+/// Format a byte count with a binary unit
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Format a tape as text, marking the head position with brackets
+fn format_tape_with_head(
+    tape: &[char],
+    head_position: i32,
+    blank_symbol: char,
+    symbols: &SymbolTable,
+) -> String {
+    let mut text = String::new();
+    if head_position < 0 {
+        text.push_str(&format!("[{}]", symbols.name(blank_symbol)));
+    }
+    for (i, cell) in tape.iter().enumerate() {
+        if i as i32 == head_position {
+            text.push_str(&format!("[{}]", symbols.name(*cell)));
+        } else {
+            text.push_str(&symbols.name(*cell));
+        }
+    }
+    if head_position >= tape.len() as i32 {
+        text.push_str(&format!("[{}]", symbols.name(blank_symbol)));
+    }
+    text
+}
+
+/// Colored verdict label for a stage or machine run
+fn format_verdict(accepts: Option<bool>) -> colored::ColoredString {
+    match accepts {
+        Some(true) => "ACCEPTS".green(),
+        Some(false) => "REJECTS".red(),
+        None => "DID NOT HALT".yellow(),
+    }
+}
+
+/// Colored verdict of a run, telling a machine proven to loop from one
+/// that ran out of steps
+fn format_run_verdict(result: &ExecutionResult) -> colored::ColoredString {
+    match result.cycle {
+        Some(cycle) if result.accepts.is_none() => {
+            format!("PROVABLY NON-HALTING ({})", describe_cycle(cycle)).yellow()
+        }
+        _ => format_verdict(result.accepts),
+    }
+}
+
+/// A cycle a run found, as in `cycle of length 4 detected at step 12`
+fn describe_cycle(cycle: Cycle) -> String {
+    match cycle.shift {
+        0 => format!(
+            "cycle of length {} detected at step {}",
+            cycle.length, cycle.step
+        ),
+        shift => format!(
+            "translated cycle of length {} moving {} {} {}, detected at step {}",
+            cycle.length,
+            shift.unsigned_abs(),
+            if shift.unsigned_abs() == 1 { "cell" } else { "cells" },
+            if shift > 0 { "right" } else { "left" },
+            cycle.step
+        ),
+    }
+}
+
+/// Exit code for a verdict
+fn verdict_exit_code(accepts: Option<bool>) -> i32 {
+    match accepts {
+        Some(true) => EXIT_ACCEPT,
+        Some(false) => EXIT_REJECT,
+        None => EXIT_STEP_LIMIT,
+    }
+}
+
+/// Colored label of a run: a limit or interruption, the output of a machine
+/// computing a function once it halts, or else the verdict
+fn format_outcome(machine: &TuringMachine, result: &ExecutionResult) -> String {
+    if result.interrupted {
+        "INTERRUPTED".yellow().to_string()
+    } else if result.memory_limit_exceeded {
+        "MEMORY LIMIT EXCEEDED".yellow().to_string()
+    } else if result.resource_limit == Some(ResourceLimit::Cells) {
+        "CELL LIMIT EXCEEDED".yellow().to_string()
+    } else if result.timed_out {
+        "TIMED OUT".yellow().to_string()
+    } else if machine.output().is_none() || !result.halted {
+        format_run_verdict(result).to_string()
+    } else {
+        match &result.output {
+            Some(output) => format!("{} {:?}", "OUTPUT".green(), output),
+            None => "NO OUTPUT".red().to_string(),
+        }
+    }
+}
+
+/// Exit code of a run: a machine computing a function exits as if it
+/// accepted when it halts with an output, and as if it rejected without one
+fn outcome_exit_code(machine: &TuringMachine, result: &ExecutionResult) -> i32 {
+    if machine.output().is_some() && result.halted {
+        verdict_exit_code(Some(result.output.is_some()))
+    } else {
+        verdict_exit_code(result.accepts)
+    }
+}
+
+/// Input handling requested on the command line
+#[derive(Default)]
+struct InputOptions {
+    /// `--map` entries, added to the machine's own input map
+    map: HashMap<char, char>,
+    /// `--normalize`: strip whitespace and fold case whatever the machine says
+    normalize: bool,
+    /// `--tape`: symbols on the tape before the input, replacing the machine's
+    tape: Option<String>,
+    /// `--input-start`: cell holding the first input symbol
+    input_start: Option<usize>,
+    /// `--head-start`: cell the head starts on
+    head_start: Option<usize>,
+}
+
+/// Load a machine file, applying `input_options` on top of the input handling
+/// it declares
+fn load_mapped_machine(path: &Path, input_options: &InputOptions) -> Result<TuringMachine, String> {
+    let mut machine = formats::load_machine(path, &mut |_| {})?;
+    if !input_options.map.is_empty() {
+        let mut combined = machine.input_map().clone();
+        combined.extend(&input_options.map);
+        machine
+            .set_input_map(combined)
+            .map_err(|e| format!("--map: {}", e))?;
+    }
+    if input_options.normalize {
+        machine.set_input_normalization(InputNormalization {
+            strip_whitespace: true,
+            fold_case: true,
+        });
+    }
+    let mut setup = machine.tape_setup().clone();
+    if let Some(tape) = &input_options.tape {
+        setup.contents = machine
+            .symbols()
+            .tokenize(tape)
+            .map_err(|e| format!("--tape: {}", e))?;
+    }
+    if let Some(input_start) = input_options.input_start {
+        setup.input_start = input_start;
+    }
+    if let Some(head_start) = input_options.head_start {
+        setup.head_start = Some(head_start);
+    }
+    machine.set_tape_setup(setup)?;
+    Ok(machine)
+}
+
+/// Run a machine file on one input and print the result, with the formal
+/// reasoning behind it if `explain` is set, and return the exit code
+///
+/// With `ids`, every configuration of the run is written to that file, or to
+/// stdout for `-`, one per line.
+fn run_machine_file(
+    config: &Config,
+    path: &Path,
+    input_options: &InputOptions,
+    input_str: &str,
+    output: &RunOutput,
+) -> Result<i32, String> {
+    let RunOutput {
+        explain,
+        ids,
+        warn,
+        format,
+    } = *output;
+    let machine = load_mapped_machine(path, input_options)?;
+    let mut watcher = RunWatcher::new(&machine, input_str);
+    let started = Instant::now();
+    let result = match ids {
+        Some(ids) => {
+            let out: Box<dyn Write> = if ids == "-" {
+                Box::new(io::stdout().lock())
+            } else {
+                let file = std::fs::File::create(ids).map_err(|e| format!("File error: {}", e))?;
+                Box::new(io::BufWriter::new(file))
+            };
+            let mut trace = formal::ConfigurationTrace::new(&machine, input_str, out);
+            let result =
+                execute_observed(config, &machine, input_str, vec![&mut trace, &mut watcher])?;
+            trace.finish().map_err(|e| format!("File error: {}", e))?;
+            result
+        }
+        None => execute_observed(config, &machine, input_str, vec![&mut watcher])?,
+    };
+    let code = if result.interrupted {
+        EXIT_INTERRUPTED
+    } else {
+        outcome_exit_code(&machine, &result)
+    };
+    if format != OutputFormat::Text {
+        let warnings = if warn {
+            watcher.warnings(&result)
+        } else {
+            Vec::new()
+        };
+        let record = run_record(input_str, &result, started.elapsed(), &warnings)?;
+        println!("{}", format.render(&record)?);
+        return Ok(code);
+    }
+    println!(
+        "{} (state: {}, steps: {})",
+        format_outcome(&machine, &result),
+        result.final_state,
+        result.steps
+    );
+    println!("Tape: {}", shown_tape(config, &machine, &result));
+    if result.interrupted || result.timed_out {
+        println!("Head: {}", result.head_position);
+    }
+    println!("Peak memory: {}", format_bytes(result.peak_memory_bytes));
+    if warn {
+        for warning in watcher.warnings(&result) {
+            eprintln!("{} {}", "warning:".yellow(), warning.message);
+            eprintln!("  hint: {}", warning.hint);
+        }
+    }
+    if explain {
+        println!();
+        println!("{}", formal::explain(&machine, &result));
+        println!();
+        println!("{}", formal::formal_definition(&machine));
+    }
+    Ok(code)
+}
+
+/// Inputs read by `run --stdin` before they are run together
+const BATCH_SIZE: usize = 4096;
+
+/// Run a machine file on every line read from `reader`, printing one result
+/// line per input, and return the exit code
+///
+/// Lines are run in batches of up to `batch_size`, spread over all CPU
+/// cores, and their results printed in input order. The exit code is the
+/// most severe one among the inputs: an input that cannot be run outranks
+/// one hitting a limit, which outranks a rejection. Ctrl+C stops the runs
+/// of the current batch, and results are printed up to the first run it
+/// cut short.
+///
+/// With `--format jsonl` each result is a JSON object on its own line, and
+/// with `--format json` they are printed together as an array at the end;
+/// both carry the warnings about each run if `warn` is set.
+fn run_machine_inputs(
+    config: &Config,
+    path: &Path,
+    input_options: &InputOptions,
+    reader: impl BufRead,
+    format: OutputFormat,
+    warn: bool,
+    batch_size: usize,
+) -> Result<i32, String> {
+    let machine = load_mapped_machine(path, input_options)?;
+    let run_config = config
+        .with_machine_defaults(machine.run_defaults())
+        .run_config();
+    // Text results leave the warnings out
+    let watch = warn && format != OutputFormat::Text;
+    let mut code = EXIT_ACCEPT;
+    let mut records = Vec::new();
+    let mut lines = reader.lines();
+    while code != EXIT_INTERRUPTED {
+        let inputs = lines
+            .by_ref()
+            .take(batch_size)
+            .map(|line| {
+                let mut line = line.map_err(|e| format!("Error reading input: {}", e))?;
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+                Ok(line)
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        if inputs.is_empty() {
+            break;
+        }
+        let results = interruptible(|flag| {
+            batch::map(&inputs, |input| {
+                let started = Instant::now();
+                let mut watcher = RunWatcher::new(&machine, input);
+                let mut executor = Executor::new(&machine).interrupt_on(flag);
+                if watch {
+                    executor = executor.observe(&mut watcher);
+                }
+                let result = executor.run_with(input, &run_config);
+                let elapsed = started.elapsed();
+                let warnings = match &result {
+                    Ok(result) if watch => watcher.warnings(result),
+                    _ => Vec::new(),
+                };
+                (result, elapsed, warnings)
+            })
+        });
+        for (input, (result, elapsed, warnings)) in inputs.iter().zip(results) {
+            let record = match result {
+                Ok(result) => {
+                    code = if result.interrupted {
+                        EXIT_INTERRUPTED
+                    } else {
+                        code.max(outcome_exit_code(&machine, &result))
+                    };
+                    if format == OutputFormat::Text {
+                        println!(
+                            "{:?}: {} (state: {}, steps: {})",
+                            input,
+                            format_outcome(&machine, &result),
+                            result.final_state,
+                            result.steps
+                        );
+                        None
+                    } else {
+                        Some(run_record(input, &result, elapsed, &warnings)?)
+                    }
+                }
+                Err(e) => {
+                    code = code.max(EXIT_ERROR);
+                    if format == OutputFormat::Text {
+                        println!("{:?}: {} ({})", input, "ERROR".red(), e);
+                        None
+                    } else {
+                        Some(serde_json::json!({ "input": input, "error": e }))
+                    }
+                }
+            };
+            match (format, record) {
+                (OutputFormat::Json, Some(record)) => records.push(record),
+                (OutputFormat::Jsonl, Some(record)) => println!("{}", format.render(&record)?),
+                _ => {}
+            }
+            if code == EXIT_INTERRUPTED {
+                break;
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", format.render(&serde_json::Value::Array(records))?);
+    }
+    Ok(code)
+}
+
+/// What `run` prints about a single input
+#[derive(Clone, Copy)]
+struct RunOutput<'a> {
+    /// `--explain`: the formal reasoning behind the verdict
+    explain: bool,
+    /// `--ids`: the file to write the configurations to, `-` for standard
+    /// output
+    ids: Option<&'a str>,
+    /// Warnings about suspicious runs, unless `--no-warnings`
+    warn: bool,
+    format: OutputFormat,
+}
+
+/// How `run` prints results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Decorated lines for people
+    Text,
+    /// Indented JSON, a single document even for `--stdin`
+    Json,
+    /// Compact JSON, one line per result
+    Jsonl,
+}
+
+impl OutputFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" => Some(OutputFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    fn render(self, value: &serde_json::Value) -> Result<String, String> {
+        let text = match self {
+            OutputFormat::Json => serde_json::to_string_pretty(value),
+            _ => serde_json::to_string(value),
+        };
+        text.map_err(|e| e.to_string())
+    }
+}
+
+/// A run as printed by `run --format json`: its input, the fields of the
+/// result and the time taken in milliseconds, with any warnings
+fn run_record(
+    input: &str,
+    result: &ExecutionResult,
+    elapsed: Duration,
+    warnings: &[Warning],
+) -> Result<serde_json::Value, String> {
+    let mut record = serde_json::Map::new();
+    record.insert("input".to_string(), input.into());
+    let serde_json::Value::Object(fields) =
+        serde_json::to_value(result).map_err(|e| e.to_string())?
+    else {
+        unreachable!("results serialize as objects");
+    };
+    record.extend(fields);
+    record.insert(
+        "elapsed_ms".to_string(),
+        (elapsed.as_micros() as f64 / 1000.0).into(),
+    );
+    if !warnings.is_empty() {
+        let warnings = warnings
+            .iter()
+            .map(|warning| serde_json::json!({ "message": warning.message, "hint": warning.hint }))
+            .collect();
+        record.insert("warnings".to_string(), serde_json::Value::Array(warnings));
+    }
+    Ok(serde_json::Value::Object(record))
+}
+
+/// Race a machine file on several inputs, printing the winning input or why
+/// there is none, and return the exit code
+///
+/// With `length`, the inputs are all strings of that length over the
+/// machine's input alphabet instead of `inputs`.
+fn run_race(
+    config: &Config,
+    path: &Path,
+    input_options: &InputOptions,
+    inputs: &[String],
+    length: Option<usize>,
+) -> Result<i32, String> {
+    let machine = load_mapped_machine(path, input_options)?;
+    let config = &config.with_machine_defaults(machine.run_defaults());
+    let inputs = match length {
+        Some(length) => match race::count_inputs_of_length(&machine, length) {
+            Some(count) if count <= MAX_RACE_INPUTS => race::inputs_of_length(&machine, length),
+            _ => {
+                return Err(format!(
+                    "Too many inputs of length {} (at most {} can be raced)",
+                    length, MAX_RACE_INPUTS
+                ))
+            }
+        },
+        None => inputs.to_vec(),
+    };
+    let outcome = interruptible(|interrupt| {
+        race::race(
+            &machine,
+            &inputs,
+            &config.run_config(),
+            Some(interrupt),
+        )
+    });
+
+    let (mut accepted, mut rejected, mut stopped, mut open, mut errors) = (0, 0, 0, 0, 0);
+    for (input, result) in inputs.iter().zip(&outcome.results) {
+        match result {
+            Ok(result) if result.interrupted => stopped += 1,
+            Ok(result) => match result.accepts {
+                Some(true) => accepted += 1,
+                Some(false) => rejected += 1,
+                None => open += 1,
+            },
+            Err(e) => {
+                println!("{:?}: {} ({})", input, "ERROR".red(), e);
+                errors += 1;
+            }
+        }
+    }
+    match outcome.winner {
+        Some(index) => {
+            let Ok(result) = &outcome.results[index] else {
+                unreachable!("the winner was accepted");
+            };
+            println!(
+                "{} {:?} (state: {}, steps: {})",
+                format_verdict(Some(true)),
+                inputs[index],
+                result.final_state,
+                result.steps
+            );
+        }
+        None if INTERRUPTED.load(Ordering::SeqCst) => {
+            println!("{} before any input was accepted", "INTERRUPTED".yellow())
+        }
+        None if outcome.accepts() == Some(false) => {
+            println!("{} every input", format_verdict(Some(false)))
+        }
+        None if open > 0 => println!(
+            "{} on {} inputs; no input was accepted",
+            format_verdict(None),
+            open
+        ),
+        None => println!("No input was accepted"),
+    }
+    println!(
+        "Raced {} inputs: {} accepted, {} rejected, {} stopped early, {} did not halt, {} errors",
+        inputs.len(),
+        accepted,
+        rejected,
+        stopped,
+        open,
+        errors
+    );
+
+    Ok(if outcome.winner.is_some() {
+        EXIT_ACCEPT
+    } else if INTERRUPTED.load(Ordering::SeqCst) {
+        EXIT_INTERRUPTED
+    } else if errors > 0 {
+        EXIT_ERROR
+    } else {
+        verdict_exit_code(outcome.accepts())
+    })
+}
+
+/// Dovetail a machine file over every input, shortest first, printing each
+/// accepted input as it is found until `count` have been found, and return
+/// the exit code
+fn run_dovetail(
+    config: &Config,
+    path: &Path,
+    input_options: &InputOptions,
+    count: usize,
+) -> Result<i32, String> {
+    let machine = load_mapped_machine(path, input_options)?;
+    let config = &config.with_machine_defaults(machine.run_defaults());
+    let (mut accepted, mut rejected, mut open, mut errors) = (0, 0, 0, 0);
+    let tried = interruptible(|interrupt| {
+        let mut dovetail = Dovetail::new(
+            &machine,
+            dovetail::all_inputs(&machine),
+            &config.run_config(),
+        )
+        .interrupt_on(interrupt);
+        for finished in dovetail.by_ref() {
+            match finished.result {
+                Ok(result) => match result.accepts {
+                    Some(true) => {
+                        println!(
+                            "{} {:?} (state: {}, steps: {})",
+                            format_verdict(Some(true)),
+                            finished.input,
+                            result.final_state,
+                            result.steps
+                        );
+                        accepted += 1;
+                    }
+                    Some(false) => rejected += 1,
+                    None => open += 1,
+                },
+                Err(e) => {
+                    println!("{:?}: {} ({})", finished.input, "ERROR".red(), e);
+                    errors += 1;
+                }
+            }
+            if accepted == count {
+                break;
+            }
+        }
+        dovetail.admitted()
+    });
+
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        println!("{}", "INTERRUPTED".yellow());
+    }
+    println!(
+        "Tried {} inputs: {} accepted, {} rejected, {} did not halt, {} errors, {} still running",
+        tried,
+        accepted,
+        rejected,
+        open,
+        errors,
+        tried - accepted - rejected - open - errors
+    );
+    Ok(if INTERRUPTED.load(Ordering::SeqCst) {
+        EXIT_INTERRUPTED
+    } else {
+        EXIT_ACCEPT
+    })
+}
+
+/// Run a multi-head machine file on one input and print the result
+/// Benchmark a machine file on every input, printing what its runs took,
+/// and return the exit code
+fn run_bench(
+    config: &Config,
+    path: &Path,
+    input_options: &InputOptions,
+    inputs: &[String],
+    iterations: usize,
+    format: OutputFormat,
+) -> Result<i32, String> {
+    let machine = load_mapped_machine(path, input_options)?;
+    let run_config = config
+        .with_machine_defaults(machine.run_defaults())
+        .run_config();
+    let mut records = Vec::new();
+    for input in inputs {
+        let bench = interruptible(|flag| {
+            bench::bench(
+                &machine,
+                input,
+                &run_config,
+                iterations,
+                Some(&ALLOCATOR),
+                Some(flag),
+            )
+        })?;
+        if format == OutputFormat::Text {
+            print_bench(&machine, input, &bench);
+        } else {
+            let record = bench_record(input, &bench);
+            match format {
+                OutputFormat::Jsonl => println!("{}", format.render(&record)?),
+                _ => records.push(record),
+            }
+        }
+        if bench.result.interrupted {
+            break;
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", format.render(&serde_json::Value::Array(records))?);
+    }
+    Ok(if INTERRUPTED.load(Ordering::SeqCst) {
+        EXIT_INTERRUPTED
+    } else {
+        EXIT_ACCEPT
+    })
+}
+
+/// Print the measurements of the runs of a machine on one input
+fn print_bench(machine: &TuringMachine, input: &str, bench: &Bench) {
+    let result = &bench.result;
+    println!(
+        "{:?}: {} (state: {}, steps: {}), {} runs",
+        input,
+        format_outcome(machine, result),
+        result.final_state,
+        result.steps,
+        bench.times.len()
+    );
+    if let Some(steps_per_second) = bench.steps_per_second() {
+        println!("  Speed:       {} steps/s", format_rate(steps_per_second));
+    }
+    if let Some(time) = bench.wall_time() {
+        println!(
+            "  Wall time:   min {:.2?}, median {:.2?}, mean {:.2?}, p90 {:.2?}, max {:.2?}",
+            time.min, time.median, time.mean, time.p90, time.max
+        );
+    }
+    println!(
+        "  Tape:        {} cells at start, {} at end (+{})",
+        bench.start_cells,
+        bench.end_cells,
+        bench.end_cells - bench.start_cells
+    );
+    if let Some(allocations) = bench.allocations {
+        println!(
+            "  Allocations: {} per run, {}",
+            allocations.count,
+            format_bytes(allocations.bytes)
+        );
+    }
+}
+
+/// The measurements of `bench` as printed by `bench --format json`, with
+/// times in milliseconds
+fn bench_record(input: &str, bench: &Bench) -> serde_json::Value {
+    let ms = |time: Duration| time.as_nanos() as f64 / 1e6;
+    let result = &bench.result;
+    serde_json::json!({
+        "input": input,
+        "accepts": result.accepts,
+        "halted": result.halted,
+        "interrupted": result.interrupted,
+        "steps": result.steps,
+        "runs": bench.times.len(),
+        "steps_per_second": bench.steps_per_second(),
+        "wall_time_ms": bench.wall_time().map(|time| serde_json::json!({
+            "min": ms(time.min),
+            "median": ms(time.median),
+            "mean": ms(time.mean),
+            "p90": ms(time.p90),
+            "max": ms(time.max),
+        })),
+        "tape_cells": { "start": bench.start_cells, "end": bench.end_cells },
+        "allocations": bench.allocations.map(|allocations| serde_json::json!({
+            "count": allocations.count,
+            "bytes": allocations.bytes,
+        })),
+    })
+}
+
+/// Format a rate with a decimal unit
+fn format_rate(rate: f64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if rate < 1000.0 {
+        return format!("{:.0}", rate);
+    }
+    let mut value = rate / 1000.0;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+fn run_multihead_file(
+    config: &Config,
+    path: &Path,
+    input_str: &str,
+) -> Result<ExecutionResult, String> {
+    let machine = MultiHeadMachine::load(path)?;
+    let result = machine.execute(input_str, config.max_steps)?;
+    println!(
+        "{} (state: {}, steps: {})",
+        format_verdict(result.accepts),
+        result.final_state,
+        result.steps
+    );
+    println!("Tape: {}", format_tape_preview(&result.tape));
+    Ok(result)
+}
+
+/// Largest grid region printed after a grid machine run, in cells
+const GRID_PRINT_WIDTH: i64 = 120;
+const GRID_PRINT_HEIGHT: i64 = 60;
+
+/// Rows of the grid shown in visual mode
+const GRID_VIEW_HEIGHT: usize = 15;
+
+/// Run a grid machine file on one input and print the result with the
+/// written region of the grid
+fn run_grid_file(config: &Config, path: &Path, input_str: &str) -> Result<Option<bool>, String> {
+    let machine = GridMachine::load(path)?;
+    let mut run = machine.start(input_str)?;
+    run.run(config.max_steps);
+    let accepts = run.verdict();
+    println!(
+        "{} (state: {}, steps: {})",
+        format_verdict(accepts),
+        run.state(),
+        run.steps()
+    );
+    match run.grid().bounds() {
+        None => println!("Grid: blank"),
+        Some((from, to)) => {
+            println!(
+                "Grid: {} non-blank cells in x {}..{}, y {}..{}",
+                run.grid().len(),
+                from.0,
+                to.0,
+                from.1,
+                to.1
+            );
+            if to.0 - from.0 < GRID_PRINT_WIDTH && to.1 - from.1 < GRID_PRINT_HEIGHT {
+                for row in run.grid().rows(from, to) {
+                    println!("  {}", row);
+                }
+            } else {
+                println!("  (too large to print; use --visual to look around the head)");
+            }
+        }
+    }
+    Ok(accepts)
+}
+
+/// Step through a grid machine run interactively, showing the grid around
+/// the head
+///
+/// Only the current configuration is kept; going back replays the run from
+/// the start.
+fn run_grid_visual(config: &Config, path: &Path, input_str: &str) -> Result<(), String> {
+    let machine = GridMachine::load(path)?;
+    let replay = |step: usize| -> Result<GridRun, String> {
+        let mut run = machine.start(input_str)?;
+        run.run(step);
+        Ok(run)
+    };
+    let mut run = replay(0)?;
+
+    loop {
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "GRID STEP-BY-STEP MODE".bold().cyan());
+        println!("{}", "=".repeat(60));
+        println!("Input: '{}'", input_str);
+        println!("Step: {}", run.steps());
+        println!("Current State: {}", run.state().bold().yellow());
+        run.display_grid(config.tape_width, GRID_VIEW_HEIGHT);
+
+        let halted = run.verdict().is_some();
+        let at_limit = run.steps() >= config.max_steps;
+        match run.verdict() {
+            Some(true) => println!(
+                "✓ Machine has {} - in ACCEPT state",
+                "HALTED".green().bold()
+            ),
+            Some(false) => println!("✗ Machine has {} - REJECTED", "HALTED".red().bold()),
+            None if at_limit => println!(
+                "Step limit of {} reached",
+                format_step_limit(config.max_steps)
+            ),
+            None => println!("Machine is running..."),
+        }
+
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "CONTROLS".bold());
+        print!("Commands: ");
+        if run.steps() > 0 {
+            print!("[{}] Previous  ", "p".bold());
+        }
+        if !halted && !at_limit {
+            print!("[{}] Next  ", "n".bold());
+        }
+        print!("[{}] Jump to step  [{}] Quit", "j".bold(), "q".bold());
+        println!("\n{}", "=".repeat(60));
+
+        let Some(command) = prompt("\nEnter command: ") else {
+            break;
+        };
+        match command.trim().to_lowercase().as_str() {
+            "n" | "next" | "" if !halted && !at_limit => {
+                run.step();
+            }
+            "p" | "prev" | "previous" if run.steps() > 0 => {
+                run = replay(run.steps() - 1)?;
+            }
+            "j" | "jump" => {
+                let Some(step_str) = prompt(&format!(
+                    "Enter step number (0-{}): ",
+                    format_step_limit(config.max_steps)
+                )) else {
+                    break;
+                };
+                match step_str.trim().parse::<usize>() {
+                    Ok(step) if step <= config.max_steps => {
+                        if step < run.steps() {
+                            run = replay(step)?;
+                        } else {
+                            run.run(step);
+                        }
+                    }
+                    _ => {
+                        println!("Invalid step number. Press Enter to continue...");
+                        let _ = read_line();
+                    }
+                }
+            }
+            "q" | "quit" | "exit" | "back" => break,
+            _ => {
+                println!("Invalid command. Press Enter to continue...");
+                let _ = read_line();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stack symbols shown in visual mode
+const PDA_VIEW_HEIGHT: usize = 10;
+
+/// Run a pushdown automaton file on one input and print the result with the
+/// final stack
+fn run_pda_file(config: &Config, path: &Path, input_str: &str) -> Result<Option<bool>, String> {
+    let machine = PdaMachine::load(path)?;
+    let mut run = machine.start(input_str)?;
+    run.run(config.max_steps);
+    let accepts = run.verdict();
+    println!(
+        "{} (state: {}, steps: {})",
+        format_verdict(accepts),
+        run.state(),
+        run.steps()
+    );
+    println!(
+        "Read: {} of {} input symbols",
+        run.position(),
+        run.input().len()
+    );
+    if run.stack().is_empty() {
+        println!("Stack: empty");
+    } else {
+        let stack: String = run.stack().iter().rev().collect();
+        println!("Stack: {} (top first)", stack);
+    }
+    Ok(accepts)
+}
+
+/// Step through a pushdown automaton run interactively, showing the input
+/// and the stack
+///
+/// Only the current configuration is kept; going back replays the run from
+/// the start.
+fn run_pda_visual(config: &Config, path: &Path, input_str: &str) -> Result<(), String> {
+    let machine = PdaMachine::load(path)?;
+    let replay = |step: usize| -> Result<PdaRun, String> {
+        let mut run = machine.start(input_str)?;
+        run.run(step);
+        Ok(run)
+    };
+    let mut run = replay(0)?;
+
+    loop {
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "PUSHDOWN AUTOMATON STEP-BY-STEP MODE".bold().cyan());
+        println!("{}", "=".repeat(60));
+        println!("Step: {}", run.steps());
+        println!("Current State: {}", run.state().bold().yellow());
+        run.display_stack(PDA_VIEW_HEIGHT);
+
+        let halted = run.verdict().is_some();
+        let at_limit = run.steps() >= config.max_steps;
+        match run.verdict() {
+            Some(true) => println!("✓ Automaton has {} - ACCEPTED", "HALTED".green().bold()),
+            Some(false) => println!("✗ Automaton has {} - REJECTED", "HALTED".red().bold()),
+            None if at_limit => println!(
+                "Step limit of {} reached",
+                format_step_limit(config.max_steps)
+            ),
+            None => println!("Automaton is running..."),
+        }
+
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "CONTROLS".bold());
+        print!("Commands: ");
+        if run.steps() > 0 {
+            print!("[{}] Previous  ", "p".bold());
+        }
+        if !halted && !at_limit {
+            print!("[{}] Next  ", "n".bold());
+        }
+        print!("[{}] Jump to step  [{}] Quit", "j".bold(), "q".bold());
+        println!("\n{}", "=".repeat(60));
+
+        let Some(command) = prompt("\nEnter command: ") else {
+            break;
+        };
+        match command.trim().to_lowercase().as_str() {
+            "n" | "next" | "" if !halted && !at_limit => {
+                run.step();
+            }
+            "p" | "prev" | "previous" if run.steps() > 0 => {
+                run = replay(run.steps() - 1)?;
+            }
+            "j" | "jump" => {
+                let Some(step_str) = prompt(&format!(
+                    "Enter step number (0-{}): ",
+                    format_step_limit(config.max_steps)
+                )) else {
+                    break;
+                };
+                match step_str.trim().parse::<usize>() {
+                    Ok(step) if step <= config.max_steps => {
+                        if step < run.steps() {
+                            run = replay(step)?;
+                        } else {
+                            run.run(step);
+                        }
+                    }
+                    _ => {
+                        println!("Invalid step number. Press Enter to continue...");
+                        let _ = read_line();
+                    }
+                }
+            }
+            "q" | "quit" | "exit" | "back" => break,
+            _ => {
+                println!("Invalid command. Press Enter to continue...");
+                let _ = read_line();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a two-stack machine file on one input and print the result with the
+/// final stacks
+fn run_two_stack_file(
+    config: &Config,
+    path: &Path,
+    input_str: &str,
+) -> Result<Option<bool>, String> {
+    let machine = TwoStackMachine::load(path)?;
+    let mut run = machine.start(input_str)?;
+    run.run(config.max_steps);
+    let accepts = run.verdict();
+    println!(
+        "{} (state: {}, steps: {})",
+        format_verdict(accepts),
+        run.state(),
+        run.steps()
+    );
+    for (name, stack) in [("Left", run.left()), ("Right", run.right())] {
+        if stack.is_empty() {
+            println!("{} stack: empty", name);
+        } else {
+            let stack: String = stack.iter().rev().collect();
+            println!("{} stack: {} (top first)", name, stack);
+        }
+    }
+    Ok(accepts)
+}
+
+/// Step through a two-stack machine run interactively, showing both stacks
+///
+/// Only the current configuration is kept; going back replays the run from
+/// the start.
+fn run_two_stack_visual(config: &Config, path: &Path, input_str: &str) -> Result<(), String> {
+    let machine = TwoStackMachine::load(path)?;
+    let replay = |step: usize| -> Result<TwoStackRun, String> {
+        let mut run = machine.start(input_str)?;
+        run.run(step);
+        Ok(run)
+    };
+    let mut run = replay(0)?;
+
+    loop {
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "TWO-STACK MACHINE STEP-BY-STEP MODE".bold().cyan());
+        println!("{}", "=".repeat(60));
+        println!("Input: '{}'", input_str);
+        println!("Step: {}", run.steps());
+        println!("Current State: {}", run.state().bold().yellow());
+        run.display_stacks(PDA_VIEW_HEIGHT);
+
+        let halted = run.verdict().is_some();
+        let at_limit = run.steps() >= config.max_steps;
+        match run.verdict() {
+            Some(true) => println!("✓ Machine has {} - ACCEPTED", "HALTED".green().bold()),
+            Some(false) => println!("✗ Machine has {} - REJECTED", "HALTED".red().bold()),
+            None if at_limit => println!(
+                "Step limit of {} reached",
+                format_step_limit(config.max_steps)
+            ),
+            None => println!("Machine is running..."),
+        }
+
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "CONTROLS".bold());
+        print!("Commands: ");
+        if run.steps() > 0 {
+            print!("[{}] Previous  ", "p".bold());
+        }
+        if !halted && !at_limit {
+            print!("[{}] Next  ", "n".bold());
+        }
+        print!("[{}] Jump to step  [{}] Quit", "j".bold(), "q".bold());
+        println!("\n{}", "=".repeat(60));
+
+        let Some(command) = prompt("\nEnter command: ") else {
+            break;
+        };
+        match command.trim().to_lowercase().as_str() {
+            "n" | "next" | "" if !halted && !at_limit => {
+                run.step();
+            }
+            "p" | "prev" | "previous" if run.steps() > 0 => {
+                run = replay(run.steps() - 1)?;
+            }
+            "j" | "jump" => {
+                let Some(step_str) = prompt(&format!(
+                    "Enter step number (0-{}): ",
+                    format_step_limit(config.max_steps)
+                )) else {
+                    break;
+                };
+                match step_str.trim().parse::<usize>() {
+                    Ok(step) if step <= config.max_steps => {
+                        if step < run.steps() {
+                            run = replay(step)?;
+                        } else {
+                            run.run(step);
+                        }
+                    }
+                    _ => {
+                        println!("Invalid step number. Press Enter to continue...");
+                        let _ = read_line();
+                    }
+                }
+            }
+            "q" | "quit" | "exit" | "back" => break,
+            _ => {
+                println!("Invalid command. Press Enter to continue...");
+                let _ = read_line();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compile a machine file into a two-stack machine file
+fn convert_to_two_stack(input: &Path, output: &Path) -> Result<(), String> {
+    let machine = formats::load_machine(input, &mut |_| {})?;
+    let definition = twostack::from_turing_machine(&machine)?;
+    let json = serde_json::to_string_pretty(&definition).map_err(|e| e.to_string())?;
+    fs::write(output, json + "\n").map_err(|e| format!("File error: {}", e))?;
+    println!(
+        "Converted {} -> {} ({} transitions)",
+        input.display(),
+        output.display(),
+        definition.transitions.len()
+    );
+    Ok(())
+}
+
+/// Convert a non-deterministic machine file into a deterministic machine
+/// file simulating it
+fn convert_to_deterministic(input: &Path, output: &Path) -> Result<(), String> {
+    let machine = NondeterministicMachine::load(input)?;
+    let simulator = determinize(&machine)?;
+    formats::write_definition(output, &MachineJson::from(&simulator))?;
+    println!(
+        "Converted {} -> {} ({} states, {} transitions)",
+        input.display(),
+        output.display(),
+        simulator.states().len(),
+        simulator.transitions().len()
+    );
+    Ok(())
+}
+
+/// Run a machine file on an input through the universal machine and print
+/// the verdict with the configuration it ended in, or with `encode_only`
+/// just print the universal machine's input; returns the exit code
+fn run_universal(
+    config: &Config,
+    path: &Path,
+    input_str: &str,
+    encode_only: bool,
+) -> Result<i32, String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let encoding = universal::encode(&machine, input_str)?;
+    if encode_only {
+        println!("{}", encoding.tape);
+        return Ok(EXIT_ACCEPT);
+    }
+    let utm = universal::universal_machine();
+    println!(
+        "Universal machine: {} states, {} transitions; encoded input: {} symbols",
+        utm.states().len(),
+        utm.transitions().len(),
+        encoding.tape.chars().count()
+    );
+    let result = execute(config, &utm, &encoding.tape)?;
+    println!(
+        "{} (universal machine steps: {})",
+        format_outcome(&utm, &result),
+        result.steps
+    );
+    if let Some(decoded) = encoding.decode(&result.tape) {
+        println!("Simulated state: {}", decoded.state);
+        println!(
+            "Simulated tape: {}",
+            format_tape_with_head(
+                &decoded.tape,
+                decoded.head as i32,
+                machine.blank_symbol(),
+                machine.symbols()
+            )
+        );
+    }
+    Ok(outcome_exit_code(&utm, &result))
+}
+
+/// Write the universal machine's definition to a machine file
+fn save_universal(output: &Path) -> Result<(), String> {
+    let utm = universal::universal_machine();
+    formats::write_definition(output, &MachineJson::from(&utm))?;
+    println!(
+        "Wrote the universal machine to {} ({} transitions)",
+        output.display(),
+        utm.transitions().len()
+    );
+    Ok(())
+}
+
+/// Explore a non-deterministic machine file on one input and print the
+/// verdict, or follow the branch of a choice file with `--replay-choices`
+fn run_nondeterministic_file(
+    config: &Config,
+    path: &Path,
+    args: &[String],
+) -> Result<Option<bool>, String> {
+    let machine = NondeterministicMachine::load(path)?;
+    let config = &config.with_machine_defaults(machine.run_defaults());
+    let mut trace = false;
+    let mut record = None;
+    let mut replay = None;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--trace" => trace = true,
+            "--record-choices" => {
+                record = Some(options.next().ok_or("--record-choices requires a file")?);
+            }
+            "--replay-choices" => {
+                replay = Some(options.next().ok_or("--replay-choices requires a file")?);
+            }
+            _ => return Err(format!("Unknown nd option: {}", option)),
+        }
+    }
+    let input_str = &args[0];
+
+    if let Some(replay) = replay {
+        let text = fs::read_to_string(replay).map_err(|e| format!("File error: {}", e))?;
+        let choices = nondeterministic::parse_choices(&text)?;
+        let branch = machine.follow(input_str, &choices)?;
+        if trace {
+            print_nondeterministic_branch(&machine, &branch.steps, &choices);
+        }
+        println!(
+            "{} (replayed {} choices)",
+            format_verdict(branch.accepts),
+            choices.len()
+        );
+        return Ok(branch.accepts);
+    }
+
+    let exploration = machine.explore(
+        input_str,
+        config.max_steps,
+        nondeterministic::DEFAULT_MAX_CONFIGURATIONS,
+    )?;
+    let choices = exploration.accepting_choices.as_deref().unwrap_or_default();
+    if trace {
+        if let Some(branch) = &exploration.accepting_branch {
+            print_nondeterministic_branch(&machine, branch, choices);
+        }
+    }
+    if let Some(record) = record {
+        fs::write(record, nondeterministic::format_choices(choices))
+            .map_err(|e| format!("File error: {}", e))?;
+    }
+    println!(
+        "{} (configurations: {}, depth: {}, duplicates pruned: {})",
+        format_verdict(exploration.accepts),
+        exploration.configurations,
+        exploration.depth,
+        exploration.pruned
+    );
+    if let Some(branch) = &exploration.accepting_branch {
+        println!("Accepting branch: {} steps", branch.len());
+    }
+    Ok(exploration.accepts)
+}
+
+/// Print a branch of a non-deterministic run step by step, with the choice
+/// taken at each step
+fn print_nondeterministic_branch(
+    machine: &NondeterministicMachine,
+    steps: &[pipeline::TraceStep],
+    choices: &[usize],
+) {
+    let symbols = machine.symbols();
+    for (step, choice) in steps.iter().zip(choices) {
+        let transition = format!(
+            "{} --{}/{},{:?}--> {}",
+            step.from_state,
+            symbols.name(step.read_symbol),
+            symbols.name(step.write_symbol),
+            step.direction,
+            step.to_state
+        );
+        println!(
+            "step {:>5}  choice {:<3} {:<32} {}",
+            step.step,
+            choice,
+            transition,
+            format_tape_with_head(
+                &step.tape,
+                step.head_position,
+                machine.blank_symbol(),
+                symbols
+            )
+        );
+    }
+}
+
+/// Configurations `amplitude --trace` shows per step
+const TRACE_CONFIGURATIONS: usize = 8;
+
+/// Run an amplitude machine file on an input and print the probabilities
+/// of acceptance and rejection, returning the verdict of the majority: true
+/// if the input is accepted with probability over 1/2, false if rejected
+/// with probability over 1/2
+fn run_amplitude_file(
+    config: &Config,
+    path: &Path,
+    args: &[String],
+) -> Result<Option<bool>, String> {
+    let machine = AmplitudeMachine::load(path)?;
+    let config = &config.with_machine_defaults(machine.run_defaults());
+    let mut max_configurations = amplitude::DEFAULT_MAX_CONFIGURATIONS;
+    let mut trace = false;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--trace" => trace = true,
+            "--max-configurations" => {
+                max_configurations = options
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--max-configurations requires a number")?;
+            }
+            _ => return Err(format!("Unknown amplitude option: {}", option)),
+        }
+    }
+
+    let mut superposition = Superposition::new(&machine, &args[0], max_configurations)?;
+    if trace {
+        while !superposition.is_finished() && superposition.steps() < config.max_steps {
+            superposition.step();
+            let configurations = superposition.configurations();
+            println!(
+                "step {:>5}  {} running, P(accept) = {:.4}, P(reject) = {:.4}",
+                superposition.steps(),
+                configurations.len(),
+                superposition.accept_probability(),
+                superposition.reject_probability()
+            );
+            for (configuration, amplitude) in configurations.iter().take(TRACE_CONFIGURATIONS) {
+                println!(
+                    "    {:<16} {:<12} {}",
+                    amplitude.to_string(),
+                    configuration.state,
+                    format_tape_with_head(
+                        &configuration.tape,
+                        configuration.head,
+                        machine.blank_symbol(),
+                        machine.symbols()
+                    )
+                );
+            }
+            if configurations.len() > TRACE_CONFIGURATIONS {
+                println!(
+                    "    ... {} more",
+                    configurations.len() - TRACE_CONFIGURATIONS
+                );
+            }
+        }
+    } else {
+        superposition.run(config.max_steps);
+    }
+
+    let (accepted, rejected) = (
+        superposition.accept_probability(),
+        superposition.reject_probability(),
+    );
+    println!("P(accept) = {:.4}", accepted);
+    println!("P(reject) = {:.4}", rejected);
+    if !superposition.is_finished() {
+        println!("P(running) = {:.4}", superposition.running_probability());
+    }
+    if superposition.pruned_probability() > 0.0 {
+        println!(
+            "P(pruned) = {:.4} (more than {} configurations)",
+            superposition.pruned_probability(),
+            max_configurations
+        );
+    }
+    let accepts = if accepted > 0.5 {
+        Some(true)
+    } else if rejected > 0.5 {
+        Some(false)
+    } else {
+        None
+    };
+    let verdict = match accepts {
+        None if superposition.is_finished() => "NO MAJORITY".yellow(),
+        _ => format_verdict(accepts),
+    };
+    println!(
+        "{} (steps: {}, peak configurations: {})",
+        verdict,
+        superposition.steps(),
+        superposition.peak_configurations()
+    );
+    Ok(accepts)
+}
+
+/// Size of the ASCII trajectory chart, in columns and rows
+const CHART_WIDTH: usize = 72;
+const CHART_HEIGHT: usize = 20;
+
+/// Run a machine file on several inputs and print the overlaid head
+/// trajectories, as an ASCII chart or, with `svg`, as an SVG image
+fn run_trajectories(
+    config: &Config,
+    path: &Path,
+    input_options: &InputOptions,
+    inputs: &[String],
+    svg: bool,
+) -> Result<(), String> {
+    let machine = load_mapped_machine(path, input_options)?;
+    let config = &config.with_machine_defaults(machine.run_defaults());
+    let trajectories = inputs
+        .iter()
+        .map(|input| {
+            Trajectory::record(&machine, input, config.max_steps)
+                .map_err(|e| format!("Input '{}': {}", input, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if svg {
+        print!("{}", trajectory::svg_chart(&trajectories));
+    } else {
+        print!(
+            "{}",
+            trajectory::ascii_chart(&trajectories, CHART_WIDTH, CHART_HEIGHT)
+        );
+    }
+    Ok(())
+}
+
+/// Generations of an elementary cellular automaton shown by default
+const CA_GENERATIONS: usize = 32;
+
+/// Run an elementary cellular automaton and print its space-time diagram
+///
+/// `args` are the rule, an optional initial row and the options. The row is
+/// wide enough by default for a single cell's influence to reach neither
+/// edge.
+fn run_cellular(args: &[String]) -> Result<(), String> {
+    let rule = cellular::Rule::parse(&args[0])?;
+    let mut pattern = "";
+    let mut generations = CA_GENERATIONS;
+    let mut width = None;
+    let mut svg = false;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--svg" => svg = true,
+            "--generations" => {
+                generations = options
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--generations requires a number")?;
+            }
+            "--width" => {
+                width = Some(
+                    options
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or("--width requires a number")?,
+                );
+            }
+            _ if option.starts_with("--") => {
+                return Err(format!("Unknown ca option: {}", option));
+            }
+            _ => pattern = option,
+        }
+    }
+    let width = width.unwrap_or(2 * generations + pattern.len().max(1));
+    let history = rule.run(cellular::initial_row(pattern, width)?, generations);
+    if svg {
+        print!("{}", cellular::svg_diagram(&history));
+    } else {
+        print!("{}", cellular::ascii_diagram(&history));
+    }
+    Ok(())
+}
+
+/// Run a pipeline file on an input and print the combined trace, returning
+/// the pipeline's verdict
+fn run_pipeline(
+    config: &Config,
+    path: &Path,
+    input_str: &str,
+    trace: bool,
+) -> Result<Option<bool>, String> {
+    let pipeline = pipeline::Pipeline::load(path)?;
+    let result = pipeline.run(input_str, config.max_steps, trace)?;
+
+    println!("{}", "=".repeat(60));
+    println!("PIPELINE: {}", path.display());
+    println!("{}", "=".repeat(60));
+    for (i, run) in result.runs.iter().enumerate() {
+        match run.vote {
+            Some(rule) => println!("[{}] {} (vote: {})", i + 1, run.stage.bold(), rule),
+            None => println!(
+                "[{}] {} ({})",
+                i + 1,
+                run.stage.bold(),
+                run.machines[0].machine.display()
+            ),
+        }
+        println!("    Input: '{}'", run.input);
+        for machine_run in &run.machines {
+            if run.vote.is_some() {
+                println!("    {}", machine_run.machine.display());
+            }
+            if let Some(trace) = &machine_run.trace {
+                for step in trace {
+                    let symbols = &machine_run.symbols;
+                    let transition = format!(
+                        "{} --{}/{},{:?}--> {}",
+                        step.from_state,
+                        symbols.name(step.read_symbol),
+                        symbols.name(step.write_symbol),
+                        step.direction,
+                        step.to_state
+                    );
+                    println!(
+                        "    step {:>5}  {:<32} {}",
+                        step.step,
+                        transition,
+                        format_tape_with_head(
+                            &step.tape,
+                            step.head_position,
+                            machine_run.blank_symbol,
+                            symbols
+                        )
+                    );
+                }
+            }
+            println!(
+                "    -> {} (state: {}, steps: {})",
+                format_run_verdict(&machine_run.result),
+                machine_run.result.final_state,
+                machine_run.result.steps
+            );
+        }
+        if run.vote.is_some() {
+            println!("    => {}", format_verdict(run.accepts));
+        }
+    }
+
+    println!("{}", "-".repeat(60));
+    match result.accepts {
+        Some(true) => println!("✓ RESULT: ACCEPTS"),
+        Some(false) => println!("✗ RESULT: REJECTS"),
+        None => println!("? RESULT: DID NOT HALT (possible infinite loop)"),
+    }
+    println!("Stages run: {}", result.runs.len());
+    println!("Total steps: {}", result.total_steps);
+    Ok(result.accepts)
+}
+
+/// Compare the golden traces kept in `<examples_dir>/golden` with fresh
+/// recordings of the example machines, or rewrite them with `bless`
+///
+/// Returns exit code 1 if a trace changed or is missing.
+fn run_golden(config: &Config, bless: bool) -> Result<i32, String> {
+    let dir = config.examples_dir.join("golden");
+    let mut machines: Vec<_> = load_example_machines(&config.examples_dir)
+        .into_iter()
+        .collect();
+    if machines.is_empty() {
+        return Err(format!(
+            "No example machines in {}",
+            config.examples_dir.display()
+        ));
+    }
+    machines.sort_by(|a, b| a.0.cmp(&b.0));
+    if bless {
+        fs::create_dir_all(&dir).map_err(|e| format!("File error: {}", e))?;
+    }
+
+    let traces = batch::map(&machines, |(_, (machine, _))| {
+        golden::record(machine, &golden::golden_inputs(machine))
+    });
+    let mut code = EXIT_ACCEPT;
+    for ((name, _), trace) in machines.iter().zip(traces) {
+        let path = dir.join(format!("{}.trace", name));
+        if bless {
+            fs::write(&path, trace).map_err(|e| format!("File error: {}", e))?;
+            println!("{} {}", "blessed".cyan(), name);
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(kept) => match golden::compare(&kept, &trace) {
+                None => println!("{} {}", "ok".green(), name),
+                Some(difference) => {
+                    println!("{} {}: {}", "CHANGED".red().bold(), name, difference);
+                    code = EXIT_REJECT;
+                }
+            },
+            Err(_) => {
+                println!("{} {}: no golden trace", "MISSING".yellow(), name);
+                code = EXIT_REJECT;
+            }
+        }
+    }
+    if code != EXIT_ACCEPT {
+        println!("\nRun golden --bless to record missing traces and accept intended changes.");
+    }
+    Ok(code)
+}
+
+/// Print a machine file's metadata, alphabets and size as JSON
+fn run_info(path: &Path) -> Result<(), String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let info = MachineInfo::from(&machine);
+    let json = serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Print the one-line summary of a machine file
+///
+/// A file that does not load as a deterministic machine is tried as a
+/// non-deterministic one, which reports `det=no`.
+fn run_info_short(config: &Config, path: &Path) -> Result<(), String> {
+    let short = match formats::load_machine(path, &mut |_| {}) {
+        Ok(machine) => {
+            let config = config.with_machine_defaults(machine.run_defaults());
+            ShortInfo::new(&machine, config.max_steps)
+        }
+        Err(e) => match NondeterministicMachine::load(path) {
+            Ok(machine) => ShortInfo::nondeterministic(&machine),
+            Err(_) => return Err(e),
+        },
+    };
+    println!("{}", short);
+    Ok(())
+}
+
+/// Name and version of a pack, as shown in listings
+fn pack_title(pack: &Pack) -> String {
+    match &pack.manifest.version {
+        Some(version) => format!("{} {}", pack.manifest.name, version),
+        None => pack.manifest.name.clone(),
+    }
+}
+
+/// Install a content pack from a directory or `.zip` archive
+fn install_pack(source: &Path) -> Result<(), String> {
+    let pack = pack_store()?.install(source)?;
+    let manifest = &pack.manifest;
+    println!(
+        "Installed {}: {} machines, {} exercises, {} tutorials",
+        pack_title(&pack),
+        pack.machine_files().len(),
+        manifest.exercises.len(),
+        manifest.tutorials.len()
+    );
+    Ok(())
+}
+
+/// Print the installed packs, one line each
+fn list_packs() -> Result<(), String> {
+    let packs = pack_store()?.packs();
+    if packs.is_empty() {
+        println!("No packs installed");
+    }
+    for pack in packs {
+        match &pack.manifest.description {
+            Some(description) => println!("{} - {}", pack_title(&pack).bold(), description),
+            None => println!("{}", pack_title(&pack).bold()),
+        }
+    }
+    Ok(())
+}
+
+/// Print the machines, exercises and tutorials of an installed pack
+fn show_pack(name: &str) -> Result<(), String> {
+    let pack = pack_store()?.pack(name)?;
+    let manifest = &pack.manifest;
+    println!("Pack: {}", manifest.name);
+    if let Some(version) = &manifest.version {
+        println!("Version: {}", version);
+    }
+    if let Some(description) = &manifest.description {
+        println!("{}", description);
+    }
+    println!("Directory: {}", pack.dir.display());
+    println!("\nMachines:");
+    for path in pack.machine_files() {
+        println!("  {}", path.display());
+    }
+    println!("\nExercises:");
+    for exercise in &manifest.exercises {
+        println!("  {}/{} - {}", manifest.name, exercise.id, exercise.title);
+    }
+    println!("\nTutorials:");
+    for tutorial in &manifest.tutorials {
+        println!("  {}/{} - {}", manifest.name, tutorial.id, tutorial.title);
+    }
+    Ok(())
+}
+
+/// The installed pack and the item id of a `pack/id` reference
+fn find_pack_item(reference: &str) -> Result<(Pack, &str), String> {
+    let (name, id) = reference
+        .split_once('/')
+        .ok_or_else(|| format!("Expected pack/id, found '{}'", reference))?;
+    Ok((pack_store()?.pack(name)?, id))
+}
+
+/// Print the exercises of all installed packs
+fn list_exercises() -> Result<(), String> {
+    for pack in pack_store()?.packs() {
+        for exercise in &pack.manifest.exercises {
+            println!(
+                "{}/{} - {} ({} tests)",
+                pack.manifest.name,
+                exercise.id,
+                exercise.title,
+                exercise.tests.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the task of an exercise
+fn show_exercise(reference: &str) -> Result<(), String> {
+    let (pack, id) = find_pack_item(reference)?;
+    let exercise = pack
+        .exercise(id)
+        .ok_or_else(|| format!("Pack {} has no exercise '{}'", pack.manifest.name, id))?;
+    println!("{}", exercise.title.bold());
+    if let Some(description) = &exercise.description {
+        println!("\n{}", description);
+    }
+    println!("\nSample inputs:");
+    for test in &exercise.tests {
+        let expected = match (&test.output, test.accepts) {
+            (Some(output), _) => format!("outputs {:?}", output),
+            (None, Some(true)) => "accepts".to_string(),
+            (None, Some(false)) => "rejects".to_string(),
+            (None, None) => "halts".to_string(),
+        };
+        println!("  {:?} {}", test.input, expected);
+    }
+    Ok(())
+}
+
+/// Check a machine file against the tests of an exercise, returning the
+/// exit code: 0 if every test passes, 1 otherwise
+fn check_exercise(config: &Config, reference: &str, path: &Path) -> Result<i32, String> {
+    let (pack, id) = find_pack_item(reference)?;
+    let exercise = pack
+        .exercise(id)
+        .ok_or_else(|| format!("Pack {} has no exercise '{}'", pack.manifest.name, id))?;
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let config = config.with_machine_defaults(machine.run_defaults());
+    let run_config = config.run_config();
+    let checks = batch::map(&exercise.tests, |test| {
+        pack::check_test(&machine, test, &run_config)
+    });
+    let mut passed = 0;
+    for (test, check) in exercise.tests.iter().zip(checks) {
+        match check {
+            Ok(()) => {
+                println!("{} {:?}", "ok".green(), test.input);
+                passed += 1;
+            }
+            Err(e) => println!("{} {:?}: {}", "FAIL".red().bold(), test.input, e),
+        }
+    }
+    println!("\n{}/{} tests passed", passed, exercise.tests.len());
+    Ok(if passed == exercise.tests.len() {
+        EXIT_ACCEPT
+    } else {
+        EXIT_REJECT
+    })
+}
+
+/// Print the tutorials of all installed packs
+fn list_tutorials() -> Result<(), String> {
+    for pack in pack_store()?.packs() {
+        for tutorial in &pack.manifest.tutorials {
+            println!(
+                "{}/{} - {}",
+                pack.manifest.name, tutorial.id, tutorial.title
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the text of a tutorial
+fn show_tutorial(reference: &str) -> Result<(), String> {
+    let (pack, id) = find_pack_item(reference)?;
+    let tutorial = pack
+        .tutorial(id)
+        .ok_or_else(|| format!("Pack {} has no tutorial '{}'", pack.manifest.name, id))?;
+    print!("{}", pack.read_tutorial(tutorial)?);
+    Ok(())
+}
+
+/// Compute a result about a machine with `compute`, through the on-disk
+/// cache with `cached` if enabled
+///
+/// A cache that cannot be written to is reported but does not stop the
+/// computation.
+fn through_cache<T>(
+    machine: &TuringMachine,
+    use_cache: bool,
+    cached: fn(&AnalysisCache, &TuringMachine) -> Result<T, String>,
+    compute: fn(&TuringMachine) -> T,
+) -> T {
+    let cache = AnalysisCache::default_dir().map(AnalysisCache::new);
+    match cache {
+        Some(cache) if use_cache => cached(&cache, machine).unwrap_or_else(|e| {
+            tracing::warn!("{}", e);
+            compute(machine)
+        }),
+        _ => compute(machine),
+    }
+}
+
+/// Load a machine and analyze it, through the on-disk cache if enabled
+///
+/// The machine is returned too, for the names of its symbols.
+fn analyze_machine_file(path: &Path, use_cache: bool) -> Result<(TuringMachine, Analysis), String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let analysis = through_cache(
+        &machine,
+        use_cache,
+        AnalysisCache::analyze,
+        analysis::analyze,
+    );
+    Ok((machine, analysis))
+}
+
+/// Print an analysis of a machine file
+fn run_analyze(path: &Path, use_cache: bool) -> Result<(), String> {
+    let (machine, analysis) = analyze_machine_file(path, use_cache)?;
+    println!("{}", "=".repeat(60));
+    println!("ANALYSIS: {}", path.display());
+    println!("{}", "=".repeat(60));
+    println!("States: {}", analysis.state_count);
+    println!("Transitions: {}", analysis.transition_count);
+    println!("Tape symbols: {}", analysis.tape_symbol_count);
+    println!(
+        "Reachable states: {} of {}",
+        analysis.reachable_states.len(),
+        analysis.state_count
+    );
+    if !analysis.unreachable_states.is_empty() {
+        println!(
+            "Unreachable states: {}",
+            analysis.unreachable_states.join(", ")
+        );
+    }
+    if !analysis.non_halting_states.is_empty() {
+        println!(
+            "States that never halt: {}",
+            analysis.non_halting_states.join(", ")
+        );
+    }
+    let implicit = if machine.accept_on_halt() {
+        "Implicit accepts"
+    } else {
+        "Implicit rejects"
+    };
+    println!("{}: {}", implicit, analysis.implicit_rejects.len());
+    for (state, symbol) in &analysis.implicit_rejects {
+        println!("  {},{}", state, machine.symbols().name(*symbol));
+    }
+
+    let minimization = through_cache(
+        &machine,
+        use_cache,
+        AnalysisCache::minimize,
+        analysis::minimize,
+    );
+    println!(
+        "Minimal states: {} of {} reachable",
+        minimization.minimal_state_count,
+        analysis.reachable_states.len()
+    );
+    for group in &minimization.equivalent_states {
+        println!("  equivalent: {}", group.join(", "));
+    }
+    let metrics = through_cache(
+        &machine,
+        use_cache,
+        AnalysisCache::metrics,
+        analysis::metrics,
+    );
+    println!(
+        "Moves: {} left, {} right, {} stay",
+        metrics.moves_left, metrics.moves_right, metrics.stays
+    );
+    println!("Rewriting transitions: {}", metrics.rewrites);
+    println!("Self loops: {}", metrics.self_loops);
+    println!("Most transitions from a state: {}", metrics.max_out_degree);
+    println!(
+        "Cyclomatic complexity: {} ({} state edges)",
+        metrics.cyclomatic_complexity, metrics.state_edges
+    );
+    Ok(())
+}
+
+/// Print lint warnings for a machine file; exits with status 1 if there are any
+fn run_lint(path: &Path, use_cache: bool) -> Result<(), String> {
+    let (machine, analysis) = analyze_machine_file(path, use_cache)?;
+    let warnings = analysis::lint(&analysis, machine.symbols());
+    for warning in &warnings {
+        println!("{}: {} {}", path.display(), "warning:".yellow(), warning);
+    }
+    if !warnings.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Process queued jobs from a spool directory, printing one line per job
+fn run_daemon(config: &Config, args: &[String]) -> Result<(), String> {
+    let mut daemon = Daemon::new(&args[0], &args[1]).max_steps(config.max_steps);
+    let mut once = false;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--once" => once = true,
+            "--jobs" => {
+                let jobs = options
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--jobs requires a number")?;
+                daemon = daemon.workers(jobs);
+            }
+            _ => return Err(format!("Unknown daemon option: {}", option)),
+        }
+    }
+
+    println!("Watching {} for jobs", args[0]);
+    daemon.run(once, &mut |report| {
+        let outcome = match (&report.error, &report.result) {
+            (Some(e), _) => format!("{} ({})", "FAILED".red(), e),
+            (None, Some(result)) => format!(
+                "{} (state: {}, steps: {})",
+                format_run_verdict(result),
+                result.final_state,
+                result.steps
+            ),
+            (None, None) => String::new(),
+        };
+        println!("[{}] {}", report.job, outcome);
+    })
+}
+
+/// Answer HTTP requests to run uploaded machines, on `--port` (8080 by
+/// default) of `--host` (127.0.0.1 by default)
+fn run_server(config: &Config, args: &[String]) -> Result<(), String> {
+    let mut server = Server::new().max_steps(config.max_steps);
+    if let Some(max_memory) = config.max_memory {
+        server = server.max_memory(max_memory);
+    }
+    let mut host = "127.0.0.1".to_string();
+    let mut port: u16 = 8080;
+    let mut stream_port: Option<u16> = None;
+    let mut grpc_port: Option<u16> = None;
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--port" => {
+                port = options
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--port requires a port number")?;
+            }
+            "--stream-port" => {
+                stream_port = Some(
+                    options
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or("--stream-port requires a port number")?,
+                );
+            }
+            "--grpc-port" => {
+                grpc_port = Some(
+                    options
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or("--grpc-port requires a port number")?,
+                );
+            }
+            "--host" => host = options.next().ok_or("--host requires an address")?.clone(),
+            "--jobs" => {
+                let jobs = options
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--jobs requires a number")?;
+                server = server.workers(jobs);
+            }
+            _ => return Err(format!("Unknown serve option: {}", option)),
+        }
+    }
+
+    let address = format!("{}:{}", host, port);
+    println!(
+        "Serving on http://{} (step limit {})",
+        address,
+        format_step_limit(config.max_steps)
+    );
+    if let Some(stream_port) = stream_port {
+        let stream_address = format!("{}:{}", host, stream_port);
+        println!("Streaming runs on ws://{}/machines/{{id}}", stream_address);
+        server = server.stream_on(&stream_address);
+    }
+    if let Some(grpc_port) = grpc_port {
+        let grpc_address = format!("{}:{}", host, grpc_port);
+        println!("Answering gRPC calls on {}", grpc_address);
+        server = server.grpc_on(&grpc_address);
+    }
+    server.serve(&address)
+}
+
+/// Which step last changed a cell and with which transition, in two lines
+fn describe_write(cell: usize, write: &CellWrite, symbols: &SymbolTable) -> String {
+    format!(
+        "Cell {} was set to {} at step {}\n  by {} --{}/{},{:?}--> {}",
+        cell,
+        symbols.name(write.write_symbol),
+        write.step,
+        write.from_state,
+        symbols.name(write.read_symbol),
+        symbols.name(write.write_symbol),
+        write.direction,
+        write.to_state
+    )
+}
+
+/// Run a machine file and print which step last changed a cell of the final
+/// tape, numbered from its first cell as `run` prints it
+fn run_blame(config: &Config, path: &Path, input_str: &str, cell: usize) -> Result<(), String> {
+    let machine = formats::load_machine(path, &mut |_| {})?;
+    let config = &config.with_machine_defaults(machine.run_defaults());
+    let mut blame = Blame::new(&machine);
+    let result = Executor::new(&machine)
+        .observe(&mut blame)
+        .run_with(input_str, &config.run_config())?;
+    match blame.last_write(cell, result.steps) {
+        Some(write) => {
+            println!("{}", describe_write(cell, write, machine.symbols()));
+            println!(
+                "To watch it happen, jump to step {} in the visual mode",
+                write.step
+            );
+        }
+        None => println!(
+            "Cell {} was not changed by the run; it holds the symbol it started with",
+            cell
+        ),
+    }
+    Ok(())
+}
+
+/// What visual mode keeps of a run: the change made by every step, to
+/// rebuild any configuration reached, and the steps that changed each cell,
+/// to blame
+struct Recording {
+    history: History,
+    blame: Blame,
+}
+
+impl StepObserver for Recording {
+    fn on_step(&mut self, event: &StepEvent) {
+        self.history.on_step(event);
+        self.blame.on_step(event);
+    }
+
+    fn on_tape_write(&mut self, position: usize, old_symbol: char, new_symbol: char) {
+        self.blame.on_tape_write(position, old_symbol, new_symbol);
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.history.memory_bytes() + self.blame.memory_bytes()
+    }
+}
+
+/// A run shown in visual mode, computed as far as it has been viewed
+struct VisualRun<'a> {
+    machine: &'a TuringMachine,
+    input: String,
+    stepper: Stepper<'a, Recording>,
+    /// The configuration shown, rebuilt from the history of the run
+    cursor: Cursor,
+    /// The error that ended the run, if one did
+    error: Option<String>,
+    /// Step being shown, kept while another input is shown
+    current_step: usize,
+}
+
+impl VisualRun<'_> {
+    /// Last step computed so far
+    fn last_step(&self) -> usize {
+        self.stepper.observer().history.steps()
+    }
+
+    /// The configuration after `step` steps, which have been computed
+    fn snapshot(&mut self, step: usize) -> ExecutionSnapshot {
+        let history = &self.stepper.observer().history;
+        self.cursor.seek(self.machine, history, step);
+        self.cursor.snapshot(history)
+    }
+
+    /// Whether the last step computed is the last of the run
+    fn ended(&self) -> bool {
+        self.error.is_some() || self.stepper.result().is_some()
+    }
+
+    /// Compute steps until `step` is reached, the run ends or Ctrl+C is
+    /// pressed, and return the step to show: `step`, or the last one
+    /// computed if it fell short
+    fn advance_to(&mut self, step: usize) -> usize {
+        interruptible(|flag| {
+            while self.stepper.steps() < step && !flag.load(Ordering::Relaxed) {
+                match self.stepper.step() {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        self.error = Some(e);
+                        break;
+                    }
+                }
             }
+        });
+        step.min(self.last_step())
+    }
+}
 
-            let mut current_step = 0;
-            let max_step = snapshots.len() - 1;
+/// Run visual step-by-step execution mode on one or more inputs
+///
+/// Steps are computed as they are shown, so the first one is shown at once
+/// however long the run; the changes they made are kept to step back
+/// through, or with `--snapshot-every` only some configurations, the steps
+/// between them being taken again. Switching between inputs keeps the step each one was left at.
+fn run_visual_mode(config: &Config, machine: &TuringMachine, inputs: &[String]) {
+    // The session waits for the user between steps, which must not count
+    // towards a time limit
+    let run_config = RunConfig {
+        timeout: None,
+        ..config
+            .with_machine_defaults(machine.run_defaults())
+            .run_config()
+    };
+    println!("\n{}", "=".repeat(60));
+    println!("{}", "VISUAL STEP-BY-STEP MODE".bold().cyan());
+    println!("{}", "=".repeat(60));
 
-            loop {
-                // Clear screen (cross-platform approach)
-                print!("\x1B[2J\x1B[1;1H");
-                
-                let snapshot = &snapshots[current_step];
-                
-                println!("\n{}", "=".repeat(60));
-                println!("{}", "VISUAL STEP-BY-STEP MODE".bold().cyan());
-                println!("{}", "=".repeat(60));
+    let mut runs = Vec::new();
+    let mut pause = false;
+    for input_str in inputs {
+        let recording = Recording {
+            history: match config.snapshot_every {
+                Some(every) => History::sampled(machine, input_str, every),
+                None => History::new(machine, input_str),
+            },
+            blame: Blame::new(machine),
+        };
+        match Stepper::start(machine, input_str, &run_config, recording) {
+            Ok(stepper) => runs.push(VisualRun {
+                machine,
+                input: input_str.clone(),
+                cursor: Cursor::new(&stepper.observer().history),
+                stepper,
+                error: None,
+                current_step: 0,
+            }),
+            Err(e) => {
                 println!("Input: '{}'", input_str);
-                println!("Step: {}/{}", current_step, max_step);
-                println!("Current State: {}", snapshot.current_state.bold().yellow());
-                
-                // Calculate next transition
-                let next_transition = if !machine.accept_states.contains(&snapshot.current_state)
-                    && !machine.reject_states.contains(&snapshot.current_state)
-                {
-                    let head_pos = snapshot.head_position as usize;
-                    let current_symbol = if head_pos < snapshot.tape.len() {
-                        snapshot.tape[head_pos]
-                    } else {
-                        machine.blank_symbol
-                    };
-                    
-                    machine
-                        .transitions
-                        .get(&(snapshot.current_state.clone(), current_symbol))
-                        .map(|(next_state, write_symbol, direction)| {
-                            (current_symbol, next_state.as_str(), *write_symbol, *direction)
-                        })
+                println!("Error during execution: {}", e);
+                pause = true;
+            }
+        }
+    }
+    if runs.is_empty() {
+        return;
+    }
+    if pause {
+        println!("\nPress Enter to view the other runs...");
+        let _ = read_line();
+    }
+
+    let mut current_input = 0;
+    let mut show_configurations = false;
+
+    loop {
+        let run_count = runs.len();
+        let run = &mut runs[current_input];
+        let mut current_step = run.current_step;
+        let shown = current_input;
+        let (last_step, ended) = (run.last_step(), run.ended());
+
+        // Clear screen (cross-platform approach)
+        print!("\x1B[2J\x1B[1;1H");
+
+        let snapshot = run.snapshot(current_step);
+
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "VISUAL STEP-BY-STEP MODE".bold().cyan());
+        println!("{}", "=".repeat(60));
+        if run_count > 1 {
+            println!("Input {}/{}: '{}'", current_input + 1, run_count, run.input);
+        } else {
+            println!("Input: '{}'", run.input);
+        }
+        if ended {
+            println!("Step: {}/{}", snapshot.step, last_step);
+        } else {
+            println!("Step: {}", snapshot.step);
+        }
+        println!("Current State: {}", snapshot.current_state.bold().yellow());
+
+        // Calculate next transition
+        let next_transition = if !machine.accept_states().contains(&snapshot.current_state)
+            && !machine.reject_states().contains(&snapshot.current_state)
+        {
+            let head_pos = snapshot.head_position as usize;
+            let current_symbol = if head_pos < snapshot.tape.len() {
+                snapshot.tape[head_pos]
+            } else {
+                machine.blank_symbol()
+            };
+
+            machine
+                .transitions()
+                .get(&(snapshot.current_state.clone(), current_symbol))
+                .map(|(next_state, write_symbol, direction)| {
+                    (
+                        current_symbol,
+                        next_state.as_str(),
+                        *write_symbol,
+                        *direction,
+                    )
+                })
+        } else {
+            None
+        };
+
+        // Display state diagram with current state highlighted and next transition
+        machine.display_state_diagram(Some(&snapshot.current_state), next_transition);
+
+        // Display tape
+        machine.display_tape(&snapshot, config.tape_width);
+
+        // Recent configurations in u q v notation
+        if show_configurations {
+            println!("{}", "CONFIGURATIONS".bold());
+            for step in current_step.saturating_sub(9)..=current_step {
+                let earlier = run.snapshot(step);
+                println!(
+                    "  {:>5}  {}",
+                    earlier.step,
+                    formal::configuration(
+                        &earlier.tape,
+                        earlier.head_position,
+                        &earlier.current_state,
+                        machine.blank_symbol(),
+                        machine.symbols()
+                    )
+                );
+            }
+            println!();
+        }
+
+        // Display status
+        let at_end = ended && current_step == last_step;
+        let result = run.stepper.result().filter(|_| at_end);
+        println!("{}", "STATUS".bold());
+        if machine.accept_states().contains(&snapshot.current_state) {
+            println!(
+                "✓ Machine has {} - in ACCEPT state",
+                "HALTED".green().bold()
+            );
+        } else if machine.reject_states().contains(&snapshot.current_state) {
+            println!("✗ Machine has {} - in REJECT state", "HALTED".red().bold());
+        } else if let Some(error) = run.error.as_ref().filter(|_| at_end) {
+            println!("Run {} here: {}", "FAILED".red().bold(), error);
+        } else if let Some(result) = result.filter(|result| !result.halted) {
+            println!("Run stopped here: {}", format_outcome(machine, result));
+        } else if let Some(result) = result {
+            if result.accepts == Some(true) {
+                println!(
+                    "✓ Machine has {} - no transition defined (accepts by halting)",
+                    "HALTED".green().bold()
+                );
+            } else {
+                println!(
+                    "✗ Machine has {} - no transition defined (implicit reject)",
+                    "HALTED".red().bold()
+                );
+            }
+        } else {
+            println!("Machine is running...");
+        }
+        let has_next = current_step < last_step || !ended;
+
+        // Navigation controls
+        println!("\n{}", "=".repeat(60));
+        println!("{}", "CONTROLS".bold());
+        print!("Commands: ");
+        if current_step > 0 {
+            print!("[{}] Previous  ", "p".bold());
+        }
+        if has_next {
+            print!("[{}] Next  ", "n".bold());
+        }
+        if current_input > 0 {
+            print!("[{}] Previous input  ", "<".bold());
+        }
+        if current_input + 1 < run_count {
+            print!("[{}] Next input  ", ">".bold());
+        }
+        print!(
+            "[{}] Jump to step  [{}] Configurations  [{}] Blame a cell  [{}] Quit",
+            "j".bold(),
+            "i".bold(),
+            "b".bold(),
+            "q".bold()
+        );
+        println!("\n{}", "=".repeat(60));
+
+        let Some(command) = prompt("\nEnter command: ") else {
+            break;
+        };
+        let command = command.trim().to_lowercase();
+
+        match command.as_str() {
+            // Enter key defaults to next
+            "n" | "next" | "" if has_next => {
+                current_step = run.advance_to(current_step + 1);
+            }
+            "p" | "prev" | "previous" if current_step > 0 => {
+                current_step -= 1;
+            }
+            "j" | "jump" => {
+                let message = if ended {
+                    format!("Enter step number (0-{}): ", last_step)
                 } else {
-                    None
+                    "Enter step number: ".to_string()
                 };
-                
-                // Display state diagram with current state highlighted and next transition
-                machine.display_state_diagram(Some(&snapshot.current_state), next_transition);
-                
-                // Display tape
-                TuringMachine::display_tape(snapshot, machine.blank_symbol);
-                
-                // Display status
-                println!("{}", "STATUS".bold());
-                if machine.accept_states.contains(&snapshot.current_state) {
-                    println!("✓ Machine has {} - in ACCEPT state", "HALTED".green().bold());
-                } else if machine.reject_states.contains(&snapshot.current_state) {
-                    println!("✗ Machine has {} - in REJECT state", "HALTED".red().bold());
-                } else if current_step == max_step {
-                    // Check if there's a valid transition
-                    let head_pos = snapshot.head_position as usize;
-                    let current_symbol = if head_pos < snapshot.tape.len() {
-                        snapshot.tape[head_pos]
-                    } else {
-                        machine.blank_symbol
-                    };
-                    
-                    if machine
-                        .transitions
-                        .contains_key(&(snapshot.current_state.clone(), current_symbol))
-                    {
-                        println!("Machine is running...");
+                let Some(step_str) = prompt(&message) else {
+                    break;
+                };
+                if let Ok(step) = step_str.trim().parse::<usize>() {
+                    if step > last_step && ended {
+                        println!("Invalid step number. Press Enter to continue...");
+                        let _ = read_line();
                     } else {
-                        println!("✗ Machine has {} - no transition defined (implicit reject)", "HALTED".red().bold());
+                        current_step = run.advance_to(step);
+                        if current_step < step {
+                            if run.ended() {
+                                println!("The run ended at step {}.", current_step);
+                            } else {
+                                println!("Stopped at step {}.", current_step);
+                            }
+                            println!("Press Enter to continue...");
+                            let _ = read_line();
+                        }
                     }
-                } else {
-                    println!("Machine is running...");
-                }
-                
-                // Navigation controls
-                println!("\n{}", "=".repeat(60));
-                println!("{}", "CONTROLS".bold());
-                print!("Commands: ");
-                if current_step > 0 {
-                    print!("[{}] Previous  ", "p".bold());
                 }
-                if current_step < max_step {
-                    print!("[{}] Next  ", "n".bold());
-                }
-                print!("[{}] Jump to step  [{}] Quit", "j".bold(), "q".bold());
-                println!("\n{}", "=".repeat(60));
-                
-                print!("\nEnter command: ");
-                io::stdout().flush().unwrap();
-                
-                let mut command = String::new();
-                io::stdin().read_line(&mut command).unwrap();
-                let command = command.trim().to_lowercase();
-                
-                match command.as_str() {
-                    "n" | "next" if current_step < max_step => {
-                        current_step += 1;
+            }
+            ">" | "next-input" if current_input + 1 < run_count => {
+                current_input += 1;
+            }
+            "<" | "previous-input" if current_input > 0 => {
+                current_input -= 1;
+            }
+            "i" | "ids" => {
+                show_configurations = !show_configurations;
+            }
+            "b" | "blame" => {
+                let Some(cell_str) = prompt("Blame which cell (Pos): ") else {
+                    break;
+                };
+                let Ok(cell) = cell_str.trim().parse::<usize>() else {
+                    println!("Invalid cell number. Press Enter to continue...");
+                    let _ = read_line();
+                    continue;
+                };
+                let blame = &run.stepper.observer().blame;
+                match blame.last_write(cell, current_step) {
+                    Some(write) => {
+                        println!("{}", describe_write(cell, write, machine.symbols()));
+                        let Some(answer) = prompt(&format!("Jump to step {}? (y/n): ", write.step))
+                        else {
+                            break;
+                        };
+                        if answer.trim().eq_ignore_ascii_case("y") {
+                            current_step = write.step;
+                        }
                     }
-                    "p" | "prev" | "previous" if current_step > 0 => {
-                        current_step -= 1;
+                    None => {
+                        println!(
+                            "Cell {} has not been changed yet. Press Enter to continue...",
+                            cell
+                        );
+                        let _ = read_line();
                     }
-                    "j" | "jump" => {
-                        print!("Enter step number (0-{}): ", max_step);
-                        io::stdout().flush().unwrap();
-                        let mut step_str = String::new();
-                        io::stdin().read_line(&mut step_str).unwrap();
-                        if let Ok(step) = step_str.trim().parse::<usize>() {
-                            if step <= max_step {
-                                current_step = step;
-                            } else {
-                                println!("Invalid step number. Press Enter to continue...");
-                                let mut _dummy = String::new();
-                                io::stdin().read_line(&mut _dummy).unwrap();
-                            }
-                        }
+                }
+            }
+            "q" | "quit" | "exit" | "back" => {
+                break;
+            }
+            _ => {
+                println!("Invalid command. Press Enter to continue...");
+                let _ = read_line();
+            }
+        }
+        runs[shown].current_step = current_step;
+    }
+}
+
+/// Run example machines for demonstration
+fn run_examples(config: &Config) {
+    println!("Turing Machine Executor - Examples\n");
+
+    // Try to load examples from the examples folder
+    let loaded_examples = load_example_machines(&config.examples_dir);
+    
+    if !loaded_examples.is_empty() {
+        // Run all loaded examples
+        for (key, (machine, display_name)) in &loaded_examples {
+            println!("{}", "=".repeat(60));
+            println!("Machine: {}", display_name);
+            println!("File: examples/{}.json", key);
+            println!("{}", "=".repeat(60));
+            
+            // Run the machine with empty input as a basic test
+            let max_steps = config
+                .with_machine_defaults(machine.run_defaults())
+                .max_steps;
+            match machine.execute("", max_steps) {
+                Ok(result) => {
+                    print!("Input: '' -> ");
+                    if let Some(true) = result.accepts {
+                        println!(
+                            "ACCEPTS (state: {}, steps: {})",
+                            result.final_state, result.steps
+                        );
+                    } else {
+                        println!(
+                            "REJECTS (state: {}, steps: {})",
+                            result.final_state, result.steps
+                        );
                     }
-                    "q" | "quit" | "exit" | "back" => {
-                        break;
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+            println!();
+        }
+    } else {
+        // Fallback to hardcoded examples
+        let examples = create_example_machines();
+
+        // Test even ones machine
+        println!("{}", "=".repeat(60));
+        println!("Machine: Even number of 1s");
+        println!("{}", "=".repeat(60));
+
+        let machine = examples.get("even_ones").unwrap();
+        let test_cases = ["", "0", "1", "11", "101", "111", "0101", "1111"];
+
+        for test in &test_cases {
+            let result = machine.execute(test, config.max_steps).unwrap();
+            print!("Input: '{}' -> ", test);
+            if let Some(true) = result.accepts {
+                println!(
+                    "ACCEPTS (state: {}, steps: {})",
+                    result.final_state, result.steps
+                );
+            } else {
+                println!(
+                    "REJECTS (state: {}, steps: {})",
+                    result.final_state, result.steps
+                );
+            }
+        }
+
+        println!("\n{}", "=".repeat(60));
+        println!("Machine: Accept all strings");
+        println!("{}", "=".repeat(60));
+
+        let machine = examples.get("accept_all").unwrap();
+        let test_cases = ["", "ab", "01010", "111"];
+
+        for test in &test_cases {
+            let result = machine.execute(test, config.max_steps).unwrap();
+            print!("Input: '{}' -> ", test);
+            if let Some(true) = result.accepts {
+                println!(
+                    "ACCEPTS (state: {}, steps: {})",
+                    result.final_state, result.steps
+                );
+            } else {
+                println!(
+                    "REJECTS (state: {}, steps: {})",
+                    result.final_state, result.steps
+                );
+            }
+        }
+    }
+}
+
+/// Remove `-v`/`-vv`/`--verbose` flags from `args`, returning how verbose to be
+fn take_verbosity(args: &mut Vec<String>) -> usize {
+    let mut verbosity = 0;
+    args.retain(|arg| match arg.as_str() {
+        "-v" | "--verbose" => {
+            verbosity += 1;
+            false
+        }
+        "-vv" => {
+            verbosity += 2;
+            false
+        }
+        _ => true,
+    });
+    verbosity
+}
+
+/// Remove `--map "a=0,b=1"` and `--normalize` from the arguments
+fn take_input_options(args: &mut Vec<String>) -> Result<InputOptions, String> {
+    let mut input_options = InputOptions::default();
+    args.retain(|arg| {
+        let normalize = arg == "--normalize";
+        input_options.normalize |= normalize;
+        !normalize
+    });
+    let mut take_value = |flag: &str| -> Result<Option<String>, String> {
+        match args.iter().position(|arg| arg == flag) {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                Ok(Some(value))
+            }
+            Some(_) => Err(format!("Missing value for {}", flag)),
+            None => Ok(None),
+        }
+    };
+    let cell = |flag: &str, value: Option<String>| {
+        value
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("{}: '{}' is not a cell number", flag, value))
+            })
+            .transpose()
+    };
+    input_options.tape = take_value("--tape")?;
+    input_options.input_start = cell("--input-start", take_value("--input-start")?)?;
+    input_options.head_start = cell("--head-start", take_value("--head-start")?)?;
+    while let Some(i) = args.iter().position(|arg| arg == "--map") {
+        if i + 1 >= args.len() {
+            return Err("Missing value for --map".to_string());
+        }
+        let value = args.remove(i + 1);
+        args.remove(i);
+        for entry in value.split(',').filter(|entry| !entry.is_empty()) {
+            let pair = entry.split_once('=').and_then(|(from, to)| {
+                let mut from = from.chars();
+                let mut to = to.chars();
+                match (from.next(), from.next(), to.next(), to.next()) {
+                    (Some(from), None, Some(to), None) => Some((from, to)),
+                    _ => None,
+                }
+            });
+            let Some((from, to)) = pair else {
+                return Err(format!("--map: '{}' is not a mapping such as a=0", entry));
+            };
+            input_options.map.insert(from, to);
+        }
+    }
+    Ok(input_options)
+}
+
+/// Log to stderr: warnings by default, parsing, validation and execution
+/// spans with `-v`, and every transition with `-vv`
+fn init_logging(config: &Config, verbosity: usize) {
+    let level = match verbosity {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_ansi(config.color_stderr())
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+/// End the program quietly when stdout is closed, e.g. when piped into
+/// `head`, instead of panicking in `println!`
+fn exit_on_closed_pipe() {
+    #[cfg(unix)]
+    // SAFETY: restoring the default disposition of a signal before any
+    // other thread exists
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+}
+
+fn main() {
+    exit_on_closed_pipe();
+    // Check if running in example mode
+    let mut args: Vec<String> = std::env::args().collect();
+    let verbosity = take_verbosity(&mut args);
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    if let Err(e) = config.apply_flags(&mut args) {
+        eprintln!("Error: {}", e);
+        std::process::exit(EXIT_USAGE);
+    }
+    let input_options = match take_input_options(&mut args) {
+        Ok(input_options) => input_options,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    config.apply_color();
+    init_logging(&config, verbosity);
+    let handler = ctrlc::set_handler(|| {
+        if RUNNING.load(Ordering::SeqCst) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        } else {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+    if let Err(e) = handler {
+        tracing::warn!("cannot handle Ctrl+C: {}", e);
+    }
+
+    if args.len() > 1 && (args[1] == "--help" || args[1] == "-h") {
+        print!("{}", help::overview(&args[0]));
+        return;
+    }
+    if args.len() > 1 && args[1] == "help" {
+        match args.get(2).map(String::as_str) {
+            None if args.len() == 2 => print!("{}", help::overview(&args[0])),
+            Some("--man") if args.len() == 3 => print!("{}", help::man_page()),
+            Some(name) if args.len() == 3 => match help::topic(name) {
+                Some(topic) => print!("{}", help::render_topic(topic)),
+                None => {
+                    let topics: Vec<&str> = help::TOPICS.iter().map(|t| t.name).collect();
+                    eprintln!(
+                        "Error: Unknown help topic '{}' (topics: {})",
+                        name,
+                        topics.join(", ")
+                    );
+                    std::process::exit(EXIT_USAGE);
+                }
+            },
+            _ => {
+                eprintln!("{}", help::usage(&args[0], "help"));
+                std::process::exit(EXIT_USAGE);
+            }
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "--examples" {
+        run_examples(&config);
+        return;
+    }
+    if args.len() > 1 && args[1] == "pipeline" {
+        if args.len() < 4 {
+            eprintln!("{}", help::usage(&args[0], "pipeline"));
+            std::process::exit(EXIT_USAGE);
+        }
+        let trace = args[4..].iter().any(|arg| arg == "--trace");
+        match run_pipeline(&config, Path::new(&args[2]), &args[3], trace) {
+            Ok(accepts) => std::process::exit(verdict_exit_code(accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "run" {
+        let explain = match args.iter().position(|arg| arg == "--explain") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        let warn = match args.iter().position(|arg| arg == "--no-warnings") {
+            Some(i) => {
+                args.remove(i);
+                false
+            }
+            None => true,
+        };
+        let ids = match args.iter().position(|arg| arg == "--ids") {
+            Some(i) if i + 1 < args.len() => {
+                let ids = args.remove(i + 1);
+                args.remove(i);
+                Some(ids)
+            }
+            Some(_) => {
+                eprintln!("Error: Missing value for --ids");
+                std::process::exit(EXIT_USAGE);
+            }
+            None => None,
+        };
+        let format = match args.iter().position(|arg| arg == "--format") {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                match OutputFormat::parse(&value) {
+                    Some(format) => format,
+                    None => {
+                        eprintln!(
+                            "Error: --format: '{}' is not text, json or jsonl",
+                            value
+                        );
+                        std::process::exit(EXIT_USAGE);
                     }
-                    "" if current_step < max_step => {
-                        // Enter key defaults to next
-                        current_step += 1;
+                }
+            }
+            Some(_) => {
+                eprintln!("Error: Missing value for --format");
+                std::process::exit(EXIT_USAGE);
+            }
+            None => OutputFormat::Text,
+        };
+        if format != OutputFormat::Text && (explain || ids.as_deref() == Some("-")) {
+            eprintln!("Error: --explain and --ids - print text, which --format json and jsonl leave out");
+            std::process::exit(EXIT_USAGE);
+        }
+        let single = explain || ids.is_some();
+        if args.len() != 4 || (single && args[3] == "--stdin") {
+            eprintln!("{}", help::usage(&args[0], "run"));
+            if single {
+                eprintln!("--explain and --ids need a single input");
+            }
+            std::process::exit(EXIT_USAGE);
+        }
+        if args[3] == "--stdin" {
+            let stdin = io::stdin();
+            // Inputs typed one at a time are answered one at a time
+            let batch_size = if stdin.is_terminal() { 1 } else { BATCH_SIZE };
+            match run_machine_inputs(
+                &config,
+                Path::new(&args[2]),
+                &input_options,
+                stdin.lock(),
+                format,
+                warn,
+                batch_size,
+            ) {
+                Ok(code) => std::process::exit(code),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(EXIT_ERROR);
+                }
+            }
+        }
+        match run_machine_file(
+            &config,
+            Path::new(&args[2]),
+            &input_options,
+            &args[3],
+            &RunOutput {
+                explain,
+                ids: ids.as_deref(),
+                warn,
+                format,
+            },
+        ) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "race" {
+        let length = match args.iter().position(|arg| arg == "--length") {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                match value.parse::<usize>() {
+                    Ok(length) => Some(length),
+                    Err(_) => {
+                        eprintln!("Error: --length: '{}' is not a number", value);
+                        std::process::exit(EXIT_USAGE);
                     }
+                }
+            }
+            Some(_) => {
+                eprintln!("Error: Missing value for --length");
+                std::process::exit(EXIT_USAGE);
+            }
+            None => None,
+        };
+        let arity_ok = match length {
+            Some(_) => args.len() == 3,
+            None => args.len() >= 4,
+        };
+        if !arity_ok {
+            eprintln!("{}", help::usage(&args[0], "race"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_race(
+            &config,
+            Path::new(&args[2]),
+            &input_options,
+            &args[3..],
+            length,
+        ) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "dovetail" {
+        let count = match args.iter().position(|arg| arg == "--count") {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                match value.parse::<usize>() {
+                    Ok(count) if count > 0 => count,
                     _ => {
-                        println!("Invalid command. Press Enter to continue...");
-                        let mut _dummy = String::new();
-                        io::stdin().read_line(&mut _dummy).unwrap();
+                        eprintln!("Error: --count: '{}' is not a positive number", value);
+                        std::process::exit(EXIT_USAGE);
                     }
                 }
             }
+            Some(_) => {
+                eprintln!("Error: Missing value for --count");
+                std::process::exit(EXIT_USAGE);
+            }
+            None => DEFAULT_DOVETAIL_COUNT,
+        };
+        if args.len() != 3 {
+            eprintln!("{}", help::usage(&args[0], "dovetail"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_dovetail(&config, Path::new(&args[2]), &input_options, count) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
         }
-        Err(e) => println!("Error during execution: {}", e),
     }
-}
-
-/// Run example machines for demonstration
-fn run_examples() {
-    println!("Turing Machine Executor - Examples\n");
-
-    // Try to load examples from the examples folder
-    let loaded_examples = load_example_machines();
-    
-    if !loaded_examples.is_empty() {
-        // Run all loaded examples
-        for (key, (machine, display_name)) in &loaded_examples {
-            println!("{}", "=".repeat(60));
-            println!("Machine: {}", display_name);
-            println!("File: examples/{}.json", key);
-            println!("{}", "=".repeat(60));
-            
-            // Run the machine with empty input as a basic test
-            match machine.execute("", 10000) {
-                Ok(result) => {
-                    print!("Input: '' -> ");
-                    if let Some(true) = result.accepts {
-                        println!(
-                            "ACCEPTS (state: {}, steps: {})",
-                            result.final_state, result.steps
-                        );
-                    } else {
-                        println!(
-                            "REJECTS (state: {}, steps: {})",
-                            result.final_state, result.steps
-                        );
+    if args.len() > 1 && args[1] == "bench" {
+        let iterations = match args.iter().position(|arg| arg == "--iterations") {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                match value.parse::<usize>() {
+                    Ok(iterations) if iterations > 0 => iterations,
+                    _ => {
+                        eprintln!("Error: --iterations: '{}' is not a positive number", value);
+                        std::process::exit(EXIT_USAGE);
                     }
                 }
-                Err(e) => println!("Error: {}", e),
             }
-            println!();
+            Some(_) => {
+                eprintln!("Error: Missing value for --iterations");
+                std::process::exit(EXIT_USAGE);
+            }
+            None => DEFAULT_BENCH_ITERATIONS,
+        };
+        let format = match args.iter().position(|arg| arg == "--format") {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                match OutputFormat::parse(&value) {
+                    Some(format) => format,
+                    None => {
+                        eprintln!("Error: --format: '{}' is not text, json or jsonl", value);
+                        std::process::exit(EXIT_USAGE);
+                    }
+                }
+            }
+            Some(_) => {
+                eprintln!("Error: Missing value for --format");
+                std::process::exit(EXIT_USAGE);
+            }
+            None => OutputFormat::Text,
+        };
+        let mut inputs = Vec::new();
+        while let Some(i) = args.iter().position(|arg| arg == "--input") {
+            if i + 1 >= args.len() {
+                eprintln!("Error: Missing value for --input");
+                std::process::exit(EXIT_USAGE);
+            }
+            inputs.push(args.remove(i + 1));
+            args.remove(i);
         }
-    } else {
-        // Fallback to hardcoded examples
-        let examples = create_example_machines();
-
-        // Test even ones machine
-        println!("{}", "=".repeat(60));
-        println!("Machine: Even number of 1s");
-        println!("{}", "=".repeat(60));
-
-        let machine = examples.get("even_ones").unwrap();
-        let test_cases = ["", "0", "1", "11", "101", "111", "0101", "1111"];
-
-        for test in &test_cases {
-            let result = machine.execute(test, 10000).unwrap();
-            print!("Input: '{}' -> ", test);
-            if let Some(true) = result.accepts {
-                println!(
-                    "ACCEPTS (state: {}, steps: {})",
-                    result.final_state, result.steps
-                );
-            } else {
-                println!(
-                    "REJECTS (state: {}, steps: {})",
-                    result.final_state, result.steps
-                );
+        if inputs.is_empty() {
+            inputs.push(String::new());
+        }
+        if args.len() != 3 {
+            eprintln!("{}", help::usage(&args[0], "bench"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_bench(
+            &config,
+            Path::new(&args[2]),
+            &input_options,
+            &inputs,
+            iterations,
+            format,
+        ) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
             }
         }
-
-        println!("\n{}", "=".repeat(60));
-        println!("Machine: Accept all strings");
-        println!("{}", "=".repeat(60));
-
-        let machine = examples.get("accept_all").unwrap();
-        let test_cases = ["", "ab", "01010", "111"];
-
-        for test in &test_cases {
-            let result = machine.execute(test, 10000).unwrap();
-            print!("Input: '{}' -> ", test);
-            if let Some(true) = result.accepts {
-                println!(
-                    "ACCEPTS (state: {}, steps: {})",
-                    result.final_state, result.steps
-                );
-            } else {
-                println!(
-                    "REJECTS (state: {}, steps: {})",
-                    result.final_state, result.steps
-                );
+    }
+    if args.len() > 1 && args[1] == "multihead" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "multihead"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_multihead_file(&config, Path::new(&args[2]), &args[3]) {
+            Ok(result) => std::process::exit(verdict_exit_code(result.accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
             }
         }
     }
-}
-
-fn main() {
-    // Check if running in example mode
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 && args[1] == "--examples" {
-        run_examples();
+    if args.len() > 1 && args[1] == "grid" {
+        let visual = match args.iter().position(|arg| arg == "--visual") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        if !(3..=4).contains(&args.len()) {
+            eprintln!("{}", help::usage(&args[0], "grid"));
+            std::process::exit(EXIT_USAGE);
+        }
+        let path = Path::new(&args[2]);
+        let input = args.get(3).map_or("", String::as_str);
+        if visual {
+            if let Err(e) = run_grid_visual(&config, path, input) {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+            return;
+        }
+        match run_grid_file(&config, path, input) {
+            Ok(accepts) => std::process::exit(verdict_exit_code(accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "pda" {
+        let visual = match args.iter().position(|arg| arg == "--visual") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        if !(3..=4).contains(&args.len()) {
+            eprintln!("{}", help::usage(&args[0], "pda"));
+            std::process::exit(EXIT_USAGE);
+        }
+        let path = Path::new(&args[2]);
+        let input = args.get(3).map_or("", String::as_str);
+        if visual {
+            if let Err(e) = run_pda_visual(&config, path, input) {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+            return;
+        }
+        match run_pda_file(&config, path, input) {
+            Ok(accepts) => std::process::exit(verdict_exit_code(accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "two-stack" {
+        let visual = match args.iter().position(|arg| arg == "--visual") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        if !(3..=4).contains(&args.len()) {
+            eprintln!("{}", help::usage(&args[0], "two-stack"));
+            std::process::exit(EXIT_USAGE);
+        }
+        let path = Path::new(&args[2]);
+        let input = args.get(3).map_or("", String::as_str);
+        if visual {
+            if let Err(e) = run_two_stack_visual(&config, path, input) {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+            return;
+        }
+        match run_two_stack_file(&config, path, input) {
+            Ok(accepts) => std::process::exit(verdict_exit_code(accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "to-two-stack" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "to-two-stack"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = convert_to_two_stack(Path::new(&args[2]), Path::new(&args[3])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "utm" {
+        if args.len() == 4 && args[2] == "--save" {
+            if let Err(e) = save_universal(Path::new(&args[3])) {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+            return;
+        }
+        let encode_only = args.len() == 5 && args[4] == "--encode";
+        if args.len() != 4 && !encode_only {
+            eprintln!("{}", help::usage(&args[0], "utm"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_universal(&config, Path::new(&args[2]), &args[3], encode_only) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "nd" {
+        if args.len() < 4 {
+            eprintln!("{}", help::usage(&args[0], "nd"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_nondeterministic_file(&config, Path::new(&args[2]), &args[3..]) {
+            Ok(accepts) => std::process::exit(verdict_exit_code(accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "determinize" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "determinize"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = convert_to_deterministic(Path::new(&args[2]), Path::new(&args[3])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "amplitude" {
+        if args.len() < 4 {
+            eprintln!("{}", help::usage(&args[0], "amplitude"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_amplitude_file(&config, Path::new(&args[2]), &args[3..]) {
+            Ok(accepts) => std::process::exit(verdict_exit_code(accepts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "blame" {
+        let cell = args.get(4).and_then(|cell| cell.parse().ok());
+        let (Some(cell), 5) = (cell, args.len()) else {
+            eprintln!("{}", help::usage(&args[0], "blame"));
+            std::process::exit(EXIT_USAGE);
+        };
+        if let Err(e) = run_blame(&config, Path::new(&args[2]), &args[3], cell) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "ca" {
+        if args.len() < 3 {
+            eprintln!("{}", help::usage(&args[0], "ca"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = run_cellular(&args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "trajectory" {
+        let svg = match args.iter().position(|arg| arg == "--svg") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        if args.len() < 4 {
+            eprintln!("{}", help::usage(&args[0], "trajectory"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = run_trajectories(
+            &config,
+            Path::new(&args[2]),
+            &input_options,
+            &args[3..],
+            svg,
+        ) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "export" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "export"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = export_machine_file(Path::new(&args[2]), &args[3]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "render" {
+        let (5, "-o" | "--output") = (args.len(), args.get(3).map_or("", String::as_str)) else {
+            eprintln!("{}", help::usage(&args[0], "render"));
+            std::process::exit(EXIT_USAGE);
+        };
+        if let Err(e) = render_machine_file(Path::new(&args[2]), Path::new(&args[4])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "encode" {
+        let decimal = args[2..].iter().any(|arg| arg == "--decimal");
+        args.retain(|arg| arg != "--decimal");
+        if args.len() != 3 {
+            eprintln!("{}", help::usage(&args[0], "encode"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = encode_machine_file(Path::new(&args[2]), decimal) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "decode" {
+        let decimal = args[2..].iter().any(|arg| arg == "--decimal");
+        args.retain(|arg| arg != "--decimal");
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "decode"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = decode_machine_file(&args[2], Path::new(&args[3]), decimal) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "qr" {
+        let svg = args[2..].iter().any(|arg| arg == "--svg");
+        args.retain(|arg| arg != "--svg");
+        if args.len() != 3 {
+            eprintln!("{}", help::usage(&args[0], "qr"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = print_machine_qr(Path::new(&args[2]), svg) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "load-qr" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "load-qr"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = load_machine_qr(&args[2], Path::new(&args[3])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "convert" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "convert"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = convert_machine_file(Path::new(&args[2]), Path::new(&args[3])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "complete" {
+        let (5, "-o" | "--output") = (args.len(), args.get(3).map_or("", String::as_str)) else {
+            eprintln!("{}", help::usage(&args[0], "complete"));
+            std::process::exit(EXIT_USAGE);
+        };
+        if let Err(e) = complete_machine_file(Path::new(&args[2]), Path::new(&args[4])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && (args[1] == "analyze" || args[1] == "lint") {
+        if args.len() < 3 {
+            eprintln!("{}", help::usage(&args[0], &args[1]));
+            std::process::exit(EXIT_USAGE);
+        }
+        let use_cache = !args[3..].iter().any(|arg| arg == "--no-cache");
+        let result = if args[1] == "analyze" {
+            run_analyze(Path::new(&args[2]), use_cache)
+        } else {
+            run_lint(Path::new(&args[2]), use_cache)
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "golden" {
+        let bless = match args.iter().position(|arg| arg == "--bless") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        if args.len() != 2 {
+            eprintln!("{}", help::usage(&args[0], "golden"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match run_golden(&config, bless) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "info" {
+        let short = match args.iter().position(|arg| arg == "--short") {
+            Some(index) => {
+                args.remove(index);
+                true
+            }
+            None => false,
+        };
+        if args.len() != 3 {
+            eprintln!("{}", help::usage(&args[0], "info"));
+            std::process::exit(EXIT_USAGE);
+        }
+        let path = Path::new(&args[2]);
+        let shown = if short {
+            run_info_short(&config, path)
+        } else {
+            run_info(path)
+        };
+        if let Err(e) = shown {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "cache" {
+        if args.get(2).map(String::as_str) != Some("clear") || args.len() != 3 {
+            eprintln!("{}", help::usage(&args[0], "cache"));
+            std::process::exit(EXIT_USAGE);
+        }
+        match AnalysisCache::default_dir().map(AnalysisCache::new) {
+            Some(cache) => match cache.clear() {
+                Ok(removed) => println!("Removed {} cached results", removed),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(EXIT_ERROR);
+                }
+            },
+            None => println!("No cache directory"),
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "pack" {
+        let result = match (args.get(2).map(String::as_str), args.len()) {
+            (Some("install"), 4) => install_pack(Path::new(&args[3])),
+            (Some("list"), 3) => list_packs(),
+            (Some("show"), 4) => show_pack(&args[3]),
+            (Some("remove"), 4) => pack_store().and_then(|store| store.remove(&args[3])),
+            (Some(_), 4) => pack_machine_file(Path::new(&args[2]), Path::new(&args[3])),
+            _ => {
+                eprintln!("{}", help::usage(&args[0], "pack"));
+                std::process::exit(EXIT_USAGE);
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "unpack" {
+        if args.len() != 4 {
+            eprintln!("{}", help::usage(&args[0], "unpack"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = unpack_machine_bundle(Path::new(&args[2]), Path::new(&args[3])) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "exercise" {
+        let result = match (args.get(2).map(String::as_str), args.len()) {
+            (Some("list"), 3) => list_exercises().map(|()| EXIT_ACCEPT),
+            (Some("show"), 4) => show_exercise(&args[3]).map(|()| EXIT_ACCEPT),
+            (Some("check"), 5) => check_exercise(&config, &args[3], Path::new(&args[4])),
+            _ => {
+                eprintln!("{}", help::usage(&args[0], "exercise"));
+                std::process::exit(EXIT_USAGE);
+            }
+        };
+        match result {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.len() > 1 && args[1] == "tutorial" {
+        let result = match (args.get(2).map(String::as_str), args.len()) {
+            (Some("list"), 3) => list_tutorials(),
+            (Some("show"), 4) => show_tutorial(&args[3]),
+            _ => {
+                eprintln!("{}", help::usage(&args[0], "tutorial"));
+                std::process::exit(EXIT_USAGE);
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "serve" {
+        if let Err(e) = run_server(&config, &args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "lsp" {
+        if let Err(e) = turing_machine::lsp::run() {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "daemon" {
+        if args.len() < 4 {
+            eprintln!("{}", help::usage(&args[0], "daemon"));
+            std::process::exit(EXIT_USAGE);
+        }
+        if let Err(e) = run_daemon(&config, &args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
         return;
     }
 
@@ -1396,17 +4184,15 @@ fn main() {
 
     loop {
         print_menu();
-        print!("\nSelect option (1-5): ");
-        io::stdout().flush().unwrap();
-
-        let mut choice = String::new();
-        io::stdin().read_line(&mut choice).unwrap();
+        let Some(choice) = prompt("\nSelect option (1-5): ") else {
+            break;
+        };
         let choice = choice.trim();
 
         match choice {
-            "1" => run_example_machine(),
-            "2" => run_custom_machine(),
-            "3" => load_machine_from_file(),
+            "1" => run_example_machine(&config),
+            "2" => run_custom_machine(&config),
+            "3" => load_machine_from_file(&config),
             "4" => print_help(),
             "5" => {
                 println!("\nThank you for using the Turing Machine Executor!");
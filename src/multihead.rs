@@ -0,0 +1,328 @@
+//! Machines with several heads on a single tape
+//!
+//! A multi-head machine reads the symbols under all of its heads at once and
+//! moves each head independently. Its file uses the fields of the machine
+//! schema plus `heads`, and spells out one symbol and one move per head:
+//!
+//! ```json
+//! {
+//!     "heads": 2,
+//!     "transitions": {
+//!         "q0,0#": ["q0", "0#", "SR"]
+//!     }
+//! }
+//! ```
+//!
+//! The key `"q0,0#"` applies in state `q0` when head 1 reads `0` and head 2
+//! reads `#`; the transition writes `0` and `#` under the heads, leaves head 1
+//! in place (`S`) and moves head 2 right. All heads start on the first input
+//! cell. Two heads on the same cell must not write different symbols.
+
+use crate::tape::Tape;
+use crate::{Direction, ExecutionResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Multi-head machine file layout
+#[derive(Debug, Deserialize)]
+struct MultiHeadJson {
+    heads: usize,
+    states: Vec<String>,
+    alphabet: Vec<String>,
+    tape_alphabet: Vec<String>,
+    initial_state: String,
+    accept_states: Vec<String>,
+    reject_states: Vec<String>,
+    blank_symbol: Option<String>,
+    transitions: BTreeMap<String, Vec<String>>,
+}
+
+/// What a multi-head transition does, with one entry per head
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiHeadTarget {
+    pub new_state: String,
+    pub write: Vec<char>,
+    pub moves: Vec<Direction>,
+}
+
+/// A Turing machine with several heads on one tape
+#[derive(Debug)]
+pub struct MultiHeadMachine {
+    heads: usize,
+    states: HashSet<String>,
+    alphabet: HashSet<char>,
+    transitions: HashMap<(String, Vec<char>), MultiHeadTarget>,
+    initial_state: String,
+    accept_states: HashSet<String>,
+    reject_states: HashSet<String>,
+    blank_symbol: char,
+}
+
+impl MultiHeadMachine {
+    /// Load a multi-head machine file (JSON, read as JSON5)
+    pub fn load(path: &Path) -> Result<MultiHeadMachine, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        MultiHeadMachine::from_json(&text)
+    }
+
+    /// Parse a multi-head machine definition
+    pub fn from_json(text: &str) -> Result<MultiHeadMachine, String> {
+        let json: MultiHeadJson =
+            json5::from_str(text).map_err(|e| format!("Invalid multi-head machine file: {}", e))?;
+        if json.heads == 0 {
+            return Err("A multi-head machine needs at least one head".to_string());
+        }
+
+        let single = |entry: &String, what: &str| -> Result<char, String> {
+            let mut chars = entry.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!(
+                    "{} entry '{}' must be a single character",
+                    what, entry
+                )),
+            }
+        };
+        let alphabet = json
+            .alphabet
+            .iter()
+            .map(|entry| single(entry, "Alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let tape_alphabet = json
+            .tape_alphabet
+            .iter()
+            .map(|entry| single(entry, "Tape alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let blank_symbol = match &json.blank_symbol {
+            Some(entry) => single(entry, "Blank symbol")?,
+            None => '_',
+        };
+        let states: HashSet<String> = json.states.iter().cloned().collect();
+        let accept_states: HashSet<String> = json.accept_states.iter().cloned().collect();
+        let reject_states: HashSet<String> = json.reject_states.iter().cloned().collect();
+
+        if !states.contains(&json.initial_state) {
+            return Err(format!(
+                "Initial state {} not in states",
+                json.initial_state
+            ));
+        }
+        if !accept_states.is_subset(&states) {
+            return Err("Accept states must be subset of states".to_string());
+        }
+        if !reject_states.is_subset(&states) {
+            return Err("Reject states must be subset of states".to_string());
+        }
+        if !accept_states.is_disjoint(&reject_states) {
+            return Err("Accept and reject states must be disjoint".to_string());
+        }
+        if !tape_alphabet.contains(&blank_symbol) {
+            return Err(format!(
+                "Blank symbol {} not in tape alphabet",
+                blank_symbol
+            ));
+        }
+        if let Some(symbol) = alphabet.iter().find(|s| !tape_alphabet.contains(s)) {
+            return Err(format!("Input symbol {} not in tape alphabet", symbol));
+        }
+
+        let mut transitions = HashMap::new();
+        for (key, value) in &json.transitions {
+            let (from, to) = parse_transition(key, value, json.heads)?;
+            for state in [&from.0, &to.new_state] {
+                if !states.contains(state) {
+                    return Err(format!("Transition {}: state {} not in states", key, state));
+                }
+            }
+            for symbol in from.1.iter().chain(&to.write) {
+                if !tape_alphabet.contains(symbol) {
+                    return Err(format!(
+                        "Transition {}: symbol {} not in tape alphabet",
+                        key, symbol
+                    ));
+                }
+            }
+            transitions.insert(from, to);
+        }
+
+        Ok(MultiHeadMachine {
+            heads: json.heads,
+            states,
+            alphabet,
+            transitions,
+            initial_state: json.initial_state,
+            accept_states,
+            reject_states,
+            blank_symbol,
+        })
+    }
+
+    /// Number of heads
+    pub fn heads(&self) -> usize {
+        self.heads
+    }
+
+    /// All state names
+    pub fn states(&self) -> &HashSet<String> {
+        &self.states
+    }
+
+    /// Transitions, keyed by state and the symbols under each head
+    pub fn transitions(&self) -> &HashMap<(String, Vec<char>), MultiHeadTarget> {
+        &self.transitions
+    }
+
+    /// Symbol representing empty tape cells
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    /// Execute the machine on the given input
+    ///
+    /// The result's `head_position` is the position of head 1.
+    pub fn execute(&self, input_string: &str, max_steps: usize) -> Result<ExecutionResult, String> {
+        for symbol in input_string.chars() {
+            if !self.alphabet.contains(&symbol) {
+                return Err(format!("Invalid input symbol: {}", symbol));
+            }
+        }
+
+        let mut cells: Vec<char> = input_string.chars().collect();
+        if cells.is_empty() {
+            cells.push(self.blank_symbol);
+        }
+        let mut tape = Tape::new(cells);
+        let mut positions = vec![0usize; self.heads];
+        let mut current_state = self.initial_state.clone();
+        let mut steps = 0;
+
+        let (accepts, halted) = loop {
+            if steps >= max_steps {
+                break (None, false);
+            }
+            if self.accept_states.contains(&current_state) {
+                break (Some(true), true);
+            }
+            if self.reject_states.contains(&current_state) {
+                break (Some(false), true);
+            }
+
+            let read: Vec<char> = positions.iter().map(|&p| tape.cells()[p]).collect();
+            let Some(target) = self.transitions.get(&(current_state.clone(), read)) else {
+                // No transition defined - implicit reject
+                break (Some(false), true);
+            };
+
+            for (head, (&position, &symbol)) in positions.iter().zip(&target.write).enumerate() {
+                if let Some(other) = positions[..head].iter().position(|&p| p == position) {
+                    if target.write[other] != symbol {
+                        return Err(format!(
+                            "Step {}: heads {} and {} write different symbols to the same cell",
+                            steps + 1,
+                            other + 1,
+                            head + 1
+                        ));
+                    }
+                }
+                tape.cells_mut()[position] = symbol;
+            }
+
+            // Grow the tape by a cell on either side a head moves past
+            let leaves_left = positions
+                .iter()
+                .zip(&target.moves)
+                .any(|(&p, d)| p == 0 && *d == Direction::L);
+            if leaves_left {
+                tape.push_front(self.blank_symbol);
+                for position in &mut positions {
+                    *position += 1;
+                }
+            }
+            for (position, direction) in positions.iter_mut().zip(&target.moves) {
+                match direction {
+                    Direction::L => *position -= 1,
+                    Direction::R => *position += 1,
+                    Direction::S => {}
+                }
+            }
+            if positions.contains(&tape.len()) {
+                tape.push_back(self.blank_symbol);
+            }
+
+            current_state = target.new_state.clone();
+            steps += 1;
+        };
+
+        Ok(ExecutionResult {
+            accepts,
+            final_state: current_state,
+            steps,
+            halted,
+            tape: tape.cells().iter().collect(),
+            tape_cells: tape.cells().iter().map(char::to_string).collect(),
+            head_position: positions[0] as i32,
+            interrupted: false,
+            memory_limit_exceeded: false,
+            resource_limit: None,
+            timed_out: false,
+            peak_memory_bytes: tape.capacity() * std::mem::size_of::<char>(),
+            output: None,
+            cycle: None,
+        })
+    }
+}
+
+/// Parse a `"state,<symbols>": [new_state, <writes>, <moves>]` entry
+fn parse_transition(
+    key: &str,
+    value: &[String],
+    heads: usize,
+) -> Result<((String, Vec<char>), MultiHeadTarget), String> {
+    let Some((state, read)) = key.split_once(',') else {
+        return Err(format!("Invalid transition key: {}", key));
+    };
+    let read: Vec<char> = read.chars().collect();
+    if read.len() != heads {
+        return Err(format!(
+            "Transition {}: expected {} symbols read, one per head",
+            key, heads
+        ));
+    }
+
+    let [new_state, write, moves] = value else {
+        return Err(format!("Invalid transition value for key: {}", key));
+    };
+    let write: Vec<char> = write.chars().collect();
+    if write.len() != heads {
+        return Err(format!(
+            "Transition {}: expected {} symbols written, one per head",
+            key, heads
+        ));
+    }
+    let moves = moves
+        .chars()
+        .map(|c| match c {
+            'L' => Ok(Direction::L),
+            'R' => Ok(Direction::R),
+            'S' | 'N' => Ok(Direction::S),
+            _ => Err(format!("Invalid direction: {}", c)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if moves.len() != heads {
+        return Err(format!(
+            "Transition {}: expected {} moves, one per head",
+            key, heads
+        ));
+    }
+
+    Ok((
+        (state.to_string(), read),
+        MultiHeadTarget {
+            new_state: new_state.clone(),
+            write,
+            moves,
+        },
+    ))
+}
@@ -0,0 +1,601 @@
+//! Non-deterministic Turing machines explored breadth-first
+//!
+//! A non-deterministic machine file uses the machine schema, except that a
+//! transition may list several targets, any of which the machine can take:
+//!
+//! ```json
+//! "transitions": {
+//!     "q0,1": [["q0", "1", "R"], ["q1", "1", "R"]],
+//!     "q1,0": ["q2", "0", "R"]
+//! }
+//! ```
+//!
+//! [`NondeterministicMachine::explore`] searches the tree of configurations
+//! level by level. The input is accepted if any branch accepts, and the
+//! shortest accepting branch is reported with its trace. It is rejected if
+//! every branch rejects within the bounds; otherwise the verdict is open.
+//!
+//! A branch is identified by its choices: the index of the target taken
+//! at each step among the targets its transition offers.
+//! [`NondeterministicMachine::follow`] runs the branch a choice sequence
+//! describes, so the accepting branch of an exploration can be reproduced
+//! and debugged without searching again.
+//!
+//! On a left-bounded tape, a branch moving left from the first cell stays
+//! there under [`LeftEdge::Stay`] and rejects under both other policies, so
+//! one branch falling off the tape does not end the whole search. On a
+//! circular tape the head wraps around as in a deterministic run.
+
+use crate::executor::RunDefaults;
+use crate::pipeline::TraceStep;
+use crate::{
+    formats, parse_machine_json, Direction, LeftEdge, MachineJson, SymbolTable, TransitionKey,
+    TransitionTarget, TuringMachine,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Configurations explored when no other bound is given
+pub const DEFAULT_MAX_CONFIGURATIONS: usize = 1_000_000;
+
+/// A transition value: a single target or a list of alternatives
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TargetsJson {
+    One(Vec<String>),
+    Many(Vec<Vec<String>>),
+}
+
+/// A Turing machine whose transitions may offer several targets
+#[derive(Debug)]
+pub struct NondeterministicMachine {
+    /// States, alphabets and halting states; its transitions are unused
+    base: TuringMachine,
+    transitions: HashMap<TransitionKey, Vec<TransitionTarget>>,
+}
+
+/// Outcome of exploring a non-deterministic machine on one input
+#[derive(Debug)]
+pub struct Exploration {
+    /// True if some branch accepts, false if every branch rejects, `None` if
+    /// a bound stopped the search first
+    pub accepts: Option<bool>,
+    /// Configurations visited, including the initial one
+    pub configurations: usize,
+    /// Steps taken by the deepest branch explored
+    pub depth: usize,
+    /// Configurations not explored because an identical one had already
+    /// been reached
+    pub pruned: usize,
+    /// The shortest accepting branch, step by step
+    pub accepting_branch: Option<Vec<TraceStep>>,
+    /// The choices of the accepting branch, one per step
+    pub accepting_choices: Option<Vec<usize>>,
+}
+
+/// A single branch, followed by its choices
+#[derive(Debug)]
+pub struct Branch {
+    /// True if the branch accepts, false if it rejects, `None` if the
+    /// choices ran out before it halted
+    pub accepts: Option<bool>,
+    /// The steps taken, one per choice
+    pub steps: Vec<TraceStep>,
+}
+
+/// A configuration waiting to be explored
+struct Frontier {
+    node: usize,
+    state: String,
+    tape: Vec<char>,
+    head: i32,
+    depth: usize,
+}
+
+/// A configuration as compared for duplicates: the state, the tape without
+/// the blank cells at its ends, and the head relative to that tape
+pub(crate) type Seen = (String, Vec<char>, i32);
+
+/// How a configuration was reached: its parent and the target taken
+struct Node {
+    parent: Option<usize>,
+    choice: Option<usize>,
+}
+
+impl NondeterministicMachine {
+    /// Load a non-deterministic machine file (JSON, read as JSON5)
+    pub fn load(path: &Path) -> Result<NondeterministicMachine, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        NondeterministicMachine::from_json(&text)
+    }
+
+    /// Parse a non-deterministic machine definition
+    pub fn from_json(text: &str) -> Result<NondeterministicMachine, String> {
+        let invalid = |e: String| format!("Invalid machine file: {}", e);
+        let mut value: Value = json5::from_str(text).map_err(|e| invalid(e.to_string()))?;
+        let Some(object) = value.as_object_mut() else {
+            return Err(invalid("expected an object".to_string()));
+        };
+        let transitions = object
+            .insert("transitions".to_string(), Value::Object(Default::default()))
+            .ok_or_else(|| invalid("missing field `transitions`".to_string()))?;
+        let definition: MachineJson =
+            serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+        let base = parse_machine_json(&definition)?;
+        if base.read_only_input() {
+            return Err(
+                "read_only_input is not supported for non-deterministic machines".to_string(),
+            );
+        }
+
+        let entries: BTreeMap<String, TargetsJson> =
+            serde_json::from_value(transitions).map_err(|e| invalid(e.to_string()))?;
+        let mut machine = NondeterministicMachine {
+            base,
+            transitions: HashMap::new(),
+        };
+        let mut transitions = Vec::new();
+        for (key, targets) in entries {
+            let targets = match targets {
+                TargetsJson::One(target) => vec![target],
+                TargetsJson::Many(targets) => targets,
+            };
+            for target in targets {
+                let (key, target) =
+                    formats::resolve_aliases(&definition.symbol_aliases, &key, &target);
+                transitions.push(formats::parse_transition(
+                    &key,
+                    &target,
+                    Arc::make_mut(&mut machine.base.symbols),
+                )?);
+            }
+        }
+        for (from, to) in formats::expand_wildcards(transitions, &machine.base.tape_alphabet) {
+            machine.add_transition(from, to)?;
+        }
+        Ok(machine)
+    }
+
+    /// Add a target to the transition at `key`, keeping any existing ones
+    pub fn add_transition(
+        &mut self,
+        key: TransitionKey,
+        target: TransitionTarget,
+    ) -> Result<(), String> {
+        for state in [&key.0, &target.0] {
+            if !self.base.states.contains(state) {
+                return Err(format!("State {} not in states", state));
+            }
+        }
+        for symbol in [key.1, target.1] {
+            if !self.base.tape_alphabet.contains(&symbol) {
+                return Err(format!(
+                    "Symbol {} not in tape alphabet",
+                    self.base.symbols.name(symbol)
+                ));
+            }
+        }
+        let targets = self.transitions.entry(key).or_default();
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+        Ok(())
+    }
+
+    /// All state names
+    pub fn states(&self) -> &HashSet<String> {
+        self.base.states()
+    }
+
+    /// Transitions, each with every target it offers
+    pub fn transitions(&self) -> &HashMap<TransitionKey, Vec<TransitionTarget>> {
+        &self.transitions
+    }
+
+    /// Symbol representing empty tape cells
+    pub fn blank_symbol(&self) -> char {
+        self.base.blank_symbol
+    }
+
+    /// Names of the machine's multi-character symbols
+    pub fn symbols(&self) -> &SymbolTable {
+        self.base.symbols()
+    }
+
+    /// Limits the machine recommends for its runs
+    pub fn run_defaults(&self) -> &RunDefaults {
+        self.base.run_defaults()
+    }
+
+    /// The machine without its transitions, for its states and alphabets
+    pub(crate) fn base(&self) -> &TuringMachine {
+        &self.base
+    }
+
+    /// Explore every branch breadth-first
+    ///
+    /// Branches are followed for at most `max_depth` steps, and the search
+    /// stops after visiting `max_configurations` configurations. A
+    /// configuration identical to one reached before is not explored again;
+    /// since the search is breadth-first, the earlier one was reached in at
+    /// most as many steps.
+    pub fn explore(
+        &self,
+        input_string: &str,
+        max_depth: usize,
+        max_configurations: usize,
+    ) -> Result<Exploration, String> {
+        let machine = &self.base;
+        let input = machine.map_input(input_string)?;
+        for symbol in &input {
+            if !machine.alphabet.contains(symbol) {
+                return Err(format!(
+                    "Invalid input symbol: {}",
+                    machine.symbols.name(*symbol)
+                ));
+            }
+        }
+        let tape = machine.start_tape(&input)?;
+        let head = machine.tape_setup.head() as i32;
+        let mut seen = HashSet::from([canonical(machine, &machine.initial_state, &tape, head)]);
+        let mut pruned = 0;
+
+        let mut nodes = vec![Node {
+            parent: None,
+            choice: None,
+        }];
+        let mut frontier = VecDeque::from([Frontier {
+            node: 0,
+            state: machine.initial_state.clone(),
+            tape,
+            head,
+            depth: 0,
+        }]);
+        let mut depth = 0;
+        let mut bounded = false;
+
+        while let Some(mut config) = frontier.pop_front() {
+            depth = depth.max(config.depth);
+            if machine.accept_states.contains(&config.state) {
+                return Ok(self.accepted(input_string, &nodes, config.node, depth, pruned));
+            }
+            if machine.reject_states.contains(&config.state) {
+                continue;
+            }
+            if config.depth >= max_depth {
+                bounded = true;
+                continue;
+            }
+
+            let symbol = read(&mut config.tape, &mut config.head, machine.blank_symbol);
+            let key = (config.state, symbol);
+            // A configuration without a transition rejects implicitly, or
+            // accepts when the machine accepts by halting
+            let Some(targets) = self.transitions.get(&key) else {
+                if machine.accept_on_halt {
+                    return Ok(self.accepted(input_string, &nodes, config.node, depth, pruned));
+                }
+                continue;
+            };
+            for (index, target) in targets.iter().enumerate() {
+                if nodes.len() >= max_configurations {
+                    bounded = true;
+                    frontier.clear();
+                    break;
+                }
+                let Some(direction) = direction_at(machine, config.head, target.2) else {
+                    continue;
+                };
+                let mut tape = config.tape.clone();
+                let mut head = config.head;
+                write_and_move(&mut tape, &mut head, target.1, direction);
+                wrap(machine, &mut head);
+                if !seen.insert(canonical(machine, &target.0, &tape, head)) {
+                    pruned += 1;
+                    continue;
+                }
+                nodes.push(Node {
+                    parent: Some(config.node),
+                    choice: Some(index),
+                });
+                frontier.push_back(Frontier {
+                    node: nodes.len() - 1,
+                    state: target.0.clone(),
+                    tape,
+                    head,
+                    depth: config.depth + 1,
+                });
+            }
+        }
+
+        Ok(Exploration {
+            accepts: if bounded { None } else { Some(false) },
+            configurations: nodes.len(),
+            depth,
+            pruned,
+            accepting_branch: None,
+            accepting_choices: None,
+        })
+    }
+
+    /// The exploration ending with `node` accepting
+    fn accepted(
+        &self,
+        input_string: &str,
+        nodes: &[Node],
+        node: usize,
+        depth: usize,
+        pruned: usize,
+    ) -> Exploration {
+        let mut choices = Vec::new();
+        let mut current = Some(node);
+        while let Some(index) = current {
+            choices.extend(nodes[index].choice);
+            current = nodes[index].parent;
+        }
+        choices.reverse();
+        let branch = self
+            .follow(input_string, &choices)
+            .expect("an explored branch can be followed");
+        Exploration {
+            accepts: Some(true),
+            configurations: nodes.len(),
+            depth,
+            pruned,
+            accepting_branch: Some(branch.steps),
+            accepting_choices: Some(choices),
+        }
+    }
+
+    /// Follow the branch taking target `choices[i]` at step `i + 1`,
+    /// recording every step
+    ///
+    /// The branch ends where it halts, where it falls off the left end of
+    /// the tape, rejecting, or after the last choice. A choice out of the
+    /// range of the targets offered, or one left over once the branch has
+    /// ended, is an error.
+    pub fn follow(&self, input_string: &str, choices: &[usize]) -> Result<Branch, String> {
+        let machine = &self.base;
+        let input = machine.map_input(input_string)?;
+        for symbol in &input {
+            if !machine.alphabet.contains(symbol) {
+                return Err(format!(
+                    "Invalid input symbol: {}",
+                    machine.symbols.name(*symbol)
+                ));
+            }
+        }
+        let mut tape = machine.start_tape(&input)?;
+        let mut head = machine.tape_setup.head() as i32;
+        let mut state = machine.initial_state.clone();
+        let mut steps = Vec::with_capacity(choices.len());
+        let accepts = loop {
+            if machine.accept_states.contains(&state) {
+                break true;
+            }
+            if machine.reject_states.contains(&state) {
+                break false;
+            }
+            let read_symbol = read(&mut tape, &mut head, machine.blank_symbol);
+            let key = (state, read_symbol);
+            let Some(targets) = self.transitions.get(&key) else {
+                break machine.accept_on_halt;
+            };
+            let Some(&choice) = choices.get(steps.len()) else {
+                return Ok(Branch {
+                    accepts: None,
+                    steps,
+                });
+            };
+            let target = targets.get(choice).ok_or_else(|| {
+                format!(
+                    "Choice {} at step {} is out of range: state {} reading {} offers {} targets",
+                    choice,
+                    steps.len() + 1,
+                    key.0,
+                    machine.symbols.name(read_symbol),
+                    targets.len()
+                )
+            })?;
+            let Some(direction) = direction_at(machine, head, target.2) else {
+                if steps.len() + 1 < choices.len() {
+                    return Err(format!(
+                        "The branch falls off the tape at step {} of {}",
+                        steps.len() + 1,
+                        choices.len()
+                    ));
+                }
+                return Ok(Branch {
+                    accepts: Some(false),
+                    steps,
+                });
+            };
+            write_and_move(&mut tape, &mut head, target.1, direction);
+            wrap(machine, &mut head);
+            state = target.0.clone();
+            steps.push(TraceStep {
+                step: steps.len() + 1,
+                from_state: key.0,
+                read_symbol,
+                to_state: target.0.clone(),
+                write_symbol: target.1,
+                direction,
+                head_position: head,
+                tape: tape.clone(),
+            });
+        };
+        if steps.len() < choices.len() {
+            return Err(format!(
+                "The branch halts after {} of {} choices",
+                steps.len(),
+                choices.len()
+            ));
+        }
+        Ok(Branch {
+            accepts: Some(accepts),
+            steps,
+        })
+    }
+}
+
+/// Read a choice sequence: indices separated by whitespace or commas, with
+/// `#` starting a comment that runs to the end of the line
+pub fn parse_choices(text: &str) -> Result<Vec<usize>, String> {
+    text.lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split([',', ' ', '\t'])
+        })
+        .filter(|choice| !choice.is_empty())
+        .map(|choice| {
+            choice
+                .parse()
+                .map_err(|_| format!("Invalid choice: {}", choice))
+        })
+        .collect()
+}
+
+/// Write a choice sequence as [`parse_choices`] reads it, one choice per
+/// line
+pub fn format_choices(choices: &[usize]) -> String {
+    choices
+        .iter()
+        .map(|choice| format!("{}\n", choice))
+        .collect()
+}
+
+/// The configuration `state`, `tape`, `head` of a run of `machine` as
+/// compared for duplicates
+///
+/// Blank cells past either end are trimmed where the tape is unbounded in
+/// that direction; a left-bounded or circular tape keeps its cells, since
+/// their position matters.
+pub(crate) fn canonical(machine: &TuringMachine, state: &str, tape: &[char], head: i32) -> Seen {
+    if machine.tape_length.is_some() {
+        return (state.to_string(), tape.to_vec(), head);
+    }
+    let blank = machine.blank_symbol;
+    let end = tape.iter().rposition(|&s| s != blank).map_or(0, |i| i + 1);
+    let start = match machine.left_edge {
+        None => tape[..end].iter().position(|&s| s != blank).unwrap_or(end),
+        Some(_) => 0,
+    };
+    (
+        state.to_string(),
+        tape[start..end].to_vec(),
+        head - start as i32,
+    )
+}
+
+/// The move `machine` actually makes from `head`, or `None` if the branch
+/// falls off the left end of a left-bounded tape
+pub(crate) fn direction_at(
+    machine: &TuringMachine,
+    head: i32,
+    direction: Direction,
+) -> Option<Direction> {
+    if direction != Direction::L || head != 0 || machine.tape_length.is_some() {
+        return Some(direction);
+    }
+    match machine.left_edge {
+        None => Some(direction),
+        Some(LeftEdge::Stay) => Some(Direction::S),
+        Some(LeftEdge::Crash | LeftEdge::Reject) => None,
+    }
+}
+
+/// Bring the head back onto a circular tape after a move
+pub(crate) fn wrap(machine: &TuringMachine, head: &mut i32) {
+    if let Some(length) = machine.tape_length {
+        *head = head.rem_euclid(length as i32);
+    }
+}
+
+/// Read the symbol under the head, growing the tape if the head is off it
+pub(crate) fn read(tape: &mut Vec<char>, head: &mut i32, blank_symbol: char) -> char {
+    if *head < 0 {
+        tape.insert(0, blank_symbol);
+        *head = 0;
+    }
+    if *head >= tape.len() as i32 {
+        tape.push(blank_symbol);
+    }
+    tape[*head as usize]
+}
+
+/// Write `symbol` under the head and move the head
+pub(crate) fn write_and_move(
+    tape: &mut [char],
+    head: &mut i32,
+    symbol: char,
+    direction: Direction,
+) {
+    tape[*head as usize] = symbol;
+    match direction {
+        Direction::L => *head -= 1,
+        Direction::R => *head += 1,
+        Direction::S => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND_TO_LAST_ONE: &str =
+        include_str!("../examples/nondeterministic/second_to_last_one.json");
+
+    #[test]
+    fn records_the_choices_of_the_accepting_branch() {
+        let machine = NondeterministicMachine::from_json(SECOND_TO_LAST_ONE).unwrap();
+        let exploration = machine.explore("0110", 100, 1000).unwrap();
+        assert_eq!(exploration.accepts, Some(true));
+        let choices = exploration.accepting_choices.unwrap();
+        assert_eq!(choices, [0, 0, 1, 0, 0]);
+
+        let branch = machine.follow("0110", &choices).unwrap();
+        assert_eq!(branch.accepts, Some(true));
+        let states = |steps: &[TraceStep]| {
+            steps
+                .iter()
+                .map(|step| (step.to_state.clone(), step.tape.clone(), step.head_position))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            states(&branch.steps),
+            states(&exploration.accepting_branch.unwrap())
+        );
+    }
+
+    #[test]
+    fn follows_other_branches() {
+        let machine = NondeterministicMachine::from_json(SECOND_TO_LAST_ONE).unwrap();
+        let guessed_early = machine.follow("0110", &[0, 1, 0]).unwrap();
+        assert_eq!(guessed_early.accepts, Some(false));
+        assert_eq!(guessed_early.steps.len(), 3);
+
+        let unfinished = machine.follow("0110", &[0, 0]).unwrap();
+        assert_eq!(unfinished.accepts, None);
+        assert_eq!(unfinished.steps.last().unwrap().head_position, 2);
+
+        assert!(machine.follow("0110", &[0, 2]).is_err());
+        assert!(machine.follow("0110", &[0, 1, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn reads_the_choices_it_writes() {
+        let choices = [0, 3, 1, 0];
+        assert_eq!(parse_choices(&format_choices(&choices)).unwrap(), choices);
+        assert_eq!(
+            parse_choices("0, 1 # guessed\n2\t0\n").unwrap(),
+            [0, 1, 2, 0]
+        );
+        assert!(parse_choices("0 x").is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! Content packs: machines, exercises and tutorials distributed together
+//!
+//! A pack is a directory, or a `.zip` archive of one, holding a `pack.json`
+//! manifest and a `machines` directory of machine files:
+//!
+//! ```json
+//! {
+//!     "name": "automata-fall",
+//!     "version": "1.0",
+//!     "description": "Exercises for the automata course",
+//!     "exercises": [{
+//!         "id": "even-ones",
+//!         "title": "Even number of 1s",
+//!         "tests": [{"input": "0110", "accepts": true}]
+//!     }],
+//!     "tutorials": [{"id": "intro", "title": "First machines", "file": "intro.md"}]
+//! }
+//! ```
+//!
+//! Exercise tests are written like the `tests` of a machine's metadata. A
+//! [`PackStore`] keeps installed packs, one directory each, named after the
+//! pack; installing a pack again replaces the installed version.
+
+use crate::executor::{Executor, RunConfig};
+use crate::formats::Format;
+use crate::info::MachineTest;
+use crate::TuringMachine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file of a pack
+pub const MANIFEST: &str = "pack.json";
+
+/// Directory of a pack holding its machines
+pub const MACHINES_DIR: &str = "machines";
+
+/// Directory in a [`PackStore`] that archives are unpacked into before
+/// being installed
+const STAGING_DIR: &str = ".staging";
+
+/// Contents of `pack.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackManifest {
+    /// Name the pack is installed and referred to under
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exercises: Vec<Exercise>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tutorials: Vec<Tutorial>,
+}
+
+/// A task checked by running a submitted machine on sample inputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exercise {
+    pub id: String,
+    pub title: String,
+    /// The task, in a sentence or a few paragraphs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub tests: Vec<MachineTest>,
+}
+
+/// A text to read, such as a Markdown file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tutorial {
+    pub id: String,
+    pub title: String,
+    /// Path of the text, relative to the pack directory
+    pub file: PathBuf,
+}
+
+/// A pack directory with its manifest
+#[derive(Debug, Clone)]
+pub struct Pack {
+    pub dir: PathBuf,
+    pub manifest: PackManifest,
+}
+
+impl Pack {
+    /// Read and check the pack in `dir`
+    pub fn load(dir: &Path) -> Result<Pack, String> {
+        let path = dir.join(MANIFEST);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("File error: {}: {}", path.display(), e))?;
+        let manifest: PackManifest = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid pack file: {}: {}", path.display(), e))?;
+        check_name(&manifest.name)
+            .map_err(|e| format!("Invalid pack file: {}: {}", path.display(), e))?;
+
+        let mut ids = HashSet::new();
+        for exercise in &manifest.exercises {
+            if !ids.insert(&exercise.id) {
+                return Err(format!(
+                    "Invalid pack file: {}: exercise '{}' is listed twice",
+                    path.display(),
+                    exercise.id
+                ));
+            }
+            if exercise.tests.is_empty() {
+                return Err(format!(
+                    "Invalid pack file: {}: exercise '{}' has no tests",
+                    path.display(),
+                    exercise.id
+                ));
+            }
+        }
+        let mut ids = HashSet::new();
+        for tutorial in &manifest.tutorials {
+            if !ids.insert(&tutorial.id) {
+                return Err(format!(
+                    "Invalid pack file: {}: tutorial '{}' is listed twice",
+                    path.display(),
+                    tutorial.id
+                ));
+            }
+            if !inside(&tutorial.file) || !dir.join(&tutorial.file).is_file() {
+                return Err(format!(
+                    "Invalid pack file: {}: tutorial '{}' has no file {}",
+                    path.display(),
+                    tutorial.id,
+                    tutorial.file.display()
+                ));
+            }
+        }
+        Ok(Pack {
+            dir: dir.to_path_buf(),
+            manifest,
+        })
+    }
+
+    /// Machine files of the pack, sorted by name
+    pub fn machine_files(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(self.dir.join(MACHINES_DIR)) else {
+            return Vec::new();
+        };
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| Format::from_path(path).is_some())
+            .collect();
+        files.sort();
+        files
+    }
+
+    pub fn exercise(&self, id: &str) -> Option<&Exercise> {
+        self.manifest.exercises.iter().find(|e| e.id == id)
+    }
+
+    pub fn tutorial(&self, id: &str) -> Option<&Tutorial> {
+        self.manifest.tutorials.iter().find(|t| t.id == id)
+    }
+
+    /// Text of a tutorial
+    pub fn read_tutorial(&self, tutorial: &Tutorial) -> Result<String, String> {
+        let path = self.dir.join(&tutorial.file);
+        fs::read_to_string(&path).map_err(|e| format!("File error: {}: {}", path.display(), e))
+    }
+}
+
+/// Run `machine` on the input of an exercise test, returning what went
+/// wrong if the result is not the expected one
+pub fn check_test(
+    machine: &TuringMachine,
+    test: &MachineTest,
+    config: &RunConfig,
+) -> Result<(), String> {
+    let result = Executor::new(machine).run_with(&test.input, config)?;
+    if !result.halted {
+        return Err(format!("did not halt within {} steps", result.steps));
+    }
+    if let Some(accepts) = test.accepts {
+        if result.accepts != Some(accepts) {
+            let verdict = |accepts| if accepts { "accept" } else { "reject" };
+            return Err(format!(
+                "expected {}, but it {}s",
+                verdict(accepts),
+                verdict(result.accepts == Some(true))
+            ));
+        }
+    }
+    if let Some(output) = &test.output {
+        if result.output.as_ref() != Some(output) {
+            return Err(match &result.output {
+                Some(got) => format!("expected output {:?}, got {:?}", output, got),
+                None => format!("expected output {:?}, got none", output),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Directory of installed packs
+#[derive(Debug, Clone)]
+pub struct PackStore {
+    dir: PathBuf,
+}
+
+impl PackStore {
+    /// Use `dir` as the pack directory; it is created on first install
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        PackStore { dir: dir.into() }
+    }
+
+    /// `$XDG_DATA_HOME/turing-machine/packs`, or `~/.local/share/...` if
+    /// unset
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => Path::new(&env::var_os("HOME")?)
+                .join(".local")
+                .join("share"),
+        };
+        Some(base.join("turing-machine").join("packs"))
+    }
+
+    /// Install the pack in the directory or `.zip` archive `source`,
+    /// replacing any installed pack of the same name
+    ///
+    /// An archive may hold the pack at its root or in a single top-level
+    /// directory.
+    pub fn install(&self, source: &Path) -> Result<Pack, String> {
+        let store_error =
+            |path: &Path, e: io::Error| format!("File error: {}: {}", path.display(), e);
+        fs::create_dir_all(&self.dir).map_err(|e| store_error(&self.dir, e))?;
+        let staging = self.dir.join(STAGING_DIR);
+        if staging.exists() {
+            fs::remove_dir_all(&staging).map_err(|e| store_error(&staging, e))?;
+        }
+
+        let source_dir = if source.is_dir() {
+            source.to_path_buf()
+        } else {
+            let file = fs::File::open(source).map_err(|e| store_error(source, e))?;
+            zip::ZipArchive::new(file)
+                .and_then(|mut archive| archive.extract(&staging))
+                .map_err(|e| format!("Invalid pack archive: {}: {}", source.display(), e))?;
+            unwrap_root(&staging).map_err(|e| store_error(&staging, e))?
+        };
+        let pack = Pack::load(&source_dir)?;
+
+        let target = self.dir.join(&pack.manifest.name);
+        if fs::canonicalize(&source_dir).ok() == fs::canonicalize(&target).ok() {
+            // Reinstalling the installed copy
+            return Ok(pack);
+        }
+        if target.exists() {
+            fs::remove_dir_all(&target).map_err(|e| store_error(&target, e))?;
+        }
+        if source.is_dir() {
+            copy_dir(&source_dir, &target).map_err(|e| store_error(&target, e))?;
+        } else {
+            fs::rename(&source_dir, &target).map_err(|e| store_error(&target, e))?;
+            if staging.exists() {
+                fs::remove_dir_all(&staging).map_err(|e| store_error(&staging, e))?;
+            }
+        }
+        Pack::load(&target)
+    }
+
+    /// Installed packs, sorted by name
+    ///
+    /// Directories that do not hold a valid pack are skipped with a
+    /// warning.
+    pub fn packs(&self) -> Vec<Pack> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut packs: Vec<Pack> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && !path.ends_with(STAGING_DIR))
+            .filter_map(|path| {
+                Pack::load(&path)
+                    .inspect_err(|e| tracing::warn!("skipping {}:\n{}", path.display(), e))
+                    .ok()
+            })
+            .collect();
+        packs.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+        packs
+    }
+
+    /// The installed pack named `name`
+    pub fn pack(&self, name: &str) -> Result<Pack, String> {
+        check_name(name)?;
+        let dir = self.dir.join(name);
+        if !dir.is_dir() {
+            return Err(format!("No pack named '{}' is installed", name));
+        }
+        Pack::load(&dir)
+    }
+
+    /// Uninstall the pack named `name`
+    pub fn remove(&self, name: &str) -> Result<(), String> {
+        let pack = self.pack(name)?;
+        fs::remove_dir_all(&pack.dir)
+            .map_err(|e| format!("File error: {}: {}", pack.dir.display(), e))
+    }
+}
+
+/// Check that a pack name can be used as a directory name
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if name.is_empty() || name.starts_with('.') || !valid {
+        return Err(format!(
+            "invalid pack name '{}' (use letters, digits, '-', '_' and '.')",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a relative path stays inside the directory it is relative to
+fn inside(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+/// `dir`, or the only entry in it if that is a directory without a
+/// manifest next to it
+fn unwrap_root(dir: &Path) -> io::Result<PathBuf> {
+    if dir.join(MANIFEST).exists() {
+        return Ok(dir.to_path_buf());
+    }
+    let entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    match entries.as_slice() {
+        [only] if only.is_dir() => Ok(only.clone()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
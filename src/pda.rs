@@ -0,0 +1,348 @@
+//! Deterministic pushdown automata
+//!
+//! A pushdown automaton reads its input once from left to right and keeps a
+//! stack as its only memory. Its file names the stack symbols and the
+//! symbols on the stack at the start, top first; a transition is keyed by
+//! the state, the input symbol read and the stack symbol popped, and gives
+//! the new state and the symbols pushed, top first. An empty input or pop
+//! symbol (or `ε`) reads or pops nothing:
+//!
+//! ```json
+//! {
+//!     "stack_alphabet": ["Z", "("],
+//!     "initial_stack": "Z",
+//!     "transitions": {
+//!         "balanced,(,Z": ["open", "(Z"],
+//!         "open,),(": ["open", ""],
+//!         "open,,Z": ["balanced", "Z"]
+//!     }
+//! }
+//! ```
+//!
+//! At most one transition may apply in any configuration, so two
+//! transitions of a state conflict if they read the same input symbol or one
+//! of them reads none, and pop the same stack symbol or one of them pops
+//! none. The automaton halts when no transition applies, accepting if it
+//! has read the whole input and is in an accept state, or as soon as it
+//! enters a reject state.
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Pushdown automaton file layout
+#[derive(Debug, Deserialize)]
+struct PdaJson {
+    states: Vec<String>,
+    alphabet: Vec<String>,
+    stack_alphabet: Vec<String>,
+    initial_state: String,
+    #[serde(default)]
+    initial_stack: String,
+    accept_states: Vec<String>,
+    #[serde(default)]
+    reject_states: Vec<String>,
+    transitions: BTreeMap<String, Vec<String>>,
+}
+
+/// What a transition reads: the state, the input symbol and the stack
+/// symbol popped, `None` for neither
+pub type PdaKey = (String, Option<char>, Option<char>);
+
+/// What a transition does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdaTarget {
+    pub new_state: String,
+    /// Symbols pushed, top first
+    pub push: Vec<char>,
+}
+
+/// A deterministic pushdown automaton
+#[derive(Debug)]
+pub struct PdaMachine {
+    states: HashSet<String>,
+    alphabet: HashSet<char>,
+    stack_alphabet: HashSet<char>,
+    transitions: HashMap<PdaKey, PdaTarget>,
+    initial_state: String,
+    /// Stack at the start, top first
+    initial_stack: Vec<char>,
+    accept_states: HashSet<String>,
+    reject_states: HashSet<String>,
+}
+
+impl PdaMachine {
+    /// Load a pushdown automaton file (JSON, read as JSON5)
+    pub fn load(path: &Path) -> Result<PdaMachine, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        PdaMachine::from_json(&text)
+    }
+
+    /// Parse a pushdown automaton definition
+    pub fn from_json(text: &str) -> Result<PdaMachine, String> {
+        let json: PdaJson =
+            json5::from_str(text).map_err(|e| format!("Invalid pushdown automaton file: {}", e))?;
+
+        let alphabet = json
+            .alphabet
+            .iter()
+            .map(|entry| single(entry, "Alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let stack_alphabet = json
+            .stack_alphabet
+            .iter()
+            .map(|entry| single(entry, "Stack alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let states: HashSet<String> = json.states.iter().cloned().collect();
+        let accept_states: HashSet<String> = json.accept_states.iter().cloned().collect();
+        let reject_states: HashSet<String> = json.reject_states.iter().cloned().collect();
+
+        if !states.contains(&json.initial_state) {
+            return Err(format!(
+                "Initial state {} not in states",
+                json.initial_state
+            ));
+        }
+        if !accept_states.is_subset(&states) {
+            return Err("Accept states must be subset of states".to_string());
+        }
+        if !reject_states.is_subset(&states) {
+            return Err("Reject states must be subset of states".to_string());
+        }
+        if !accept_states.is_disjoint(&reject_states) {
+            return Err("Accept and reject states must be disjoint".to_string());
+        }
+        let initial_stack: Vec<char> = json.initial_stack.chars().collect();
+        if let Some(symbol) = initial_stack.iter().find(|s| !stack_alphabet.contains(s)) {
+            return Err(format!(
+                "Initial stack symbol {} not in stack alphabet",
+                symbol
+            ));
+        }
+
+        let mut transitions = HashMap::new();
+        let mut keys = Vec::new();
+        for (key, value) in &json.transitions {
+            let (from, to) = parse_transition(key, value)?;
+            for state in [&from.0, &to.new_state] {
+                if !states.contains(state) {
+                    return Err(format!("Transition {}: state {} not in states", key, state));
+                }
+            }
+            if let Some(symbol) = from.1.filter(|s| !alphabet.contains(s)) {
+                return Err(format!(
+                    "Transition {}: input symbol {} not in alphabet",
+                    key, symbol
+                ));
+            }
+            let popped = from.2.iter();
+            if let Some(symbol) = popped.chain(&to.push).find(|s| !stack_alphabet.contains(s)) {
+                return Err(format!(
+                    "Transition {}: symbol {} not in stack alphabet",
+                    key, symbol
+                ));
+            }
+            keys.push((key, from.clone()));
+            transitions.insert(from, to);
+        }
+        check_deterministic(&keys, "a pushdown automaton")?;
+
+        Ok(PdaMachine {
+            states,
+            alphabet,
+            stack_alphabet,
+            transitions,
+            initial_state: json.initial_state,
+            initial_stack,
+            accept_states,
+            reject_states,
+        })
+    }
+
+    /// All state names
+    pub fn states(&self) -> &HashSet<String> {
+        &self.states
+    }
+
+    /// Symbols that can be pushed on the stack
+    pub fn stack_alphabet(&self) -> &HashSet<char> {
+        &self.stack_alphabet
+    }
+
+    /// Transitions, keyed by state, input symbol and popped stack symbol
+    pub fn transitions(&self) -> &HashMap<PdaKey, PdaTarget> {
+        &self.transitions
+    }
+
+    /// Set up a run on the given input, before its first step
+    pub fn start(&self, input_string: &str) -> Result<PdaRun<'_>, String> {
+        let input: Vec<char> = input_string.chars().collect();
+        if let Some(symbol) = input.iter().find(|s| !self.alphabet.contains(s)) {
+            return Err(format!("Invalid input symbol: {}", symbol));
+        }
+        Ok(PdaRun {
+            machine: self,
+            input,
+            position: 0,
+            stack: self.initial_stack.iter().rev().copied().collect(),
+            state: self.initial_state.clone(),
+            steps: 0,
+        })
+    }
+}
+
+/// A run of a pushdown automaton, advanced one step at a time
+#[derive(Debug, Clone)]
+pub struct PdaRun<'a> {
+    machine: &'a PdaMachine,
+    input: Vec<char>,
+    position: usize,
+    /// Stack, bottom first
+    stack: Vec<char>,
+    state: String,
+    steps: usize,
+}
+
+impl PdaRun<'_> {
+    /// The transition that applies in the current configuration
+    fn applicable(&self) -> Option<(&PdaKey, &PdaTarget)> {
+        let next = self.input.get(self.position).copied();
+        let top = self.stack.last().copied();
+        let mut reads = vec![None];
+        reads.extend(next.map(Some));
+        let mut pops = vec![None];
+        pops.extend(top.map(Some));
+        reads
+            .iter()
+            .flat_map(|read| pops.iter().map(move |pop| (*read, *pop)))
+            .find_map(|(read, pop)| {
+                self.machine
+                    .transitions
+                    .get_key_value(&(self.state.clone(), read, pop))
+            })
+    }
+
+    /// The verdict, once the automaton has halted: accepting in an accept
+    /// state with the whole input read, rejecting otherwise
+    pub fn verdict(&self) -> Option<bool> {
+        let machine = self.machine;
+        if machine.reject_states.contains(&self.state) {
+            Some(false)
+        } else if self.applicable().is_none() {
+            Some(self.position == self.input.len() && machine.accept_states.contains(&self.state))
+        } else {
+            None
+        }
+    }
+
+    /// Take one transition; returns `false` without changing anything if the
+    /// automaton has halted
+    pub fn step(&mut self) -> bool {
+        if self.verdict().is_some() {
+            return false;
+        }
+        let Some(((_, read, pop), target)) = self.applicable() else {
+            return false;
+        };
+        let (read, pop, target) = (read.is_some(), pop.is_some(), target.clone());
+        if read {
+            self.position += 1;
+        }
+        if pop {
+            self.stack.pop();
+        }
+        self.stack.extend(target.push.iter().rev());
+        self.state = target.new_state;
+        self.steps += 1;
+        true
+    }
+
+    /// Take transitions until the automaton halts or has taken `max_steps`
+    pub fn run(&mut self, max_steps: usize) {
+        while self.steps < max_steps && self.step() {}
+    }
+
+    /// The whole input
+    pub fn input(&self) -> &[char] {
+        &self.input
+    }
+
+    /// Number of input symbols read
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The stack, bottom first
+    pub fn stack(&self) -> &[char] {
+        &self.stack
+    }
+
+    /// The current state
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// Number of transitions taken
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+/// The single character of `entry`
+pub(crate) fn single(entry: &str, what: &str) -> Result<char, String> {
+    let mut chars = entry.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!(
+            "{} entry '{}' must be a single character",
+            what, entry
+        )),
+    }
+}
+
+/// Parse a `"state,input,pop": [new_state, push]` entry
+fn parse_transition(key: &str, value: &[String]) -> Result<(PdaKey, PdaTarget), String> {
+    let parts: Vec<&str> = key.split(',').collect();
+    let [state, read, pop] = parts[..] else {
+        return Err(format!("Invalid transition key: {}", key));
+    };
+    let optional = |symbol: &str| match symbol {
+        "" | "ε" => Ok(None),
+        symbol => single(symbol, &format!("Transition {}: symbol", key)).map(Some),
+    };
+    let [new_state, push] = value else {
+        return Err(format!("Invalid transition value for key: {}", key));
+    };
+    let push = match push.as_str() {
+        "ε" => Vec::new(),
+        push => push.chars().collect(),
+    };
+
+    Ok((
+        (state.to_string(), optional(read)?, optional(pop)?),
+        PdaTarget {
+            new_state: new_state.clone(),
+            push,
+        },
+    ))
+}
+
+/// Reject two transitions of `what` that can apply in the same
+/// configuration, given with the keys they were written with
+///
+/// Keys are the state and two symbols, each `None` if nothing is read there.
+pub(crate) fn check_deterministic(keys: &[(&String, PdaKey)], what: &str) -> Result<(), String> {
+    let overlap = |a: Option<char>, b: Option<char>| a.is_none() || b.is_none() || a == b;
+    for (i, (key, (state, read, pop))) in keys.iter().enumerate() {
+        for (other, (other_state, other_read, other_pop)) in &keys[i + 1..] {
+            if state == other_state && overlap(*read, *other_read) && overlap(*pop, *other_pop) {
+                return Err(format!(
+                    "Transitions {} and {} can both apply; {} must be deterministic",
+                    key, other, what
+                ));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,499 @@
+//! Pipelines: several machines composed into one decision procedure
+//!
+//! A pipeline file lists stages, each running a machine on the tape left
+//! behind by the previous stage. After a stage halts, its verdict decides
+//! what happens next:
+//!
+//! ```json
+//! {
+//!     "stages": [
+//!         { "name": "normalize", "machine": "normalize.json", "on_reject": "reject" },
+//!         { "name": "decide", "machine": "decide.json" }
+//!     ]
+//! }
+//! ```
+//!
+//! An action is `"next"` (run the following stage), `"accept"`, `"reject"`, or
+//! the name of a stage to jump to. By default an accepting stage continues with
+//! the next one and a rejecting stage rejects the whole pipeline. When the last
+//! stage continues with `"next"`, its verdict is the pipeline's verdict.
+//!
+//! A stage can also route on the exact state it halted in, which takes
+//! precedence over `on_accept`/`on_reject`:
+//!
+//! ```json
+//! { "machine": "add.json", "on_state": { "q_overflow": "handle_overflow" } }
+//! ```
+//!
+//! A stage can also run several machines concurrently on the same tape and
+//! combine their verdicts with `"and"`, `"or"` or `"majority"` voting. Such a
+//! stage hands its input tape on unchanged:
+//!
+//! ```json
+//! { "machines": ["even_ones.json", "palindrome.json"], "vote": "and" }
+//! ```
+//!
+//! Stages with different input conventions can be chained by listing tape
+//! transforms, applied in order to the tape a stage receives:
+//!
+//! ```json
+//! { "machine": "decide.json", "transforms": ["reverse", { "prepend": "#" }] }
+//! ```
+
+use crate::executor::{Executor, StepEvent, StepObserver};
+use crate::{formats, Direction, ExecutionResult, SymbolTable, TuringMachine};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Upper bound on stage runs, so jumps between stages cannot loop forever
+const MAX_STAGE_RUNS: usize = 1000;
+
+/// Pipeline file layout
+#[derive(Debug, Deserialize)]
+struct PipelineFile {
+    stages: Vec<StageSpec>,
+}
+
+/// A stage as written in the pipeline file
+#[derive(Debug, Deserialize)]
+struct StageSpec {
+    name: Option<String>,
+    machine: Option<String>,
+    #[serde(default)]
+    machines: Vec<String>,
+    vote: Option<VoteRule>,
+    on_accept: Option<String>,
+    on_reject: Option<String>,
+    #[serde(default)]
+    on_state: HashMap<String, String>,
+    #[serde(default)]
+    transforms: Vec<TapeTransform>,
+}
+
+/// How the verdicts of a multi-machine stage are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteRule {
+    /// Accept if every machine accepts
+    And,
+    /// Accept if any machine accepts
+    Or,
+    /// Accept if more than half of the machines accept
+    Majority,
+}
+
+impl VoteRule {
+    /// Combine verdicts; machines that did not halt count as undecided
+    ///
+    /// The result is only undecided if the undecided machines could still
+    /// change the outcome.
+    pub fn combine(&self, verdicts: &[Option<bool>]) -> Option<bool> {
+        let accepts = verdicts.iter().filter(|v| **v == Some(true)).count();
+        let rejects = verdicts.iter().filter(|v| **v == Some(false)).count();
+        let needed = match self {
+            VoteRule::And => verdicts.len(),
+            VoteRule::Or => 1,
+            VoteRule::Majority => verdicts.len() / 2 + 1,
+        };
+        if accepts >= needed {
+            Some(true)
+        } else if verdicts.len() - rejects < needed {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for VoteRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            VoteRule::And => "and",
+            VoteRule::Or => "or",
+            VoteRule::Majority => "majority",
+        })
+    }
+}
+
+/// A declarative edit of the tape handed to a stage
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TapeTransform {
+    /// Remove the receiving machine's blank symbol from both ends
+    TrimBlanks,
+    /// Remove any of the given symbols from both ends
+    Trim(String),
+    /// Reverse the tape contents
+    Reverse,
+    /// Keep only what lies between the first start marker and the next end
+    /// marker after it
+    Extract(String, String),
+    /// Add symbols at the start of the tape
+    Prepend(String),
+    /// Add symbols at the end of the tape
+    Append(String),
+}
+
+impl TapeTransform {
+    /// Apply the transform to tape contents
+    ///
+    /// `blank` is the name of the receiving machine's blank symbol.
+    fn apply(&self, tape: &str, blank: &str) -> Result<String, String> {
+        Ok(match self {
+            TapeTransform::TrimBlanks => trim_blanks(tape, blank),
+            TapeTransform::Trim(symbols) => tape.trim_matches(|c| symbols.contains(c)).to_string(),
+            TapeTransform::Reverse => tape.chars().rev().collect(),
+            TapeTransform::Extract(start, end) => {
+                let (_, after_start) = tape
+                    .split_once(start.as_str())
+                    .ok_or_else(|| format!("Start marker '{}' not found on tape", start))?;
+                let (between, _) = after_start
+                    .split_once(end.as_str())
+                    .ok_or_else(|| format!("End marker '{}' not found on tape", end))?;
+                between.to_string()
+            }
+            TapeTransform::Prepend(symbols) => format!("{}{}", symbols, tape),
+            TapeTransform::Append(symbols) => format!("{}{}", tape, symbols),
+        })
+    }
+}
+
+/// What to do once a stage has halted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Next,
+    Accept,
+    Reject,
+    Goto(usize),
+}
+
+/// A loaded pipeline stage
+#[derive(Debug)]
+struct Stage {
+    name: String,
+    /// Machines with their paths; more than one only for voting stages
+    machines: Vec<(PathBuf, TuringMachine)>,
+    vote: Option<VoteRule>,
+    on_accept: Action,
+    on_reject: Action,
+    on_state: HashMap<String, Action>,
+    transforms: Vec<TapeTransform>,
+}
+
+/// A sequence of machines with verdict-based routing between them
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+/// One stage execution within a pipeline run
+#[derive(Debug)]
+pub struct StageRun {
+    pub stage: String,
+    pub input: String,
+    /// The stage's verdict, combined by `vote` for multi-machine stages
+    pub accepts: Option<bool>,
+    pub vote: Option<VoteRule>,
+    pub machines: Vec<MachineRun>,
+}
+
+/// One machine execution within a stage
+#[derive(Debug)]
+pub struct MachineRun {
+    pub machine: PathBuf,
+    pub blank_symbol: char,
+    /// Names of the machine's multi-character symbols
+    pub symbols: SymbolTable,
+    pub result: ExecutionResult,
+    /// Every transition taken, recorded only when tracing
+    pub trace: Option<Vec<TraceStep>>,
+}
+
+/// A transition taken during a traced run
+#[derive(Debug, Serialize)]
+pub struct TraceStep {
+    pub step: usize,
+    pub from_state: String,
+    pub read_symbol: char,
+    pub to_state: String,
+    pub write_symbol: char,
+    pub direction: Direction,
+    pub head_position: i32,
+    pub tape: Vec<char>,
+}
+
+/// Observer collecting the trace of a run
+#[derive(Default)]
+pub(crate) struct TraceRecorder {
+    pub(crate) steps: Vec<TraceStep>,
+    bytes: usize,
+}
+
+impl StepObserver for TraceRecorder {
+    fn on_step(&mut self, event: &StepEvent) {
+        self.steps.push(TraceStep {
+            step: event.step,
+            from_state: event.from_state.to_string(),
+            read_symbol: event.read_symbol,
+            to_state: event.to_state.to_string(),
+            write_symbol: event.write_symbol,
+            direction: event.direction,
+            head_position: event.head_position,
+            tape: event.tape.to_vec(),
+        });
+        self.bytes += std::mem::size_of::<TraceStep>()
+            + std::mem::size_of_val(event.tape)
+            + event.from_state.len()
+            + event.to_state.len();
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Outcome of running a pipeline
+#[derive(Debug)]
+pub struct PipelineResult {
+    /// True if accepts, False if rejects, None if a stage didn't halt
+    pub accepts: Option<bool>,
+    pub runs: Vec<StageRun>,
+    pub total_steps: usize,
+}
+
+impl Pipeline {
+    /// Load a pipeline file; machine paths are relative to the file
+    pub fn load(path: &Path) -> Result<Pipeline, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        let file: PipelineFile = match path.extension().and_then(|s| s.to_str()) {
+            Some("toml") => {
+                toml::from_str(&text).map_err(|e| format!("Invalid pipeline file: {}", e))?
+            }
+            _ => json5::from_str(&text).map_err(|e| format!("Invalid pipeline file: {}", e))?,
+        };
+        if file.stages.is_empty() {
+            return Err("Pipeline has no stages".to_string());
+        }
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        let names: Vec<String> = file
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                spec.name
+                    .clone()
+                    .unwrap_or_else(|| format!("stage{}", i + 1))
+            })
+            .collect();
+        let resolve = |action: Option<&str>, default: Action| -> Result<Action, String> {
+            match action {
+                None => Ok(default),
+                Some("next") => Ok(Action::Next),
+                Some("accept") => Ok(Action::Accept),
+                Some("reject") => Ok(Action::Reject),
+                Some(name) => names
+                    .iter()
+                    .position(|n| n == name)
+                    .map(Action::Goto)
+                    .ok_or_else(|| format!("Unknown pipeline stage: {}", name)),
+            }
+        };
+
+        let mut stages = Vec::with_capacity(file.stages.len());
+        for (spec, name) in file.stages.iter().zip(&names) {
+            let paths = match (&spec.machine, spec.machines.as_slice(), spec.vote) {
+                (Some(machine), [], None) => vec![machine],
+                (None, [_, ..], Some(_)) => spec.machines.iter().collect(),
+                (None, [_, ..], None) => {
+                    return Err(format!("Stage {}: 'machines' requires a 'vote' rule", name))
+                }
+                _ => {
+                    return Err(format!(
+                        "Stage {}: give either 'machine' or 'machines' with 'vote'",
+                        name
+                    ))
+                }
+            };
+            let mut machines = Vec::with_capacity(paths.len());
+            for path in paths {
+                let machine_path = base.join(path);
+                let machine = formats::load_machine(&machine_path, &mut |_| {})
+                    .map_err(|e| format!("Stage {}: {}", name, e))?;
+                machines.push((machine_path, machine));
+            }
+
+            if spec.vote.is_some() && !spec.on_state.is_empty() {
+                return Err(format!(
+                    "Stage {}: on_state cannot be used with voting stages",
+                    name
+                ));
+            }
+            let mut on_state = HashMap::new();
+            for (state, action) in &spec.on_state {
+                if !machines[0].1.states.contains(state) {
+                    return Err(format!(
+                        "Stage {}: on_state refers to unknown state {}",
+                        name, state
+                    ));
+                }
+                on_state.insert(state.clone(), resolve(Some(action), Action::Next)?);
+            }
+
+            stages.push(Stage {
+                name: name.clone(),
+                machines,
+                vote: spec.vote,
+                on_accept: resolve(spec.on_accept.as_deref(), Action::Next)?,
+                on_reject: resolve(spec.on_reject.as_deref(), Action::Reject)?,
+                on_state,
+                transforms: spec.transforms.clone(),
+            });
+        }
+
+        Ok(Pipeline { stages })
+    }
+
+    /// Run the pipeline on an input string
+    ///
+    /// Each stage receives the previous stage's final tape with the
+    /// surrounding blanks removed, after applying the stage's transforms.
+    pub fn run(
+        &self,
+        input: &str,
+        max_steps: usize,
+        trace: bool,
+    ) -> Result<PipelineResult, String> {
+        let mut runs = Vec::new();
+        let mut total_steps = 0;
+        let mut tape = input.to_string();
+        let mut index = 0;
+
+        loop {
+            if runs.len() >= MAX_STAGE_RUNS {
+                return Err(format!(
+                    "Pipeline exceeded {} stage runs (stages jump in a cycle?)",
+                    MAX_STAGE_RUNS
+                ));
+            }
+
+            let stage = &self.stages[index];
+            let first = &stage.machines[0].1;
+            let blank = first.symbols.name(first.blank_symbol);
+            for transform in &stage.transforms {
+                tape = transform
+                    .apply(&tape, &blank)
+                    .map_err(|e| format!("Stage {}: {}", stage.name, e))?;
+            }
+
+            let machines = match stage.machines.as_slice() {
+                [(path, machine)] => vec![run_machine(path, machine, &tape, max_steps, trace)],
+                all => thread::scope(|scope| {
+                    let handles: Vec<_> = all
+                        .iter()
+                        .map(|(path, machine)| {
+                            let tape = &tape;
+                            scope.spawn(move || run_machine(path, machine, tape, max_steps, trace))
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().expect("machine thread panicked"))
+                        .collect()
+                }),
+            }
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Stage {}: {}", stage.name, e))?;
+            total_steps += machines.iter().map(|run| run.result.steps).sum::<usize>();
+
+            let (accepts, routed, output) = match stage.vote {
+                None => {
+                    let result = &machines[0].result;
+                    (
+                        result.accepts,
+                        stage.on_state.get(&result.final_state).copied(),
+                        trim_blanks(&result.tape, &blank),
+                    )
+                }
+                Some(rule) => {
+                    let verdicts: Vec<_> = machines.iter().map(|run| run.result.accepts).collect();
+                    (rule.combine(&verdicts), None, tape.clone())
+                }
+            };
+            runs.push(StageRun {
+                stage: stage.name.clone(),
+                input: std::mem::replace(&mut tape, output),
+                accepts,
+                vote: stage.vote,
+                machines,
+            });
+
+            let action = match (accepts, routed) {
+                (Some(_), Some(action)) => action,
+                (Some(true), None) => stage.on_accept,
+                (Some(false), None) => stage.on_reject,
+                (None, _) => {
+                    return Ok(PipelineResult {
+                        accepts: None,
+                        runs,
+                        total_steps,
+                    })
+                }
+            };
+
+            let verdict = match action {
+                Action::Next if index + 1 < self.stages.len() => {
+                    index += 1;
+                    continue;
+                }
+                Action::Next => accepts,
+                Action::Accept => Some(true),
+                Action::Reject => Some(false),
+                Action::Goto(target) => {
+                    index = target;
+                    continue;
+                }
+            };
+            return Ok(PipelineResult {
+                accepts: verdict,
+                runs,
+                total_steps,
+            });
+        }
+    }
+}
+
+/// Remove a blank symbol, given by name, from both ends of a tape
+fn trim_blanks(tape: &str, blank: &str) -> String {
+    tape.trim_start_matches(blank)
+        .trim_end_matches(blank)
+        .to_string()
+}
+
+/// Run one machine of a stage, recording its trace if requested
+fn run_machine(
+    path: &Path,
+    machine: &TuringMachine,
+    tape: &str,
+    max_steps: usize,
+    trace: bool,
+) -> Result<MachineRun, String> {
+    let mut recorder = TraceRecorder::default();
+    let mut executor = Executor::new(machine);
+    if trace {
+        executor = executor.observe(&mut recorder);
+    }
+    let result = executor.run(tape, max_steps)?;
+    Ok(MachineRun {
+        machine: path.to_path_buf(),
+        blank_symbol: machine.blank_symbol,
+        symbols: machine.symbols().clone(),
+        result,
+        trace: trace.then_some(recorder.steps),
+    })
+}
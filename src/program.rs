@@ -0,0 +1,145 @@
+//! Machines compiled for execution
+//!
+//! The executor does not look transitions up by state name and symbol:
+//! states and tape symbols are numbered densely, and the transition
+//! function becomes a table indexed by both numbers. A step then costs two
+//! array lookups instead of hashing a state name, and the current state is
+//! a number rather than a string cloned on every step.
+//!
+//! A machine compiles its program the first time it runs and keeps it
+//! until its transitions or states change.
+
+use crate::{Direction, TuringMachine};
+use std::collections::{BTreeSet, HashMap};
+
+/// Entry of [`Program::ascii`] for symbols that are not tape symbols
+const NO_SYMBOL: u32 = u32::MAX;
+
+/// A state's number in a [`Program`]
+pub(crate) type StateId = u32;
+
+/// A transition, with its new state numbered
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Target {
+    pub(crate) state: StateId,
+    pub(crate) write: char,
+    pub(crate) direction: Direction,
+}
+
+/// A machine's states and transition function, numbered for execution
+#[derive(Debug)]
+pub(crate) struct Program {
+    /// State names by number, the initial state first
+    states: Vec<String>,
+    /// Whether each state accepts or rejects, `None` if it does not halt
+    halts: Vec<Option<bool>>,
+    /// Numbers of the ASCII symbols by code, the most common symbols
+    ascii: [u32; 128],
+    /// Numbers of the other symbols, sorted by symbol
+    others: Vec<(char, u32)>,
+    symbol_count: usize,
+    /// Transitions by `state * symbol_count + symbol`
+    table: Vec<Option<Target>>,
+}
+
+impl Program {
+    pub(crate) fn new(machine: &TuringMachine) -> Self {
+        // Transitions and halting states may name states and symbols the
+        // declarations leave out; they are numbered all the same
+        let mut names: BTreeSet<&str> = machine.states.iter().map(String::as_str).collect();
+        let mut symbols: BTreeSet<char> = machine.tape_alphabet.iter().copied().collect();
+        for ((state, read), (next, _, _)) in &machine.transitions {
+            names.extend([state.as_str(), next.as_str()]);
+            symbols.insert(*read);
+        }
+        names.extend(machine.accept_states.iter().map(String::as_str));
+        names.extend(machine.reject_states.iter().map(String::as_str));
+        names.remove(machine.initial_state.as_str());
+        let states: Vec<String> = std::iter::once(machine.initial_state.as_str())
+            .chain(names)
+            .map(str::to_string)
+            .collect();
+        let halts = states
+            .iter()
+            .map(|state| {
+                if machine.accept_states.contains(state) {
+                    Some(true)
+                } else if machine.reject_states.contains(state) {
+                    Some(false)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut ascii = [NO_SYMBOL; 128];
+        let mut others = Vec::new();
+        for (id, &symbol) in (0..).zip(&symbols) {
+            match ascii.get_mut(symbol as usize) {
+                Some(entry) => *entry = id,
+                None => others.push((symbol, id)),
+            }
+        }
+
+        let state_ids: HashMap<&str, StateId> =
+            states.iter().map(String::as_str).zip(0..).collect();
+        let symbol_ids: HashMap<char, usize> = symbols.iter().copied().zip(0..).collect();
+        let mut table = vec![None; states.len() * symbols.len()];
+        for ((state, read), (next, write, direction)) in &machine.transitions {
+            let row = state_ids[state.as_str()] as usize;
+            table[row * symbols.len() + symbol_ids[read]] = Some(Target {
+                state: state_ids[next.as_str()],
+                write: *write,
+                direction: *direction,
+            });
+        }
+        Program {
+            halts,
+            ascii,
+            others,
+            symbol_count: symbols.len(),
+            table,
+            states,
+        }
+    }
+
+    /// The number of the initial state
+    pub(crate) fn initial(&self) -> StateId {
+        0
+    }
+
+    pub(crate) fn name(&self, state: StateId) -> &str {
+        &self.states[state as usize]
+    }
+
+    /// The number of the state named `name`
+    pub(crate) fn id(&self, name: &str) -> Option<StateId> {
+        self.states
+            .iter()
+            .position(|state| state == name)
+            .map(|state| state as StateId)
+    }
+
+    /// Whether a state accepts or rejects, `None` if it does not halt
+    pub(crate) fn halts(&self, state: StateId) -> Option<bool> {
+        self.halts[state as usize]
+    }
+
+    /// The transition taken in `state` reading `symbol`
+    pub(crate) fn target(&self, state: StateId, symbol: char) -> Option<&Target> {
+        let symbol = self.symbol(symbol)?;
+        self.table[state as usize * self.symbol_count + symbol as usize].as_ref()
+    }
+
+    fn symbol(&self, symbol: char) -> Option<u32> {
+        match self.ascii.get(symbol as usize) {
+            Some(&NO_SYMBOL) => None,
+            Some(&id) => Some(id),
+            None => self
+                .others
+                .binary_search_by_key(&symbol, |(other, _)| *other)
+                .ok()
+                .map(|index| self.others[index].1),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! QR codes for sharing small machines
+//!
+//! A machine definition is written as compact JSON, compressed with DEFLATE
+//! and encoded as URL-safe base64 behind a `tm1:` prefix. The resulting
+//! payload is short enough for a QR code when the machine is small, so it
+//! can be put on a slide or a handout and scanned with a phone; the scanned
+//! text is the payload itself, which [`from_payload`] turns back into a
+//! definition.
+
+use crate::{parse_machine_definition, MachineJson};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use qrcode::render::{svg, unicode};
+use qrcode::{EcLevel, QrCode};
+use std::io::{Read, Write};
+
+/// Prefix of every payload, naming the encoding version
+pub const PAYLOAD_PREFIX: &str = "tm1:";
+
+/// Largest decompressed definition accepted from a payload
+const MAX_DEFINITION_BYTES: u64 = 1 << 20;
+
+/// Encode a definition as a payload
+pub fn to_payload(definition: &MachineJson) -> Result<String, String> {
+    let json = serde_json::to_vec(definition).map_err(|e| e.to_string())?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&json).map_err(|e| e.to_string())?;
+    let compressed = encoder.finish().map_err(|e| e.to_string())?;
+    Ok(format!(
+        "{}{}",
+        PAYLOAD_PREFIX,
+        URL_SAFE_NO_PAD.encode(compressed)
+    ))
+}
+
+/// Decode and validate a payload, as scanned or pasted
+///
+/// Whitespace around the payload, such as a trailing newline, is ignored.
+pub fn from_payload(payload: &str) -> Result<MachineJson, String> {
+    let encoded = payload.trim().strip_prefix(PAYLOAD_PREFIX).ok_or_else(|| {
+        format!(
+            "Invalid QR payload: expected it to start with {}",
+            PAYLOAD_PREFIX
+        )
+    })?;
+    let compressed = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid QR payload: {}", e))?;
+    let mut json = String::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_DEFINITION_BYTES)
+        .read_to_string(&mut json)
+        .map_err(|e| format!("Invalid QR payload: {}", e))?;
+    parse_machine_definition(&json).map_err(|e| format!("Invalid JSON in QR payload: {}", e))
+}
+
+/// Build the QR code for a payload
+///
+/// Medium error correction is used, so a code stays readable with a little
+/// damage or glare; payloads beyond its capacity are reported as too large.
+fn code(payload: &str) -> Result<QrCode, String> {
+    QrCode::with_error_correction_level(payload, EcLevel::M).map_err(|_| {
+        format!(
+            "Machine is too large for a QR code ({} bytes encoded)",
+            payload.len()
+        )
+    })
+}
+
+/// The QR code for a payload as text for a terminal with a dark background
+///
+/// Each character covers two modules stacked vertically.
+pub fn to_text(payload: &str) -> Result<String, String> {
+    Ok(code(payload)?
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
+
+/// The QR code for a payload as an SVG image
+pub fn to_svg(payload: &str) -> Result<String, String> {
+    Ok(code(payload)?
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .build())
+}
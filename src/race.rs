@@ -0,0 +1,118 @@
+//! Racing a machine on several inputs at once
+//!
+//! [`race`] dovetails a machine over many inputs and stops as soon as the
+//! answer to "does it accept any of them?" is known. Every input gets the
+//! same step budget, doubled each round, so an input accepted after `n`
+//! steps wins once the budget reaches `n`, even if other inputs never halt,
+//! and the other runs are cut off at that budget.
+//!
+//! The winner is the input accepted after the fewest steps, the earliest
+//! input on a tie, so a race gives the same answer every time.
+
+use crate::dovetail::Dovetail;
+use crate::executor::RunConfig;
+use crate::{ExecutionResult, TuringMachine};
+use std::sync::atomic::AtomicBool;
+
+/// Outcome of a race
+#[derive(Debug)]
+pub struct Race {
+    /// Index of the winning input, if any input was accepted
+    pub winner: Option<usize>,
+    /// The run of every input, in input order; runs cut off because another
+    /// input already won are marked as interrupted
+    pub results: Vec<Result<ExecutionResult, String>>,
+}
+
+impl Race {
+    /// True if some input is accepted, false if every input is rejected,
+    /// `None` otherwise
+    pub fn accepts(&self) -> Option<bool> {
+        if self.winner.is_some() {
+            return Some(true);
+        }
+        let all_reject = self
+            .results
+            .iter()
+            .all(|result| matches!(result, Ok(result) if result.accepts == Some(false)));
+        all_reject.then_some(false)
+    }
+}
+
+/// Run `machine` on all `inputs` until one accepts or all halt
+///
+/// Setting `interrupt` stops every run, as with
+/// [`Executor::interrupt_on`](crate::executor::Executor::interrupt_on).
+pub fn race(
+    machine: &TuringMachine,
+    inputs: &[String],
+    config: &RunConfig,
+    interrupt: Option<&AtomicBool>,
+) -> Race {
+    let mut dovetail =
+        Dovetail::new(machine, inputs.iter().cloned(), config).admit_per_round(inputs.len());
+    if let Some(flag) = interrupt {
+        dovetail = dovetail.interrupt_on(flag);
+    }
+    let mut results: Vec<Option<Result<ExecutionResult, String>>> =
+        inputs.iter().map(|_| None).collect();
+    let mut winner = None;
+    while let Some(finished) = dovetail.next() {
+        if winner.is_none()
+            && matches!(finished.result, Ok(ref result) if result.accepts == Some(true))
+        {
+            winner = Some(finished.index);
+        }
+        results[finished.index] = Some(finished.result);
+        // Inputs finishing in the winner's round are reported anyway
+        if winner.is_some() && dovetail.pending() == 0 {
+            break;
+        }
+    }
+    for (index, _, result) in dovetail.into_open() {
+        results[index] = result.map(|result| {
+            Ok(ExecutionResult {
+                interrupted: true,
+                ..result
+            })
+        });
+    }
+    let results = results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("Stopped before running".to_string())))
+        .collect();
+    Race { winner, results }
+}
+
+/// Number of inputs of `length` symbols over the machine's input alphabet,
+/// or `None` if it does not fit in a `usize`
+pub fn count_inputs_of_length(machine: &TuringMachine, length: usize) -> Option<usize> {
+    let length = u32::try_from(length).ok()?;
+    machine.alphabet().len().checked_pow(length)
+}
+
+/// Every input of `length` symbols over the machine's input alphabet, in
+/// lexicographic order of the sorted alphabet
+///
+/// Check [`count_inputs_of_length`] first; the count grows exponentially.
+pub fn inputs_of_length(machine: &TuringMachine, length: usize) -> Vec<String> {
+    let mut alphabet: Vec<char> = machine.alphabet().iter().copied().collect();
+    alphabet.sort_by_key(|symbol| machine.symbols().name(*symbol));
+    let mut inputs = vec![Vec::new()];
+    for _ in 0..length {
+        inputs = inputs
+            .into_iter()
+            .flat_map(|prefix: Vec<char>| {
+                alphabet.iter().map(move |symbol| {
+                    let mut input = prefix.clone();
+                    input.push(*symbol);
+                    input
+                })
+            })
+            .collect();
+    }
+    inputs
+        .iter()
+        .map(|input| machine.symbols().render(input))
+        .collect()
+}
@@ -0,0 +1,147 @@
+//! Translated cyclers, found by Lin recurrence
+//!
+//! Some machines never come back to the same configuration, yet provably
+//! loop: they repeat the same steps further and further along the tape,
+//! leaving a growing trail behind. Such a translated cycler is caught the
+//! way the busy beaver searches do, following Lin and Brady. Take two
+//! configurations in the same state with only blanks right of the head,
+//! the second one `d` cells further right, and the lowest cell the head
+//! visited in between. If the tape from that cell to the head is the same
+//! in both, shifted by `d`, then everything the machine read between the
+//! two is there again, shifted: it takes the same steps again, `d` cells
+//! further, and so on forever. The same holds for a machine moving left.
+//!
+//! As with exact cycles, one configuration at the edge of the tape is kept,
+//! replaced at doubling step counts, and later ones at the same edge are
+//! compared with it.
+
+use crate::program::StateId;
+use crate::Cycle;
+
+/// Side of the tape beyond which only blanks remain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// A configuration at the edge of the tape, kept to compare later ones with
+struct Record {
+    step: usize,
+    state: StateId,
+    head: i64,
+    side: Side,
+    /// The tape and its first cell
+    cells: Vec<char>,
+    first: i64,
+}
+
+/// Watch for a translated cycle across the configurations of a run
+pub(crate) struct Recurrence {
+    blank: char,
+    record: Option<Record>,
+    /// Step at which the next configuration at an edge is kept
+    next_save: usize,
+    /// Lowest and highest head cell since the kept configuration
+    low: i64,
+    high: i64,
+}
+
+/// A configuration between two steps, with cells relative to the input
+pub(crate) struct Configuration<'a> {
+    pub(crate) step: usize,
+    pub(crate) state: StateId,
+    pub(crate) head: i64,
+    /// Cells that have held a non-blank symbol, as tracked by the run
+    pub(crate) used: Option<(i64, i64)>,
+    pub(crate) cells: &'a [char],
+    /// Cell of `cells[0]`
+    pub(crate) first: i64,
+    /// First cell of a left-bounded tape, where the edge policy could make
+    /// a step behave differently from its translation
+    pub(crate) left_bound: Option<i64>,
+}
+
+impl Recurrence {
+    pub(crate) fn new(blank: char) -> Self {
+        Recurrence {
+            blank,
+            record: None,
+            next_save: 0,
+            low: 0,
+            high: 0,
+        }
+    }
+
+    /// Look at the configuration reached, returning the translated cycle it
+    /// completes, if any
+    pub(crate) fn watch(&mut self, now: &Configuration) -> Option<Cycle> {
+        // Only blanks are right (left) of the head if no cell there has held
+        // anything else
+        let (right, left) = match now.used {
+            Some((lo, hi)) => (hi <= now.head, lo >= now.head),
+            None => (true, true),
+        };
+        if let Some(record) = &self.record {
+            self.low = self.low.min(now.head);
+            self.high = self.high.max(now.head);
+            let shift = now.head - record.head;
+            let repeats = now.state == record.state
+                && match record.side {
+                    Side::Right => {
+                        right
+                            && shift > 0
+                            && now.left_bound.is_none_or(|bound| self.low > bound)
+                            && self.shifted(record, now, shift)
+                    }
+                    Side::Left => {
+                        left && shift < 0
+                            && now.left_bound.is_none()
+                            && self.shifted(record, now, shift)
+                    }
+                };
+            if repeats {
+                return Some(Cycle {
+                    length: now.step - record.step,
+                    step: now.step,
+                    shift,
+                });
+            }
+        }
+        if now.step >= self.next_save && (right || left) {
+            self.record = Some(Record {
+                step: now.step,
+                state: now.state,
+                head: now.head,
+                side: if right { Side::Right } else { Side::Left },
+                cells: now.cells.to_vec(),
+                first: now.first,
+            });
+            self.next_save = (now.step * 2).max(1);
+            (self.low, self.high) = (now.head, now.head);
+        }
+        None
+    }
+
+    /// Whether the cells of the kept tape between the head and the furthest
+    /// cell visited since are on the tape now, `shift` cells further
+    fn shifted(&self, record: &Record, now: &Configuration, shift: i64) -> bool {
+        let same = |cell: i64| {
+            symbol(&record.cells, record.first, cell, self.blank)
+                == symbol(now.cells, now.first, cell + shift, self.blank)
+        };
+        // From the head, where the tapes usually differ first
+        match record.side {
+            Side::Right => (self.low..=record.head).rev().all(same),
+            Side::Left => (record.head..=self.high).all(same),
+        }
+    }
+}
+
+/// The symbol in `cell` of a tape whose first cell is `first`
+fn symbol(cells: &[char], first: i64, cell: i64, blank: char) -> char {
+    usize::try_from(cell - first)
+        .ok()
+        .and_then(|index| cells.get(index).copied())
+        .unwrap_or(blank)
+}
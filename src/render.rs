@@ -0,0 +1,286 @@
+//! State diagrams rendered as images
+//!
+//! [`svg`] draws a machine's state graph as an SVG image and [`png`]
+//! rasterizes that image, without Graphviz or any other external program.
+//! States are placed in the [`layers`] of the diagram module, one column
+//! per layer from left to right, and transitions between the same two
+//! states share an arrow labelled with one line per transition.
+//!
+//! [`layers`]: crate::diagram::layers
+
+use crate::diagram::{self, ordered_transitions, transition_label};
+use crate::TuringMachine;
+use resvg::{tiny_skia, usvg};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Horizontal distance between the centres of two layers
+const LAYER_SPACING: f64 = 190.0;
+/// Vertical distance between the centres of two states of a layer
+const STATE_SPACING: f64 = 120.0;
+/// Space around the states, their loops and labels
+const MARGIN: f64 = 40.0;
+/// Radius of a state with a short name
+const RADIUS: f64 = 24.0;
+/// Height of a line of text
+const LINE_HEIGHT: f64 = 14.0;
+/// Length of the arrow into the initial state
+const ENTRY_LENGTH: f64 = 36.0;
+/// Height of a loop above a state, without its label
+const LOOP_HEIGHT: f64 = 40.0;
+/// How far the arrows between two states going both ways bend apart
+const BEND: f64 = 28.0;
+/// Pixels per SVG unit in PNG images, so that text stays sharp
+const PNG_SCALE: f32 = 2.0;
+
+/// A state placed on the image
+struct Node {
+    x: f64,
+    y: f64,
+    /// Horizontal radius, see [`width_radius`]
+    rx: f64,
+}
+
+impl Node {
+    /// The point on the outline in the direction of `(x, y)`
+    fn boundary(&self, x: f64, y: f64) -> (f64, f64) {
+        let (dx, dy) = (x - self.x, y - self.y);
+        let length = dx.hypot(dy).max(f64::EPSILON);
+        let (ux, uy) = (dx / length, dy / length);
+        let scale = 1.0 / ((ux / self.rx).powi(2) + (uy / RADIUS).powi(2)).sqrt();
+        (self.x + ux * scale, self.y + uy * scale)
+    }
+}
+
+/// The machine's state diagram as an SVG image
+///
+/// The initial state has an entry arrow, accept states are drawn with a
+/// double outline and shaded green, and reject states shaded red.
+pub fn svg(machine: &TuringMachine) -> String {
+    let layers = diagram::layers(machine);
+    let ids: HashMap<&str, usize> = layers
+        .iter()
+        .flatten()
+        .zip(0..)
+        .map(|(s, i)| (*s, i))
+        .collect();
+
+    // One arrow per pair of states, labelled with all their transitions
+    let mut arrows: Vec<((&str, &str), Vec<String>)> = Vec::new();
+    let mut arrow_of = HashMap::new();
+    for (state, read, next, write, direction) in ordered_transitions(machine, &ids) {
+        let arrow = *arrow_of.entry((state, next)).or_insert_with(|| {
+            arrows.push(((state, next), Vec::new()));
+            arrows.len() - 1
+        });
+        arrows[arrow]
+            .1
+            .push(transition_label(machine, read, write, direction));
+    }
+
+    // Leave room above every state for the tallest loop and its label
+    let loop_lines = arrows
+        .iter()
+        .filter(|((state, next), _)| state == next)
+        .map(|(_, labels)| labels.len())
+        .max();
+    let loop_height = loop_lines.map_or(0.0, |lines| LOOP_HEIGHT + lines as f64 * LINE_HEIGHT);
+    let state_spacing = STATE_SPACING.max(2.0 * RADIUS + loop_height + 16.0);
+    let title_height = if machine.metadata.name.is_some() {
+        30.0
+    } else {
+        0.0
+    };
+    let top = MARGIN + title_height + loop_height + RADIUS;
+
+    // Room on the sides for the widest state and the initial state's arrow
+    let widest = |layer: &[&str]| {
+        layer
+            .iter()
+            .map(|state| width_radius(state))
+            .fold(RADIUS, f64::max)
+    };
+    let left = MARGIN + ENTRY_LENGTH + widest(&layers[0]);
+    let right = MARGIN + widest(&layers[layers.len() - 1]);
+    let rows = layers.iter().map(Vec::len).max().unwrap_or(1);
+    let width = left + (layers.len() - 1) as f64 * LAYER_SPACING + right;
+    let height = top + (rows - 1) as f64 * state_spacing + RADIUS + MARGIN;
+    let mut nodes: HashMap<&str, Node> = HashMap::new();
+    for (column, layer) in layers.iter().enumerate() {
+        let offset = (rows - layer.len()) as f64 / 2.0;
+        for (row, state) in layer.iter().enumerate() {
+            let node = Node {
+                x: left + column as f64 * LAYER_SPACING,
+                y: top + (offset + row as f64) * state_spacing,
+                rx: width_radius(state),
+            };
+            nodes.insert(*state, node);
+        }
+    }
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="DejaVu Sans, Helvetica, Arial, sans-serif" font-size="12">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        svg,
+        r#"  <defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z"/></marker></defs>"#
+    );
+    let _ = writeln!(svg, r#"  <rect width="100%" height="100%" fill="white"/>"#);
+    if let Some(name) = &machine.metadata.name {
+        let _ = writeln!(
+            svg,
+            r#"  <text x="{}" y="{}" text-anchor="middle" font-size="14">{}</text>"#,
+            width / 2.0,
+            MARGIN,
+            escape(name)
+        );
+    }
+
+    // Arrows first, so the states are drawn over their ends
+    for ((state, next), labels) in &arrows {
+        let from = &nodes[state];
+        let to = &nodes[next];
+        let label_at = if state == next {
+            let top = from.y - RADIUS * 0.85;
+            let _ = writeln!(
+                svg,
+                r#"  <path d="M {:.1} {:.1} C {:.1} {:.1} {:.1} {:.1} {:.1} {:.1}" fill="none" stroke="black" marker-end="url(#arrow)"/>"#,
+                from.x - 12.0,
+                top,
+                from.x - 30.0,
+                top - 50.0,
+                from.x + 30.0,
+                top - 50.0,
+                from.x + 12.0,
+                top
+            );
+            (from.x, top - 42.0)
+        } else if arrow_of.contains_key(&(*next, *state)) {
+            let (dx, dy) = (to.x - from.x, to.y - from.y);
+            let length = dx.hypot(dy);
+            let (nx, ny) = (dy / length * BEND, -dx / length * BEND);
+            let control = ((from.x + to.x) / 2.0 + nx, (from.y + to.y) / 2.0 + ny);
+            let start = from.boundary(control.0, control.1);
+            let end = to.boundary(control.0, control.1);
+            let _ = writeln!(
+                svg,
+                r#"  <path d="M {:.1} {:.1} Q {:.1} {:.1} {:.1} {:.1}" fill="none" stroke="black" marker-end="url(#arrow)"/>"#,
+                start.0, start.1, control.0, control.1, end.0, end.1
+            );
+            (
+                (start.0 + end.0) / 4.0 + control.0 / 2.0 + nx / 2.0,
+                (start.1 + end.1) / 4.0 + control.1 / 2.0 + ny / 2.0,
+            )
+        } else {
+            let start = from.boundary(to.x, to.y);
+            let end = to.boundary(from.x, from.y);
+            let _ = writeln!(
+                svg,
+                r#"  <line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black" marker-end="url(#arrow)"/>"#,
+                start.0, start.1, end.0, end.1
+            );
+            // Short of the middle, so that the labels of crossing arrows
+            // do not meet
+            (
+                start.0 + (end.0 - start.0) * 0.4,
+                start.1 + (end.1 - start.1) * 0.4 - 4.0,
+            )
+        };
+        // The label's last line sits on the point, the others above it
+        for (i, label) in labels.iter().enumerate() {
+            let _ = writeln!(
+                svg,
+                r##"  <text x="{:.1}" y="{:.1}" text-anchor="middle" fill="#333" stroke="white" stroke-width="3" paint-order="stroke">{}</text>"##,
+                label_at.0,
+                label_at.1 - (labels.len() - 1 - i) as f64 * LINE_HEIGHT,
+                escape(label)
+            );
+        }
+    }
+
+    let initial = &nodes[machine.initial_state.as_str()];
+    let _ = writeln!(
+        svg,
+        r#"  <line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black" marker-end="url(#arrow)"/>"#,
+        initial.x - initial.rx - ENTRY_LENGTH,
+        initial.y,
+        initial.x - initial.rx,
+        initial.y
+    );
+    for layer in &layers {
+        for state in layer {
+            let node = &nodes[state];
+            let accepting = machine.accept_states.contains(*state);
+            let fill = if accepting {
+                "#c8e6c9"
+            } else if machine.reject_states.contains(*state) {
+                "#ffcdd2"
+            } else {
+                "white"
+            };
+            let _ = writeln!(
+                svg,
+                r#"  <ellipse cx="{:.1}" cy="{:.1}" rx="{:.1}" ry="{}" fill="{}" stroke="black" stroke-width="1.5"/>"#,
+                node.x, node.y, node.rx, RADIUS, fill
+            );
+            if accepting {
+                let _ = writeln!(
+                    svg,
+                    r#"  <ellipse cx="{:.1}" cy="{:.1}" rx="{:.1}" ry="{}" fill="none" stroke="black"/>"#,
+                    node.x,
+                    node.y,
+                    node.rx - 4.0,
+                    RADIUS - 4.0
+                );
+            }
+            let _ = writeln!(
+                svg,
+                r#"  <text x="{:.1}" y="{:.1}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                node.x,
+                node.y,
+                escape(state)
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// The machine's state diagram as a PNG image
+///
+/// Text is set in DejaVu Sans, Helvetica or Arial from the system's fonts;
+/// on a system with none of them the image has no text.
+pub fn png(machine: &TuringMachine) -> Result<Vec<u8>, String> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(&svg(machine), &options).map_err(|e| e.to_string())?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(PNG_SCALE)
+        .ok_or("Diagram too large")?;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Diagram too large")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(PNG_SCALE, PNG_SCALE),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+/// Horizontal radius of a state, wider than [`RADIUS`] for long names
+fn width_radius(state: &str) -> f64 {
+    RADIUS.max(state.chars().count() as f64 * 3.5 + 10.0)
+}
+
+/// Escape text for use in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,1047 @@
+//! HTTP server running uploaded machines
+//!
+//! A JSON API for web front-ends, such as a classroom web UI, that want to
+//! run machines with this executor:
+//!
+//! | Request | Does |
+//! |---------|------|
+//! | `POST /machines` | Upload a definition in the JSON schema; answers with its `id` |
+//! | `GET /machines` | List the uploaded machines |
+//! | `GET /machines/{id}` | The machine's definition |
+//! | `GET /machines/{id}/info` | Its [`MachineInfo`] summary, with its [`Analysis`] under `analysis` |
+//! | `DELETE /machines/{id}` | Forget the machine |
+//! | `POST /machines/{id}/runs` | Run it on `{"input": "0110", "max_steps": 5000, "trace": true}` |
+//! | `GET /runs/{id}` | The report of an earlier run |
+//!
+//! A run report holds the execution result and, if asked for, the trace
+//! of every transition, as in the reports of the
+//! [daemon](crate::daemon). Runs are limited to the server's step limit,
+//! which also applies when a run or its machine asks for more steps, and
+//! to its memory limit, [`DEFAULT_MAX_MEMORY`] unless set, which counts the
+//! trace; a run reaching it stops without a verdict. Errors
+//! are answered with a status code and `{"error": "..."}`. Machines and
+//! reports are kept in memory only, the reports of the last
+//! [`RUN_HISTORY`] runs.
+//!
+//! With [`Server::stream_on`] the server also listens for WebSocket
+//! connections to `/machines/{id}`, over which a front-end can watch a run
+//! of the machine as it happens, to animate the tape. The client sends
+//! commands as JSON text messages:
+//!
+//! | Command | Does |
+//! |---------|------|
+//! | `{"command": "run", "input": "0110", "max_steps": 5000, "paused": false}` | Start a run, replacing the one going on |
+//! | `{"command": "pause"}` | Stop sending steps |
+//! | `{"command": "step"}` | Take one step while paused |
+//! | `{"command": "resume"}` | Carry on |
+//! | `{"command": "speed", "interval": 50, "batch": 1}` | Wait `interval` milliseconds between messages, each holding `batch` steps |
+//!
+//! and the server answers with messages whose `type` is `steps`, holding
+//! the configurations reached, starting at step 0, `paused` or `resumed`,
+//! `result`, holding the execution result at the end of the run, or
+//! `error`.
+
+use crate::analysis::{self, Analysis};
+use crate::executor::{self, Executor, RunConfig, StepEvent, StepObserver, DEFAULT_MAX_STEPS};
+use crate::info::MachineInfo;
+use crate::pipeline::{TraceRecorder, TraceStep};
+use crate::{
+    parse_machine_definition, parse_machine_json, ExecutionResult, MachineJson, TuringMachine,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Read};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response};
+use tungstenite::handshake::server::{ErrorResponse, Request as Handshake, Response as Accept};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+/// Number of run reports kept for `GET /runs/{id}`
+pub const RUN_HISTORY: usize = 1000;
+
+/// Memory a run and its trace may use, in bytes, unless the server sets
+/// another limit
+pub const DEFAULT_MAX_MEMORY: usize = 256 << 20;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY_BYTES: u64 = 16 << 20;
+
+/// Time between messages of a stream session, unless the client sets it
+const STREAM_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Body of `POST /machines/{id}/runs`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RunRequest {
+    pub(crate) input: String,
+    #[serde(default)]
+    pub(crate) trace: bool,
+    #[serde(default, deserialize_with = "executor::deserialize_max_steps")]
+    pub(crate) max_steps: Option<usize>,
+}
+
+/// Report of a run, answered to `POST /machines/{id}/runs` and kept for
+/// `GET /runs/{id}`
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub id: usize,
+    pub machine: usize,
+    pub input: String,
+    /// Step limit the run had
+    pub max_steps: usize,
+    pub result: ExecutionResult,
+    /// Every transition taken, recorded only when the run asks for a trace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<TraceStep>>,
+}
+
+/// Entry of `GET /machines`
+#[derive(Debug, Serialize)]
+pub(crate) struct MachineSummary {
+    pub(crate) id: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    pub(crate) states: usize,
+    pub(crate) transitions: usize,
+}
+
+/// Answer to `GET /machines/{id}/info`
+#[derive(Debug, Serialize)]
+pub(crate) struct MachineDetails {
+    #[serde(flatten)]
+    pub(crate) info: MachineInfo,
+    pub(crate) analysis: Analysis,
+}
+
+impl MachineDetails {
+    fn new(machine: &TuringMachine) -> Self {
+        MachineDetails {
+            info: MachineInfo::from(machine),
+            analysis: analysis::analyze(machine),
+        }
+    }
+}
+
+impl MachineSummary {
+    fn new(id: usize, machine: &TuringMachine) -> Self {
+        MachineSummary {
+            id,
+            name: machine.metadata().name.clone(),
+            states: machine.states().len(),
+            transitions: machine.transitions().len(),
+        }
+    }
+}
+
+/// Why a request was refused
+#[derive(Debug)]
+pub(crate) enum Refusal {
+    /// No machine or run has the id asked for
+    NotFound(String),
+    /// The definition, input or request is invalid
+    Invalid(String),
+}
+
+/// Machines and run reports, shared by the workers
+#[derive(Default)]
+struct Store {
+    machines: BTreeMap<usize, Arc<TuringMachine>>,
+    /// Id of the last machine uploaded
+    last_machine: usize,
+    runs: BTreeMap<usize, Arc<RunReport>>,
+    /// Id of the last run
+    last_run: usize,
+}
+
+/// A command sent by the client of a stream session
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case", deny_unknown_fields)]
+enum Command {
+    Run {
+        input: String,
+        #[serde(default, deserialize_with = "executor::deserialize_max_steps")]
+        max_steps: Option<usize>,
+        /// Wait for `step` or `resume` before taking the first step
+        #[serde(default)]
+        paused: bool,
+    },
+    Pause,
+    Resume,
+    Step,
+    Speed {
+        /// Milliseconds between messages
+        interval: Option<u64>,
+        /// Steps per message
+        batch: Option<usize>,
+    },
+}
+
+/// A message sent to the client of a stream session
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event<'a> {
+    Steps { steps: &'a [StreamStep] },
+    Paused { step: usize },
+    Resumed { step: usize },
+    Result { result: &'a ExecutionResult },
+    Error { error: &'a str },
+}
+
+/// A configuration sent to the client of a stream session
+#[derive(Debug, Serialize)]
+pub(crate) struct StreamStep {
+    pub(crate) step: usize,
+    pub(crate) state: String,
+    /// Tape contents, with named symbols written out by their names
+    pub(crate) tape: String,
+    /// Tape contents, the name of the symbol in each cell
+    pub(crate) tape_cells: Vec<String>,
+    /// Head position, in cells of `tape_cells`
+    pub(crate) head_position: i32,
+}
+
+impl StreamStep {
+    /// The configuration a run of `machine` on `input` starts in, or why
+    /// the input is invalid
+    pub(crate) fn initial(machine: &TuringMachine, input: &str) -> Result<Self, String> {
+        let input = machine.map_input(input)?;
+        if let Some(symbol) = input.iter().find(|s| !machine.alphabet.contains(s)) {
+            return Err(format!(
+                "Invalid input symbol: {}",
+                machine.symbols.name(*symbol)
+            ));
+        }
+        let tape = machine.start_tape(&input)?;
+        Ok(StreamStep {
+            step: 0,
+            state: machine.initial_state.clone(),
+            tape: machine.symbols.render(&tape),
+            tape_cells: machine.symbols.names(&tape),
+            head_position: machine.tape_setup.head() as i32,
+        })
+    }
+
+    /// The configuration after a step
+    pub(crate) fn after(machine: &TuringMachine, event: &StepEvent) -> Self {
+        StreamStep {
+            step: event.step,
+            state: event.to_state.to_string(),
+            tape: machine.symbols.render(event.tape),
+            tape_cells: event.tape_names(),
+            head_position: event.head_position,
+        }
+    }
+}
+
+/// The connection of a stream session and its settings, which last from
+/// run to run
+struct Session {
+    socket: WebSocket<TcpStream>,
+    interval: Duration,
+    batch: usize,
+    paused: bool,
+}
+
+/// Whether a stream session goes on after a run
+enum Outcome {
+    /// Wait for the next command
+    Done,
+    /// Start the run the client asked for during the last one
+    Restart(Command),
+    /// The client is gone
+    Closed,
+}
+
+/// An answer to a request
+struct Reply {
+    status: u16,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: u16, value: &impl Serialize) -> Reply {
+        match serde_json::to_string_pretty(value) {
+            Ok(body) => Reply { status, body },
+            Err(e) => Reply::error(500, &e.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Reply {
+        Reply::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    fn empty() -> Reply {
+        Reply {
+            status: 204,
+            body: String::new(),
+        }
+    }
+}
+
+impl From<Refusal> for Reply {
+    fn from(refusal: Refusal) -> Self {
+        match refusal {
+            Refusal::NotFound(message) => Reply::error(404, &message),
+            Refusal::Invalid(message) => Reply::error(400, &message),
+        }
+    }
+}
+
+/// An HTTP server answering requests with a fixed number of workers
+///
+/// Clones share their machines and runs.
+#[derive(Clone)]
+pub struct Server {
+    workers: usize,
+    max_steps: usize,
+    max_memory: usize,
+    stream_address: Option<String>,
+    #[cfg(feature = "grpc")]
+    grpc_address: Option<String>,
+    store: Arc<Mutex<Store>>,
+}
+
+impl Server {
+    /// Create a server with no machines
+    pub fn new() -> Self {
+        Server {
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            max_steps: DEFAULT_MAX_STEPS,
+            max_memory: DEFAULT_MAX_MEMORY,
+            stream_address: None,
+            #[cfg(feature = "grpc")]
+            grpc_address: None,
+            store: Arc::default(),
+        }
+    }
+
+    /// Set the number of requests answered at the same time (at least one)
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Set the step limit of runs, which is also their default
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Set the memory limit of runs, their traces included, in bytes
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// Also listen for WebSocket stream sessions on `address`
+    pub fn stream_on(mut self, address: &str) -> Self {
+        self.stream_address = Some(address.to_string());
+        self
+    }
+
+    /// Also answer gRPC calls on `address`, sharing the machines and runs
+    #[cfg(feature = "grpc")]
+    pub fn grpc_on(mut self, address: &str) -> Self {
+        self.grpc_address = Some(address.to_string());
+        self
+    }
+
+    /// Listen on `address`, such as `127.0.0.1:8080`, and answer requests
+    /// until the process is stopped
+    pub fn serve(&self, address: &str) -> Result<(), String> {
+        let http = tiny_http::Server::http(address)
+            .map_err(|e| format!("Cannot listen on {}: {}", address, e))?;
+        let bind = |address: &Option<String>| match address {
+            Some(address) => TcpListener::bind(address)
+                .map(Some)
+                .map_err(|e| format!("Cannot listen on {}: {}", address, e)),
+            None => Ok(None),
+        };
+        let streams = bind(&self.stream_address)?;
+        #[cfg(feature = "grpc")]
+        let grpc = bind(&self.grpc_address)?;
+        thread::scope(|scope| {
+            if let Some(listener) = &streams {
+                scope.spawn(move || {
+                    for connection in listener.incoming() {
+                        match connection {
+                            Ok(connection) => {
+                                scope.spawn(move || self.stream(connection));
+                            }
+                            Err(e) => tracing::warn!("cannot accept a stream session: {}", e),
+                        }
+                    }
+                });
+            }
+            #[cfg(feature = "grpc")]
+            if let Some(listener) = grpc {
+                scope.spawn(move || {
+                    if let Err(e) = crate::grpc::serve(self.clone(), listener) {
+                        tracing::error!("gRPC service stopped: {}", e);
+                    }
+                });
+            }
+            for _ in 0..self.workers {
+                scope.spawn(|| {
+                    while let Ok(request) = http.recv() {
+                        self.answer(request);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Read a request, route it and send the reply
+    fn answer(&self, mut request: Request) {
+        let method = request.method().clone();
+        let url = request.url().to_string();
+        let reply = match read_body(request.as_reader()) {
+            Ok(body) => self.handle(&method, &url, &body),
+            Err(reply) => reply,
+        };
+        tracing::info!(%method, %url, status = reply.status, "request");
+
+        let mut response = Response::from_string(reply.body).with_status_code(reply.status);
+        for (name, value) in [
+            ("Content-Type", "application/json"),
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS"),
+            ("Access-Control-Allow-Headers", "Content-Type"),
+        ] {
+            if let Ok(header) = Header::from_bytes(name, value) {
+                response.add_header(header);
+            }
+        }
+        if let Err(e) = request.respond(response) {
+            tracing::warn!("cannot send the reply to {}: {}", url, e);
+        }
+    }
+
+    /// The reply to a request
+    fn handle(&self, method: &Method, url: &str, body: &str) -> Reply {
+        let path = url.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let id = |segment: &str| segment.parse::<usize>().ok();
+        match (method, &segments[..]) {
+            (Method::Options, _) => Reply::empty(),
+            (Method::Get, ["machines"]) => Reply::json(200, &self.machine_summaries()),
+            (Method::Post, ["machines"]) => self
+                .add_machine(body)
+                .map_or_else(Reply::from, |summary| Reply::json(201, &summary)),
+            (Method::Get, ["machines", machine]) => match id(machine) {
+                Some(machine) => self
+                    .find_machine(machine)
+                    .map_or_else(Reply::from, |machine| {
+                        Reply::json(200, &MachineJson::from(machine.as_ref()))
+                    }),
+                None => not_found(path),
+            },
+            (Method::Get, ["machines", machine, "info"]) => match id(machine) {
+                Some(machine) => self
+                    .find_machine(machine)
+                    .map_or_else(Reply::from, |machine| {
+                        Reply::json(200, &MachineDetails::new(&machine))
+                    }),
+                None => not_found(path),
+            },
+            (Method::Delete, ["machines", machine]) => match id(machine) {
+                Some(machine) => self
+                    .remove_machine(machine)
+                    .map_or_else(Reply::from, |()| Reply::empty()),
+                None => not_found(path),
+            },
+            (Method::Post, ["machines", machine, "runs"]) => match id(machine) {
+                Some(machine) => {
+                    let report = self.find_machine(machine).and_then(|_| {
+                        let request = serde_json::from_str(body)
+                            .map_err(|e| Refusal::Invalid(format!("Invalid run request: {}", e)))?;
+                        self.run(machine, request)
+                    });
+                    report.map_or_else(Reply::from, |report| Reply::json(201, report.as_ref()))
+                }
+                None => not_found(path),
+            },
+            (Method::Get, ["runs", run]) => match id(run) {
+                Some(run) => self
+                    .report(run)
+                    .map_or_else(Reply::from, |report| Reply::json(200, report.as_ref())),
+                None => not_found(path),
+            },
+            (
+                _,
+                ["machines"] | ["machines", _] | ["machines", _, "info" | "runs"] | ["runs", _],
+            ) => Reply::error(405, &format!("{} is not allowed on {}", method, path)),
+            _ => not_found(path),
+        }
+    }
+
+    /// The uploaded machines, by id
+    pub(crate) fn machine_summaries(&self) -> Vec<MachineSummary> {
+        let store = self.store.lock().unwrap();
+        store
+            .machines
+            .iter()
+            .map(|(id, machine)| MachineSummary::new(*id, machine))
+            .collect()
+    }
+
+    /// Upload a definition in the JSON schema
+    pub(crate) fn add_machine(&self, definition: &str) -> Result<MachineSummary, Refusal> {
+        let machine = parse_machine_definition(definition)
+            .and_then(|definition| parse_machine_json(&definition))
+            .map_err(Refusal::Invalid)?;
+        let mut store = self.store.lock().unwrap();
+        store.last_machine += 1;
+        let id = store.last_machine;
+        let summary = MachineSummary::new(id, &machine);
+        store.machines.insert(id, Arc::new(machine));
+        Ok(summary)
+    }
+
+    pub(crate) fn find_machine(&self, id: usize) -> Result<Arc<TuringMachine>, Refusal> {
+        self.machine(id)
+            .ok_or_else(|| Refusal::NotFound(format!("No machine {}", id)))
+    }
+
+    pub(crate) fn remove_machine(&self, id: usize) -> Result<(), Refusal> {
+        match self.store.lock().unwrap().machines.remove(&id) {
+            Some(_) => Ok(()),
+            None => Err(Refusal::NotFound(format!("No machine {}", id))),
+        }
+    }
+
+    /// Run a machine and keep the report
+    pub(crate) fn run(
+        &self,
+        machine_id: usize,
+        request: RunRequest,
+    ) -> Result<Arc<RunReport>, Refusal> {
+        let machine = self.find_machine(machine_id)?;
+        let config = self.run_config(&machine, request.max_steps);
+
+        let mut recorder = TraceRecorder::default();
+        let mut executor = Executor::new(&machine);
+        if request.trace {
+            executor = executor.observe(&mut recorder);
+        }
+        let result = executor
+            .run_with(&request.input, &config)
+            .map_err(Refusal::Invalid)?;
+
+        let mut store = self.store.lock().unwrap();
+        store.last_run += 1;
+        let id = store.last_run;
+        let report = Arc::new(RunReport {
+            id,
+            machine: machine_id,
+            input: request.input,
+            max_steps: config.max_steps,
+            result,
+            trace: request.trace.then_some(recorder.steps),
+        });
+        store.runs.insert(id, report.clone());
+        while store.runs.len() > RUN_HISTORY {
+            store.runs.pop_first();
+        }
+        Ok(report)
+    }
+
+    /// The report of one of the last [`RUN_HISTORY`] runs
+    pub(crate) fn report(&self, id: usize) -> Result<Arc<RunReport>, Refusal> {
+        self.store
+            .lock()
+            .unwrap()
+            .runs
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| {
+                Refusal::NotFound(format!(
+                    "No run {}; only the last {} runs are kept",
+                    id, RUN_HISTORY
+                ))
+            })
+    }
+
+    fn machine(&self, id: usize) -> Option<Arc<TuringMachine>> {
+        self.store.lock().unwrap().machines.get(&id).cloned()
+    }
+
+    /// Limits of a run asking for `requested` steps: the server's, or the
+    /// machine's and the run's where they are lower
+    pub(crate) fn run_config(
+        &self,
+        machine: &TuringMachine,
+        requested: Option<usize>,
+    ) -> RunConfig {
+        let defaults = machine.run_defaults();
+        RunConfig {
+            max_steps: requested
+                .or(defaults.max_steps)
+                .unwrap_or(self.max_steps)
+                .min(self.max_steps),
+            max_memory_bytes: Some(
+                defaults
+                    .max_memory
+                    .unwrap_or(usize::MAX)
+                    .min(self.max_memory),
+            ),
+            ..RunConfig::default()
+        }
+    }
+
+    /// Accept a stream session and answer its commands until the client
+    /// leaves
+    fn stream(&self, connection: TcpStream) {
+        let mut machine = None;
+        // The refusal's type is the handshake's
+        #[allow(clippy::result_large_err)]
+        let accept = |request: &Handshake, response: Accept| {
+            let path = request.uri().path();
+            machine = path
+                .strip_prefix("/machines/")
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| Some((id, self.machine(id)?)));
+            if machine.is_some() {
+                return Ok(response);
+            }
+            let mut refusal = ErrorResponse::new(Some(format!("No such machine: {}", path)));
+            *refusal.status_mut() = StatusCode::NOT_FOUND;
+            Err(refusal)
+        };
+        let socket = match tungstenite::accept_hdr(connection, accept) {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::info!("stream session refused: {}", e);
+                return;
+            }
+        };
+        let Some((id, machine)) = machine else {
+            return;
+        };
+        tracing::info!(machine = id, "stream session");
+
+        let mut session = Session {
+            socket,
+            interval: STREAM_INTERVAL,
+            batch: 1,
+            paused: false,
+        };
+        let mut next = None;
+        loop {
+            let command = match next.take() {
+                Some(command) => command,
+                None => match session.receive(None) {
+                    Ok(Some(command)) => command,
+                    Ok(None) => continue,
+                    Err(()) => break,
+                },
+            };
+            let outcome = match command {
+                Command::Run {
+                    input,
+                    max_steps,
+                    paused,
+                } => {
+                    session.paused = paused;
+                    let config = self.run_config(&machine, max_steps);
+                    session.run(&machine, &input, &config)
+                }
+                command => session.control(command, 0),
+            };
+            match outcome {
+                Outcome::Done => {}
+                Outcome::Restart(command) => next = Some(command),
+                Outcome::Closed => break,
+            }
+        }
+    }
+}
+
+impl Session {
+    /// Stream a run of `machine`
+    fn run(&mut self, machine: &TuringMachine, input: &str, config: &RunConfig) -> Outcome {
+        let initial = match StreamStep::initial(machine, input) {
+            Ok(initial) => initial,
+            Err(error) => return self.send(&Event::Error { error: &error }),
+        };
+
+        let interrupt = AtomicBool::new(false);
+        let mut streamer = Streamer {
+            session: self,
+            machine,
+            interrupt: &interrupt,
+            pending: Vec::new(),
+            outcome: Outcome::Done,
+        };
+        streamer.add(initial);
+        let result = Executor::new(machine)
+            .observe(&mut streamer)
+            .interrupt_on(&interrupt)
+            .run_with(input, config);
+        match streamer.outcome {
+            Outcome::Done => match result {
+                Ok(result) => self.send(&Event::Result { result: &result }),
+                Err(error) => self.send(&Event::Error { error: &error }),
+            },
+            outcome => outcome,
+        }
+    }
+
+    /// Follow a command other than `run`, at `step` of the current run
+    fn control(&mut self, command: Command, step: usize) -> Outcome {
+        match command {
+            Command::Run { .. } => Outcome::Restart(command),
+            Command::Pause if !self.paused => {
+                self.paused = true;
+                self.send(&Event::Paused { step })
+            }
+            Command::Resume if self.paused => {
+                self.paused = false;
+                self.send(&Event::Resumed { step })
+            }
+            Command::Speed { interval, batch } => {
+                if let Some(interval) = interval {
+                    self.interval = Duration::from_millis(interval);
+                }
+                if let Some(batch) = batch {
+                    self.batch = batch.max(1);
+                }
+                Outcome::Done
+            }
+            Command::Pause | Command::Resume | Command::Step => Outcome::Done,
+        }
+    }
+
+    /// Wait up to `timeout`, or for as long as it takes if `None`, for the
+    /// next command; `Err` if the client is gone
+    fn receive(&mut self, timeout: Option<Duration>) -> Result<Option<Command>, ()> {
+        // A zero timeout would mean no timeout
+        let timeout = timeout.map(|timeout| timeout.max(Duration::from_millis(1)));
+        if self.socket.get_ref().set_read_timeout(timeout).is_err() {
+            return Err(());
+        }
+        match self.socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(command) => Ok(Some(command)),
+                Err(e) => {
+                    let error = format!("Invalid command: {}", e);
+                    match self.send(&Event::Error { error: &error }) {
+                        Outcome::Closed => Err(()),
+                        _ => Ok(None),
+                    }
+                }
+            },
+            Ok(Message::Close(_)) => Err(()),
+            Ok(_) => Ok(None),
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                Ok(None)
+            }
+            Err(_) => Err(()),
+        }
+    }
+
+    /// Send a message to the client
+    fn send(&mut self, event: &Event) -> Outcome {
+        let text = match serde_json::to_string(event) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("cannot encode a stream message: {}", e);
+                return Outcome::Done;
+            }
+        };
+        match self.socket.send(Message::text(text)) {
+            Ok(()) => Outcome::Done,
+            Err(_) => Outcome::Closed,
+        }
+    }
+}
+
+/// Observer sending the steps of a run to a stream session, pacing the run
+/// and following the commands received meanwhile
+struct Streamer<'a> {
+    session: &'a mut Session,
+    machine: &'a TuringMachine,
+    /// Set to end the run early
+    interrupt: &'a AtomicBool,
+    /// Steps not sent yet
+    pending: Vec<StreamStep>,
+    /// Set when the run ends early
+    outcome: Outcome,
+}
+
+impl Streamer<'_> {
+    /// Queue a step, and send the queue and wait once it is full
+    fn add(&mut self, step: StreamStep) {
+        let number = step.step;
+        self.pending.push(step);
+        if self.session.paused || self.pending.len() >= self.session.batch {
+            self.flush();
+            self.wait(number);
+        }
+    }
+
+    /// Send the queued steps
+    fn flush(&mut self) {
+        if self.pending.is_empty() || self.interrupt.load(Ordering::Relaxed) {
+            return;
+        }
+        let outcome = self.session.send(&Event::Steps {
+            steps: &self.pending,
+        });
+        self.pending.clear();
+        self.stop(outcome);
+    }
+
+    /// Follow commands until the next step is due: after the interval while
+    /// running, or on `step` or `resume` while paused
+    fn wait(&mut self, step: usize) {
+        let deadline = Instant::now() + self.session.interval;
+        while !self.interrupt.load(Ordering::Relaxed) {
+            let timeout = if self.session.paused {
+                None
+            } else {
+                match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) if !timeout.is_zero() => Some(timeout),
+                    _ => return,
+                }
+            };
+            match self.session.receive(timeout) {
+                Ok(None) => {}
+                Ok(Some(Command::Step)) if self.session.paused => return,
+                Ok(Some(command)) => {
+                    let outcome = self.session.control(command, step);
+                    self.stop(outcome);
+                }
+                Err(()) => self.stop(Outcome::Closed),
+            }
+        }
+    }
+
+    /// End the run early unless `outcome` is `Done`
+    fn stop(&mut self, outcome: Outcome) {
+        if !matches!(outcome, Outcome::Done) {
+            self.outcome = outcome;
+            self.interrupt.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl StepObserver for Streamer<'_> {
+    fn on_step(&mut self, event: &StepEvent) {
+        if self.interrupt.load(Ordering::Relaxed) {
+            return;
+        }
+        self.add(StreamStep::after(self.machine, event));
+    }
+
+    fn on_halt(&mut self, _result: &ExecutionResult) {
+        self.flush();
+    }
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read a request body, or the reply refusing it
+fn read_body(reader: impl Read) -> Result<String, Reply> {
+    let mut body = String::new();
+    match reader.take(MAX_BODY_BYTES + 1).read_to_string(&mut body) {
+        Err(e) => Err(Reply::error(
+            400,
+            &format!("Cannot read the request body: {}", e),
+        )),
+        Ok(_) if body.len() as u64 > MAX_BODY_BYTES => {
+            Err(Reply::error(413, "The request body is too large"))
+        }
+        Ok(_) => Ok(body),
+    }
+}
+
+fn not_found(path: &str) -> Reply {
+    Reply::error(404, &format!("No such resource: {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVEN_ONES: &str = include_str!("../examples/even_ones.json");
+
+    fn body(reply: &Reply) -> serde_json::Value {
+        serde_json::from_str(&reply.body).unwrap()
+    }
+
+    #[test]
+    fn streams_the_cells_of_named_symbols() {
+        let definition = r#"{
+            "states": ["q0", "acc"],
+            "alphabet": ["a"],
+            "tape_alphabet": ["a", "X1", "_"],
+            "initial_state": "q0",
+            "accept_states": ["acc"],
+            "reject_states": [],
+            "initial_tape": "X1",
+            "input_start": 1,
+            "transitions": {"q0,a": ["acc", "X1", "R"]}
+        }"#;
+        let machine = parse_machine_json(&parse_machine_definition(definition).unwrap()).unwrap();
+
+        let initial = StreamStep::initial(&machine, "a").unwrap();
+        assert_eq!(initial.tape, "X1a");
+        assert_eq!(initial.tape_cells, ["X1", "a"]);
+        assert_eq!(initial.head_position, 1);
+
+        struct Steps<'a>(&'a TuringMachine, Vec<StreamStep>);
+        impl StepObserver for Steps<'_> {
+            fn on_step(&mut self, event: &StepEvent) {
+                self.1.push(StreamStep::after(self.0, event));
+            }
+        }
+        let mut steps = Steps(&machine, Vec::new());
+        Executor::new(&machine)
+            .observe(&mut steps)
+            .run_with("a", &executor::RunConfig::default())
+            .unwrap();
+        let json = serde_json::to_value(&steps.1).unwrap();
+        assert_eq!(json[0]["tape_cells"], serde_json::json!(["X1", "X1"]));
+        assert_eq!(json[0]["head_position"], 2);
+    }
+
+    /// Counts up in binary forever, never repeating a configuration
+    const COUNTER: &str = r#"{
+        "states": ["right", "carry"],
+        "alphabet": ["0", "1"],
+        "tape_alphabet": ["0", "1", "_"],
+        "initial_state": "right",
+        "accept_states": [],
+        "reject_states": [],
+        "transitions": {
+            "right,0": ["right", "0", "R"],
+            "right,1": ["right", "1", "R"],
+            "right,_": ["carry", "_", "L"],
+            "carry,1": ["carry", "0", "L"],
+            "carry,0": ["right", "1", "R"],
+            "carry,_": ["right", "1", "R"]
+        }
+    }"#;
+
+    fn upload(server: &Server, definition: &str) -> u64 {
+        let uploaded = server.handle(&Method::Post, "/machines", definition);
+        assert_eq!(uploaded.status, 201);
+        body(&uploaded)["id"].as_u64().unwrap()
+    }
+
+    #[test]
+    fn traces_runs_on_request() {
+        let server = Server::new();
+        let id = upload(&server, EVEN_ONES);
+        let runs = format!("/machines/{}/runs", id);
+
+        let reply = server.handle(&Method::Post, &runs, r#"{"input": "0110", "trace": true}"#);
+        assert_eq!(reply.status, 201);
+        let report = body(&reply);
+        assert_eq!(report["result"]["accepts"], true);
+        let steps = report["result"]["steps"].as_u64().unwrap();
+        assert_eq!(report["trace"].as_array().unwrap().len() as u64, steps);
+
+        let run = report["id"].as_u64().unwrap();
+        let stored = server.handle(&Method::Get, &format!("/runs/{}", run), "");
+        assert_eq!(body(&stored), report);
+
+        let untraced = server.handle(&Method::Post, &runs, r#"{"input": "0110"}"#);
+        assert!(body(&untraced)
+            .get("trace")
+            .is_none_or(|trace| trace.is_null()));
+    }
+
+    #[test]
+    fn stops_traced_runs_at_the_memory_limit() {
+        let server = Server::new().max_steps(1_000_000).max_memory(1 << 20);
+        let id = upload(&server, COUNTER);
+        let reply = server.handle(
+            &Method::Post,
+            &format!("/machines/{}/runs", id),
+            r#"{"input": "", "trace": true}"#,
+        );
+        assert_eq!(reply.status, 201);
+        let report = body(&reply);
+        assert_eq!(report["result"]["memory_limit_exceeded"], true);
+        assert_eq!(report["result"]["resource_limit"], "memory");
+        let steps = report["result"]["steps"].as_u64().unwrap();
+        assert!(0 < steps && steps < 1_000_000, "{} steps", steps);
+        assert_eq!(report["trace"].as_array().unwrap().len() as u64, steps);
+    }
+
+    #[test]
+    fn refuses_invalid_and_oversized_run_requests() {
+        let server = Server::new();
+        let runs = format!("/machines/{}/runs", upload(&server, EVEN_ONES));
+        assert_eq!(server.handle(&Method::Post, &runs, "{").status, 400);
+        assert_eq!(
+            server
+                .handle(&Method::Post, &runs, r#"{"input": "012"}"#)
+                .status,
+            400
+        );
+        assert_eq!(
+            server
+                .handle(&Method::Post, "/machines/99/runs", r#"{"input": ""}"#)
+                .status,
+            404
+        );
+
+        let large = std::io::repeat(b' ').take(MAX_BODY_BYTES + 1);
+        assert_eq!(read_body(large).unwrap_err().status, 413);
+        let fits = std::io::repeat(b' ').take(MAX_BODY_BYTES);
+        assert!(read_body(fits).is_ok_and(|body| body.len() as u64 == MAX_BODY_BYTES));
+    }
+
+    #[test]
+    fn answers_the_info_of_an_uploaded_machine() {
+        let server = Server::new();
+        let uploaded = server.handle(&Method::Post, "/machines", EVEN_ONES);
+        assert_eq!(uploaded.status, 201);
+        let id = body(&uploaded)["id"].as_u64().unwrap();
+
+        let reply = server.handle(&Method::Get, &format!("/machines/{}/info", id), "");
+        assert_eq!(reply.status, 200);
+        let info = body(&reply);
+        let machine = parse_machine_json(&parse_machine_definition(EVEN_ONES).unwrap()).unwrap();
+        let expected = MachineInfo::from(&machine);
+        assert_eq!(info["states"], serde_json::json!(expected.states));
+        assert_eq!(info["alphabet"], serde_json::json!(expected.alphabet));
+        assert_eq!(info["transition_count"], expected.transition_count);
+        assert_eq!(
+            info["analysis"],
+            serde_json::to_value(analysis::analyze(&machine)).unwrap()
+        );
+    }
+
+    #[test]
+    fn refuses_the_info_of_unknown_machines() {
+        let server = Server::new();
+        assert_eq!(
+            server.handle(&Method::Get, "/machines/7/info", "").status,
+            404
+        );
+        assert_eq!(
+            server.handle(&Method::Get, "/machines/x/info", "").status,
+            404
+        );
+        assert_eq!(
+            server.handle(&Method::Post, "/machines/7/info", "").status,
+            405
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Heuristic warnings about finished runs
+//!
+//! Some runs are legal but rarely what the author meant: a machine that
+//! halts before its first step, never moves left, never changes a cell, or
+//! accepts before its head has been over the whole input usually has a
+//! wrong state, direction or write symbol somewhere. [`RunWatcher`] follows a
+//! run as a [`StepObserver`] and [`RunWatcher::warnings`] turns what it saw
+//! into warnings, each with a hint at the usual cause.
+//!
+//! The heuristics can fire on correct machines too; a machine recognizing a
+//! regular language may well read its input once from left to right without
+//! writing anything.
+
+use crate::executor::{StepEvent, StepObserver};
+use crate::{Direction, ExecutionResult, TuringMachine};
+
+/// Something suspicious about a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// What the run did, e.g. "never moved left"
+    pub message: String,
+    /// The usual cause, in one line
+    pub hint: &'static str,
+}
+
+/// Observer recording what a run did to the tape and head
+pub struct RunWatcher {
+    /// Number of input symbols
+    input_len: usize,
+    /// Head position relative to the first input cell, followed from the
+    /// moves since tape indices shift when the tape grows to the left
+    head: i64,
+    /// Leftmost and rightmost positions a transition has read, an empty
+    /// range before the first step
+    low: i64,
+    high: i64,
+    moved_left: bool,
+    wrote: bool,
+}
+
+impl RunWatcher {
+    /// Watch a run of `machine` on `input_string`
+    pub fn new(machine: &TuringMachine, input_string: &str) -> Self {
+        let setup = machine.tape_setup();
+        let head = setup.head() as i64 - setup.input_start as i64;
+        RunWatcher {
+            input_len: machine
+                .map_input(input_string)
+                .map_or(0, |input| input.len()),
+            head,
+            low: i64::MAX,
+            high: i64::MIN,
+            moved_left: false,
+            wrote: false,
+        }
+    }
+
+    /// Warnings about the run that ended with `result`
+    ///
+    /// Interrupted runs get none, since they were stopped from outside.
+    pub fn warnings(&self, result: &ExecutionResult) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        if result.interrupted {
+            return warnings;
+        }
+        if result.halted && result.steps == 0 {
+            warnings.push(Warning {
+                message: "halted without taking a single step".to_string(),
+                hint: "the initial state is an accept or reject state, or has no transition for the symbol under the head",
+            });
+            return warnings;
+        }
+        if !self.moved_left {
+            warnings.push(Warning {
+                message: "never moved left".to_string(),
+                hint: "a head that only moves right cannot go back to what it has read; check the directions of the transitions",
+            });
+        }
+        if !self.wrote {
+            warnings.push(Warning {
+                message: "never wrote anything".to_string(),
+                hint:
+                    "every transition taken wrote back the symbol it read; check the write symbols",
+            });
+        }
+        let read = self.input_read();
+        if result.accepts == Some(true) && read < self.input_len {
+            warnings.push(Warning {
+                message: format!(
+                    "accepted after reading {} of {} input symbols",
+                    read, self.input_len
+                ),
+                hint: "an accept state is entered too early; check the transitions into accept states",
+            });
+        }
+        warnings
+    }
+
+    /// Number of input cells a transition has read
+    fn input_read(&self) -> usize {
+        let low = self.low.max(0);
+        let high = self.high.min(self.input_len as i64 - 1);
+        if low > high {
+            0
+        } else {
+            (high - low + 1) as usize
+        }
+    }
+}
+
+impl StepObserver for RunWatcher {
+    fn on_step(&mut self, event: &StepEvent) {
+        self.low = self.low.min(self.head);
+        self.high = self.high.max(self.head);
+        match event.direction {
+            Direction::L => {
+                self.head -= 1;
+                self.moved_left = true;
+            }
+            Direction::R => self.head += 1,
+            Direction::S => {}
+        }
+    }
+
+    fn on_tape_write(&mut self, _position: usize, old_symbol: char, new_symbol: char) {
+        self.wrote |= old_symbol != new_symbol;
+    }
+}
@@ -0,0 +1,219 @@
+//! Tape symbols with names longer than one character
+//!
+//! Machines can name symbols like `"blank"`, `"X1"` or `"#end"`. Each named
+//! symbol is interned as a character from Unicode's supplementary private use
+//! area, so the executor, tapes and transition tables keep working on `char`.
+//! The [`SymbolTable`] of a machine translates between the two at its edges:
+//! when definitions and inputs are read, and when tapes are shown.
+//!
+//! Characters of that area are reserved for named symbols: they cannot be
+//! symbols of their own, and inputs containing them are rejected, so a
+//! character read can never be mistaken for a named symbol.
+
+use std::collections::HashMap;
+
+/// First character handed out for a named symbol
+const FIRST_HANDLE: u32 = 0xF_0000;
+
+/// Last character handed out for a named symbol
+const LAST_HANDLE: u32 = 0xF_FFFD;
+
+/// The named symbols of a machine and the characters standing for them
+///
+/// Single-character symbols stand for themselves and are not stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    names: HashMap<char, String>,
+    handles: HashMap<String, char>,
+}
+
+impl SymbolTable {
+    /// An empty table, for machines using only single-character symbols
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `symbol` is in the range of characters reserved for named
+    /// symbols
+    pub fn is_reserved(symbol: char) -> bool {
+        (FIRST_HANDLE..=LAST_HANDLE).contains(&(symbol as u32))
+    }
+
+    /// The character standing for `name`, allocating one for a new name
+    pub fn intern(&mut self, name: &str) -> Result<char, String> {
+        reject_reserved(name)?;
+        if let Some(symbol) = self.get(name) {
+            return Ok(symbol);
+        }
+        if name.is_empty() {
+            return Err("Symbols must not be empty".to_string());
+        }
+        if name.contains(',') || name.chars().any(char::is_whitespace) {
+            return Err(format!(
+                "Symbol '{}' must not contain commas or whitespace",
+                name
+            ));
+        }
+        let symbol = char::from_u32(FIRST_HANDLE + self.names.len() as u32)
+            .filter(|symbol| *symbol as u32 <= LAST_HANDLE)
+            .ok_or("Too many named symbols")?;
+        self.names.insert(symbol, name.to_string());
+        self.handles.insert(name.to_string(), symbol);
+        Ok(symbol)
+    }
+
+    /// The character standing for `name`, if it is a single character or
+    /// has been interned
+    pub fn get(&self, name: &str) -> Option<char> {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(symbol), None) => Some(symbol),
+            _ => self.handles.get(name).copied(),
+        }
+    }
+
+    /// The name of a symbol
+    pub fn name(&self, symbol: char) -> String {
+        match self.names.get(&symbol) {
+            Some(name) => name.clone(),
+            None => symbol.to_string(),
+        }
+    }
+
+    /// Whether the table holds no named symbols
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Symbols written out by name, one after the other
+    pub fn render(&self, symbols: &[char]) -> String {
+        symbols.iter().map(|symbol| self.name(*symbol)).collect()
+    }
+
+    /// The name of every symbol, one per symbol
+    pub fn names(&self, symbols: &[char]) -> Vec<String> {
+        symbols.iter().map(|symbol| self.name(*symbol)).collect()
+    }
+
+    /// Split text into symbols, preferring the longest named symbol at each
+    /// position and taking any other character as a symbol of its own
+    ///
+    /// Text containing a character reserved for named symbols is rejected.
+    pub fn tokenize(&self, text: &str) -> Result<Vec<char>, String> {
+        reject_reserved(text)?;
+        if self.names.is_empty() {
+            return Ok(text.chars().collect());
+        }
+        let mut symbols = Vec::new();
+        let mut rest = text;
+        while let Some(first) = rest.chars().next() {
+            let named = self
+                .handles
+                .iter()
+                .filter(|(name, _)| rest.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len());
+            match named {
+                Some((name, symbol)) => {
+                    symbols.push(*symbol);
+                    rest = &rest[name.len()..];
+                }
+                None => {
+                    symbols.push(first);
+                    rest = &rest[first.len_utf8()..];
+                }
+            }
+        }
+        Ok(symbols)
+    }
+}
+
+/// Fail if `text` contains a character reserved for named symbols
+fn reject_reserved(text: &str) -> Result<(), String> {
+    match text.chars().find(|c| SymbolTable::is_reserved(*c)) {
+        Some(symbol) => Err(format!(
+            "U+{:04X} is reserved for named symbols",
+            symbol as u32
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_machine_definition, parse_machine_json};
+
+    /// Marks the cell after a run of `a`s and `ab`s with `end`
+    const NAMED: &str = r#"{
+        "states": ["scan", "done"],
+        "alphabet": ["a", "ab"],
+        "tape_alphabet": ["a", "ab", "_", "end"],
+        "initial_state": "scan",
+        "accept_states": ["done"],
+        "reject_states": [],
+        "transitions": {
+            "scan,a": ["scan", "a", "R"],
+            "scan,ab": ["scan", "ab", "R"],
+            "scan,_": ["done", "end", "S"]
+        }
+    }"#;
+
+    #[test]
+    fn interns_each_name_once() {
+        let mut symbols = SymbolTable::new();
+        let end = symbols.intern("end").unwrap();
+        let blank = symbols.intern("blank").unwrap();
+        assert_ne!(end, blank);
+        assert_eq!(symbols.intern("end").unwrap(), end);
+        assert_eq!(symbols.intern("e").unwrap(), 'e');
+        for handle in [end, blank] {
+            assert!(SymbolTable::is_reserved(handle));
+        }
+        assert_eq!(symbols.name(end), "end");
+        assert_eq!(symbols.names(&[end, 'e', blank]), ["end", "e", "blank"]);
+    }
+
+    #[test]
+    fn rejects_reserved_characters() {
+        let mut symbols = SymbolTable::new();
+        let end = symbols.intern("end").unwrap();
+        assert!(symbols.intern(&end.to_string()).is_err());
+        assert!(symbols.intern("\u{F_FFFD}").is_err());
+        assert!(symbols.intern("\u{E000}").is_ok());
+        assert!(symbols.tokenize(&format!("a{}", end)).is_err());
+    }
+
+    #[test]
+    fn tokenizes_the_longest_name() {
+        let mut symbols = SymbolTable::new();
+        let ab = symbols.intern("ab").unwrap();
+        let abc = symbols.intern("abc").unwrap();
+        assert_eq!(symbols.tokenize("abcab a").unwrap(), [abc, ab, ' ', 'a']);
+    }
+
+    #[test]
+    fn does_not_read_a_handle_as_the_symbol_it_stands_for() {
+        let definition = parse_machine_definition(NAMED).unwrap();
+        let machine = parse_machine_json(&definition).unwrap();
+        let ab = machine.symbols().get("ab").unwrap();
+        assert!(machine.execute(&format!("a{}", ab), 10).is_err());
+
+        let reserved = NAMED.replace("\"end\"", "\"\u{F0000}\"");
+        let definition = parse_machine_definition(&reserved).unwrap();
+        assert!(parse_machine_json(&definition).is_err());
+    }
+
+    #[test]
+    fn names_every_cell_of_a_result() {
+        let definition = parse_machine_definition(NAMED).unwrap();
+        let machine = parse_machine_json(&definition).unwrap();
+        let result = machine.execute("aaba", 10).unwrap();
+        assert_eq!(result.tape, "aabaend");
+        assert_eq!(result.tape_cells, ["a", "ab", "a", "end"]);
+        assert_eq!(result.tape_cells[result.head_position as usize], "end");
+
+        let snapshots = machine.execute_step_by_step("aaba", 10).unwrap();
+        let last = snapshots.last().unwrap();
+        assert_eq!(last.tape_names(), result.tape_cells);
+    }
+}
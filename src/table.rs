@@ -0,0 +1,111 @@
+//! Transition tables for documents
+//!
+//! The transition function as a grid, with a row per state and a column
+//! per tape symbol, as assignment write-ups usually show it. Each cell is
+//! `new_state, write, move`, as in spreadsheets, and is empty where the
+//! machine has no transition. [`markdown`] marks the initial state with
+//! `→`, accept states with `✓` and reject states with `✗`; [`csv`] leaves
+//! the states unmarked for spreadsheets and scripts.
+
+use crate::diagram::ordered_states;
+use crate::TuringMachine;
+use std::fmt::Write;
+
+/// The header and rows of the table, unescaped
+fn grid(machine: &TuringMachine) -> (Vec<String>, Vec<(&str, Vec<String>)>) {
+    // Tape symbols in order, with the blank last
+    let mut symbols: Vec<char> = machine
+        .tape_alphabet
+        .iter()
+        .copied()
+        .filter(|symbol| *symbol != machine.blank_symbol)
+        .collect();
+    symbols.sort_unstable();
+    symbols.push(machine.blank_symbol);
+
+    let mut header = vec!["state".to_string()];
+    header.extend(symbols.iter().map(|symbol| machine.symbols.name(*symbol)));
+    let rows = ordered_states(machine)
+        .into_iter()
+        .map(|state| {
+            (
+                state,
+                symbols
+                    .iter()
+                    .map(|symbol| cell(machine, state, *symbol))
+                    .collect(),
+            )
+        })
+        .collect();
+    (header, rows)
+}
+
+/// The cell of a state and symbol read, empty without a transition
+fn cell(machine: &TuringMachine, state: &str, symbol: char) -> String {
+    match machine.transitions.get(&(state.to_string(), symbol)) {
+        Some((next, write, direction)) => {
+            format!(
+                "{}, {}, {:?}",
+                next,
+                machine.symbols.name(*write),
+                direction
+            )
+        }
+        None => String::new(),
+    }
+}
+
+/// The transition table as a Markdown table
+pub fn markdown(machine: &TuringMachine) -> String {
+    let (header, rows) = grid(machine);
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('|', "\\|");
+
+    let mut text = String::new();
+    if let Some(name) = &machine.metadata.name {
+        let _ = writeln!(text, "**{}**\n", escape(name));
+    }
+    let header: Vec<String> = header.iter().map(|cell| escape(cell)).collect();
+    let _ = writeln!(text, "| {} |", header.join(" | "));
+    let _ = writeln!(text, "|{}", "---|".repeat(header.len()));
+    for (state, cells) in rows {
+        let mut label = escape(state);
+        if state == machine.initial_state {
+            label.insert_str(0, "→ ");
+        }
+        if machine.accept_states.contains(state) {
+            label.push_str(" ✓");
+        } else if machine.reject_states.contains(state) {
+            label.push_str(" ✗");
+        }
+        let cells: Vec<String> = cells.iter().map(|cell| escape(cell)).collect();
+        let _ = writeln!(text, "| {} | {} |", label, cells.join(" | "));
+    }
+    text
+}
+
+/// The transition table as CSV, with a header row of the tape symbols
+pub fn csv(machine: &TuringMachine) -> String {
+    let (header, rows) = grid(machine);
+    let mut text = String::new();
+    let _ = writeln!(text, "{}", csv_row(&header));
+    for (state, mut cells) in rows {
+        cells.insert(0, state.to_string());
+        let _ = writeln!(text, "{}", csv_row(&cells));
+    }
+    text
+}
+
+/// A CSV line, quoting the fields that need it
+fn csv_row(fields: &[String]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    fields.join(",")
+}
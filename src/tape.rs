@@ -0,0 +1,83 @@
+//! Tapes that grow at both ends
+//!
+//! A head that keeps moving left must not make every step shift the whole
+//! tape right, as inserting at the front of a `Vec` does; a machine
+//! sweeping left would run in quadratic time. [`Tape`] keeps its cells in
+//! a buffer with spare room in front, so growing in either direction takes
+//! amortized constant time, while the cells stay one contiguous slice for
+//! observers and snapshots.
+
+/// Least spare room made in front of a tape growing left
+const MIN_ROOM: usize = 16;
+
+/// Tape cells in a buffer with room to grow at both ends
+///
+/// Cells are indexed from the leftmost one, so growing at the front moves
+/// every index one to the right, as inserting at the front of a `Vec`
+/// would. When the room in front runs out it is doubled, as a `Vec` doubles
+/// at the back.
+#[derive(Debug, Clone)]
+pub(crate) struct Tape {
+    buffer: Vec<char>,
+    /// Index in `buffer` of the leftmost cell
+    start: usize,
+}
+
+impl Tape {
+    pub(crate) fn new(cells: Vec<char>) -> Self {
+        Tape {
+            buffer: cells,
+            start: 0,
+        }
+    }
+
+    pub(crate) fn cells(&self) -> &[char] {
+        &self.buffer[self.start..]
+    }
+
+    pub(crate) fn cells_mut(&mut self) -> &mut [char] {
+        &mut self.buffer[self.start..]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Add a cell holding `blank` before the leftmost cell
+    pub(crate) fn push_front(&mut self, blank: char) {
+        if self.start == 0 {
+            let room = self.buffer.len().max(MIN_ROOM);
+            let mut buffer = Vec::with_capacity(room + self.buffer.capacity());
+            buffer.resize(room, blank);
+            buffer.extend_from_slice(&self.buffer);
+            self.buffer = buffer;
+            self.start = room;
+        }
+        self.start -= 1;
+        self.buffer[self.start] = blank;
+    }
+
+    /// Add a cell holding `blank` after the rightmost cell
+    pub(crate) fn push_back(&mut self, blank: char) {
+        self.buffer.push(blank);
+    }
+
+    /// Remove the leftmost cell, keeping its room for the tape to grow back
+    pub(crate) fn pop_front(&mut self) {
+        if self.start < self.buffer.len() {
+            self.start += 1;
+        }
+    }
+
+    /// Remove the rightmost cell
+    pub(crate) fn pop_back(&mut self) {
+        if self.buffer.len() > self.start {
+            self.buffer.pop();
+        }
+    }
+
+    /// Cells allocated, spare room included
+    pub(crate) fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
@@ -0,0 +1,267 @@
+//! Head trajectories of several runs, drawn on one chart
+//!
+//! A trajectory is the head position after every step of a run, counted in
+//! cells from the first input cell. Running a machine on inputs of growing
+//! length and overlaying the trajectories shows how its scanning pattern
+//! scales: a machine sweeping back and forth over the input draws zigzags
+//! that widen and lengthen with the input.
+//!
+//! [`ascii_chart`] draws the overlay as text, marking each run with its own
+//! character; [`svg_chart`] draws it as an SVG image with one colored line per
+//! run.
+
+use crate::executor::{Executor, StepEvent, StepObserver};
+use crate::{Direction, ExecutionResult, TuringMachine};
+use std::fmt::Write;
+
+/// Characters marking the runs of an ASCII chart, in order
+const MARKERS: &[u8] = b"123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Line colors of the runs of an SVG chart, in order
+const COLORS: [&str; 8] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+/// The head positions of one run
+#[derive(Debug)]
+pub struct Trajectory {
+    pub input: String,
+    /// Head position before the first step and after every step, relative to
+    /// the first input cell
+    pub positions: Vec<i64>,
+    pub result: ExecutionResult,
+}
+
+impl Trajectory {
+    /// Run `machine` on `input_string` and record where its head goes
+    pub fn record(
+        machine: &TuringMachine,
+        input_string: &str,
+        max_steps: usize,
+    ) -> Result<Trajectory, String> {
+        let setup = machine.tape_setup();
+        let start = setup.head() as i64 - setup.input_start as i64;
+        let mut path = HeadPath {
+            positions: vec![start],
+        };
+        let result = Executor::new(machine)
+            .observe(&mut path)
+            .run(input_string, max_steps)?;
+        Ok(Trajectory {
+            input: input_string.to_string(),
+            positions: path.positions,
+            result,
+        })
+    }
+
+    /// Legend entry: the input, the verdict and the number of steps
+    fn label(&self) -> String {
+        let verdict = match self.result.accepts {
+            Some(true) => "accepts",
+            Some(false) => "rejects",
+            None => "no verdict",
+        };
+        format!(
+            "'{}' ({}, {} steps)",
+            self.input, verdict, self.result.steps
+        )
+    }
+}
+
+/// Observer following the head from the moves of the transitions, since
+/// tape indices shift when the tape grows to the left
+struct HeadPath {
+    positions: Vec<i64>,
+}
+
+impl StepObserver for HeadPath {
+    fn on_step(&mut self, event: &StepEvent) {
+        let last = self.positions.last().copied().unwrap_or(0);
+        self.positions.push(match event.direction {
+            Direction::L => last - 1,
+            Direction::R => last + 1,
+            Direction::S => last,
+        });
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.positions.capacity() * std::mem::size_of::<i64>()
+    }
+}
+
+/// Longest run and the range of head positions over all trajectories
+fn bounds(trajectories: &[Trajectory]) -> (usize, i64, i64) {
+    let max_step = trajectories
+        .iter()
+        .map(|t| t.positions.len().saturating_sub(1))
+        .max()
+        .unwrap_or(0);
+    let positions = trajectories.iter().flat_map(|t| &t.positions);
+    let low = positions.clone().copied().min().unwrap_or(0).min(0);
+    let high = positions.copied().max().unwrap_or(0).max(0);
+    (max_step, low, high)
+}
+
+/// The trajectories as a text chart at most `width` columns by `height`
+/// rows, step to the right and head position upwards
+///
+/// Each run is drawn with its own marker, listed in the legend below the
+/// chart; where runs meet, the later one is shown. Longer runs and wider
+/// ranges are scaled down to fit.
+pub fn ascii_chart(trajectories: &[Trajectory], width: usize, height: usize) -> String {
+    let (max_step, low, high) = bounds(trajectories);
+    let columns = (max_step + 1).min(width.max(2));
+    let rows = ((high - low) as usize + 1).min(height.max(2));
+    let column = |step: usize| step * (columns - 1) / max_step.max(1);
+    let row = |position: i64| {
+        let offset = (high - position) as usize;
+        offset * (rows - 1) / ((high - low) as usize).max(1)
+    };
+
+    let mut grid = vec![vec![' '; columns]; rows];
+    for (i, trajectory) in trajectories.iter().enumerate() {
+        let marker = MARKERS[i % MARKERS.len()] as char;
+        for (step, position) in trajectory.positions.iter().enumerate() {
+            grid[row(*position)][column(step)] = marker;
+        }
+    }
+
+    let label_width = high.to_string().len().max(low.to_string().len());
+    let mut text = String::from("Head position by step\n");
+    for (i, cells) in grid.iter().enumerate() {
+        let label = if i == 0 {
+            high.to_string()
+        } else if i == rows - 1 {
+            low.to_string()
+        } else if i == row(0) {
+            "0".to_string()
+        } else {
+            String::new()
+        };
+        let line: String = cells.iter().collect();
+        let _ = writeln!(text, "{:>label_width$} │{}", label, line.trim_end());
+    }
+    let _ = writeln!(text, "{:>label_width$} └{}", "", "─".repeat(columns));
+    let _ = writeln!(
+        text,
+        "{:>label_width$}  0{:>width$}",
+        "",
+        max_step,
+        width = columns.saturating_sub(1)
+    );
+    for (i, trajectory) in trajectories.iter().enumerate() {
+        let marker = MARKERS[i % MARKERS.len()] as char;
+        let _ = writeln!(text, "  {}: {}", marker, trajectory.label());
+    }
+    text
+}
+
+/// The trajectories as an SVG image, one colored line per run
+pub fn svg_chart(trajectories: &[Trajectory]) -> String {
+    const WIDTH: f64 = 720.0;
+    const HEIGHT: f64 = 420.0;
+    const MARGIN: f64 = 50.0;
+    let legend_height = 18.0 * trajectories.len() as f64;
+    let plot_width = WIDTH - 2.0 * MARGIN;
+    let plot_height = HEIGHT - 2.0 * MARGIN;
+
+    let (max_step, low, high) = bounds(trajectories);
+    let x = |step: usize| MARGIN + step as f64 * plot_width / max_step.max(1) as f64;
+    let y = |position: i64| {
+        MARGIN + (high - position) as f64 * plot_height / (high - low).max(1) as f64
+    };
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = WIDTH,
+        h = HEIGHT + legend_height
+    );
+    let _ = writeln!(svg, r#"  <rect width="100%" height="100%" fill="white"/>"#);
+    let _ = writeln!(
+        svg,
+        r#"  <text x="{}" y="{}" text-anchor="middle" font-size="14">Head position by step</text>"#,
+        WIDTH / 2.0,
+        MARGIN / 2.0
+    );
+
+    // Axes, the zero line and their labels
+    let _ = writeln!(
+        svg,
+        r#"  <path d="M {l} {t} V {b} H {r}" fill="none" stroke="black"/>"#,
+        l = MARGIN,
+        t = MARGIN,
+        b = MARGIN + plot_height,
+        r = MARGIN + plot_width
+    );
+    if low < 0 {
+        let _ = writeln!(
+            svg,
+            r##"  <line x1="{}" y1="{y}" x2="{}" y2="{y}" stroke="#ccc" stroke-dasharray="4 4"/>"##,
+            MARGIN,
+            MARGIN + plot_width,
+            y = y(0)
+        );
+    }
+    let mut ticks = vec![low, 0, high];
+    ticks.dedup();
+    for position in ticks {
+        let _ = writeln!(
+            svg,
+            r#"  <text x="{}" y="{}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+            MARGIN - 6.0,
+            y(position),
+            position
+        );
+    }
+    for step in [0, max_step] {
+        let _ = writeln!(
+            svg,
+            r#"  <text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            x(step),
+            MARGIN + plot_height + 16.0,
+            step
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"  <text x="{}" y="{}" text-anchor="middle">step</text>"#,
+        WIDTH / 2.0,
+        MARGIN + plot_height + 32.0
+    );
+
+    // One line and legend entry per run
+    for (i, trajectory) in trajectories.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let points: Vec<String> = trajectory
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(step, position)| format!("{:.1},{:.1}", x(step), y(*position)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+            points.join(" "),
+            color
+        );
+        let _ = writeln!(
+            svg,
+            r#"  <text x="{}" y="{}" fill="{}">{}</text>"#,
+            MARGIN,
+            HEIGHT + 18.0 * i as f64,
+            color,
+            escape(&trajectory.label())
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Escape text for use in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
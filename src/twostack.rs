@@ -0,0 +1,420 @@
+//! Two-stack machines
+//!
+//! A two-stack machine has a finite control and two stacks, and is as
+//! powerful as a Turing machine. The input is pushed onto the right stack
+//! before the run, first symbol on top. A transition is keyed by the state
+//! and the symbols popped from the left and the right stack, and gives the
+//! new state and the symbols pushed onto each, top first. An empty pop
+//! symbol (or `ε`) pops nothing:
+//!
+//! ```json
+//! "transitions": {
+//!     "q0,,0": ["q0", "0", ""],
+//!     "q0,0,_": ["q1", "", "0_"]
+//! }
+//! ```
+//!
+//! As in a pushdown automaton, at most one transition may apply in any
+//! configuration. The machine halts in an accept or reject state, or
+//! rejects when no transition applies.
+//!
+//! [`from_turing_machine`] compiles a single-tape Turing machine into an
+//! equivalent two-stack machine: the left stack holds the cells left of the
+//! head, nearest on top, and the right stack the cell under the head and
+//! those right of it. Each stack has [`BOTTOM`] at its bottom, so an empty
+//! side of the tape reads as blank.
+
+use crate::pda::{check_deterministic, single, PdaKey};
+use crate::{Direction, TuringMachine};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Bottom marker of the stacks of a machine made by [`from_turing_machine`]
+pub const BOTTOM: char = '⊥';
+
+/// Two-stack machine file layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoStackJson {
+    pub states: Vec<String>,
+    pub alphabet: Vec<String>,
+    pub stack_alphabet: Vec<String>,
+    pub initial_state: String,
+    /// Left stack at the start, top first
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub initial_left: String,
+    /// Right stack at the start, top first, below the input
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub initial_right: String,
+    pub accept_states: Vec<String>,
+    #[serde(default)]
+    pub reject_states: Vec<String>,
+    /// `"state,left,right"` to `[new_state, push_left, push_right]`
+    pub transitions: BTreeMap<String, Vec<String>>,
+}
+
+/// What a transition does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwoStackTarget {
+    pub new_state: String,
+    /// Symbols pushed onto the left stack, top first
+    pub push_left: Vec<char>,
+    /// Symbols pushed onto the right stack, top first
+    pub push_right: Vec<char>,
+}
+
+/// A two-stack machine
+#[derive(Debug)]
+pub struct TwoStackMachine {
+    states: HashSet<String>,
+    alphabet: HashSet<char>,
+    /// Keyed by the state and the symbols popped from the left and the right
+    /// stack
+    transitions: HashMap<PdaKey, TwoStackTarget>,
+    initial_state: String,
+    /// Stacks at the start, top first
+    initial_left: Vec<char>,
+    initial_right: Vec<char>,
+    accept_states: HashSet<String>,
+    reject_states: HashSet<String>,
+}
+
+impl TwoStackMachine {
+    /// Load a two-stack machine file (JSON, read as JSON5)
+    pub fn load(path: &Path) -> Result<TwoStackMachine, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("File error: {}", e))?;
+        let json: TwoStackJson =
+            json5::from_str(&text).map_err(|e| format!("Invalid two-stack machine file: {}", e))?;
+        TwoStackMachine::from_json(json)
+    }
+
+    /// Check and build a two-stack machine definition
+    pub fn from_json(json: TwoStackJson) -> Result<TwoStackMachine, String> {
+        let alphabet = json
+            .alphabet
+            .iter()
+            .map(|entry| single(entry, "Alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let stack_alphabet = json
+            .stack_alphabet
+            .iter()
+            .map(|entry| single(entry, "Stack alphabet"))
+            .collect::<Result<HashSet<char>, String>>()?;
+        let states: HashSet<String> = json.states.iter().cloned().collect();
+        let accept_states: HashSet<String> = json.accept_states.iter().cloned().collect();
+        let reject_states: HashSet<String> = json.reject_states.iter().cloned().collect();
+
+        if !states.contains(&json.initial_state) {
+            return Err(format!(
+                "Initial state {} not in states",
+                json.initial_state
+            ));
+        }
+        if !accept_states.is_subset(&states) {
+            return Err("Accept states must be subset of states".to_string());
+        }
+        if !reject_states.is_subset(&states) {
+            return Err("Reject states must be subset of states".to_string());
+        }
+        if !accept_states.is_disjoint(&reject_states) {
+            return Err("Accept and reject states must be disjoint".to_string());
+        }
+        if !alphabet.is_subset(&stack_alphabet) {
+            return Err("Alphabet must be subset of stack alphabet".to_string());
+        }
+        let initial_left: Vec<char> = json.initial_left.chars().collect();
+        let initial_right: Vec<char> = json.initial_right.chars().collect();
+        let initial = initial_left.iter().chain(&initial_right);
+        if let Some(symbol) = initial.clone().find(|s| !stack_alphabet.contains(s)) {
+            return Err(format!(
+                "Initial stack symbol {} not in stack alphabet",
+                symbol
+            ));
+        }
+
+        let mut transitions = HashMap::new();
+        let mut keys = Vec::new();
+        for (key, value) in &json.transitions {
+            let (from, to) = parse_transition(key, value)?;
+            for state in [&from.0, &to.new_state] {
+                if !states.contains(state) {
+                    return Err(format!("Transition {}: state {} not in states", key, state));
+                }
+            }
+            let popped = from.1.iter().chain(&from.2);
+            let pushed = to.push_left.iter().chain(&to.push_right);
+            if let Some(symbol) = popped.chain(pushed).find(|s| !stack_alphabet.contains(s)) {
+                return Err(format!(
+                    "Transition {}: symbol {} not in stack alphabet",
+                    key, symbol
+                ));
+            }
+            keys.push((key, from.clone()));
+            transitions.insert(from, to);
+        }
+        check_deterministic(&keys, "a two-stack machine")?;
+
+        Ok(TwoStackMachine {
+            states,
+            alphabet,
+            transitions,
+            initial_state: json.initial_state,
+            initial_left,
+            initial_right,
+            accept_states,
+            reject_states,
+        })
+    }
+
+    /// All state names
+    pub fn states(&self) -> &HashSet<String> {
+        &self.states
+    }
+
+    /// Transitions, keyed by state and the symbols popped from the left and
+    /// the right stack
+    pub fn transitions(&self) -> &HashMap<PdaKey, TwoStackTarget> {
+        &self.transitions
+    }
+
+    /// Set up a run on the given input, before its first step
+    pub fn start(&self, input_string: &str) -> Result<TwoStackRun<'_>, String> {
+        let input: Vec<char> = input_string.chars().collect();
+        if let Some(symbol) = input.iter().find(|s| !self.alphabet.contains(s)) {
+            return Err(format!("Invalid input symbol: {}", symbol));
+        }
+        let right = input.iter().chain(&self.initial_right).rev().copied();
+        Ok(TwoStackRun {
+            machine: self,
+            left: self.initial_left.iter().rev().copied().collect(),
+            right: right.collect(),
+            state: self.initial_state.clone(),
+            steps: 0,
+        })
+    }
+}
+
+/// A run of a two-stack machine, advanced one step at a time
+#[derive(Debug, Clone)]
+pub struct TwoStackRun<'a> {
+    machine: &'a TwoStackMachine,
+    /// Stacks, bottom first
+    left: Vec<char>,
+    right: Vec<char>,
+    state: String,
+    steps: usize,
+}
+
+impl TwoStackRun<'_> {
+    /// The transition that applies in the current configuration
+    fn applicable(&self) -> Option<(&PdaKey, &TwoStackTarget)> {
+        let mut lefts = vec![None];
+        lefts.extend(self.left.last().map(|&s| Some(s)));
+        let mut rights = vec![None];
+        rights.extend(self.right.last().map(|&s| Some(s)));
+        lefts
+            .iter()
+            .flat_map(|left| rights.iter().map(move |right| (*left, *right)))
+            .find_map(|(left, right)| {
+                self.machine
+                    .transitions
+                    .get_key_value(&(self.state.clone(), left, right))
+            })
+    }
+
+    /// The verdict, once the machine has halted: accepting in an accept
+    /// state, rejecting in a reject state or when no transition applies
+    pub fn verdict(&self) -> Option<bool> {
+        let machine = self.machine;
+        if machine.accept_states.contains(&self.state) {
+            Some(true)
+        } else if machine.reject_states.contains(&self.state) || self.applicable().is_none() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Take one transition; returns `false` without changing anything if the
+    /// machine has halted
+    pub fn step(&mut self) -> bool {
+        if self.verdict().is_some() {
+            return false;
+        }
+        let Some(((_, left, right), target)) = self.applicable() else {
+            return false;
+        };
+        let (left, right, target) = (left.is_some(), right.is_some(), target.clone());
+        if left {
+            self.left.pop();
+        }
+        if right {
+            self.right.pop();
+        }
+        self.left.extend(target.push_left.iter().rev());
+        self.right.extend(target.push_right.iter().rev());
+        self.state = target.new_state;
+        self.steps += 1;
+        true
+    }
+
+    /// Take transitions until the machine halts or has taken `max_steps`
+    pub fn run(&mut self, max_steps: usize) {
+        while self.steps < max_steps && self.step() {}
+    }
+
+    /// The left stack, bottom first
+    pub fn left(&self) -> &[char] {
+        &self.left
+    }
+
+    /// The right stack, bottom first
+    pub fn right(&self) -> &[char] {
+        &self.right
+    }
+
+    /// The current state
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// Number of transitions taken
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+/// Parse a `"state,left,right": [new_state, push_left, push_right]` entry
+fn parse_transition(key: &str, value: &[String]) -> Result<(PdaKey, TwoStackTarget), String> {
+    let parts: Vec<&str> = key.split(',').collect();
+    let [state, left, right] = parts[..] else {
+        return Err(format!("Invalid transition key: {}", key));
+    };
+    let optional = |symbol: &str| match symbol {
+        "" | "ε" => Ok(None),
+        symbol => single(symbol, &format!("Transition {}: symbol", key)).map(Some),
+    };
+    let [new_state, push_left, push_right] = value else {
+        return Err(format!("Invalid transition value for key: {}", key));
+    };
+    let push = |symbols: &str| match symbols {
+        "ε" => Vec::new(),
+        symbols => symbols.chars().collect(),
+    };
+
+    Ok((
+        (state.to_string(), optional(left)?, optional(right)?),
+        TwoStackTarget {
+            new_state: new_state.clone(),
+            push_left: push(push_left),
+            push_right: push(push_right),
+        },
+    ))
+}
+
+/// Compile a single-tape Turing machine into an equivalent two-stack machine
+///
+/// Each transition becomes one two-stack transition per symbol that can be
+/// on top of the left stack if it moves left, and gets a second form for an
+/// empty right side of the tape if it reads the blank. Only plain machines
+/// with single-character symbols on a two-way infinite tape starting at the
+/// input can be converted.
+pub fn from_turing_machine(machine: &TuringMachine) -> Result<TwoStackJson, String> {
+    let unsupported = [
+        (machine.read_only_input, "read_only_input"),
+        (machine.accept_on_halt, "accept_on_halt"),
+        (!machine.input_map.is_empty(), "input_map"),
+        (!machine.input_normalization.is_none(), "normalize_input"),
+        (machine.left_edge.is_some(), "left_edge"),
+        (machine.tape_length.is_some(), "tape_length"),
+        (!machine.tape_setup.is_default(), "tape"),
+        (machine.output.is_some(), "output"),
+    ];
+    if let Some((_, field)) = unsupported.iter().find(|(set, _)| *set) {
+        return Err(format!(
+            "{} is not supported by the two-stack conversion",
+            field
+        ));
+    }
+    let mut tape_alphabet: Vec<char> = machine.tape_alphabet.iter().copied().collect();
+    tape_alphabet.sort();
+    for &symbol in &tape_alphabet {
+        if machine.symbols.name(symbol).chars().count() != 1 {
+            return Err(format!(
+                "Symbol {} has a multi-character name, which stacks do not support",
+                machine.symbols.name(symbol)
+            ));
+        }
+        if matches!(symbol, ',' | 'ε' | BOTTOM) {
+            return Err(format!(
+                "Symbol {} cannot be used on a two-stack machine's stacks",
+                symbol
+            ));
+        }
+    }
+
+    let halting = |state: &String| {
+        machine.accept_states.contains(state) || machine.reject_states.contains(state)
+    };
+    let blank = machine.blank_symbol;
+    let mut transitions = BTreeMap::new();
+    let mut add = |state: &str, left: Option<char>, right: char, to: [String; 3]| {
+        let left = left.map(String::from).unwrap_or_default();
+        transitions.insert(format!("{},{},{}", state, left, right), to.to_vec());
+    };
+    for ((state, read), (new_state, write, direction)) in &machine.transitions {
+        if halting(state) {
+            continue;
+        }
+        // The cell under the head is on top of the right stack, or the right
+        // stack is down to its bottom and the cell is blank
+        let mut reads = vec![(*read, String::new())];
+        if *read == blank {
+            reads.push((BOTTOM, BOTTOM.to_string()));
+        }
+        for (top, rest) in reads {
+            let to = |left: String, right: String| [new_state.clone(), left, right];
+            match direction {
+                Direction::R => add(state, None, top, to(write.to_string(), rest.clone())),
+                Direction::S => add(
+                    state,
+                    None,
+                    top,
+                    to(String::new(), format!("{}{}", write, rest)),
+                ),
+                Direction::L => {
+                    for &left in &tape_alphabet {
+                        let right = format!("{}{}{}", left, write, rest);
+                        add(state, Some(left), top, to(String::new(), right));
+                    }
+                    // Moving left off the written cells reaches a blank one
+                    let right = format!("{}{}{}", blank, write, rest);
+                    add(state, Some(BOTTOM), top, to(BOTTOM.to_string(), right));
+                }
+            }
+        }
+    }
+
+    let sorted = |symbols: &mut dyn Iterator<Item = char>| {
+        let mut symbols: Vec<String> = symbols.map(String::from).collect();
+        symbols.sort();
+        symbols
+    };
+    let states = |states: &mut dyn Iterator<Item = &String>| {
+        let mut states: Vec<String> = states.cloned().collect();
+        states.sort();
+        states
+    };
+    Ok(TwoStackJson {
+        states: states(&mut machine.states.iter()),
+        alphabet: sorted(&mut machine.alphabet.iter().copied()),
+        stack_alphabet: sorted(&mut tape_alphabet.iter().copied().chain([BOTTOM])),
+        initial_state: machine.initial_state.clone(),
+        initial_left: BOTTOM.to_string(),
+        initial_right: BOTTOM.to_string(),
+        accept_states: states(&mut machine.accept_states.iter()),
+        reject_states: states(&mut machine.reject_states.iter()),
+        transitions,
+    })
+}
@@ -0,0 +1,522 @@
+//! A universal Turing machine and the encoding of machines it runs
+//!
+//! [`encode`] writes a machine and its input as a single string, the input
+//! of the machine built by [`universal_machine`], which then behaves as the
+//! encoded machine would: it accepts if and only if that machine accepts,
+//! rejects if it rejects, and runs forever if it does.
+//!
+//! States and tape symbols are numbered in binary, all with as many digits
+//! as the largest number needs. The blank symbol is 0 and the initial state
+//! is 0. The encoding has three parts separated by `#`:
+//!
+//! ```text
+//! $;00.00n01.00<;00.01n00.01>  #  n00.yy  #  h01c10
+//!  rules                          state      tape
+//! ```
+//!
+//! - Each rule is `;`, the state and symbol it reads separated by `.`, the
+//!   kind of the new state (`n` for running, `a` for accept and `r` for
+//!   reject), the new state and the symbol written separated by `.`, and the
+//!   move (`<`, `>` or `=` for left, right and stay). Halting states have no
+//!   rules.
+//! - The state part is the kind and number of the current state, then `.`
+//!   and one `y` per symbol digit, room for the symbol under the head.
+//! - The tape is one cell after the other, each a marker followed by its
+//!   symbol: `h` for the cell under the head and `c` for the others.
+//!
+//! The universal machine repeats one cycle: halt if the current state is an
+//! accept or reject state, copy the symbol under the head next to the
+//! current state, compare that pair digit by digit with each rule until one
+//! matches (rejecting if none does), copy the rule's new state and symbol
+//! back, write the symbol into the head's cell, and move the `h` marker. A
+//! move past either end of the tape adds a blank cell there, as wide as its
+//! neighbour. Digits are marked while being compared or copied, `0` as `o`
+//! and `1` as `i`, and the rule being tried starts with `*` instead of `;`.
+//!
+//! Only machines on the default two-way infinite tape, rejecting when no
+//! transition applies and without input preprocessing, can be encoded. The
+//! `output` of a machine computing a function is not read; it can be read
+//! off the decoded tape.
+
+use crate::executor::RunDefaults;
+use crate::info::Metadata;
+use crate::{Direction, TuringMachine, TuringMachineBuilder};
+
+/// Step limit the universal machine recommends, since it takes thousands
+/// of steps for every step of the machine it runs
+pub const UTM_MAX_STEPS: usize = 50_000_000;
+
+/// Symbols of the universal machine besides the blank
+const ALPHABET: &str = "$;*#.01oinarNARxych<>=";
+
+/// Digits and the same digits marked
+const DIGITS: &str = "01";
+const MARKED: &str = "oi";
+/// Kinds of states and the same kinds marked
+const KINDS: &str = "nar";
+const MARKED_KINDS: &str = "NAR";
+const MOVES: &str = "<>=";
+/// Symbols carried when the tape is shifted to make room for a cell
+const CELL_SYMBOLS: &str = "hc01oi";
+
+const BLANK: char = '_';
+
+/// A machine and input written as input of the universal machine, with the
+/// numbering used to read the result back
+#[derive(Debug, Clone)]
+pub struct Encoding {
+    /// Input of the universal machine
+    pub tape: String,
+    /// States, by number
+    states: Vec<String>,
+    /// Tape symbols, by number
+    symbols: Vec<char>,
+}
+
+/// The configuration of the encoded machine on a tape of the universal
+/// machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub state: String,
+    pub tape: Vec<char>,
+    /// Index of the head in `tape`
+    pub head: usize,
+}
+
+impl Encoding {
+    /// Read the encoded machine's configuration back from a tape of the
+    /// universal machine, as shown in an [`ExecutionResult`]
+    ///
+    /// [`ExecutionResult`]: crate::ExecutionResult
+    pub fn decode(&self, tape: &str) -> Option<Decoded> {
+        let mut parts = tape.trim_matches(BLANK).split('#');
+        let (_, register, cells) = (parts.next()?, parts.next()?, parts.next()?);
+        let digits = register.get(1..)?.split('.').next()?;
+        let state = self.states.get(number(digits)?)?.clone();
+
+        let width = digit_width(self.symbols.len());
+        let cells: Vec<char> = cells.chars().collect();
+        let mut decoded = Decoded {
+            state,
+            tape: Vec::new(),
+            head: 0,
+        };
+        for cell in cells.chunks(width + 1) {
+            if cell[0] == 'h' {
+                decoded.head = decoded.tape.len();
+            }
+            let digits: String = cell[1..].iter().collect();
+            decoded.tape.push(*self.symbols.get(number(&digits)?)?);
+        }
+        Some(decoded)
+    }
+}
+
+/// Digits needed to number `count` things in binary
+fn digit_width(count: usize) -> usize {
+    (usize::BITS - count.saturating_sub(1).leading_zeros()).max(1) as usize
+}
+
+fn binary(value: usize, width: usize) -> String {
+    format!("{:0width$b}", value, width = width)
+}
+
+fn number(digits: &str) -> Option<usize> {
+    usize::from_str_radix(digits, 2).ok()
+}
+
+/// Encode `machine` and `input_string` as input of the universal machine
+pub fn encode(machine: &TuringMachine, input_string: &str) -> Result<Encoding, String> {
+    let unsupported = [
+        (machine.read_only_input, "read_only_input"),
+        (machine.accept_on_halt, "accept_on_halt"),
+        (!machine.input_map.is_empty(), "input_map"),
+        (!machine.input_normalization.is_none(), "normalize_input"),
+        (machine.left_edge.is_some(), "left_edge"),
+        (machine.tape_length.is_some(), "tape_length"),
+        (!machine.tape_setup.is_default(), "tape"),
+    ];
+    if let Some((_, field)) = unsupported.iter().find(|(set, _)| *set) {
+        return Err(format!(
+            "{} is not supported by the universal machine",
+            field
+        ));
+    }
+    let input = machine.map_input(input_string)?;
+    for symbol in &input {
+        if !machine.alphabet.contains(symbol) {
+            return Err(format!(
+                "Invalid input symbol: {}",
+                machine.symbols.name(*symbol)
+            ));
+        }
+    }
+
+    let blank = machine.blank_symbol;
+    let mut symbols: Vec<char> = machine.tape_alphabet.iter().copied().collect();
+    symbols.sort_by_key(|&symbol| (symbol != blank, symbol));
+    let mut states: Vec<String> = machine.states.iter().cloned().collect();
+    states.sort_by(|a, b| (a != &machine.initial_state, a).cmp(&(b != &machine.initial_state, b)));
+    let symbol_width = digit_width(symbols.len());
+    let state_width = digit_width(states.len());
+    let state_code = |state: &String| {
+        let index = states.iter().position(|s| s == state).unwrap_or(0);
+        binary(index, state_width)
+    };
+    let symbol_code = |symbol: &char| {
+        let index = symbols.iter().position(|s| s == symbol).unwrap_or(0);
+        binary(index, symbol_width)
+    };
+    let kind = |state: &String| {
+        if machine.accept_states.contains(state) {
+            'a'
+        } else if machine.reject_states.contains(state) {
+            'r'
+        } else {
+            'n'
+        }
+    };
+
+    let mut tape = String::from("$");
+    let mut transitions: Vec<_> = machine.transitions.iter().collect();
+    transitions.sort_by(|a, b| a.0.cmp(b.0));
+    for ((state, read), (new_state, write, direction)) in transitions {
+        if kind(state) != 'n' {
+            continue;
+        }
+        let direction = match direction {
+            Direction::L => '<',
+            Direction::R => '>',
+            Direction::S => '=',
+        };
+        tape.push_str(&format!(
+            ";{}.{}{}{}.{}{}",
+            state_code(state),
+            symbol_code(read),
+            kind(new_state),
+            state_code(new_state),
+            symbol_code(write),
+            direction
+        ));
+    }
+    let initial = &machine.initial_state;
+    tape.push_str(&format!(
+        "#{}{}.{}#",
+        kind(initial),
+        state_code(initial),
+        "y".repeat(symbol_width)
+    ));
+    let cells = if input.is_empty() { vec![blank] } else { input };
+    for (i, symbol) in cells.iter().enumerate() {
+        tape.push(if i == 0 { 'h' } else { 'c' });
+        tape.push_str(&symbol_code(symbol));
+    }
+    Ok(Encoding {
+        tape,
+        states,
+        symbols,
+    })
+}
+
+/// Transitions of the universal machine under construction
+struct Table {
+    builder: TuringMachineBuilder,
+}
+
+impl Table {
+    /// In `state`, on each symbol of `reads`, write `write` (or keep the
+    /// symbol read), move and continue in `to`
+    fn add(
+        &mut self,
+        state: &str,
+        reads: &str,
+        to: &str,
+        write: Option<char>,
+        direction: Direction,
+    ) {
+        for read in reads.chars() {
+            let builder = std::mem::take(&mut self.builder);
+            self.builder = builder.transition(state, read, to, write.unwrap_or(read), direction);
+        }
+    }
+
+    /// Move over the symbols of `reads`, staying in `state`
+    fn skip(&mut self, state: &str, reads: &str, direction: Direction) {
+        self.add(state, reads, state, None, direction);
+    }
+
+    /// Move over every symbol but those of `until`, staying in `state`
+    fn seek(&mut self, state: &str, until: &str, direction: Direction) {
+        self.skip(state, &others(until), direction);
+    }
+}
+
+/// The universal machine's symbols, blank included, except those of
+/// `excluded`
+fn others(excluded: &str) -> String {
+    ALPHABET
+        .chars()
+        .chain([BLANK])
+        .filter(|c| !excluded.contains(*c))
+        .collect()
+}
+
+fn mark(digit: char) -> char {
+    if digit == '0' {
+        'o'
+    } else {
+        'i'
+    }
+}
+
+/// The universal machine, running the machine and input given as an
+/// [`Encoding`]
+pub fn universal_machine() -> TuringMachine {
+    use Direction::{L, R, S};
+    let mut t = Table {
+        builder: TuringMachine::builder()
+            .initial("restart")
+            .accept("accept")
+            .reject("reject")
+            .input_symbols(ALPHABET.chars())
+            .blank(BLANK)
+            .run_defaults(RunDefaults {
+                max_steps: Some(UTM_MAX_STEPS),
+                ..RunDefaults::default()
+            })
+            .metadata(Metadata {
+                name: Some("Universal Turing machine".to_string()),
+                description: Some(
+                    "Runs the machine and input encoded on its tape by `universal::encode`"
+                        .to_string(),
+                ),
+                ..Metadata::default()
+            }),
+    };
+
+    // Go to the current state and halt if it halts
+    t.seek("restart", "$", L);
+    t.add("restart", "$", "start", None, R);
+    t.seek("start", "#", R);
+    t.add("start", "#", "kind", None, R);
+    t.add("kind", "a", "accept", None, S);
+    t.add("kind", "r", "reject", None, S);
+    t.add("kind", "n", "read", None, R);
+
+    // Copy the symbol under the head next to the current state
+    t.seek("read", "h", R);
+    t.add("read", "h", "read_digit", None, R);
+    t.skip("read_digit", MARKED, R);
+    for digit in DIGITS.chars() {
+        let carry = format!("read_carry_{}", digit);
+        let state_part = format!("read_carry_{}_state", digit);
+        let slot = format!("read_carry_{}_slot", digit);
+        t.add(
+            "read_digit",
+            &digit.to_string(),
+            &carry,
+            Some(mark(digit)),
+            L,
+        );
+        t.seek(&carry, "#", L);
+        t.add(&carry, "#", &state_part, None, L);
+        t.seek(&state_part, ".", L);
+        t.add(&state_part, ".", &slot, None, R);
+        t.seek(&slot, "y", R);
+        t.add(&slot, "y", "read", Some(digit), R);
+    }
+    t.add("read_digit", "c_", "read_unmark", None, L);
+    t.add("read_unmark", "o", "read_unmark", Some('0'), L);
+    t.add("read_unmark", "i", "read_unmark", Some('1'), L);
+    t.add("read_unmark", "h", "match", None, L);
+
+    // Compare the current state and symbol with each rule in turn
+    t.seek("match", "$", L);
+    t.add("match", "$", "match_first", None, R);
+    t.add("match_first", ";", "compare", Some('*'), R);
+    t.add("match_first", "#", "reject", None, S);
+    t.seek("compare", "#", R);
+    t.add("compare", "#", "compare_kind", None, R);
+    t.add("compare_kind", "n", "compare_digit", None, R);
+    t.skip("compare_digit", "oi.", R);
+    for digit in DIGITS.chars() {
+        let other = if digit == '0' { "1" } else { "0" };
+        let rule = format!("compare_{}", digit);
+        let rule_digit = format!("compare_{}_rule", digit);
+        t.add(
+            "compare_digit",
+            &digit.to_string(),
+            &rule,
+            Some(mark(digit)),
+            L,
+        );
+        t.seek(&rule, "*", L);
+        t.add(&rule, "*", &rule_digit, None, R);
+        t.skip(&rule_digit, "oi.", R);
+        t.add(
+            &rule_digit,
+            &digit.to_string(),
+            "compare",
+            Some(mark(digit)),
+            R,
+        );
+        t.add(&rule_digit, other, "mismatch", None, L);
+    }
+    t.add("compare_digit", "#", "matched", None, L);
+
+    // On a mismatch, unmark both sides and try the next rule
+    t.seek("mismatch", "*", L);
+    t.add("mismatch", "*", "mismatch_unmark", Some(';'), R);
+    t.add("mismatch_unmark", "o", "mismatch_unmark", Some('0'), R);
+    t.add("mismatch_unmark", "i", "mismatch_unmark", Some('1'), R);
+    t.skip("mismatch_unmark", ".01", R);
+    t.add("mismatch_unmark", KINDS, "next_rule", None, R);
+    t.seek("next_rule", ";#", R);
+    t.add("next_rule", ";", "next_rule_state", Some('*'), R);
+    t.add("next_rule", "#", "reject", None, S);
+    t.seek("next_rule_state", "#", R);
+    t.add("next_rule_state", "#", "next_rule_unmark", None, R);
+    t.add("next_rule_unmark", "o", "next_rule_unmark", Some('0'), R);
+    t.add("next_rule_unmark", "i", "next_rule_unmark", Some('1'), R);
+    t.skip("next_rule_unmark", "n.01", R);
+    t.add("next_rule_unmark", "#", "next_rule_start", None, L);
+    t.seek("next_rule_start", "*", L);
+    t.add("next_rule_start", "*", "compare", None, R);
+
+    // Clear the current state and symbol, then copy the rule's new state
+    // and symbol in their place
+    t.add("matched", "oi", "matched", Some('y'), L);
+    t.add("matched", ".", "matched_state", None, L);
+    t.add("matched_state", "oi", "matched_state", Some('x'), L);
+    t.add("matched_state", "n", "copy", Some('x'), L);
+    t.seek("copy", "*", L);
+    t.add("copy", "*", "copy_next", None, R);
+    t.skip("copy_next", "oi.NAR", R);
+    for (symbol, marked) in KINDS
+        .chars()
+        .zip(MARKED_KINDS.chars())
+        .chain([('0', 'o'), ('1', 'i')])
+    {
+        let carry = format!("copy_{}", symbol);
+        let slot = format!("copy_{}_slot", symbol);
+        t.add("copy_next", &symbol.to_string(), &carry, Some(marked), R);
+        t.seek(&carry, "#", R);
+        t.add(&carry, "#", &slot, None, R);
+        t.seek(&slot, "xy", R);
+        t.add(&slot, "xy", "copy", Some(symbol), L);
+    }
+    t.add("copy_next", MOVES, "write", None, S);
+
+    // Write the new symbol into the head's cell, digit by digit
+    t.seek("write", "#", R);
+    t.add("write", "#", "write_state", None, R);
+    t.seek("write_state", ".", R);
+    t.add("write_state", ".", "write_digit", None, R);
+    t.skip("write_digit", "y", R);
+    for digit in DIGITS.chars() {
+        let carry = format!("write_{}", digit);
+        let head = format!("write_{}_head", digit);
+        let cell = format!("write_{}_cell", digit);
+        t.add("write_digit", &digit.to_string(), &carry, Some('y'), R);
+        t.seek(&carry, "#", R);
+        t.add(&carry, "#", &head, None, R);
+        t.seek(&head, "h", R);
+        t.add(&head, "h", &cell, None, R);
+        t.skip(&cell, MARKED, R);
+        t.add(&cell, DIGITS, "write_back", Some(mark(digit)), L);
+    }
+    t.seek("write_back", "#", L);
+    t.add("write_back", "#", "write_back_state", None, L);
+    t.seek("write_back_state", ".", L);
+    t.add("write_back_state", ".", "write_digit", None, R);
+    t.add("write_digit", "#", "move", None, L);
+
+    // Fetch the rule's move, unmarking the rule, and make it
+    t.seek("move", "*", L);
+    t.add("move", "*", "move_find", None, R);
+    t.skip("move_find", "oi.NAR", R);
+    for (symbol, name) in MOVES.chars().zip(["left", "right", "stay"]) {
+        let restore = format!("move_{}_restore", name);
+        let state_part = format!("move_{}_state", name);
+        let tape = format!("move_{}_tape", name);
+        let head = format!("move_{}_head", name);
+        let unmark = format!("move_{}_unmark", name);
+        let back = format!("move_{}_back", name);
+        t.add("move_find", &symbol.to_string(), &restore, None, L);
+        t.add(&restore, "o", &restore, Some('0'), L);
+        t.add(&restore, "i", &restore, Some('1'), L);
+        for (kind, marked) in KINDS.chars().zip(MARKED_KINDS.chars()) {
+            t.add(&restore, &marked.to_string(), &restore, Some(kind), L);
+        }
+        t.skip(&restore, ".", L);
+        t.add(&restore, "*", &state_part, Some(';'), R);
+        t.seek(&state_part, "#", R);
+        t.add(&state_part, "#", &tape, None, R);
+        t.seek(&tape, "#", R);
+        t.add(&tape, "#", &head, None, R);
+        t.seek(&head, "h", R);
+        t.add(&head, "h", &unmark, None, R);
+        t.add(&unmark, "o", &unmark, Some('0'), R);
+        t.add(&unmark, "i", &unmark, Some('1'), R);
+        t.add(&unmark, "c_", &back, None, L);
+        t.skip(&back, DIGITS, L);
+    }
+    t.add("move_stay_back", "h", "restart", None, L);
+    t.add("move_right_back", "h", "right", Some('c'), R);
+    t.add("move_left_back", "h", "left", Some('c'), L);
+
+    // Move right, adding a blank cell as wide as the last one at the end
+    t.skip("right", DIGITS, R);
+    t.add("right", "c", "restart", Some('h'), L);
+    t.add("right", "_", "append", Some('h'), L);
+    t.skip("append", "01oi", L);
+    t.add("append", "c", "append_digit", None, R);
+    t.skip("append_digit", MARKED, R);
+    t.add("append_digit", "0", "append_end", Some('o'), R);
+    t.add("append_digit", "1", "append_end", Some('i'), R);
+    t.add("append_digit", "h", "append_unmark", None, L);
+    t.skip("append_end", "01oih", R);
+    t.add("append_end", "_", "append_back", Some('0'), L);
+    t.skip("append_back", "0", L);
+    t.add("append_back", "h", "append", None, L);
+    t.add("append_unmark", "o", "append_unmark", Some('0'), L);
+    t.add("append_unmark", "i", "append_unmark", Some('1'), L);
+    t.add("append_unmark", "c", "restart", None, L);
+
+    // Move left, adding a blank cell as wide as the first one at the start
+    // by shifting the tape right one symbol at a time
+    t.skip("left", DIGITS, L);
+    t.add("left", "c", "restart", Some('h'), L);
+    t.add("left", "#", "shift_h", None, R);
+    for carried in CELL_SYMBOLS.chars() {
+        let state = format!("shift_{}", carried);
+        for read in CELL_SYMBOLS.chars() {
+            t.add(
+                &state,
+                &read.to_string(),
+                &format!("shift_{}", read),
+                Some(carried),
+                R,
+            );
+        }
+        t.add(&state, "_", "insert", Some(carried), L);
+    }
+    t.seek("insert", "#", L);
+    t.add("insert", "#", "insert_cell", None, R);
+    t.add("insert_cell", "h", "insert_new", None, R);
+    t.skip("insert_new", "0", R);
+    t.add("insert_new", "c", "insert_digit", None, R);
+    t.skip("insert_digit", MARKED, R);
+    t.add("insert_digit", "0", "insert_zero", Some('o'), L);
+    t.add("insert_digit", "1", "insert_zero", Some('i'), L);
+    t.add("insert_digit", "c_", "insert_unmark", None, L);
+    t.skip("insert_zero", MARKED, L);
+    t.add("insert_zero", "c", "shift_0", None, S);
+    t.add("insert_unmark", "o", "insert_unmark", Some('0'), L);
+    t.add("insert_unmark", "i", "insert_unmark", Some('1'), L);
+    t.add("insert_unmark", "c", "restart", None, L);
+
+    t.builder
+        .build()
+        .expect("the universal machine is well-formed")
+}
@@ -0,0 +1,290 @@
+//! JavaScript bindings for WebAssembly builds
+//!
+//! Web-based visualizers can run machines with this crate's executor
+//! instead of reimplementing its semantics:
+//!
+//! ```js
+//! import init, { loadMachine } from "./pkg/turing_machine.js";
+//!
+//! await init();
+//! const machine = loadMachine(definitionJson);
+//! const result = machine.run("0110", 10000);
+//! console.log(result.accepts, result.steps, result.tape);
+//!
+//! machine.reset("0110");
+//! for (let snapshot = machine.snapshot(); snapshot; snapshot = machine.step()) {
+//!     draw(snapshot.tapeCells, snapshot.headPosition, snapshot.state);
+//! }
+//! ```
+//!
+//! Stepping replays a run recorded by `reset`, one configuration at a
+//! time, so it takes exactly the steps `run` does; `stepBack` and
+//! `snapshotAt` move around in it freely. The run is kept as the change
+//! made by each step, not as a copy of the tape per step, so long runs fit
+//! in a browser tab. Given `snapshotEvery`, `reset` keeps only every Nth
+//! configuration, and the steps between them are taken again when visited.
+
+use crate::executor::{Executor, RunConfig};
+use crate::history::{Cursor, History};
+use crate::{
+    parse_machine_definition, parse_machine_json, ExecutionResult, ExecutionSnapshot, TuringMachine,
+};
+use wasm_bindgen::prelude::*;
+
+/// Parse a machine definition in the JSON schema
+#[wasm_bindgen(js_name = loadMachine)]
+pub fn load_machine(json: &str) -> Result<Machine, JsError> {
+    let definition = parse_machine_definition(json).map_err(|e| JsError::new(&e))?;
+    let machine = parse_machine_json(&definition).map_err(|e| JsError::new(&e))?;
+    Ok(Machine {
+        machine,
+        recorded: None,
+        result: None,
+    })
+}
+
+/// A loaded machine, with the run being stepped through
+#[wasm_bindgen]
+pub struct Machine {
+    machine: TuringMachine,
+    /// The run recorded by `reset`, and the configuration it is at
+    recorded: Option<(History, Cursor)>,
+    result: Option<RunResult>,
+}
+
+#[wasm_bindgen]
+impl Machine {
+    /// Run the machine on an input, with the machine's recommended step
+    /// limit, or [`DEFAULT_MAX_STEPS`](crate::executor::DEFAULT_MAX_STEPS), if
+    /// `max_steps` is not given
+    pub fn run(&self, input: &str, max_steps: Option<usize>) -> Result<RunResult, JsError> {
+        let result = Executor::new(&self.machine)
+            .run_with(input, &self.config(max_steps))
+            .map_err(|e| JsError::new(&e))?;
+        Ok(RunResult::from(result))
+    }
+
+    /// Record a run on an input to step through, starting at its initial
+    /// configuration, keeping every `snapshot_every`th configuration if
+    /// given
+    pub fn reset(
+        &mut self,
+        input: &str,
+        max_steps: Option<usize>,
+        snapshot_every: Option<usize>,
+    ) -> Result<(), JsError> {
+        let mut history = match snapshot_every {
+            Some(every) => History::sampled(&self.machine, input, every),
+            None => History::new(&self.machine, input),
+        };
+        let result = Executor::new(&self.machine)
+            .observe(&mut history)
+            .run_with(input, &self.config(max_steps))
+            .map_err(|e| JsError::new(&e))?;
+        let cursor = Cursor::new(&history);
+        self.recorded = Some((history, cursor));
+        self.result = Some(RunResult::from(result));
+        Ok(())
+    }
+
+    /// Move to the next configuration and return it, or `undefined` at the
+    /// end of the run
+    pub fn step(&mut self) -> Option<Snapshot> {
+        let (history, cursor) = self.recorded.as_mut()?;
+        if !cursor.seek(&self.machine, history, cursor.step() + 1) {
+            return None;
+        }
+        self.snapshot()
+    }
+
+    /// Move to the previous configuration and return it, or `undefined` at
+    /// the start of the run
+    #[wasm_bindgen(js_name = stepBack)]
+    pub fn step_back(&mut self) -> Option<Snapshot> {
+        let (history, cursor) = self.recorded.as_mut()?;
+        cursor.seek(&self.machine, history, cursor.step().checked_sub(1)?);
+        self.snapshot()
+    }
+
+    /// The current configuration, or `undefined` before `reset`
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        let (history, cursor) = self.recorded.as_ref()?;
+        Some(self.render(&cursor.snapshot(history)))
+    }
+
+    /// The configuration after `step` steps of the recorded run
+    #[wasm_bindgen(js_name = snapshotAt)]
+    pub fn snapshot_at(&self, step: usize) -> Option<Snapshot> {
+        let (history, _) = self.recorded.as_ref()?;
+        Some(self.render(&history.snapshot(&self.machine, step)?))
+    }
+
+    /// Number of configurations in the recorded run, one more than its steps
+    #[wasm_bindgen(getter, js_name = snapshotCount)]
+    pub fn snapshot_count(&self) -> usize {
+        self.recorded
+            .as_ref()
+            .map_or(0, |(history, _)| history.steps() + 1)
+    }
+
+    /// How the recorded run ended, or `undefined` before `reset`
+    #[wasm_bindgen(getter)]
+    pub fn result(&self) -> Option<RunResult> {
+        self.result.clone()
+    }
+
+    /// Names of the states, sorted
+    #[wasm_bindgen(getter)]
+    pub fn states(&self) -> Vec<String> {
+        let mut states: Vec<String> = self.machine.states.iter().cloned().collect();
+        states.sort();
+        states
+    }
+
+    #[wasm_bindgen(getter, js_name = initialState)]
+    pub fn initial_state(&self) -> String {
+        self.machine.initial_state.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = acceptStates)]
+    pub fn accept_states(&self) -> Vec<String> {
+        let mut states: Vec<String> = self.machine.accept_states.iter().cloned().collect();
+        states.sort();
+        states
+    }
+
+    #[wasm_bindgen(getter, js_name = rejectStates)]
+    pub fn reject_states(&self) -> Vec<String> {
+        let mut states: Vec<String> = self.machine.reject_states.iter().cloned().collect();
+        states.sort();
+        states
+    }
+
+    #[wasm_bindgen(getter, js_name = blankSymbol)]
+    pub fn blank_symbol(&self) -> String {
+        self.machine.symbols.name(self.machine.blank_symbol)
+    }
+
+    /// The limits of a run, from the machine's recommendations
+    fn config(&self, max_steps: Option<usize>) -> RunConfig {
+        let mut config = self.machine.run_defaults.apply(&RunConfig::default());
+        if let Some(max_steps) = max_steps {
+            config.max_steps = max_steps;
+        }
+        config
+    }
+
+    /// A configuration, with the symbols of its tape written out by their
+    /// names
+    fn render(&self, snapshot: &ExecutionSnapshot) -> Snapshot {
+        Snapshot {
+            tape: self.machine.symbols.render(&snapshot.tape),
+            tape_cells: self.machine.symbols.names(&snapshot.tape),
+            head_position: snapshot.head_position,
+            state: snapshot.current_state.clone(),
+            step: snapshot.step,
+        }
+    }
+}
+
+/// A configuration of a run
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// Tape contents, with named symbols written out by their names
+    pub tape: String,
+    /// Tape contents, the name of the symbol in each cell
+    #[wasm_bindgen(js_name = tapeCells)]
+    pub tape_cells: Vec<String>,
+    /// Head position, an index into `tapeCells`
+    #[wasm_bindgen(js_name = headPosition)]
+    pub head_position: i32,
+    pub state: String,
+    pub step: usize,
+}
+
+/// How a run ended
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    /// `true` or `false` if the machine halted, `undefined` if it ran out
+    /// of steps
+    pub accepts: Option<bool>,
+    #[wasm_bindgen(js_name = finalState)]
+    pub final_state: String,
+    pub steps: usize,
+    pub halted: bool,
+    /// Tape contents, with named symbols written out by their names
+    pub tape: String,
+    /// Tape contents, the name of the symbol in each cell
+    #[wasm_bindgen(js_name = tapeCells)]
+    pub tape_cells: Vec<String>,
+    /// Head position, an index into `tapeCells`; it is `-1` or the tape
+    /// length if the head just moved onto a blank not yet on the tape
+    #[wasm_bindgen(js_name = headPosition)]
+    pub head_position: i32,
+    /// What a machine computing a function left on the tape
+    pub output: Option<String>,
+    /// Steps after which the run came back to the same configuration,
+    /// proving that the machine never halts; `undefined` otherwise
+    #[wasm_bindgen(js_name = cycleLength)]
+    pub cycle_length: Option<usize>,
+    /// Cells the configuration moved right in every round of the cycle,
+    /// negative for left
+    #[wasm_bindgen(js_name = cycleShift)]
+    pub cycle_shift: Option<i64>,
+}
+
+impl From<ExecutionResult> for RunResult {
+    fn from(result: ExecutionResult) -> Self {
+        RunResult {
+            accepts: result.accepts,
+            final_state: result.final_state,
+            steps: result.steps,
+            halted: result.halted,
+            tape: result.tape,
+            tape_cells: result.tape_cells,
+            head_position: result.head_position,
+            output: result.output,
+            cycle_length: result.cycle.map(|cycle| cycle.length),
+            cycle_shift: result.cycle.map(|cycle| cycle.shift),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Marks its input cell by cell with the named symbol `X1`
+    const MARKER: &str = r#"{
+        "states": ["q0", "back", "acc"],
+        "alphabet": ["a"],
+        "tape_alphabet": ["a", "X1", "_"],
+        "initial_state": "q0",
+        "accept_states": ["acc"],
+        "reject_states": [],
+        "transitions": {
+            "q0,a": ["q0", "X1", "R"],
+            "q0,_": ["back", "_", "L"],
+            "back,X1": ["acc", "X1", "S"]
+        }
+    }"#;
+
+    #[test]
+    fn places_the_head_on_cells_of_named_symbols() {
+        let mut machine = load_machine(MARKER).unwrap();
+        let result = machine.run("aa", None).unwrap();
+        assert_eq!(result.accepts, Some(true));
+        assert_eq!(result.tape, "X1X1_");
+        assert_eq!(result.tape_cells, ["X1", "X1", "_"]);
+        assert_eq!(result.head_position, 1);
+
+        machine.reset("aa", None, None).unwrap();
+        let snapshot = machine.snapshot_at(1).unwrap();
+        assert_eq!(snapshot.tape_cells, ["X1", "a"]);
+        assert_eq!(snapshot.head_position, 1);
+        let cell = &snapshot.tape_cells[snapshot.head_position as usize];
+        assert_eq!(cell, "a");
+    }
+}